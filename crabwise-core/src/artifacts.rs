@@ -0,0 +1,148 @@
+//! Names of the files crabwise leaves on a drive while it runs, and finding the ones a
+//! crashed or killed run left behind.
+//!
+//! Every name starts with [`PREFIX`] followed by this process's tag (`<pid>-<start time>`)
+//! and the number of the run within it (see [`begin_run`]), so two runs never collide, not
+//! even two of one server or `watch`, and a leftover can be told apart from a run still in
+//! progress.
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use std::cell::Cell;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use sysinfo::{Pid, System};
+
+/// Every test file and directory name starts with this.
+pub const PREFIX: &str = ".usbbench";
+
+static TAG: Lazy<String> = Lazy::new(|| format!("{}-{}", std::process::id(), Local::now().format("%Y%m%d%H%M%S")));
+
+/// This process's `<pid>-<start time>` tag.
+pub fn tag() -> &'static str {
+    &TAG
+}
+
+/// Runs started so far by [`begin_run`]; run 0 is whatever runs outside one.
+static RUNS: AtomicU64 = AtomicU64::new(0);
+
+/// Runs of this process that haven't finished; their files aren't leftovers.
+static ACTIVE: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static RUN: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A run in progress on the thread that began it; dropping it ends the run.
+pub struct Run {
+    id: u64,
+    outer: u64,
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).retain(|&r| r != self.id);
+        RUN.with(|r| r.set(self.outer));
+    }
+}
+
+/// Starts a run on the calling thread: until the returned guard is dropped, [`name`] gives
+/// names no earlier run of this process used, so a second run doesn't overwrite the files
+/// the first one kept.
+pub fn begin_run() -> Run {
+    let id = RUNS.fetch_add(1, Ordering::Relaxed) + 1;
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).push(id);
+    Run { id, outer: RUN.with(|r| r.replace(id)) }
+}
+
+/// `PREFIX-tag-run` plus `suffix`, e.g. `name(".tmp")` for the main test file.
+pub fn name(suffix: &str) -> String {
+    format!("{PREFIX}-{}-{}{suffix}", tag(), RUN.with(Cell::get))
+}
+
+/// A file or directory found in a target directory that an earlier run created.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub path: PathBuf,
+    /// Size on disk; a directory counts the files directly inside it.
+    pub bytes: u64,
+    /// The run's process ID, when its name carries one (names from older versions don't).
+    pub pid: Option<u32>,
+}
+
+/// The PID in a tagged name such as `.usbbench-4242-20250101120000-1.tmp`.
+fn pid_of(name: &str) -> Option<u32> {
+    let rest = name.strip_prefix(PREFIX)?.strip_prefix('-')?;
+    rest.split('-').next()?.parse().ok()
+}
+
+/// The run number in a name this process gave, `None` for another process's.
+fn run_of(name: &str) -> Option<u64> {
+    let rest = name.strip_prefix(PREFIX)?.strip_prefix('-')?.strip_prefix(tag())?.strip_prefix('-')?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    rest[..digits].parse().ok()
+}
+
+fn size_of(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else { return 0 };
+    if !meta.is_dir() { return meta.len(); }
+    std::fs::read_dir(path).into_iter().flatten().flatten()
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Crabwise files and directories directly inside `dir` that don't belong to a run still in
+/// progress: this process's unfinished runs (and run 0) or another crabwise process that is
+/// still running. Missing or unreadable directories have none.
+pub fn leftovers(dir: &Path) -> Vec<Artifact> {
+    let active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let mut sys = System::new();
+    let mut found: Vec<Artifact> = std::fs::read_dir(dir).into_iter().flatten().flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            if !name.starts_with(PREFIX) { return None; }
+            if let Some(run) = run_of(&name) {
+                if run == 0 || active.contains(&run) { return None; }
+                return Some(Artifact { bytes: size_of(&e.path()), path: e.path(), pid: Some(std::process::id()) });
+            }
+            let pid = pid_of(&name);
+            if pid.is_some_and(|p| sys.refresh_process(Pid::from_u32(p))) {
+                crate::debug!("{name} belongs to running process {}", pid.unwrap_or_default());
+                return None;
+            }
+            Some(Artifact { bytes: size_of(&e.path()), path: e.path(), pid })
+        })
+        .collect();
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+/// Deletes `a`, recursively for a directory.
+pub fn remove(a: &Artifact) -> io::Result<()> {
+    if a.path.is_dir() { std::fs::remove_dir_all(&a.path) } else { std::fs::remove_file(&a.path) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_run_gets_its_own_names() {
+        let dir = std::env::temp_dir().join(format!("crabwise-artifacts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = begin_run();
+        let kept = dir.join(name(".tmp"));
+        std::fs::write(&kept, b"kept").unwrap();
+        assert!(leftovers(&dir).is_empty(), "a run in progress has no leftovers");
+        drop(first);
+        let second = begin_run();
+        assert_ne!(dir.join(name(".tmp")), kept);
+        let found: Vec<PathBuf> = leftovers(&dir).into_iter().map(|a| a.path).collect();
+        assert_eq!(found, vec![kept]);
+        drop(second);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,368 @@
+//! Storage backends: where the benchmark's bytes actually go.
+//!
+//! The runner only talks to a [`StorageBackend`], so the same loop can drive a
+//! temporary file ([`FileBackend`]), a raw block device ([`RawDeviceBackend`]),
+//! or an in-memory buffer ([`MemoryBackend`]) for exercising the statistics
+//! without touching a disk.
+
+use crate::extents::{fragmentation, Fragmentation};
+use crate::file::{evict, open_existing_write, open_read, open_read_write, open_write, preallocate};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// One benchmark target. Calls arrive in order: `open_write`, `write`*, `sync`,
+/// `open_read`, `read`* (until it returns 0), then optionally the random stage
+/// (`open_random`, `write_at`*, `sync`, `read_at`*), the shuffled read (`open_random`,
+/// `read_at`*) and finally `cleanup`.
+///
+/// Backends are `Send`: the sequential write runs on its own thread while the caller's
+/// thread prepares the next buffer and reports progress.
+pub trait StorageBackend: Send {
+    /// Prepares the target for a fresh write pass; `block` is the I/O size the runner will use.
+    fn open_write(&mut self, block: usize, direct: bool) -> io::Result<()>;
+    fn write(&mut self, buf: &[u8]) -> io::Result<()>;
+    /// Reserves `bytes` of space for the pass just opened, before any `write`. Default:
+    /// `Unsupported`, for targets with nothing to allocate.
+    fn preallocate(&mut self, _bytes: u64) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "this target can't be preallocated"))
+    }
+    /// Writes all of `bufs` in order, in as few calls as the backend can manage. Default: one `write` each.
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        bufs.iter().try_for_each(|b| self.write(b))
+    }
+    /// Flushes buffered data and waits until it is durable.
+    fn sync(&mut self) -> io::Result<()>;
+    /// Drops any OS-cached copy of what was written so reads hit the device. Default: nothing to drop.
+    fn evict(&mut self) -> io::Result<()> { Ok(()) }
+    fn open_read(&mut self, block: usize, direct: bool) -> io::Result<()>;
+    /// Reads the next chunk of what was written; 0 means the end.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+    /// Like `read`, filling `bufs` in order from one call where the backend can; returns the
+    /// bytes read, 0 at the end. Default: a single `read` into the first buffer.
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        match bufs.iter_mut().find(|b| !b.is_empty()) {
+            Some(b) => self.read(b),
+            None => Ok(0),
+        }
+    }
+    /// Reopens what was written for positional I/O. Backends without random access keep the default.
+    fn open_random(&mut self, _direct: bool) -> io::Result<()> { Err(no_random()) }
+    fn write_at(&mut self, _buf: &[u8], _offset: u64) -> io::Result<()> { Err(no_random()) }
+    /// Fills `buf` from `offset`.
+    fn read_at(&mut self, _buf: &mut [u8], _offset: u64) -> io::Result<()> { Err(no_random()) }
+    /// How the written data is laid out on disk. Default: `Unsupported`, for targets that
+    /// aren't a file on a filesystem.
+    fn fragmentation(&self) -> io::Result<Fragmentation> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "this target isn't a file"))
+    }
+    /// Removes whatever the benchmark left behind.
+    fn cleanup(&mut self) -> io::Result<()> { Ok(()) }
+    /// Human-readable location, recorded as the result's `test_file`.
+    fn describe(&self) -> String;
+}
+
+/// A regular file, created and removed by the benchmark.
+pub struct FileBackend {
+    path: PathBuf,
+    writer: Option<BufWriter<File>>,
+    reader: Option<BufReader<File>>,
+    random: Option<File>,
+}
+
+impl FileBackend {
+    pub fn new(path: impl Into<PathBuf>) -> FileBackend {
+        FileBackend { path: path.into(), writer: None, reader: None, random: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl StorageBackend for FileBackend {
+    fn open_write(&mut self, block: usize, direct: bool) -> io::Result<()> {
+        self.random = None;
+        let f = open_write(&self.path, direct)?;
+        self.writer = Some(BufWriter::with_capacity(block, f));
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.writer.as_mut().ok_or_else(not_open)?.write_all(buf)
+    }
+
+    fn preallocate(&mut self, bytes: u64) -> io::Result<()> {
+        let t = std::time::Instant::now();
+        preallocate(self.writer.as_ref().ok_or_else(not_open)?.get_ref(), bytes)?;
+        crate::debug!("preallocated {bytes} bytes for {} in {:.1} ms", self.path.display(), t.elapsed().as_secs_f64() * 1e3);
+        Ok(())
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        write_all_vectored(self.writer.as_mut().ok_or_else(not_open)?, bufs)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        let t = std::time::Instant::now();
+        if let Some(f) = &self.random {
+            f.sync_all()?;
+        } else {
+            let mut writer = self.writer.take().ok_or_else(not_open)?;
+            writer.flush()?;
+            writer.get_ref().sync_all()?; // ensure data + metadata on disk
+        }
+        crate::debug!("sync_all {} took {:.1} ms", self.path.display(), t.elapsed().as_secs_f64() * 1e3);
+        Ok(())
+    }
+
+    fn evict(&mut self) -> io::Result<()> {
+        evict(&self.path)
+    }
+
+    fn fragmentation(&self) -> io::Result<Fragmentation> {
+        fragmentation(&self.path)
+    }
+
+    fn open_read(&mut self, block: usize, direct: bool) -> io::Result<()> {
+        let f = open_read(&self.path, direct)?;
+        self.reader = Some(BufReader::with_capacity(block, f));
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.as_mut().ok_or_else(not_open)?.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.reader.as_mut().ok_or_else(not_open)?.read_vectored(bufs)
+    }
+
+    fn open_random(&mut self, direct: bool) -> io::Result<()> {
+        self.reader = None;
+        self.random = Some(open_read_write(&self.path, direct)?);
+        Ok(())
+    }
+
+    fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<()> {
+        let f = self.random.as_mut().ok_or_else(not_open)?;
+        f.seek(SeekFrom::Start(offset))?;
+        f.write_all(buf)
+    }
+
+    fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        let f = self.random.as_mut().ok_or_else(not_open)?;
+        f.seek(SeekFrom::Start(offset))?;
+        f.read_exact(buf)
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        self.reader = None;
+        self.random = None;
+        std::fs::remove_file(&self.path)
+    }
+
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// A block device (e.g. `/dev/sdb`) written from offset 0. **Destroys the data on it.**
+///
+/// Reads stop after the number of bytes written, since the device itself has no end-of-test marker.
+pub struct RawDeviceBackend {
+    path: PathBuf,
+    file: Option<File>,
+    written: u64,
+    remaining: u64,
+}
+
+impl RawDeviceBackend {
+    pub fn new(path: impl Into<PathBuf>) -> RawDeviceBackend {
+        RawDeviceBackend { path: path.into(), file: None, written: 0, remaining: 0 }
+    }
+}
+
+impl StorageBackend for RawDeviceBackend {
+    fn open_write(&mut self, _block: usize, direct: bool) -> io::Result<()> {
+        self.file = Some(open_existing_write(&self.path, direct)?);
+        self.written = 0;
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.file.as_mut().ok_or_else(not_open)?.write_all(buf)?;
+        self.written += buf.len() as u64;
+        Ok(())
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+        write_all_vectored(self.file.as_mut().ok_or_else(not_open)?, bufs)?;
+        self.written += bufs.iter().map(|b| b.len() as u64).sum::<u64>();
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        let t = std::time::Instant::now();
+        self.file.as_ref().ok_or_else(not_open)?.sync_all()?;
+        crate::debug!("sync_all {} took {:.1} ms", self.path.display(), t.elapsed().as_secs_f64() * 1e3);
+        Ok(())
+    }
+
+    /// The device's page cache holds what was just written; dirty pages can't be dropped, so
+    /// they are flushed first.
+    fn evict(&mut self) -> io::Result<()> {
+        if let Some(f) = &self.file { f.sync_all()?; }
+        evict(&self.path)
+    }
+
+    fn open_read(&mut self, _block: usize, direct: bool) -> io::Result<()> {
+        self.file = Some(open_read(&self.path, direct)?);
+        self.remaining = self.written;
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let want = buf.len().min(self.remaining as usize);
+        if want == 0 { return Ok(0); }
+        let n = self.file.as_mut().ok_or_else(not_open)?.read(&mut buf[..want])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+
+    fn open_random(&mut self, direct: bool) -> io::Result<()> {
+        self.file = Some(open_read_write(&self.path, direct)?);
+        Ok(())
+    }
+
+    fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<()> {
+        let f = self.file.as_mut().ok_or_else(not_open)?;
+        f.seek(SeekFrom::Start(offset))?;
+        f.write_all(buf)
+    }
+
+    fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        let f = self.file.as_mut().ok_or_else(not_open)?;
+        f.seek(SeekFrom::Start(offset))?;
+        f.read_exact(buf)
+    }
+
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// Keeps everything in a `Vec`, optionally sleeping per call to simulate a slow device.
+///
+/// ```
+/// use crabwise_core::{BenchConfig, BenchRunner, MemoryBackend, NoProgress};
+///
+/// let mut config = BenchConfig::new("unused");
+/// config.size = 1024 * 1024;
+/// config.block = 64 * 1024;
+/// let result = BenchRunner::new(config).run_with(&mut MemoryBackend::new(), &mut NoProgress)?;
+/// assert_eq!(result.read.bytes, 1024 * 1024);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Default)]
+pub struct MemoryBackend {
+    data: Vec<u8>,
+    pos: usize,
+    /// Artificial delay added to every `write` call.
+    pub write_delay: Option<Duration>,
+    /// Artificial delay added to every `read` call.
+    pub read_delay: Option<Duration>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+
+    /// Bytes currently held.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn open_write(&mut self, _block: usize, _direct: bool) -> io::Result<()> {
+        self.data.clear();
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        if let Some(d) = self.write_delay { std::thread::sleep(d); }
+        self.data.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> { Ok(()) }
+
+    fn open_read(&mut self, _block: usize, _direct: bool) -> io::Result<()> {
+        self.pos = 0;
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(d) = self.read_delay { std::thread::sleep(d); }
+        let n = buf.len().min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn open_random(&mut self, _direct: bool) -> io::Result<()> { Ok(()) }
+
+    fn write_at(&mut self, buf: &[u8], offset: u64) -> io::Result<()> {
+        if let Some(d) = self.write_delay { std::thread::sleep(d); }
+        let dst = self.data.get_mut(offset as usize..offset as usize + buf.len()).ok_or_else(past_end)?;
+        dst.copy_from_slice(buf);
+        Ok(())
+    }
+
+    fn read_at(&mut self, buf: &mut [u8], offset: u64) -> io::Result<()> {
+        if let Some(d) = self.read_delay { std::thread::sleep(d); }
+        let src = self.data.get(offset as usize..offset as usize + buf.len()).ok_or_else(past_end)?;
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        self.data = Vec::new();
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+fn not_open() -> io::Error {
+    io::Error::other("storage backend used before it was opened")
+}
+
+fn no_random() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "this storage backend does not support random I/O")
+}
+
+fn past_end() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "offset is past the end of the written data")
+}
+
+/// `write_vectored` until every byte of `bufs` is written (`Write::write_all_vectored` is unstable).
+/// Platforms without a gather write (Windows) still get one call per buffer.
+fn write_all_vectored(w: &mut impl Write, bufs: &[IoSlice<'_>]) -> io::Result<()> {
+    let mut slices = bufs.to_vec();
+    let mut rest = &mut slices[..];
+    while !rest.is_empty() {
+        match w.write_vectored(rest) {
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer")),
+            Ok(n) => IoSlice::advance_slices(&mut rest, n),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! Benchmark parameters.
+
+use crate::artifacts;
+use crate::guard::AbortBelow;
+use crate::thresholds::Thresholds;
+use std::io;
+use std::path::PathBuf;
+
+/// Default total test size (1 GiB).
+pub const DEFAULT_SIZE: u64 = 1024 * 1024 * 1024;
+/// Default block size (4 MiB).
+pub const DEFAULT_BLOCK: u64 = 4 * 1024 * 1024;
+/// Blocks per call with `vectored`.
+pub const VECTORED_BLOCKS: usize = 64;
+
+/// Order in which the read pass visits the test file's blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadPattern {
+    /// Front to back as one stream.
+    #[default]
+    Sequential,
+    /// Last block first, back to the start.
+    Reverse,
+    /// Every n-th block, then the same again starting one block later, until all are read.
+    Stride(u64),
+}
+
+impl ReadPattern {
+    /// `(offset, length)` of each read for a file of `total` bytes in `block`-byte blocks.
+    pub fn offsets(self, total: u64, block: u64) -> Vec<(u64, usize)> {
+        let blocks = total.div_ceil(block);
+        let at = |i: u64| (i * block, block.min(total - i * block) as usize);
+        match self {
+            ReadPattern::Sequential => (0..blocks).map(at).collect(),
+            ReadPattern::Reverse => (0..blocks).rev().map(at).collect(),
+            ReadPattern::Stride(n) => {
+                let n = n.clamp(1, blocks.max(1));
+                (0..n).flat_map(|start| (start..blocks).step_by(n as usize)).map(at).collect()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ReadPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadPattern::Sequential => f.write_str("seq"),
+            ReadPattern::Reverse => f.write_str("reverse"),
+            ReadPattern::Stride(n) => write!(f, "stride:{n}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    /// Directory on the device under test; the test file is created here.
+    pub target_dir: PathBuf,
+    /// Total bytes written and read back.
+    pub size: u64,
+    /// Bytes per write/read call.
+    pub block: u64,
+    /// Probe a range of block sizes first (see [`tune`](crate::tune)) and use the one where
+    /// throughput levels off instead of `block`.
+    pub auto_block: bool,
+    /// Open the test file with synchronous / uncached I/O so results reflect the device.
+    pub direct: bool,
+    /// Move up to [`VECTORED_BLOCKS`] blocks per system call in the sequential passes
+    /// (writev/readv) instead of one, so syscall overhead doesn't hide small-block speed.
+    pub vectored: bool,
+    /// Leave the test file in place after the run, with a manifest for `crabwise verify`.
+    pub keep_file: bool,
+    /// Sequential write/read passes; results are aggregated across them.
+    pub iterations: u32,
+    /// Add a 4 KiB random write/read stage after the sequential passes.
+    pub random: bool,
+    /// Compare every byte read back against what was written.
+    pub verify: bool,
+    /// Block order of the read pass.
+    pub read_pattern: ReadPattern,
+    /// Also write and read this many test files at once, splitting `size` between them (1 = off).
+    pub files: u32,
+    /// Re-read the test file's blocks in a random permutation after the sequential passes.
+    pub shuffled: bool,
+    /// Evict the test file before the read pass, then read it twice more through the OS cache
+    /// and time the second, to show the device read next to what the cache serves.
+    pub cached_read: bool,
+    /// Reserve the test file's full size on disk before each write pass, so the pass times
+    /// data transfer rather than allocation and a full drive fails before it starts.
+    pub preallocate: bool,
+    /// Finish with a sequential write lasting this many seconds, to expose cache exhaustion.
+    pub sustained: Option<f64>,
+    /// Also evict the small-file and parallel test files before reading them back (network
+    /// shares); the main test file is always evicted before its read pass.
+    pub drop_cache: bool,
+    /// Write, read back and reopen this many small files after the other stages (0 = off).
+    pub small_files: u32,
+    /// Queue depths to run the random-read sweep at, in order (empty = off).
+    pub sweep_qd: Vec<u32>,
+    /// Time uncached single-block reads at random offsets, one in flight at a time.
+    pub access_time: bool,
+    /// Time this many [`FSYNC_IO_BYTES`](crate::runner::FSYNC_IO_BYTES) appends, each followed by an fsync (0 = off).
+    pub fsync_ops: u32,
+    /// TRIM the filesystem's free space once the test file is removed.
+    pub trim: bool,
+    /// Rewrite the test file over the blocks just freed, then again after a TRIM, to show what
+    /// discarding does for write speed.
+    pub trim_compare: bool,
+    /// Checksum the filesystem's boot sectors and superblocks before and after the run (read
+    /// from the raw device, see [`fscheck::snapshot`](crate::fscheck::snapshot)) and fail the
+    /// run if they changed.
+    pub corruption_check: bool,
+    /// Before the passes, compare zero-fill with random-data writes to catch a compressing controller.
+    pub compression_check: bool,
+    /// Time the write and read per 1% of the test file ([`OffsetMap`](crate::offsetmap::OffsetMap)).
+    pub offset_map: bool,
+    /// Stop the run with a [`BelowExpectations`](crate::guard::BelowExpectations) error once
+    /// sequential throughput stays under the floor for the window.
+    pub abort_below: Option<AbortBelow>,
+    /// Seed for the test pattern; varying it between runs keeps data left by an
+    /// earlier run from passing verification.
+    pub seed: u64,
+    /// `key=value` labels copied into the result, e.g. the port or cable used.
+    pub tags: Vec<(String, String)>,
+    pub thresholds: Thresholds,
+}
+
+impl BenchConfig {
+    /// A config with the default size, block and direct I/O for `target_dir`.
+    pub fn new(target_dir: impl Into<PathBuf>) -> BenchConfig {
+        BenchConfig {
+            target_dir: target_dir.into(),
+            size: DEFAULT_SIZE,
+            block: DEFAULT_BLOCK,
+            auto_block: false,
+            direct: true,
+            vectored: false,
+            keep_file: false,
+            iterations: 1,
+            random: false,
+            verify: false,
+            read_pattern: ReadPattern::Sequential,
+            files: 1,
+            shuffled: false,
+            cached_read: false,
+            preallocate: false,
+            sustained: None,
+            drop_cache: false,
+            small_files: 0,
+            sweep_qd: Vec::new(),
+            access_time: false,
+            fsync_ops: 0,
+            trim: false,
+            trim_compare: false,
+            corruption_check: false,
+            compression_check: false,
+            offset_map: false,
+            abort_below: None,
+            seed: 0x5EED_CAFE,
+            tags: Vec::new(),
+            thresholds: Thresholds::default(),
+        }
+    }
+
+    /// The test file, named uniquely for this process (see [`artifacts`](crate::artifacts)).
+    pub fn test_path(&self) -> PathBuf {
+        self.target_dir.join(artifacts::name(".tmp"))
+    }
+
+    pub fn validate(&self) -> io::Result<()> {
+        if self.block == 0 || self.size < self.block {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "block must be >0 and <= total size"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_pattern_offsets() {
+        let seq = ReadPattern::Sequential.offsets(10, 4);
+        assert_eq!(seq, [(0, 4), (4, 4), (8, 2)]);
+        assert_eq!(ReadPattern::Reverse.offsets(10, 4), [(8, 2), (4, 4), (0, 4)]);
+        let stride = ReadPattern::Stride(2).offsets(20, 4);
+        assert_eq!(stride, [(0, 4), (8, 4), (16, 4), (4, 4), (12, 4)]);
+        assert_eq!(ReadPattern::Sequential.offsets(0, 4), []);
+    }
+
+    #[test]
+    fn every_pattern_reads_each_block_once() {
+        for pattern in [ReadPattern::Sequential, ReadPattern::Reverse, ReadPattern::Stride(3), ReadPattern::Stride(1000)] {
+            let mut offsets = pattern.offsets(1000, 64);
+            offsets.sort_unstable();
+            assert_eq!(offsets, ReadPattern::Sequential.offsets(1000, 64), "{pattern}");
+        }
+    }
+}
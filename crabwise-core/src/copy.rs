@@ -0,0 +1,209 @@
+//! Device-to-device copy: reads a test file from one target while writing it to another, as
+//! an offload from a card to a disk would, then checks the copy. Each side is also measured
+//! alone, so the result says which one held the copy back.
+
+use crate::file::{evict, open_read, open_write, read_exact_at, write_all_at};
+use crate::json::Value;
+use crate::progress::{Phase, Progress, Update};
+use crate::sampler::Sampler;
+use crate::stage::{run_stage, Mode, Stage};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Instant;
+
+/// Blocks the reader may get ahead of the writer.
+const QUEUE: usize = 4;
+/// A copy this close to the slower side's own rate is limited by that side alone.
+const EXPECTED: f64 = 0.8;
+/// Sides within this ratio of each other are equally slow.
+const EVEN: f64 = 0.9;
+
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub size: u64,
+    pub block: u64,
+    pub direct: bool,
+}
+
+/// What limited the copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bottleneck {
+    Source,
+    Destination,
+    /// Both sides alone run at about the copy's rate.
+    Both,
+    /// The copy ran well below either side alone: the drives share a hub, controller or
+    /// bus, or the host can't keep both busy.
+    Shared,
+}
+
+impl Bottleneck {
+    pub fn id(self) -> &'static str {
+        match self {
+            Bottleneck::Source => "source",
+            Bottleneck::Destination => "destination",
+            Bottleneck::Both => "both",
+            Bottleneck::Shared => "shared",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CopyResult {
+    pub bytes: u64,
+    pub block: u64,
+    /// The source read by itself.
+    pub source_read_mb_s: f64,
+    /// The destination written by itself.
+    pub dest_write_mb_s: f64,
+    pub copy_seconds: f64,
+    /// Bytes of the copy that differ from the source.
+    pub mismatches: u64,
+}
+
+impl CopyResult {
+    pub fn copy_mb_s(&self) -> f64 {
+        crate::stats::mbs(self.bytes as u128, self.copy_seconds)
+    }
+
+    /// The copy's rate as a share of the slower side's own.
+    pub fn efficiency(&self) -> f64 {
+        let slower = self.source_read_mb_s.min(self.dest_write_mb_s);
+        if slower > 0.0 { self.copy_mb_s() / slower } else { 0.0 }
+    }
+
+    pub fn bottleneck(&self) -> Bottleneck {
+        let (src, dst) = (self.source_read_mb_s, self.dest_write_mb_s);
+        if self.efficiency() < EXPECTED {
+            Bottleneck::Shared
+        } else if src.min(dst) >= src.max(dst) * EVEN {
+            Bottleneck::Both
+        } else if src < dst {
+            Bottleneck::Source
+        } else {
+            Bottleneck::Destination
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("bytes", self.bytes.into()),
+            ("block_bytes", self.block.into()),
+            ("source_read_mb_s", self.source_read_mb_s.into()),
+            ("dest_write_mb_s", self.dest_write_mb_s.into()),
+            ("copy_seconds", self.copy_seconds.into()),
+            ("copy_mb_s", self.copy_mb_s().into()),
+            ("efficiency", self.efficiency().into()),
+            ("bottleneck", self.bottleneck().id().into()),
+            ("mismatches", self.mismatches.into()),
+        ])
+    }
+}
+
+/// Writes a `size`-byte test file to `from` and times reading it back, times writing one
+/// to `to`, then copies the first to the second, times it, and compares the two. The test
+/// files are left in place; [`test_paths`] names them.
+pub fn copy_bench(opts: &CopyOptions, progress: &mut dyn Progress) -> io::Result<CopyResult> {
+    let same = |a: &Path, b: &Path| std::fs::canonicalize(a).ok().zip(std::fs::canonicalize(b).ok()).is_some_and(|(a, b)| a == b);
+    if same(&opts.from, &opts.to) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the source and destination are the same directory"));
+    }
+    let stage = |dir: &Path, mode| Stage { mode, block: opts.block, size: opts.size / opts.block * opts.block, direct: opts.direct, ..Stage::new("copy", dir) };
+    let (src, dst) = (stage(&opts.from, Mode::Write), stage(&opts.to, Mode::Write));
+
+    // The source's write only puts the data there; its read, from a cold cache, is the probe.
+    run_stage(&src, progress)?;
+    evict(&src.test_path())?;
+    let source = run_stage(&Stage { mode: Mode::Read, ..src.clone() }, progress)?;
+    let dest = run_stage(&dst, progress)?;
+    let rate = |p: &Option<crate::PhaseStats>| p.as_ref().map_or(0.0, |p| p.mb_s());
+
+    let (from, to) = (src.test_path(), dst.test_path());
+    evict(&from)?;
+    let copy_seconds = stream(&from, &to, src.size, opts.block, opts.direct, progress)?;
+    evict(&from)?;
+    evict(&to)?;
+    let mismatches = compare(&from, &to, src.size, opts.block, progress)?;
+
+    let r = CopyResult {
+        bytes: src.size,
+        block: opts.block,
+        source_read_mb_s: rate(&source.read),
+        dest_write_mb_s: rate(&dest.write),
+        copy_seconds,
+        mismatches,
+    };
+    crate::info!(
+        "copy {} -> {}: {:.1} MB/s (source {:.1}, destination {:.1}), bottleneck {}, {} mismatched bytes",
+        opts.from.display(), opts.to.display(), r.copy_mb_s(), r.source_read_mb_s, r.dest_write_mb_s, r.bottleneck().id(), mismatches
+    );
+    Ok(r)
+}
+
+/// The test files [`copy_bench`] leaves on the source and destination.
+pub fn test_paths(opts: &CopyOptions) -> [PathBuf; 2] {
+    [Stage::new("copy", &opts.from).test_path(), Stage::new("copy", &opts.to).test_path()]
+}
+
+/// Copies `from` to `to` with a reader thread running up to [`QUEUE`] blocks ahead of the
+/// writer, so both devices stay busy; returns the seconds taken, final sync included.
+fn stream(from: &Path, to: &Path, size: u64, block: u64, direct: bool, progress: &mut dyn Progress) -> io::Result<f64> {
+    let src = open_read(from, direct)?;
+    let dst = open_write(to, direct)?;
+    let start = Instant::now();
+    let mut sampler = Sampler::new(start);
+    let mut latencies = Vec::new();
+    let (tx, rx) = mpsc::sync_channel::<io::Result<Vec<u8>>>(QUEUE);
+    std::thread::scope(|s| {
+        let src = &src;
+        s.spawn(move || {
+            let mut pos = 0;
+            while pos < size {
+                let mut buf = vec![0u8; block.min(size - pos) as usize];
+                let r = read_exact_at(src, &mut buf, pos).map(|()| buf);
+                let failed = r.is_err();
+                // The writer has stopped, or the read failed and the error is on its way.
+                if tx.send(r).is_err() || failed { return; }
+                pos += block;
+            }
+        });
+        let mut done = 0;
+        for buf in rx {
+            let buf = buf?;
+            let t = Instant::now();
+            write_all_at(&dst, &buf, done)?;
+            latencies.push(t.elapsed().as_secs_f64());
+            done += buf.len() as u64;
+            sampler.tick(done, &latencies);
+            progress.update(&Update { phase: Phase::Copy, done, total: size, start, samples: sampler.samples(), latencies: &latencies })?;
+        }
+        Ok::<_, io::Error>(())
+    })?;
+    dst.sync_all()?;
+    let seconds = start.elapsed().as_secs_f64();
+    progress.finish(Phase::Copy)?;
+    Ok(seconds)
+}
+
+/// Reads both files back and counts the bytes that differ.
+fn compare(a: &Path, b: &Path, size: u64, block: u64, progress: &mut dyn Progress) -> io::Result<u64> {
+    let (fa, fb) = (open_read(a, false)?, open_read(b, false)?);
+    let (mut ba, mut bb) = (vec![0u8; block as usize], vec![0u8; block as usize]);
+    let start = Instant::now();
+    let mut sampler = Sampler::new(start);
+    let (mut pos, mut mismatches) = (0, 0);
+    while pos < size {
+        let n = block.min(size - pos) as usize;
+        read_exact_at(&fa, &mut ba[..n], pos)?;
+        read_exact_at(&fb, &mut bb[..n], pos)?;
+        mismatches += ba[..n].iter().zip(&bb[..n]).filter(|(x, y)| x != y).count() as u64;
+        pos += n as u64;
+        sampler.tick(pos, &[]);
+        progress.update(&Update { phase: Phase::Verify, done: pos, total: size, start, samples: sampler.samples(), latencies: &[] })?;
+    }
+    progress.finish(Phase::Verify)?;
+    Ok(mismatches)
+}
@@ -0,0 +1,66 @@
+//! Host CPU load while a phase runs, so a host bottleneck can be told apart from a slow drive.
+
+use sysinfo::{Pid, System};
+
+/// CPU load over one phase, in percent.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpuUsage {
+    /// This process, relative to one core (a single pegged thread reads 100).
+    pub process_pct: f64,
+    /// All cores together, as the OS reports overall load.
+    pub system_pct: f64,
+    /// The busiest single core.
+    pub busiest_core_pct: f64,
+}
+
+impl CpuUsage {
+    /// A core this busy is the likelier limit than the drive.
+    pub const BOUND_PCT: f64 = 90.0;
+
+    /// Whether the process or any one core was close to saturated.
+    pub fn host_bound(&self) -> bool {
+        self.process_pct >= Self::BOUND_PCT || self.busiest_core_pct >= Self::BOUND_PCT
+    }
+
+    /// Time-weighted mean of `parts` as `(seconds, usage)`, for phases run in several passes.
+    pub fn weighted(parts: &[(f64, CpuUsage)]) -> Option<CpuUsage> {
+        let total: f64 = parts.iter().map(|(s, _)| s).sum();
+        if parts.is_empty() || total <= 0.0 { return parts.first().map(|p| p.1); }
+        let avg = |f: fn(&CpuUsage) -> f64| parts.iter().map(|(s, u)| s * f(u)).sum::<f64>() / total;
+        Some(CpuUsage {
+            process_pct: avg(|u| u.process_pct),
+            system_pct: avg(|u| u.system_pct),
+            busiest_core_pct: avg(|u| u.busiest_core_pct),
+        })
+    }
+}
+
+/// Started when a phase begins and read when it ends.
+///
+/// The OS counters behind this are coarse: phases shorter than a few hundred
+/// milliseconds give rough figures.
+pub struct CpuMeter {
+    sys: System,
+    pid: Option<Pid>,
+}
+
+impl CpuMeter {
+    pub fn start() -> CpuMeter {
+        let mut sys = System::new();
+        let pid = sysinfo::get_current_pid().ok();
+        sys.refresh_cpu_usage();
+        if let Some(pid) = pid { sys.refresh_process(pid); }
+        CpuMeter { sys, pid }
+    }
+
+    /// Load since [`start`](Self::start).
+    pub fn stop(mut self) -> CpuUsage {
+        self.sys.refresh_cpu_usage();
+        let process_pct = match self.pid {
+            Some(pid) if self.sys.refresh_process(pid) => self.sys.process(pid).map_or(0.0, |p| f64::from(p.cpu_usage())),
+            _ => 0.0,
+        };
+        let busiest_core_pct = self.sys.cpus().iter().map(|c| f64::from(c.cpu_usage())).fold(0.0, f64::max);
+        CpuUsage { process_pct, system_pct: f64::from(self.sys.global_cpu_info().cpu_usage()), busiest_core_pct }
+    }
+}
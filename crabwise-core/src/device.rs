@@ -0,0 +1,515 @@
+//! Enumerating candidate devices and describing the volume behind a path.
+
+use std::path::{Path, PathBuf};
+use sysinfo::Disks;
+
+/// Details about the volume that backs the target directory.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub mount_point: String,
+    pub file_system: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    pub removable: bool,
+}
+
+/// How a disk is attached, as far as the OS tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bus {
+    Usb,
+    /// SD/MMC card slots and readers on their own bus.
+    Sd,
+    Sata,
+    Nvme,
+    /// SCSI and SAS.
+    Scsi,
+    /// Virtual disks, VHDs and storage pools.
+    Virtual,
+    Other,
+}
+
+impl std::fmt::Display for Bus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Bus::Usb => "USB",
+            Bus::Sd => "SD/MMC",
+            Bus::Sata => "SATA",
+            Bus::Nvme => "NVMe",
+            Bus::Scsi => "SCSI/SAS",
+            Bus::Virtual => "virtual",
+            Bus::Other => "other",
+        })
+    }
+}
+
+/// The bus of the disk behind a volume such as `E:\`, from `IOCTL_STORAGE_QUERY_PROPERTY`.
+#[cfg(target_os = "windows")]
+fn windows_bus(mount: &Path) -> Option<Bus> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Ioctl::{
+        PropertyStandardQuery, StorageDeviceProperty, IOCTL_STORAGE_QUERY_PROPERTY, STORAGE_DEVICE_DESCRIPTOR, STORAGE_PROPERTY_QUERY,
+    };
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    let volume = format!(r"\\.\{}", mount.display().to_string().trim_end_matches('\\'));
+    // No access rights are needed to ask for the descriptor, so this works without elevation.
+    let f = std::fs::OpenOptions::new().access_mode(0).share_mode(3).open(&volume).ok()?;
+    let query = STORAGE_PROPERTY_QUERY { PropertyId: StorageDeviceProperty, QueryType: PropertyStandardQuery, AdditionalParameters: [0] };
+    let mut desc: STORAGE_DEVICE_DESCRIPTOR = unsafe { std::mem::zeroed() };
+    let mut returned = 0u32;
+    let ok = unsafe {
+        DeviceIoControl(
+            f.as_raw_handle(), IOCTL_STORAGE_QUERY_PROPERTY,
+            (&query as *const STORAGE_PROPERTY_QUERY).cast(), std::mem::size_of::<STORAGE_PROPERTY_QUERY>() as u32,
+            (&mut desc as *mut STORAGE_DEVICE_DESCRIPTOR).cast(), std::mem::size_of::<STORAGE_DEVICE_DESCRIPTOR>() as u32,
+            &mut returned, std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        crate::debug!("{volume}: IOCTL_STORAGE_QUERY_PROPERTY failed: {}", std::io::Error::last_os_error());
+        return None;
+    }
+    // STORAGE_BUS_TYPE values from winioctl.h.
+    let bus = match desc.BusType as i32 {
+        0x07 => Bus::Usb,
+        0x0C | 0x0D => Bus::Sd,
+        0x02 | 0x03 | 0x0B => Bus::Sata,
+        0x11 => Bus::Nvme,
+        0x01 | 0x0A => Bus::Scsi,
+        0x0E | 0x0F | 0x10 => Bus::Virtual,
+        _ => Bus::Other,
+    };
+    crate::trace!("{volume}: bus type {} ({bus})", desc.BusType as i32);
+    Some(bus)
+}
+
+/// The bus of a `/sys/block` disk, from the subsystems of the devices above it: a SCSI disk
+/// under a USB interface is USB, under an `ataN` port SATA. Device-mapper and md volumes take
+/// the bus of the first disk they sit on (so LUKS on a stick still counts as USB).
+#[cfg(target_os = "linux")]
+fn linux_bus(disk: &Path) -> Option<Bus> {
+    if disk.starts_with("/sys/devices/virtual") {
+        let slave = std::fs::read_dir(disk.join("slaves")).ok()?.flatten().next();
+        return match slave {
+            Some(s) => {
+                let dir = std::fs::canonicalize(s.path()).ok()?;
+                let dir = if dir.join("partition").exists() { dir.parent()?.to_path_buf() } else { dir };
+                linux_bus(&dir)
+            }
+            // loop, zram, ram disks
+            None => Some(Bus::Virtual),
+        };
+    }
+    let mut subsystems = Vec::new();
+    let mut ata = false;
+    let mut dir = disk.to_path_buf();
+    while dir.pop() && dir != Path::new("/sys/devices") {
+        if let Ok(link) = std::fs::read_link(dir.join("subsystem")) {
+            subsystems.push(link.file_name().unwrap_or_default().to_string_lossy().to_string());
+        }
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+        ata |= name.strip_prefix("ata").is_some_and(|n| n.starts_with(|c: char| c.is_ascii_digit()));
+    }
+    let has = |s: &str| subsystems.iter().any(|x| x == s);
+    let bus = if has("usb") { Bus::Usb }
+        else if has("mmc") { Bus::Sd }
+        else if has("nvme") { Bus::Nvme }
+        else if ata { Bus::Sata }
+        else if has("virtio") || has("xen") || has("vmbus") { Bus::Virtual }
+        else if has("scsi") { Bus::Scsi }
+        else { Bus::Other };
+    crate::trace!("{}: bus {bus} (subsystems {})", disk.display(), subsystems.join(" "));
+    Some(bus)
+}
+
+/// The bus of the disk behind `info`, where the OS tells (sysfs on Linux,
+/// `IOCTL_STORAGE_QUERY_PROPERTY` on Windows, DiskArbitration on macOS).
+pub fn bus(info: &DeviceInfo) -> Option<Bus> {
+    #[cfg(target_os = "windows")]
+    { windows_bus(Path::new(&info.mount_point)) }
+    #[cfg(target_os = "linux")]
+    { linux_bus(&sys_block_dir(info)?) }
+    #[cfg(target_os = "macos")]
+    { crate::macos::volume(Path::new(&info.mount_point))?.bus() }
+    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+    { let _ = info; None }
+}
+
+/// `sysinfo` disk as far as [`bus`] needs it.
+fn disk_info(d: &sysinfo::Disk) -> DeviceInfo {
+    DeviceInfo {
+        name: d.name().to_string_lossy().to_string(),
+        mount_point: d.mount_point().display().to_string(),
+        removable: d.is_removable(),
+        ..DeviceInfo::default()
+    }
+}
+
+/// `" [USB]"`, or with `extra` appended: `" [SATA, internal]"`.
+fn bus_tag(bus: Option<Bus>, extra: Option<&str>) -> String {
+    let parts: Vec<String> = bus.map(|b| b.to_string()).into_iter().chain(extra.map(str::to_string)).collect();
+    if parts.is_empty() { String::new() } else { format!(" [{}]", parts.join(", ")) }
+}
+
+/// Lists the mounts offered in the device picker as `(label, mount point)`: drives on a USB or
+/// SD bus, or reported removable where the bus can't be told; on macOS every external volume
+/// under `/Volumes`. Labels name the bus (and on macOS the USB link speed).
+pub fn candidate_devices() -> Vec<(String, PathBuf)> {
+    let t = std::time::Instant::now();
+    let disks = Disks::new_with_refreshed_list();
+
+    // Gather candidates
+    let mut candidates: Vec<(String, PathBuf)> = Vec::new();
+    #[cfg(target_os = "macos")]
+    for (mount, v) in crate::macos::external_volumes() {
+        let name = v.usb_name.clone().or_else(|| v.model.clone())
+            .unwrap_or_else(|| mount.file_name().unwrap_or_default().to_string_lossy().to_string());
+        let speed = v.usb_speed.map(|bps| if bps >= 1_000_000_000 { format!("{} Gb/s", bps / 1_000_000_000) } else { format!("{} Mb/s", bps as f64 / 1e6) });
+        candidates.push((format!("{} — {}{}", name, mount.display(), bus_tag(v.bus(), speed.as_deref())), mount));
+    }
+    #[cfg(not(target_os = "macos"))]
+    for d in disks.list() {
+        let mount = d.mount_point().to_path_buf();
+        let name = d.name().to_string_lossy().to_string();
+        let bus = bus(&disk_info(d));
+        let keep = match bus {
+            // USB HDD enclosures often report non-removable; eSATA and internal card slots removable.
+            Some(b) => matches!(b, Bus::Usb | Bus::Sd) || (cfg!(target_os = "windows") && d.is_removable()),
+            // When the bus can't be read: on Windows everything but the system drive.
+            #[cfg(target_os = "windows")]
+            None => {
+                let system = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".into());
+                !mount.display().to_string().to_ascii_uppercase().starts_with(&system.to_ascii_uppercase())
+            }
+            #[cfg(not(target_os = "windows"))]
+            None => d.is_removable(),
+        };
+        if keep {
+            candidates.push((format!("{} — {}{}", name, mount.display(), bus_tag(bus, None)), mount.clone()));
+        }
+    }
+
+    // De-dup (some OSes report multiple entries for same mount point)
+    candidates.sort_by(|a,b| a.1.cmp(&b.1));
+    candidates.dedup_by(|a,b| a.1 == b.1);
+    crate::debug!("enumerated {} disk(s), {} candidate(s) in {:.1} ms", disks.list().len(), candidates.len(), t.elapsed().as_secs_f64() * 1e3);
+    for (label, _) in &candidates { crate::trace!("candidate: {label}"); }
+    candidates
+}
+
+/// File systems that are never real storage (RAM, kernel interfaces, read-only images).
+const PSEUDO_FS: &[&str] = &["tmpfs", "devtmpfs", "ramfs", "overlay", "squashfs", "proc", "sysfs", "efivarfs", "autofs", "devfs", "nullfs"];
+
+/// File systems served over the network.
+const NETWORK_FS: &[&str] = &["nfs", "nfs4", "cifs", "smb3", "smbfs", "afpfs", "webdav", "davfs", "fuse.sshfs", "9p"];
+
+fn is_network_fs(fs: &str) -> bool {
+    NETWORK_FS.iter().any(|n| fs.eq_ignore_ascii_case(n))
+}
+
+/// Whether the volume is an NFS/SMB/AFP/... share rather than a local disk.
+pub fn is_network(info: &DeviceInfo) -> bool {
+    is_network_fs(&info.file_system)
+        // Windows reports mapped drives and UNC paths by their \\server\share name.
+        || info.name.starts_with("\\\\") || info.mount_point.starts_with("\\\\")
+}
+
+/// Mounted network shares as `(label, mount point)`, labelled with the share path (e.g. `//nas/media`).
+pub fn network_devices() -> Vec<(String, PathBuf)> {
+    let disks = Disks::new_with_refreshed_list();
+    let mut out: Vec<(String, PathBuf)> = disks.list().iter()
+        .filter(|d| is_network_fs(&d.file_system().to_string_lossy()))
+        .map(|d| (format!("{} — {} [{}]", d.name().to_string_lossy(), d.mount_point().display(), d.file_system().to_string_lossy()), d.mount_point().to_path_buf()))
+        .collect();
+    out.sort_by(|a, b| a.1.cmp(&b.1));
+    out.dedup_by(|a, b| a.1 == b.1);
+    out
+}
+
+/// Mounts left out of [`candidate_devices`]: internal SATA/NVMe volumes, Thunderbolt storage and,
+/// on Windows, anything not on a USB or SD bus. Labels are marked `[internal]`, after the bus when known.
+pub fn internal_devices() -> Vec<(String, PathBuf)> {
+    let removable: Vec<PathBuf> = candidate_devices().into_iter().map(|(_, p)| p).collect();
+    let disks = Disks::new_with_refreshed_list();
+    let mut out: Vec<(String, PathBuf)> = disks.list().iter()
+        .filter(|d| { let fs = d.file_system().to_string_lossy(); !PSEUDO_FS.contains(&fs.as_ref()) && !is_network_fs(&fs) })
+        // macOS system sub-volumes (Preboot, VM, Update, ...); the Data volume is the one users write to.
+        .filter(|d| !d.mount_point().starts_with("/System/Volumes") || d.mount_point() == Path::new("/System/Volumes/Data"))
+        .filter(|d| !removable.iter().any(|p| p == d.mount_point()))
+        .map(|d| (format!("{} — {}{}", d.name().to_string_lossy(), d.mount_point().display(), bus_tag(bus(&disk_info(d)), Some("internal"))), d.mount_point().to_path_buf()))
+        .collect();
+    out.sort_by(|a, b| a.1.cmp(&b.1));
+    out.dedup_by(|a, b| a.1 == b.1);
+    for (label, _) in &out { crate::trace!("internal candidate: {label}"); }
+    out
+}
+
+/// A drive named on the command line (`--device`) rather than by its mount path, which changes
+/// between insertions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSpec {
+    /// `serial:ABC123`: the drive's serial number, WWN or [`fingerprint`].
+    Serial(String),
+    /// `E:`: a Windows drive letter.
+    Letter(char),
+    /// Anything else, matched case-insensitively against the picker label (device and volume
+    /// name, mount point) and the drive's vendor and model.
+    Label(String),
+}
+
+impl std::fmt::Display for DeviceSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceSpec::Serial(s) => write!(f, "serial:{s}"),
+            DeviceSpec::Letter(c) => write!(f, "{c}:"),
+            DeviceSpec::Label(s) => f.write_str(s),
+        }
+    }
+}
+
+impl DeviceSpec {
+    /// Whether the picker entry `(label, mount)` is the drive this names.
+    pub fn matches(&self, label: &str, mount: &Path) -> bool {
+        match self {
+            DeviceSpec::Letter(c) => mount.display().to_string().to_ascii_uppercase().starts_with(&format!("{c}:")),
+            DeviceSpec::Serial(s) => {
+                let Some(id) = device_info(mount).map(|i| identity(&i)) else { return false };
+                [id.serial.clone(), id.wwn.clone(), id.fingerprint()].into_iter().flatten().any(|x| x.eq_ignore_ascii_case(s))
+            }
+            DeviceSpec::Label(s) => {
+                let s = s.to_lowercase();
+                if label.to_lowercase().contains(&s) { return true; }
+                let Some(id) = device_info(mount).map(|i| identity(&i)) else { return false };
+                [id.vendor, id.model].into_iter().flatten().any(|x| x.to_lowercase().contains(&s))
+            }
+        }
+    }
+}
+
+/// Finds the mounted volume that contains `dir` (longest matching mount point).
+pub fn device_info(dir: &Path) -> Option<DeviceInfo> {
+    let t = std::time::Instant::now();
+    let dir = std::fs::canonicalize(dir).ok()?;
+    let disks = Disks::new_with_refreshed_list();
+    let info = disks.list().iter()
+        .filter(|d| dir.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| DeviceInfo {
+            name: d.name().to_string_lossy().to_string(),
+            mount_point: d.mount_point().display().to_string(),
+            file_system: d.file_system().to_string_lossy().to_string(),
+            total_bytes: d.total_space(),
+            available_bytes: d.available_space(),
+            removable: d.is_removable(),
+        });
+    match &info {
+        Some(i) => crate::debug!("{} is on {} ({}, mounted at {}) [{:.1} ms]", dir.display(), i.name, i.file_system, i.mount_point, t.elapsed().as_secs_f64() * 1e3),
+        None => crate::debug!("no mounted volume contains {}", dir.display()),
+    }
+    info
+}
+
+/// Mounted volumes on the disk behind the device node `device` (e.g. `/dev/sdb` or
+/// `/dev/disk4`), including those on its partitions. Empty when none are, and on Windows,
+/// where volumes aren't named by device node.
+pub fn mounted_on(device: &Path) -> Vec<DeviceInfo> {
+    let name = device.display().to_string();
+    let disks = Disks::new_with_refreshed_list();
+    let on_disk = |d: &str| -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            let disk = |n: &str| sys_block_dir(&DeviceInfo { name: n.to_string(), ..DeviceInfo::default() });
+            disk(d).is_some_and(|a| disk(&name) == Some(a))
+        }
+        // `/dev/disk4s1` is a slice of `/dev/disk4`; `/dev/rdisk4` is the same disk unbuffered.
+        #[cfg(target_os = "macos")]
+        {
+            let whole = |n: &str| {
+                let n = n.replacen("/dev/rdisk", "/dev/disk", 1);
+                match n.rfind('s') { Some(i) if i > "/dev/disk".len() => n[..i].to_string(), _ => n }
+            };
+            whole(d) == whole(&name)
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        { let _ = d; false }
+    };
+    disks.list().iter()
+        .filter(|d| on_disk(&d.name().to_string_lossy()))
+        .map(|d| DeviceInfo {
+            name: d.name().to_string_lossy().to_string(),
+            mount_point: d.mount_point().display().to_string(),
+            file_system: d.file_system().to_string_lossy().to_string(),
+            total_bytes: d.total_space(),
+            available_bytes: d.available_space(),
+            removable: d.is_removable(),
+        })
+        .collect()
+}
+
+/// The whole-disk `/sys/block` directory behind a device such as `/dev/sdb1` (Linux).
+#[cfg(target_os = "linux")]
+pub(crate) fn sys_block_dir(info: &DeviceInfo) -> Option<PathBuf> {
+    let name = Path::new(&info.name).file_name()?;
+    let dir = std::fs::canonicalize(Path::new("/sys/class/block").join(name)).ok()?;
+    // Partitions live inside their disk's directory.
+    if dir.join("partition").exists() { dir.parent().map(Path::to_path_buf) } else { Some(dir) }
+}
+
+/// What the hardware says it is, as far as the OS exposes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    /// USB vendor and product IDs, when the drive hangs off a USB bus.
+    pub vendor_id: Option<u16>,
+    pub product_id: Option<u16>,
+    pub vendor: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    /// World Wide Name (or NVMe EUI/NGUID) the disk reports, e.g. `naa.5000c500a1b2c3d4`.
+    pub wwn: Option<String>,
+}
+
+impl Identity {
+    /// A name for the physical drive that survives remounts and drive-letter changes: the WWN,
+    /// else the serial prefixed with the USB IDs (serials are only unique per vendor and product).
+    /// Whitespace inside either becomes `_`, so the result is one word.
+    pub fn fingerprint(&self) -> Option<String> {
+        let word = |s: &str| s.split_whitespace().collect::<Vec<_>>().join("_");
+        if let Some(w) = &self.wwn { return Some(word(w)); }
+        let serial = word(self.serial.as_ref()?);
+        Some(match (self.vendor_id, self.product_id) {
+            (Some(v), Some(p)) => format!("usb:{v:04x}:{p:04x}:{serial}"),
+            _ => serial,
+        })
+    }
+}
+
+/// Reads identification from sysfs (Linux only for now; empty elsewhere).
+pub fn identity(info: &DeviceInfo) -> Identity {
+    let mut id = Identity::default();
+    #[cfg(target_os = "linux")]
+    {
+        let read = |p: &Path| std::fs::read_to_string(p).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let Some(disk) = sys_block_dir(info) else { return id };
+        let Some(dev) = std::fs::canonicalize(disk.join("device")).ok() else { return id };
+        // NVMe namespaces have `wwid` on the block device, SCSI disks on the device.
+        id.wwn = read(&disk.join("wwid")).or_else(|| read(&dev.join("wwid")));
+        id.vendor = read(&dev.join("vendor"));
+        id.model = read(&dev.join("model"));
+        // virtio disks carry the serial on the block device, NVMe and some SCSI disks on the
+        // device, and USB drives on the USB device further up.
+        id.serial = read(&disk.join("serial"));
+        let mut dir = dev;
+        loop {
+            if id.serial.is_none() { id.serial = read(&dir.join("serial")); }
+            if let (Some(v), Some(p)) = (read(&dir.join("idVendor")), read(&dir.join("idProduct"))) {
+                id.vendor_id = u16::from_str_radix(&v, 16).ok();
+                id.product_id = u16::from_str_radix(&p, 16).ok();
+                if let Some(m) = read(&dir.join("manufacturer")) { id.vendor = Some(m); }
+                if id.model.is_none() { id.model = read(&dir.join("product")); }
+                break;
+            }
+            if !dir.pop() || dir == Path::new("/sys/devices") { break; }
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = info;
+    id
+}
+
+/// Hardware serial number of the drive, when the OS exposes one.
+pub fn serial(info: &DeviceInfo) -> Option<String> {
+    identity(info).serial
+}
+
+/// [`Identity::fingerprint`] of the drive behind `info`.
+pub fn fingerprint(info: &DeviceInfo) -> Option<String> {
+    let id = identity(info).fingerprint();
+    crate::debug!("{} fingerprint: {}", info.name, id.as_deref().unwrap_or("unknown"));
+    id
+}
+
+/// Drive temperature in °C from its own sensor (`drivetemp`/NVMe hwmon on Linux).
+pub fn temperature(info: &DeviceInfo) -> Option<f64> {
+    #[cfg(target_os = "linux")]
+    {
+        let hwmon = sys_block_dir(info)?.join("device").join("hwmon");
+        std::fs::read_dir(hwmon).ok()?.flatten()
+            .filter_map(|e| std::fs::read_to_string(e.path().join("temp1_input")).ok())
+            .find_map(|t| t.trim().parse::<f64>().ok())
+            .map(|milli| milli / 1000.0)
+    }
+    #[cfg(not(target_os = "linux"))]
+    { let _ = info; None }
+}
+
+/// Queue limits the kernel applies to the disk and, for a USB drive, the descriptors of the
+/// USB device and the driver bound to it (`uas` or `usb-storage`), as `(attribute, value)`
+/// pairs for bug reports. Serial numbers are left out. Linux only; empty elsewhere.
+pub fn descriptors(info: &DeviceInfo) -> Vec<(String, String)> {
+    #[cfg(target_os = "linux")]
+    {
+        let read = |p: &Path| std::fs::read_to_string(p).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let Some(disk) = sys_block_dir(info) else { return Vec::new() };
+        let mut out = Vec::new();
+        for q in ["rotational", "logical_block_size", "physical_block_size", "optimal_io_size", "max_sectors_kb", "max_hw_sectors_kb", "scheduler", "discard_max_bytes", "write_cache"] {
+            if let Some(v) = read(&disk.join("queue").join(q)) { out.push((format!("queue/{q}"), v)); }
+        }
+        let Ok(mut dir) = std::fs::canonicalize(disk.join("device")) else { return out };
+        let mut driver = None;
+        while !dir.join("idVendor").exists() {
+            // The interface just below the USB device has the storage driver bound to it.
+            if dir.join("bInterfaceClass").exists() {
+                driver = std::fs::read_link(dir.join("driver")).ok().and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()));
+            }
+            if !dir.pop() || dir == Path::new("/sys/devices") { return out; }
+        }
+        for a in ["idVendor", "idProduct", "manufacturer", "product", "version", "bcdDevice", "speed", "bMaxPower", "bmAttributes", "bNumConfigurations", "removable", "power/control"] {
+            if let Some(v) = read(&dir.join(a)) { out.push((format!("usb/{a}"), v)); }
+        }
+        if let Some(d) = driver { out.push(("usb/driver".into(), d)); }
+        out
+    }
+    #[cfg(not(target_os = "linux"))]
+    { let _ = info; Vec::new() }
+}
+
+/// Boundary partitions should start on; also the fallback when no optimal I/O size is reported.
+pub const ALIGN_BYTES: u64 = 1024 * 1024;
+
+/// Where one partition starts relative to the boundary it should sit on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionAlignment {
+    /// Kernel name, e.g. `sdb1`.
+    pub name: String,
+    pub start_bytes: u64,
+    /// [`ALIGN_BYTES`], or the disk's optimal I/O size when that is a larger power of two.
+    pub boundary: u64,
+}
+
+impl PartitionAlignment {
+    pub fn aligned(&self) -> bool {
+        self.start_bytes.is_multiple_of(self.boundary)
+    }
+}
+
+/// Start offsets of every partition on the disk behind `info`, from sysfs (Linux only; empty elsewhere).
+pub fn partition_alignment(info: &DeviceInfo) -> Vec<PartitionAlignment> {
+    #[cfg(target_os = "linux")]
+    {
+        let read = |p: &Path| std::fs::read_to_string(p).ok().and_then(|s| s.trim().parse::<u64>().ok());
+        let Some(disk) = sys_block_dir(info) else { return Vec::new() };
+        // Some USB bridges report nonsense here (0xFFFF sectors is common), so only a power of two counts.
+        let boundary = read(&disk.join("queue/optimal_io_size")).filter(|n| n.is_power_of_two() && *n > ALIGN_BYTES).unwrap_or(ALIGN_BYTES);
+        let mut parts: Vec<PartitionAlignment> = std::fs::read_dir(&disk).into_iter().flatten().flatten()
+            .filter(|e| e.path().join("partition").exists())
+            // sysfs counts `start` in 512-byte sectors whatever the logical block size.
+            .filter_map(|e| Some(PartitionAlignment { name: e.file_name().to_string_lossy().to_string(), start_bytes: read(&e.path().join("start"))? * 512, boundary }))
+            .collect();
+        parts.sort_by_key(|p| p.start_bytes);
+        parts
+    }
+    #[cfg(not(target_os = "linux"))]
+    { let _ = info; Vec::new() }
+}
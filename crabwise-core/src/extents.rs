@@ -0,0 +1,177 @@
+//! How many pieces the test file ended up in on disk. On a nearly full FAT or exFAT stick the
+//! file can be scattered over dozens of fragments, and the "sequential" read becomes a string
+//! of seeks, which quietly drags the read figure down.
+
+use crate::json::Value;
+use std::io;
+use std::path::Path;
+
+/// Fragments below this average size are small enough for the seeks between them to show in
+/// sequential throughput; a big file in a few large pieces reads as fast as a contiguous one.
+const CONCERN_AVG_BYTES: u64 = 16 << 20;
+/// Fewer fragments than this are noise whatever their size.
+const CONCERN_MIN_FRAGMENTS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Fragmentation {
+    /// Physically contiguous runs the file is stored in; 1 is a contiguous file.
+    pub fragments: u32,
+    /// Size of the largest fragment.
+    pub largest_bytes: u64,
+    /// Bytes the fragments cover.
+    pub file_bytes: u64,
+}
+
+impl Fragmentation {
+    /// Many small fragments: sequential passes over the file also measured the seeks between them.
+    pub fn likely_affects(&self) -> bool {
+        self.fragments >= CONCERN_MIN_FRAGMENTS && self.file_bytes / u64::from(self.fragments) < CONCERN_AVG_BYTES
+    }
+
+    /// e.g. `37 fragments, largest 6.0 MiB` or `contiguous`.
+    pub fn summary(&self) -> String {
+        if self.fragments <= 1 { return "contiguous".into(); }
+        format!("{} fragments, largest {:.1} MiB", self.fragments, self.largest_bytes as f64 / (1u64 << 20) as f64)
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("fragments", u64::from(self.fragments).into()),
+            ("largest_bytes", self.largest_bytes.into()),
+            ("file_bytes", self.file_bytes.into()),
+        ])
+    }
+
+    pub fn from_json(v: &Value) -> Option<Fragmentation> {
+        Some(Fragmentation {
+            fragments: v.get("fragments")?.as_u64()? as u32,
+            largest_bytes: v.get("largest_bytes").and_then(Value::as_u64).unwrap_or(0),
+            file_bytes: v.get("file_bytes").and_then(Value::as_u64).unwrap_or(0),
+        })
+    }
+}
+
+/// Folds `(physical start, length)` runs in file order into fragments: a run that starts where
+/// the previous one ended continues it (filesystems split extents at their own limits, such as
+/// ext4's 128 MiB, without the file being fragmented).
+fn merge(runs: impl IntoIterator<Item = (u64, u64)>) -> Fragmentation {
+    let mut f = Fragmentation::default();
+    let (mut end, mut current) = (None, 0u64);
+    for (start, len) in runs {
+        if end != Some(start) {
+            f.fragments += 1;
+            current = 0;
+        }
+        current += len;
+        f.largest_bytes = f.largest_bytes.max(current);
+        f.file_bytes += len;
+        end = Some(start + len);
+    }
+    f
+}
+
+/// The on-disk layout of the file at `path`: `FIEMAP` on Linux, `FSCTL_GET_RETRIEVAL_POINTERS`
+/// on Windows. Filesystems that don't map their files (tmpfs, network shares) and other
+/// systems fail with `Unsupported`.
+pub fn fragmentation(path: &Path) -> io::Result<Fragmentation> {
+    let f = runs(path).map(merge)?;
+    crate::debug!("{}: {} ({} bytes mapped)", path.display(), f.summary(), f.file_bytes);
+    Ok(f)
+}
+
+/// `(physical start, length)` of each run of the file (or directory) at `path` on its
+/// filesystem's device, in file order; same platforms as [`fragmentation`].
+#[cfg(target_os = "linux")]
+pub(crate) fn runs(path: &Path) -> io::Result<Vec<(u64, u64)>> {
+    use std::os::fd::AsRawFd;
+    // struct fiemap and struct fiemap_extent from linux/fiemap.h.
+    #[repr(C)]
+    struct Extent { logical: u64, physical: u64, length: u64, reserved64: [u64; 2], flags: u32, reserved: [u32; 3] }
+    #[repr(C)]
+    struct Map { start: u64, length: u64, flags: u32, mapped: u32, count: u32, reserved: u32, extents: [Extent; BATCH] }
+    const BATCH: usize = 256;
+    const FS_IOC_FIEMAP: libc::c_ulong = 0xC020_660B;
+    const FIEMAP_FLAG_SYNC: u32 = 1;
+    const FIEMAP_EXTENT_LAST: u32 = 1;
+    // Not yet placed on disk, so `physical` means nothing.
+    const FIEMAP_EXTENT_UNKNOWN: u32 = 2;
+
+    let file = std::fs::File::open(path)?;
+    let mut runs = Vec::new();
+    let mut map: Box<Map> = Box::new(unsafe { std::mem::zeroed() });
+    let mut start = 0;
+    loop {
+        *map = unsafe { std::mem::zeroed() };
+        map.start = start;
+        map.length = u64::MAX - start;
+        map.flags = FIEMAP_FLAG_SYNC;
+        map.count = BATCH as u32;
+        if unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FIEMAP as _, &mut *map as *mut Map) } != 0 {
+            let e = io::Error::last_os_error();
+            return Err(match e.raw_os_error() {
+                Some(libc::EOPNOTSUPP | libc::ENOTTY | libc::EINVAL) => io::Error::new(io::ErrorKind::Unsupported, e),
+                _ => e,
+            });
+        }
+        let got = &map.extents[..map.mapped as usize];
+        runs.extend(got.iter().filter(|e| e.flags & FIEMAP_EXTENT_UNKNOWN == 0).map(|e| (e.physical, e.length)));
+        match got.last() {
+            Some(e) if e.flags & FIEMAP_EXTENT_LAST == 0 => start = e.logical + e.length,
+            _ => return Ok(runs),
+        }
+    }
+}
+
+/// Runs come back as `(NextVcn, Lcn)` pairs in clusters; they are scaled to bytes by the
+/// file's length rather than asking the volume for its cluster size.
+#[cfg(target_os = "windows")]
+pub(crate) fn runs(path: &Path) -> io::Result<Vec<(u64, u64)>> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Ioctl::FSCTL_GET_RETRIEVAL_POINTERS;
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+    const ERROR_MORE_DATA: i32 = 234;
+    const ERROR_HANDLE_EOF: i32 = 38;
+
+    let file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut clusters: Vec<(u64, u64)> = Vec::new();
+    // RETRIEVAL_POINTERS_BUFFER: ExtentCount (u32, padded to 8), StartingVcn, then pairs.
+    let mut out = vec![0i64; 2 + 2 * 512];
+    let mut vcn: i64 = 0;
+    loop {
+        let mut returned = 0u32;
+        let ok = unsafe {
+            DeviceIoControl(
+                file.as_raw_handle(), FSCTL_GET_RETRIEVAL_POINTERS,
+                (&vcn as *const i64).cast(), std::mem::size_of::<i64>() as u32,
+                out.as_mut_ptr().cast(), (out.len() * std::mem::size_of::<i64>()) as u32,
+                &mut returned, std::ptr::null_mut(),
+            )
+        };
+        let err = (ok == 0).then(io::Error::last_os_error);
+        match err.as_ref().and_then(io::Error::raw_os_error) {
+            None | Some(ERROR_MORE_DATA) => {}
+            // Small files live in the MFT record and have no clusters.
+            Some(ERROR_HANDLE_EOF) => break,
+            Some(_) => return Err(io::Error::new(io::ErrorKind::Unsupported, err.unwrap())),
+        }
+        let count = out[0] as u32 as usize;
+        let mut next_start = out[1];
+        for pair in out[2..2 + 2 * count].chunks_exact(2) {
+            let (next, lcn) = (pair[0], pair[1]);
+            // An Lcn of -1 is a sparse or compressed run with nothing on disk.
+            if lcn >= 0 { clusters.push((lcn as u64, (next - next_start) as u64)); }
+            next_start = next;
+        }
+        vcn = next_start;
+        if err.is_none() || count == 0 { break; }
+    }
+    let total: u64 = clusters.iter().map(|&(_, n)| n).sum();
+    let per = if total == 0 { 0 } else { len.div_ceil(total) };
+    Ok(clusters.into_iter().map(|(at, n)| (at * per, n * per)).collect())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub(crate) fn runs(_path: &Path) -> io::Result<Vec<(u64, u64)>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "file layout isn't available on this system"))
+}
@@ -0,0 +1,194 @@
+//! Opening the test file with platform-specific synchronous / uncached flags.
+
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(target_os = "macos")]
+use std::os::fd::AsRawFd;
+#[cfg(target_os = "windows")]
+use std::os::windows::fs::OpenOptionsExt as WinOpenOptionsExt;
+
+#[cfg(target_os = "macos")]
+fn set_nocache(file: &File) {
+    unsafe {
+        let fd = file.as_raw_fd();
+        let _ = libc::fcntl(fd, libc::F_NOCACHE, 1);
+    }
+}
+
+#[allow(dead_code)]
+#[cfg(not(target_os = "macos"))]
+fn set_nocache(_file: &File) {}
+
+/// Base options with the platform's write-through / O_SYNC flag applied when `direct`.
+fn options(direct: bool) -> OpenOptions {
+    let mut opts = OpenOptions::new();
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_WRITE_THROUGH};
+        if direct { opts.custom_flags(FILE_FLAG_WRITE_THROUGH as u32); } // Fix type
+    }
+    #[cfg(unix)]
+    if direct { opts.custom_flags(libc::O_SYNC); }
+    opts
+}
+
+/// The flags `options(direct)` adds, for diagnostics.
+fn flag_names(direct: bool) -> &'static str {
+    if !direct { return "buffered"; }
+    if cfg!(target_os = "windows") { "FILE_FLAG_WRITE_THROUGH" }
+    else if cfg!(target_os = "macos") { "O_SYNC|F_NOCACHE" }
+    else { "O_SYNC" }
+}
+
+fn finish_open(opts: &OpenOptions, path: &Path, direct: bool, mode: &str) -> std::io::Result<File> {
+    let f = opts.open(path)?;
+    crate::debug!("opened {} for {mode} ({})", path.display(), flag_names(direct));
+    #[cfg(target_os = "macos")]
+    if direct { set_nocache(&f); }
+    #[cfg(not(target_os = "macos"))]
+    let _ = direct;
+    Ok(f)
+}
+
+/// Creates (or truncates) `path` for writing.
+pub fn open_write(path: &Path, direct: bool) -> std::io::Result<File> {
+    let mut opts = options(direct);
+    opts.create(true).write(true).truncate(true);
+    finish_open(&opts, path, direct, "write, create+truncate")
+}
+
+/// Opens an existing file or block device for writing without truncating it.
+pub fn open_existing_write(path: &Path, direct: bool) -> std::io::Result<File> {
+    let mut opts = options(direct);
+    opts.write(true);
+    finish_open(&opts, path, direct, "write")
+}
+
+pub fn open_read(path: &Path, direct: bool) -> std::io::Result<File> {
+    let mut opts = options(direct);
+    opts.read(true);
+    finish_open(&opts, path, direct, "read")
+}
+
+/// Opens an existing file for positional reads and writes.
+pub fn open_read_write(path: &Path, direct: bool) -> std::io::Result<File> {
+    let mut opts = options(direct);
+    opts.read(true).write(true);
+    finish_open(&opts, path, direct, "read/write")
+}
+
+/// Asks the OS to drop its cached copy of `path`, so the next read goes to the device or file server.
+///
+/// Linux uses `posix_fadvise(DONTNEED)`; Windows opens the file unbuffered, which purges its cache
+/// when no other handle is open. Elsewhere this is a no-op.
+pub fn evict(path: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd;
+        let f = File::open(path)?;
+        let rc = unsafe { libc::posix_fadvise(f.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+        if rc != 0 { return Err(std::io::Error::from_raw_os_error(rc)); }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use windows_sys::Win32::Storage::FileSystem::FILE_FLAG_NO_BUFFERING;
+        drop(OpenOptions::new().read(true).custom_flags(FILE_FLAG_NO_BUFFERING).open(path)?);
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    let _ = path;
+    Ok(())
+}
+
+/// Reserves `len` bytes of disk space for `file` and extends it to that length, so writes
+/// into it move data without allocating as they go: `fallocate` on Linux, `F_PREALLOCATE`
+/// on macOS, the allocation size (`SetFileInformationByHandle`) on Windows. Filesystems that
+/// can't (FAT on older kernels, most network shares) fail with `Unsupported`; a full one
+/// with `StorageFull`.
+pub fn preallocate(file: &File, len: u64) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd;
+        let len = libc::off_t::try_from(len).map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        if unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, len) } != 0 {
+            return Err(unsupported_if(std::io::Error::last_os_error(), &[libc::EOPNOTSUPP, libc::ENOSYS]));
+        }
+        Ok(())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut store = libc::fstore_t {
+            fst_flags: libc::F_ALLOCATEALL,
+            fst_posmode: libc::F_PEOFPOSMODE,
+            fst_offset: 0,
+            fst_length: len as libc::off_t,
+            fst_bytesalloc: 0,
+        };
+        if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_PREALLOCATE, &mut store) } == -1 {
+            return Err(unsupported_if(std::io::Error::last_os_error(), &[libc::ENOTSUP, libc::EINVAL]));
+        }
+        file.set_len(len)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Storage::FileSystem::{FileAllocationInfo, SetFileInformationByHandle, FILE_ALLOCATION_INFO};
+        let info = FILE_ALLOCATION_INFO { AllocationSize: len as i64 };
+        let ok = unsafe {
+            SetFileInformationByHandle(file.as_raw_handle(), FileAllocationInfo, &info as *const _ as *const _, std::mem::size_of::<FILE_ALLOCATION_INFO>() as u32)
+        };
+        if ok == 0 { return Err(std::io::Error::last_os_error()); }
+        file.set_len(len)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = (file, len);
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// `e` as `Unsupported` when its OS error code is one of `codes`, keeping the message.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn unsupported_if(e: std::io::Error, codes: &[i32]) -> std::io::Error {
+    match e.raw_os_error() {
+        Some(c) if codes.contains(&c) => std::io::Error::new(std::io::ErrorKind::Unsupported, e),
+        _ => e,
+    }
+}
+
+/// Writes all of `buf` at `offset` without moving a shared cursor, so several threads can use one handle.
+pub fn write_all_at(file: &File, buf: &[u8], offset: u64) -> std::io::Result<()> {
+    #[cfg(unix)]
+    { std::os::unix::fs::FileExt::write_all_at(file, buf, offset) }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::FileExt;
+        let (mut done, mut off) = (0, offset);
+        while done < buf.len() {
+            let n = file.seek_write(&buf[done..], off)?;
+            if n == 0 { return Err(std::io::ErrorKind::WriteZero.into()); }
+            done += n;
+            off += n as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Fills `buf` from `offset`; positional counterpart of `read_exact`.
+pub fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
+    #[cfg(unix)]
+    { std::os::unix::fs::FileExt::read_exact_at(file, buf, offset) }
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::FileExt;
+        let (mut done, mut off) = (0, offset);
+        while done < buf.len() {
+            let n = file.seek_read(&mut buf[done..], off)?;
+            if n == 0 { return Err(std::io::ErrorKind::UnexpectedEof.into()); }
+            done += n;
+            off += n as u64;
+        }
+        Ok(())
+    }
+}
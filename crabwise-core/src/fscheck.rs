@@ -0,0 +1,299 @@
+//! Read-only filesystem sanity check before a run: the dirty and error flags that FAT,
+//! exFAT, NTFS and ext2/3/4 keep on disk, read straight from the raw device. A
+//! [`Snapshot`] before and after a run also catches structures the run changed.
+//!
+//! Raw devices are usually readable by root (or an administrator) only; without access the
+//! check is skipped. A filesystem mounted read-write can carry its own mount's marks, so the
+//! flags Linux sets on every FAT/exFAT mount are not reported there.
+
+use crate::device::DeviceInfo;
+use crate::file::{open_read, read_exact_at};
+use crate::manifest::fnv1a;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Raw reads are rounded out to this, which satisfies any sector size (Windows volume
+/// handles reject unaligned reads).
+const ALIGN: u64 = 4096;
+
+/// NTFS protects every 512 bytes of an MFT record with the update sequence, whatever the sector size.
+const NTFS_STRIDE: usize = 512;
+
+/// Linux marks FAT and exFAT volumes dirty when it mounts them read-write and clears the flag
+/// on unmount, so on a mounted volume the flag says nothing about earlier sessions.
+const MOUNT_SETS_FAT_DIRTY: bool = cfg!(target_os = "linux");
+
+/// What the on-disk structures say about the filesystem on one volume.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsHealth {
+    /// Filesystem found in the boot sector or superblock: `FAT16`, `FAT32`, `exFAT`, `NTFS` or `ext`.
+    pub kind: &'static str,
+    /// Everything that suggests a check (fsck/chkdsk) is due; empty when it looks clean.
+    pub problems: Vec<String>,
+}
+
+/// The raw device behind a mounted volume: `\\.\E:` on Windows, the device node elsewhere.
+pub fn raw_device(info: &DeviceInfo) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(format!(r"\\.\{}", info.mount_point.trim_end_matches(['\\', '/'])))
+    } else {
+        PathBuf::from(&info.name)
+    }
+}
+
+pub(crate) fn le16(b: &[u8], at: usize) -> u16 { u16::from_le_bytes([b[at], b[at + 1]]) }
+pub(crate) fn le32(b: &[u8], at: usize) -> u32 { u32::from_le_bytes(b[at..at + 4].try_into().unwrap_or_default()) }
+pub(crate) fn le64(b: &[u8], at: usize) -> u64 { u64::from_le_bytes(b[at..at + 8].try_into().unwrap_or_default()) }
+
+/// `len` bytes at `offset`, read through an aligned window.
+pub(crate) fn read_raw(f: &File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let start = offset / ALIGN * ALIGN;
+    let end = (offset + len as u64).div_ceil(ALIGN) * ALIGN;
+    let mut buf = vec![0u8; (end - start) as usize];
+    read_exact_at(f, &mut buf, start)?;
+    let skip = (offset - start) as usize;
+    Ok(buf[skip..skip + len].to_vec())
+}
+
+/// NTFS geometry from its boot sector.
+pub(crate) struct Ntfs {
+    pub record: usize,
+    pub mft: u64,
+    pub mft_mirror: u64,
+}
+
+impl Ntfs {
+    pub fn parse(boot: &[u8]) -> Option<Ntfs> {
+        if &boot[3..11] != b"NTFS    " { return None; }
+        let sector = u64::from(le16(boot, 0x0B));
+        // Cluster sizes above 64 KiB are stored as a negative power of two.
+        let spc = match boot[0x0D] { n if n > 0x80 => 1u64 << (256 - u32::from(n)), n => u64::from(n) };
+        let cluster = sector * spc;
+        let record = match boot[0x40] as i8 {
+            n if n > 0 => n as usize * cluster as usize,
+            n => 1usize << n.unsigned_abs(),
+        };
+        (cluster > 0 && record >= NTFS_STRIDE).then_some(Ntfs { record, mft: le64(boot, 0x30) * cluster, mft_mirror: le64(boot, 0x38) * cluster })
+    }
+}
+
+/// Undoes an MFT record's update sequence in place; false when a sector's check value doesn't
+/// match (a torn write) or the record isn't a `FILE` record at all.
+pub(crate) fn ntfs_fixup(rec: &mut [u8]) -> bool {
+    if rec.len() < 8 || &rec[..4] != b"FILE" { return false; }
+    let (ofs, count) = (usize::from(le16(rec, 4)), usize::from(le16(rec, 6)));
+    if count == 0 || ofs + count * 2 > rec.len() || (count - 1) * NTFS_STRIDE > rec.len() { return false; }
+    let check = [rec[ofs], rec[ofs + 1]];
+    for i in 1..count {
+        let at = i * NTFS_STRIDE - 2;
+        if rec[at..at + 2] != check { return false; }
+        rec[at] = rec[ofs + i * 2];
+        rec[at + 1] = rec[ofs + i * 2 + 1];
+    }
+    true
+}
+
+/// Flags of `$Volume`'s `$VOLUME_INFORMATION` attribute (type 0x70) in a fixed-up MFT record.
+fn ntfs_volume_flags(rec: &[u8]) -> Option<u16> {
+    let mut at = usize::from(le16(rec, 0x14));
+    while at + 24 <= rec.len() {
+        let (kind, len) = (le32(rec, at), le32(rec, at + 4) as usize);
+        if kind == 0xFFFF_FFFF || len == 0 { return None; }
+        if kind == 0x70 && rec[at + 8] == 0 {
+            let content = at + usize::from(le16(rec, at + 0x14));
+            return (content + 12 <= rec.len()).then(|| le16(rec, content + 0x0A));
+        }
+        at += len;
+    }
+    None
+}
+
+fn check_ntfs(f: &File, boot: &[u8], problems: &mut Vec<String>) -> io::Result<()> {
+    let Some(n) = Ntfs::parse(boot) else {
+        problems.push("NTFS boot sector has an impossible cluster or MFT record size".into());
+        return Ok(());
+    };
+    // $MFTMirr holds copies of $MFT, $MFTMirr, $LogFile and $Volume.
+    let mut mft = read_raw(f, n.mft, 4 * n.record)?;
+    let mut mirror = read_raw(f, n.mft_mirror, 4 * n.record)?;
+    for (i, (a, b)) in mft.chunks_mut(n.record).zip(mirror.chunks_mut(n.record)).enumerate() {
+        let (ok_a, ok_b) = (ntfs_fixup(a), ntfs_fixup(b));
+        let used = (le32(a, 0x18) as usize).min(n.record);
+        if !ok_a {
+            problems.push(format!("MFT record {i} is damaged (bad signature or torn write)"));
+        } else if !ok_b || a[..used] != b[..used] {
+            problems.push(format!("$MFTMirr does not match $MFT (record {i})"));
+        }
+    }
+    let volume = &mft[3 * n.record..];
+    if let Some(flags) = ntfs_volume_flags(volume) {
+        if flags & 0x0001 != 0 { problems.push("volume is marked dirty (chkdsk is due)".into()); }
+    }
+    Ok(())
+}
+
+fn check_fat(f: &File, boot: &[u8], fat32: bool, problems: &mut Vec<String>) -> io::Result<()> {
+    let fat = u64::from(le16(boot, 0x0E)) * u64::from(le16(boot, 0x0B));
+    let entries = read_raw(f, fat, 8)?;
+    // The second FAT entry's top bits: clean shutdown, and no I/O error seen.
+    let (clean, no_error) = if fat32 {
+        let e = le32(&entries, 4);
+        (e & 0x0800_0000 != 0, e & 0x0400_0000 != 0)
+    } else {
+        let e = le16(&entries, 2);
+        (e & 0x8000 != 0, e & 0x4000 != 0)
+    };
+    if !clean && !MOUNT_SETS_FAT_DIRTY { problems.push("volume is marked dirty (not cleanly unmounted)".into()); }
+    if !no_error { problems.push("the FAT records a disk I/O error".into()); }
+    Ok(())
+}
+
+fn check_exfat(boot: &[u8], problems: &mut Vec<String>) {
+    let flags = le16(boot, 106);
+    if flags & 0x0002 != 0 && !MOUNT_SETS_FAT_DIRTY { problems.push("volume is marked dirty (not cleanly unmounted)".into()); }
+    if flags & 0x0004 != 0 { problems.push("volume is flagged for media failure".into()); }
+}
+
+fn check_ext(sb: &[u8], problems: &mut Vec<String>) {
+    if le16(sb, 0x3A) & 0x0002 != 0 { problems.push("superblock says errors were detected".into()); }
+    match le32(sb, 0x194) {
+        0 => {}
+        n => problems.push(format!("{n} filesystem error(s) recorded since the last fsck")),
+    }
+}
+
+fn open(info: &DeviceInfo) -> io::Result<(PathBuf, File, Vec<u8>)> {
+    let dev = raw_device(info);
+    let f = open_read(&dev, false).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", dev.display())))?;
+    let head = read_raw(&f, 0, 2048)?;
+    Ok((dev, f, head))
+}
+
+fn health(dev: &Path, f: &File, head: &[u8]) -> io::Result<Option<FsHealth>> {
+    let mut problems = Vec::new();
+    let kind = if &head[3..11] == b"NTFS    " {
+        check_ntfs(f, head, &mut problems)?;
+        "NTFS"
+    } else if &head[3..11] == b"EXFAT   " {
+        check_exfat(head, &mut problems);
+        "exFAT"
+    } else if &head[0x52..0x5A] == b"FAT32   " {
+        check_fat(f, head, true, &mut problems)?;
+        "FAT32"
+    } else if &head[0x36..0x3D] == b"FAT16  " {
+        check_fat(f, head, false, &mut problems)?;
+        "FAT16"
+    } else if le16(head, 1024 + 0x38) == 0xEF53 {
+        check_ext(&head[1024..], &mut problems);
+        "ext"
+    } else {
+        crate::debug!("{}: no FAT, exFAT, NTFS or ext filesystem recognized", dev.display());
+        return Ok(None);
+    };
+    crate::debug!("{}: {kind}, {} problem(s)", dev.display(), problems.len());
+    Ok(Some(FsHealth { kind, problems }))
+}
+
+/// Reads the boot sector or superblock of the volume behind `info` and reports what looks
+/// wrong. `Ok(None)` for filesystems it doesn't know; an error when the raw device can't be read.
+pub fn check(info: &DeviceInfo) -> io::Result<Option<FsHealth>> {
+    let (dev, f, head) = open(info)?;
+    health(&dev, &f, &head)
+}
+
+/// FAT copies compared by [`snapshot`] are capped at this many bytes each.
+const FAT_COMPARE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Checksums of the on-disk structures a benchmark has no business changing, plus the
+/// [`check`] findings, taken before and after a run to spot metadata damage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub health: FsHealth,
+    /// `(structure, FNV-1a 64 of its unchanging bytes)`.
+    pub regions: Vec<(&'static str, u64)>,
+}
+
+/// FNV-1a 64 over `ranges` of `b`, skipping whatever lies between them.
+fn hash_ranges(b: &[u8], ranges: &[(usize, usize)]) -> u64 {
+    let bytes: Vec<u8> = ranges.iter().flat_map(|&(from, to)| b[from..to].iter().copied()).collect();
+    fnv1a(&bytes)
+}
+
+/// Superblock fields ext never rewrites: counts and geometry, revision, UUID and label.
+const EXT_STATIC: [(usize, usize); 3] = [(0x00, 0x08), (0x14, 0x2C), (0x68, 0x88)];
+
+fn regions(f: &File, kind: &str, head: &[u8], problems: &mut Vec<String>) -> Vec<(&'static str, u64)> {
+    let sector = u64::from(le16(head, 0x0B)).max(512);
+    let mut out = Vec::new();
+    match kind {
+        "NTFS" => {
+            out.push(("boot sector", fnv1a(&head[..512])));
+            // The backup boot sector sits just past the sectors the volume claims.
+            if let Ok(b) = read_raw(f, le64(head, 0x28) * sector, 512) { out.push(("backup boot sector", fnv1a(&b))); }
+        }
+        "FAT32" | "FAT16" => {
+            out.push(("boot sector", fnv1a(&head[..512])));
+            let backup = u64::from(le16(head, 0x32));
+            if kind == "FAT32" && backup > 0 {
+                if let Ok(b) = read_raw(f, backup * sector, 512) { out.push(("backup boot sector", fnv1a(&b))); }
+            }
+            let fat_bytes = if kind == "FAT32" { u64::from(le32(head, 0x24)) } else { u64::from(le16(head, 0x16)) } * sector;
+            let len = (fat_bytes as usize).min(FAT_COMPARE_BYTES);
+            let first = u64::from(le16(head, 0x0E)) * sector;
+            if head[0x10] >= 2 && len > 0 {
+                if let (Ok(a), Ok(b)) = (read_raw(f, first, len), read_raw(f, first + fat_bytes, len)) {
+                    if a != b { problems.push("the two FAT copies differ".into()); }
+                }
+            }
+        }
+        "exFAT" => {
+            // 12 sectors of main boot region, then the same again as backup; VolumeFlags and
+            // PercentInUse (bytes 106, 107 and 112) change in normal use.
+            let sector = 1u64 << head[108].clamp(9, 12);
+            let len = 12 * sector as usize;
+            let masked = |b: &[u8]| hash_ranges(b, &[(0, 106), (108, 112), (113, len)]);
+            if let Ok(b) = read_raw(f, 0, len) { out.push(("boot region", masked(&b))); }
+            if let Ok(b) = read_raw(f, 12 * sector, len) { out.push(("backup boot region", masked(&b))); }
+        }
+        "ext" => {
+            out.push(("boot block", fnv1a(&head[..1024])));
+            let sb = &head[1024..];
+            out.push(("superblock", hash_ranges(sb, &EXT_STATIC)));
+            // Group 1 starts with a backup superblock (sparse_super keeps that one).
+            let block = 1024u64 << le32(sb, 0x18).min(6);
+            let group1 = (u64::from(le32(sb, 0x20)) + u64::from(le32(sb, 0x14))) * block;
+            if let Ok(b) = read_raw(f, group1, 1024) {
+                if le16(&b, 0x38) == 0xEF53 { out.push(("backup superblock", hash_ranges(&b, &EXT_STATIC))); }
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+/// Takes a [`Snapshot`] of the volume behind `info`; `Ok(None)` for filesystems it doesn't know.
+pub fn snapshot(info: &DeviceInfo) -> io::Result<Option<Snapshot>> {
+    // Metadata still in the OS's caches isn't on the device yet.
+    #[cfg(unix)]
+    unsafe { libc::sync() };
+    let (dev, f, head) = open(info)?;
+    let Some(mut health) = health(&dev, &f, &head)? else { return Ok(None) };
+    let regions = regions(&f, health.kind, &head, &mut health.problems);
+    Ok(Some(Snapshot { health, regions }))
+}
+
+impl Snapshot {
+    /// What differs in `after`: changed structures and problems that weren't there before.
+    pub fn changes(&self, after: &Snapshot) -> Vec<String> {
+        if after.health.kind != self.health.kind {
+            return vec![format!("filesystem now reads as {} instead of {}", after.health.kind, self.health.kind)];
+        }
+        let mut out: Vec<String> = self.regions.iter()
+            .filter(|(name, h)| !after.regions.iter().any(|(n, a)| n == name && a == h))
+            .map(|(name, _)| format!("{name} changed"))
+            .collect();
+        out.extend(after.health.problems.iter().filter(|p| !self.health.problems.contains(p)).cloned());
+        out
+    }
+}
@@ -0,0 +1,83 @@
+//! `--abort-below`: stops a run whose sequential throughput stays under a floor for a whole
+//! window, e.g. a drive that fell back to a USB 1.1 link, instead of grinding through it.
+
+use crate::progress::{Phase, Progress, Update};
+use crate::result::BenchResult;
+use std::fmt;
+use std::io;
+
+/// Throughput floor in bytes per second that must be missed for `window` seconds in a row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbortBelow {
+    pub floor: f64,
+    pub window: f64,
+}
+
+/// Why a guarded run stopped; the source of the `io::Error` the runner returns.
+#[derive(Debug, Clone)]
+pub struct BelowExpectations {
+    pub phase: Phase,
+    /// Mean MB/s over the slow stretch.
+    pub mb_s: f64,
+    pub seconds: f64,
+    pub limit: AbortBelow,
+}
+
+impl fmt::Display for BelowExpectations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "device far below expectations: {} ran at {:.2} MB/s for {:.0} s, under the {:.2} MB/s floor (--abort-below)",
+            self.phase.id(), self.mb_s, self.seconds, self.limit.floor / 1e6)
+    }
+}
+
+impl std::error::Error for BelowExpectations {}
+
+/// The abort reason when `e` came from an [`AbortGuard`].
+pub fn below_expectations(e: &io::Error) -> Option<&BelowExpectations> {
+    e.get_ref().and_then(|e| e.downcast_ref::<BelowExpectations>())
+}
+
+/// Phases that stream large blocks; random and small-file I/O is slow on any flash, so the
+/// floor doesn't apply to it.
+fn guarded(phase: Phase) -> bool {
+    matches!(phase, Phase::Write | Phase::Read | Phase::Sustained | Phase::ParallelWrite
+        | Phase::ParallelRead | Phase::DirtyWrite | Phase::TrimmedWrite)
+}
+
+/// Passes progress through to `inner` and fails the run once the trailing throughput samples
+/// of a guarded phase have all been under the floor for the window.
+pub struct AbortGuard<'a> {
+    inner: &'a mut dyn Progress,
+    limit: AbortBelow,
+}
+
+impl<'a> AbortGuard<'a> {
+    pub fn new(inner: &'a mut dyn Progress, limit: AbortBelow) -> AbortGuard<'a> {
+        AbortGuard { inner, limit }
+    }
+
+    fn check(&self, u: &Update) -> Option<BelowExpectations> {
+        if !guarded(u.phase) { return None; }
+        let slow = u.samples.iter().rev().take_while(|s| s.mb_s * 1e6 < self.limit.floor).count();
+        let first = u.samples.len().checked_sub(slow)?;
+        let (from, to) = (first.checked_sub(1).map_or(0.0, |i| u.samples[i].t), u.samples.last()?.t);
+        let seconds = to - from;
+        if slow == 0 || seconds < self.limit.window { return None; }
+        let mb_s = u.samples[first..].iter().map(|s| s.mb_s).sum::<f64>() / slow as f64;
+        Some(BelowExpectations { phase: u.phase, mb_s, seconds, limit: self.limit })
+    }
+}
+
+impl Progress for AbortGuard<'_> {
+    fn update(&mut self, u: &Update) -> io::Result<()> {
+        if let Some(b) = self.check(u) {
+            crate::info!("{b}");
+            return Err(io::Error::other(b));
+        }
+        self.inner.update(u)
+    }
+
+    fn finish(&mut self, phase: Phase) -> io::Result<()> { self.inner.finish(phase) }
+
+    fn done(&mut self, result: &BenchResult) -> io::Result<()> { self.inner.done(result) }
+}
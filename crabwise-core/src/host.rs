@@ -0,0 +1,172 @@
+//! The machine a run was performed on, stored with every result so runs can be compared
+//! across hosts and months: OS, kernel, hostname, the controller the target hangs off, the
+//! power source and the crabwise version.
+
+use crate::device::DeviceInfo;
+use crate::json::Value;
+use sysinfo::System;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Power {
+    Ac,
+    Battery,
+}
+
+impl Power {
+    pub fn id(self) -> &'static str {
+        match self { Power::Ac => "ac", Power::Battery => "battery" }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HostInfo {
+    pub hostname: Option<String>,
+    /// Distribution or product name and version, e.g. `Linux (Ubuntu 24.04)`, `Windows 11 Pro`.
+    pub os: Option<String>,
+    pub kernel: Option<String>,
+    /// CPU architecture crabwise was built for, e.g. `x86_64`, `aarch64`.
+    pub arch: String,
+    /// PCI controller the target's disk hangs off (the xHCI controller for a USB drive), as
+    /// `Vendor vvvv:dddd (driver)`; Linux only.
+    pub controller: Option<String>,
+    /// `None` where it can't be told, e.g. a desktop that reports no power supplies.
+    pub power: Option<Power>,
+    pub crabwise_version: String,
+}
+
+impl HostInfo {
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("hostname", self.hostname.clone().into()),
+            ("os", self.os.clone().into()),
+            ("kernel", self.kernel.clone().into()),
+            ("arch", self.arch.as_str().into()),
+            ("controller", self.controller.clone().into()),
+            ("power", self.power.map(Power::id).into()),
+            ("crabwise_version", self.crabwise_version.as_str().into()),
+        ])
+    }
+
+    pub fn from_json(v: &Value) -> Option<HostInfo> {
+        let text = |k: &str| v.get(k).and_then(Value::as_str).map(str::to_string);
+        Some(HostInfo {
+            hostname: text("hostname"),
+            os: text("os"),
+            kernel: text("kernel"),
+            arch: text("arch").unwrap_or_default(),
+            controller: text("controller"),
+            power: match v.get("power").and_then(Value::as_str) {
+                Some("ac") => Some(Power::Ac),
+                Some("battery") => Some(Power::Battery),
+                _ => None,
+            },
+            crabwise_version: text("crabwise_version")?,
+        })
+    }
+
+    /// One line for logs and reports, e.g. `host1, Linux (Ubuntu 24.04), kernel 6.8.0, x86_64, on AC, crabwise 0.1.0`.
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = [&self.hostname, &self.os].into_iter().flatten().cloned().collect();
+        if let Some(k) = &self.kernel { parts.push(format!("kernel {k}")); }
+        if !self.arch.is_empty() { parts.push(self.arch.clone()); }
+        if let Some(c) = &self.controller { parts.push(format!("controller {c}")); }
+        if let Some(p) = self.power { parts.push(if p == Power::Ac { "on AC".into() } else { "on battery".into() }); }
+        parts.push(format!("crabwise {}", self.crabwise_version));
+        parts.join(", ")
+    }
+}
+
+/// Describes this machine; `device` is the target's volume, for the controller lookup.
+pub fn host_info(device: Option<&DeviceInfo>) -> HostInfo {
+    let info = HostInfo {
+        hostname: System::host_name(),
+        os: System::long_os_version(),
+        kernel: System::kernel_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        controller: device.and_then(controller),
+        power: power(),
+        crabwise_version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+    crate::info!("host: {}", info.summary());
+    info
+}
+
+/// Names for the PCI vendor IDs of common USB host controllers.
+const PCI_VENDORS: &[(u16, &str)] = &[
+    (0x8086, "Intel"),
+    (0x1022, "AMD"),
+    (0x1b21, "ASMedia"),
+    (0x1912, "Renesas"),
+    (0x1106, "VIA"),
+    (0x104c, "Texas Instruments"),
+    (0x1b73, "Fresco Logic"),
+    (0x10de, "NVIDIA"),
+    (0x1b4b, "Marvell"),
+    (0x106b, "Apple"),
+    (0x1af4, "Red Hat (virtio)"),
+];
+
+/// The nearest PCI device above the disk in sysfs: for USB drives the xHCI controller, for
+/// SATA the AHCI controller, for NVMe the drive itself.
+#[cfg(target_os = "linux")]
+fn controller(device: &DeviceInfo) -> Option<String> {
+    let mut dir = crate::device::sys_block_dir(device)?;
+    while dir.pop() && dir != std::path::Path::new("/sys/devices") {
+        let is_pci = std::fs::read_link(dir.join("subsystem")).is_ok_and(|l| l.file_name().is_some_and(|n| n == "pci"));
+        if !is_pci { continue; }
+        let id = |f: &str| std::fs::read_to_string(dir.join(f)).ok()
+            .and_then(|s| u16::from_str_radix(s.trim().trim_start_matches("0x"), 16).ok());
+        let (vendor, product) = (id("vendor")?, id("device")?);
+        let name = PCI_VENDORS.iter().find(|(v, _)| *v == vendor).map(|(_, n)| *n).unwrap_or("PCI");
+        let driver = std::fs::read_link(dir.join("driver")).ok()
+            .and_then(|l| l.file_name().map(|n| format!(" ({})", n.to_string_lossy())))
+            .unwrap_or_default();
+        return Some(format!("{name} {vendor:04x}:{product:04x}{driver}"));
+    }
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn controller(_device: &DeviceInfo) -> Option<String> {
+    None
+}
+
+/// Mains (or USB-C) power online means AC; otherwise a discharging battery means battery.
+#[cfg(target_os = "linux")]
+fn power() -> Option<Power> {
+    let read = |p: &std::path::Path, f: &str| std::fs::read_to_string(p.join(f)).map(|s| s.trim().to_string()).unwrap_or_default();
+    let supplies: Vec<_> = std::fs::read_dir("/sys/class/power_supply").ok()?.flatten().map(|e| e.path()).collect();
+    if supplies.iter().any(|p| matches!(read(p, "type").as_str(), "Mains" | "USB" | "USB_C") && read(p, "online") == "1") {
+        return Some(Power::Ac);
+    }
+    if supplies.iter().any(|p| read(p, "type") == "Battery" && read(p, "status") == "Discharging") {
+        return Some(Power::Battery);
+    }
+    None
+}
+
+/// `GetSystemPowerStatus`' AC line status: 0 offline, 1 online, 255 unknown.
+#[cfg(target_os = "windows")]
+fn power() -> Option<Power> {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 { return None; }
+    match status.ACLineStatus {
+        0 => Some(Power::Battery),
+        1 => Some(Power::Ac),
+        _ => None,
+    }
+}
+
+/// `pmset -g batt` starts with `Now drawing from 'AC Power'` or `'Battery Power'`.
+#[cfg(target_os = "macos")]
+fn power() -> Option<Power> {
+    let out = std::process::Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    if text.contains("'AC Power'") { Some(Power::Ac) } else if text.contains("'Battery Power'") { Some(Power::Battery) } else { None }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+fn power() -> Option<Power> {
+    None
+}
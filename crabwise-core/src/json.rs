@@ -0,0 +1,243 @@
+//! Minimal JSON value type with a compact serializer and a parser.
+//!
+//! Crabwise only needs JSON for its own result records, so this keeps the
+//! dependency list short instead of pulling in a full serialization stack.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Keys keep insertion order so emitted records read naturally.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Builds an object from `(key, value)` pairs.
+    pub fn object<K: Into<String>>(pairs: Vec<(K, Value)>) -> Value {
+        Value::Object(pairs.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self { Value::Number(n) => Some(*n), _ => None }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_f64().filter(|n| *n >= 0.0).map(|n| n as u64)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self { Value::String(s) => Some(s), _ => None }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self { Value::Bool(b) => Some(*b), _ => None }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self { Value::Array(a) => Some(a), _ => None }
+    }
+}
+
+impl From<bool> for Value { fn from(b: bool) -> Self { Value::Bool(b) } }
+impl From<f64> for Value { fn from(n: f64) -> Self { Value::Number(n) } }
+impl From<u64> for Value { fn from(n: u64) -> Self { Value::Number(n as f64) } }
+impl From<usize> for Value { fn from(n: usize) -> Self { Value::Number(n as f64) } }
+impl From<i32> for Value { fn from(n: i32) -> Self { Value::Number(n as f64) } }
+impl From<&str> for Value { fn from(s: &str) -> Self { Value::String(s.to_string()) } }
+impl From<String> for Value { fn from(s: String) -> Self { Value::String(s) } }
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(o: Option<T>) -> Self { o.map(Into::into).unwrap_or(Value::Null) }
+}
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self { Value::Array(v.into_iter().map(Into::into).collect()) }
+}
+
+fn write_str(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) if !n.is_finite() => f.write_str("null"),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write_str(f, s),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, v) in items.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+                    write!(f, "{v}")?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(pairs) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in pairs.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+                    write_str(f, k)?;
+                    write!(f, ":{v}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Parses a single JSON document.
+pub fn parse(input: &str) -> Result<Value, String> {
+    let mut p = Parser { s: input.as_bytes(), pos: 0 };
+    let v = p.value()?;
+    p.ws();
+    if p.pos != p.s.len() {
+        return Err(format!("trailing characters at offset {}", p.pos));
+    }
+    Ok(v)
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn ws(&mut self) {
+        while matches!(self.s.get(self.pos), Some(b' ' | b'\t' | b'\n' | b'\r')) { self.pos += 1; }
+    }
+
+    fn err<T>(&self, what: &str) -> Result<T, String> {
+        Err(format!("{what} at offset {}", self.pos))
+    }
+
+    fn eat(&mut self, lit: &str) -> bool {
+        if self.s[self.pos..].starts_with(lit.as_bytes()) { self.pos += lit.len(); true } else { false }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.ws();
+        match self.s.get(self.pos) {
+            None => self.err("unexpected end of input"),
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') if self.eat("true") => Ok(Value::Bool(true)),
+            Some(b'f') if self.eat("false") => Ok(Value::Bool(false)),
+            Some(b'n') if self.eat("null") => Ok(Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => self.err("unexpected character"),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut pairs = Vec::new();
+        self.ws();
+        if self.eat("}") { return Ok(Value::Object(pairs)); }
+        loop {
+            self.ws();
+            if self.s.get(self.pos) != Some(&b'"') { return self.err("expected object key"); }
+            let k = self.string()?;
+            self.ws();
+            if !self.eat(":") { return self.err("expected ':'"); }
+            let v = self.value()?;
+            pairs.push((k, v));
+            self.ws();
+            if self.eat(",") { continue; }
+            if self.eat("}") { return Ok(Value::Object(pairs)); }
+            return self.err("expected ',' or '}'");
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.ws();
+        if self.eat("]") { return Ok(Value::Array(items)); }
+        loop {
+            items.push(self.value()?);
+            self.ws();
+            if self.eat(",") { continue; }
+            if self.eat("]") { return Ok(Value::Array(items)); }
+            return self.err("expected ',' or ']'");
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(&b) = self.s.get(self.pos) {
+                if b == b'"' || b == b'\\' { break; }
+                self.pos += 1;
+            }
+            out.push_str(std::str::from_utf8(&self.s[start..self.pos]).map_err(|e| e.to_string())?);
+            match self.s.get(self.pos) {
+                None => return self.err("unterminated string"),
+                Some(b'"') => { self.pos += 1; return Ok(out); }
+                _ => {}
+            }
+            self.pos += 1;
+            let esc = match self.s.get(self.pos) { Some(&b) => b, None => return self.err("unterminated escape") };
+            self.pos += 1;
+            match esc {
+                b'"' => out.push('"'),
+                b'\\' => out.push('\\'),
+                b'/' => out.push('/'),
+                b'b' => out.push('\u{8}'),
+                b'f' => out.push('\u{c}'),
+                b'n' => out.push('\n'),
+                b'r' => out.push('\r'),
+                b't' => out.push('\t'),
+                b'u' => {
+                    let mut cp = self.hex4()?;
+                    if (0xD800..0xDC00).contains(&cp) && self.eat("\\u") {
+                        let lo = self.hex4()?;
+                        cp = 0x10000 + ((cp - 0xD800) << 10) + (lo.wrapping_sub(0xDC00) & 0x3FF);
+                    }
+                    out.push(char::from_u32(cp).unwrap_or('\u{FFFD}'));
+                }
+                _ => return self.err("invalid escape"),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let h = self.s.get(self.pos..self.pos + 4).ok_or_else(|| format!("truncated \\u escape at offset {}", self.pos))?;
+        self.pos += 4;
+        u32::from_str_radix(std::str::from_utf8(h).map_err(|e| e.to_string())?, 16).map_err(|e| e.to_string())
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while matches!(self.s.get(self.pos), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) { self.pos += 1; }
+        let text = std::str::from_utf8(&self.s[start..self.pos]).map_err(|e| e.to_string())?;
+        text.parse::<f64>().map(Value::Number).or_else(|_| self.err("invalid number"))
+    }
+}
@@ -0,0 +1,56 @@
+//! Benchmark engine behind the `crabwise` USB read/write speed test.
+//!
+//! The binary is a thin interactive shell around this crate; other tools can
+//! embed the same benchmark without shelling out:
+//!
+//! ```no_run
+//! use crabwise_core::{BenchConfig, BenchRunner, NoProgress};
+//!
+//! let config = BenchConfig::new("/media/usb");
+//! let result = BenchRunner::new(config).run(&mut NoProgress)?;
+//! println!("write {:.1} MB/s, read {:.1} MB/s", result.write.mb_s(), result.read.mb_s());
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod artifacts;
+pub mod backend;
+pub mod config;
+pub mod copy;
+pub mod cpu;
+pub mod device;
+pub mod extents;
+pub mod file;
+pub mod fscheck;
+pub mod guard;
+pub mod host;
+pub mod json;
+pub mod log;
+#[cfg(target_os = "macos")]
+mod macos;
+pub mod manifest;
+pub mod offsetmap;
+pub mod parse;
+pub mod progress;
+pub mod result;
+pub mod runner;
+pub mod sampler;
+pub mod scan;
+pub mod stage;
+pub mod stats;
+pub mod thresholds;
+pub mod trace;
+pub mod transform;
+pub mod trim;
+pub mod tune;
+pub mod usbpower;
+pub mod wipe;
+
+pub use backend::{FileBackend, MemoryBackend, RawDeviceBackend, StorageBackend};
+pub use config::BenchConfig;
+pub use device::DeviceInfo;
+pub use progress::{NoProgress, Phase, Progress, Update};
+pub use result::{BenchResult, CompressionCheck, LatencyStats, PhaseStats};
+pub use runner::BenchRunner;
+pub use sampler::Sample;
+pub use stage::{run_stage, Stage, StageResult};
+pub use thresholds::Thresholds;
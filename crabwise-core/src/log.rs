@@ -0,0 +1,65 @@
+//! Leveled diagnostics on stderr, off the hot path unless enabled.
+//!
+//! Library code logs through the [`error!`](crate::error), [`warn!`](crate::warn),
+//! [`info!`](crate::info), [`debug!`](crate::debug) and [`trace!`](crate::trace) macros;
+//! the `crabwise` binary maps `-q`/`-v`/`-vv`/`-vvv` onto [`set_level`]. Embedders get
+//! warnings only unless they raise the level.
+
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn label(self) -> &'static str {
+        match self { Level::Error => "ERROR", Level::Warn => "WARN", Level::Info => "INFO", Level::Debug => "DEBUG", Level::Trace => "TRACE" }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// Messages above `level` are discarded.
+pub fn set_level(level: Level) {
+    START.get_or_init(Instant::now);
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Writes one line; use the macros, which skip formatting when the level is off.
+#[doc(hidden)]
+pub fn write(level: Level, target: &str, args: fmt::Arguments) {
+    let t = START.get_or_init(Instant::now).elapsed().as_secs_f64();
+    let _ = writeln!(std::io::stderr().lock(), "[{t:>9.3}s {:<5} {target}] {args}", level.label());
+}
+
+#[macro_export]
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)+) => {
+        if $crate::log::enabled($level) { $crate::log::write($level, module_path!(), format_args!($($arg)+)) }
+    };
+}
+
+#[macro_export]
+macro_rules! error { ($($arg:tt)+) => { $crate::log_at!($crate::log::Level::Error, $($arg)+) }; }
+#[macro_export]
+macro_rules! warn { ($($arg:tt)+) => { $crate::log_at!($crate::log::Level::Warn, $($arg)+) }; }
+#[macro_export]
+macro_rules! info { ($($arg:tt)+) => { $crate::log_at!($crate::log::Level::Info, $($arg)+) }; }
+#[macro_export]
+macro_rules! debug { ($($arg:tt)+) => { $crate::log_at!($crate::log::Level::Debug, $($arg)+) }; }
+#[macro_export]
+macro_rules! trace { ($($arg:tt)+) => { $crate::log_at!($crate::log::Level::Trace, $($arg)+) }; }
@@ -0,0 +1,173 @@
+//! Volume details from DiskArbitration and IOKit (macOS).
+//!
+//! `sysinfo` only knows mount points and a removable flag that most USB drives don't set, so
+//! on macOS the picker asks DiskArbitration which volumes under `/Volumes` sit on an external
+//! device and how it is attached, and IOKit for the USB product name and link speed.
+
+use crate::device::Bus;
+use std::ffi::{c_char, c_void, CStr};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Where macOS mounts external media.
+const MEDIA_DIRS: &[&str] = &["/Volumes"];
+
+type CFTypeRef = *const c_void;
+type CFStringRef = *const c_void;
+type CFDictionaryRef = *const c_void;
+type CFIndex = isize;
+type IoObject = u32;
+
+const CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+const CF_NUMBER_SINT64_TYPE: CFIndex = 4;
+const IO_REGISTRY_ITERATE_RECURSIVELY: u32 = 1;
+const IO_REGISTRY_ITERATE_PARENTS: u32 = 2;
+
+#[allow(non_upper_case_globals)]
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    static kCFAllocatorDefault: CFTypeRef;
+    static kCFBooleanTrue: CFTypeRef;
+    fn CFRelease(cf: CFTypeRef);
+    fn CFGetTypeID(cf: CFTypeRef) -> usize;
+    fn CFStringGetTypeID() -> usize;
+    fn CFNumberGetTypeID() -> usize;
+    fn CFStringCreateWithCString(alloc: CFTypeRef, s: *const c_char, encoding: u32) -> CFStringRef;
+    fn CFStringGetCString(s: CFStringRef, buf: *mut c_char, len: CFIndex, encoding: u32) -> u8;
+    fn CFNumberGetValue(n: CFTypeRef, kind: CFIndex, out: *mut c_void) -> u8;
+    fn CFDictionaryGetValue(d: CFDictionaryRef, key: CFTypeRef) -> CFTypeRef;
+    fn CFURLCreateFromFileSystemRepresentation(alloc: CFTypeRef, buf: *const u8, len: CFIndex, is_dir: u8) -> CFTypeRef;
+}
+
+#[allow(non_upper_case_globals)]
+#[link(name = "DiskArbitration", kind = "framework")]
+extern "C" {
+    static kDADiskDescriptionDeviceInternalKey: CFStringRef;
+    static kDADiskDescriptionDeviceProtocolKey: CFStringRef;
+    static kDADiskDescriptionDeviceModelKey: CFStringRef;
+    fn DASessionCreate(alloc: CFTypeRef) -> CFTypeRef;
+    fn DADiskCreateFromVolumePath(alloc: CFTypeRef, session: CFTypeRef, path: CFTypeRef) -> CFTypeRef;
+    fn DADiskCopyDescription(disk: CFTypeRef) -> CFDictionaryRef;
+    fn DADiskCopyIOMedia(disk: CFTypeRef) -> IoObject;
+}
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IORegistryEntrySearchCFProperty(entry: IoObject, plane: *const c_char, key: CFStringRef, alloc: CFTypeRef, options: u32) -> CFTypeRef;
+    fn IOObjectRelease(object: IoObject) -> i32;
+}
+
+/// Releases a CoreFoundation object when dropped; holds null for "nothing".
+struct Owned(CFTypeRef);
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        if !self.0.is_null() { unsafe { CFRelease(self.0) } }
+    }
+}
+
+fn cf_str(s: &CStr) -> Owned {
+    Owned(unsafe { CFStringCreateWithCString(kCFAllocatorDefault, s.as_ptr(), CF_STRING_ENCODING_UTF8) })
+}
+
+fn to_string(v: CFTypeRef) -> Option<String> {
+    if v.is_null() || unsafe { CFGetTypeID(v) != CFStringGetTypeID() } { return None; }
+    let mut buf = [0 as c_char; 256];
+    if unsafe { CFStringGetCString(v, buf.as_mut_ptr(), buf.len() as CFIndex, CF_STRING_ENCODING_UTF8) } == 0 { return None; }
+    let s = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().trim().to_string();
+    (!s.is_empty()).then_some(s)
+}
+
+fn to_i64(v: CFTypeRef) -> Option<i64> {
+    if v.is_null() || unsafe { CFGetTypeID(v) != CFNumberGetTypeID() } { return None; }
+    let mut n = 0i64;
+    (unsafe { CFNumberGetValue(v, CF_NUMBER_SINT64_TYPE, (&mut n as *mut i64).cast()) } != 0).then_some(n)
+}
+
+/// What DiskArbitration and IOKit say about the device behind one volume.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Volume {
+    /// `DADeviceProtocol`, e.g. `USB`, `SATA`, `PCI-Express`, `Secure Digital`.
+    pub protocol: Option<String>,
+    pub internal: bool,
+    pub model: Option<String>,
+    /// The USB device's product string, when it is on USB.
+    pub usb_name: Option<String>,
+    /// Negotiated USB link speed in bits per second.
+    pub usb_speed: Option<u64>,
+}
+
+impl Volume {
+    pub(crate) fn bus(&self) -> Option<Bus> {
+        Some(match self.protocol.as_deref()? {
+            "USB" => Bus::Usb,
+            "Secure Digital" => Bus::Sd,
+            "SATA" | "ATA" => Bus::Sata,
+            "PCI-Express" | "PCI" | "NVMe" | "Apple Fabric" => Bus::Nvme,
+            "SAS" | "SCSI" | "Fibre Channel Interface" => Bus::Scsi,
+            "Virtual Interface" | "Disk Image" => Bus::Virtual,
+            _ => Bus::Other,
+        })
+    }
+}
+
+/// Searches the IOService plane from `media` up through its parents for `key`.
+fn io_property(media: IoObject, key: &CStr) -> Owned {
+    let key = cf_str(key);
+    Owned(unsafe {
+        IORegistryEntrySearchCFProperty(media, c"IOService".as_ptr(), key.0, kCFAllocatorDefault, IO_REGISTRY_ITERATE_RECURSIVELY | IO_REGISTRY_ITERATE_PARENTS)
+    })
+}
+
+/// Looks up the volume mounted at `mount`.
+pub(crate) fn volume(mount: &Path) -> Option<Volume> {
+    let bytes = mount.as_os_str().as_bytes();
+    let session = Owned(unsafe { DASessionCreate(kCFAllocatorDefault) });
+    if session.0.is_null() { return None; }
+    let url = Owned(unsafe { CFURLCreateFromFileSystemRepresentation(kCFAllocatorDefault, bytes.as_ptr(), bytes.len() as CFIndex, 1) });
+    if url.0.is_null() { return None; }
+    let disk = Owned(unsafe { DADiskCreateFromVolumePath(kCFAllocatorDefault, session.0, url.0) });
+    if disk.0.is_null() { return None; }
+    let desc = Owned(unsafe { DADiskCopyDescription(disk.0) });
+    if desc.0.is_null() { return None; }
+    let get = |key: CFStringRef| unsafe { CFDictionaryGetValue(desc.0, key) };
+    let mut v = unsafe {
+        Volume {
+            protocol: to_string(get(kDADiskDescriptionDeviceProtocolKey)),
+            internal: get(kDADiskDescriptionDeviceInternalKey) == kCFBooleanTrue,
+            model: to_string(get(kDADiskDescriptionDeviceModelKey)),
+            ..Volume::default()
+        }
+    };
+    if v.protocol.as_deref() == Some("USB") {
+        let media = unsafe { DADiskCopyIOMedia(disk.0) };
+        if media != 0 {
+            v.usb_name = to_string(io_property(media, c"USB Product Name").0);
+            // IOUSBHostDevice speed codes: low, full, high, super, super+ and super+ 2x2.
+            v.usb_speed = to_i64(io_property(media, c"Device Speed").0).and_then(|s| match s {
+                0 => Some(1_500_000),
+                1 => Some(12_000_000),
+                2 => Some(480_000_000),
+                3 => Some(5_000_000_000),
+                4 => Some(10_000_000_000),
+                5 => Some(20_000_000_000),
+                _ => None,
+            });
+            unsafe { IOObjectRelease(media) };
+        }
+    }
+    crate::trace!("{}: {v:?}", mount.display());
+    Some(v)
+}
+
+/// Mounted volumes under `/Volumes` on a device DiskArbitration calls external (the boot
+/// volume's `/Volumes` symlink and internal APFS volumes are left out).
+pub(crate) fn external_volumes() -> Vec<(PathBuf, Volume)> {
+    MEDIA_DIRS.iter()
+        .filter_map(|d| std::fs::read_dir(d).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter_map(|e| volume(&e.path()).map(|v| (e.path(), v)))
+        .filter(|(_, v)| !v.internal)
+        .collect()
+}
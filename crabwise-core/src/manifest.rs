@@ -0,0 +1,267 @@
+//! Sidecar manifests for test files kept with `keep_file`, so the file can be checked again
+//! later (`crabwise verify`), on any machine.
+//!
+//! The test file is one pattern block tiled from offset 0 with every 4 KiB stamped with its
+//! offset, so the manifest only needs the pattern's seed, the block size and the length.
+//! Verification regenerates the expected bytes for each offset and compares the file with them
+//! byte for byte, which also catches a drive handing back the right data at the wrong address.
+//!
+//! Version 1 manifests, from before the stamp, hold a checksum of the block instead: their
+//! file's first block is checked against it and every other block compared with the first;
+//! if the first block itself is damaged, each block is checked against the checksum instead.
+
+use crate::json::{self, Value};
+use crate::progress::{Phase, Progress, Update};
+use crate::sampler::Sampler;
+use crate::stats::mbs;
+use chrono::Local;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Appended to the test file's name to get its manifest's.
+pub const SUFFIX: &str = ".manifest.json";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub created: String,
+    /// File length when it was kept.
+    pub bytes: u64,
+    pub block: u64,
+    /// Seed the pattern block was generated from; `None` in a version 1 manifest.
+    pub seed: Option<u64>,
+    /// Version 1: FNV-1a 64 of one full pattern block.
+    pub block_hash: Option<u64>,
+    /// Version 1: FNV-1a 64 of the trailing partial block, when the length isn't a whole number of blocks.
+    pub tail_hash: Option<u64>,
+}
+
+/// 64-bit FNV-1a; enough to tell a damaged block from an intact one.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3))
+}
+
+pub fn path_for(file: &Path) -> PathBuf {
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(SUFFIX);
+    file.with_file_name(name)
+}
+
+impl Manifest {
+    /// Describes a file of `bytes` bytes holding the test pattern of `block`-byte blocks made from `seed`.
+    pub fn new(seed: u64, block: u64, bytes: u64) -> Manifest {
+        Manifest {
+            created: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            bytes,
+            block,
+            seed: Some(seed),
+            block_hash: None,
+            tail_hash: None,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        // Seeds and hashes are hex strings: JSON numbers lose 64-bit precision.
+        let hex = |h: Option<u64>| h.map(|h| format!("{h:016x}")).into();
+        let mut fields = vec![
+            ("version", if self.seed.is_some() { 2u64 } else { 1 }.into()),
+            ("created", self.created.as_str().into()),
+            ("bytes", self.bytes.into()),
+            ("block_bytes", self.block.into()),
+        ];
+        match self.seed {
+            Some(seed) => fields.push(("seed", hex(Some(seed)))),
+            None => fields.extend([("block_fnv1a", hex(self.block_hash)), ("tail_fnv1a", hex(self.tail_hash))]),
+        }
+        Value::object(fields)
+    }
+
+    pub fn from_json(v: &Value) -> Option<Manifest> {
+        let hex = |k: &str| v.get(k).and_then(Value::as_str).and_then(|s| u64::from_str_radix(s, 16).ok());
+        let seed = hex("seed");
+        Some(Manifest {
+            created: v.get("created").and_then(Value::as_str).unwrap_or_default().to_string(),
+            bytes: v.get("bytes")?.as_u64()?,
+            block: v.get("block_bytes")?.as_u64().filter(|&b| b > 0)?,
+            seed,
+            // Without a seed there is nothing to check against but the block's checksum.
+            block_hash: if seed.is_some() { None } else { Some(hex("block_fnv1a")?) },
+            tail_hash: hex("tail_fnv1a"),
+        })
+    }
+
+    /// Writes the manifest next to `file`.
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        std::fs::write(path_for(file), format!("{}\n", self.to_json()))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Manifest> {
+        let text = std::fs::read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+        json::parse(&text).ok().as_ref().and_then(Manifest::from_json)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{}: not a crabwise manifest", path.display())))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub bytes: u64,
+    pub seconds: f64,
+    /// Offsets of blocks that don't match.
+    pub bad_blocks: Vec<u64>,
+    /// Differing bytes across the bad blocks; `None` when a version 1 file's first block was
+    /// damaged, so blocks could only be checked by checksum.
+    pub bad_bytes: Option<u64>,
+    /// The file's length differs from the manifest's (`Some(actual)`).
+    pub length_mismatch: Option<u64>,
+}
+
+impl VerifyReport {
+    pub fn ok(&self) -> bool {
+        self.bad_blocks.is_empty() && self.length_mismatch.is_none()
+    }
+
+    pub fn mb_s(&self) -> f64 {
+        if self.seconds > 0.0 { mbs(self.bytes as u128, self.seconds) } else { 0.0 }
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("ok", self.ok().into()),
+            ("bytes", self.bytes.into()),
+            ("seconds", self.seconds.into()),
+            ("mb_s", self.mb_s().into()),
+            ("bad_blocks", Value::Array(self.bad_blocks.iter().map(|&o| o.into()).collect())),
+            ("bad_bytes", self.bad_bytes.into()),
+            ("length_mismatch", self.length_mismatch.into()),
+        ])
+    }
+}
+
+/// Reads `file` back block by block and checks it against `m`.
+pub fn verify(file: &Path, m: &Manifest, progress: &mut dyn Progress) -> io::Result<VerifyReport> {
+    let f = crate::file::open_read(file, false)?;
+    if let Err(e) = crate::file::evict(file) { crate::debug!("evict {}: {e}", file.display()); }
+    let actual = f.metadata()?.len();
+    let mut report = VerifyReport { length_mismatch: (actual != m.bytes).then_some(actual), ..VerifyReport::default() };
+    let end = actual.min(m.bytes);
+    let block = m.block as usize;
+    // With a seed, the expected bytes of every block are regenerated for its offset.
+    let tile = m.seed.map(|seed| crate::runner::pattern_block(seed, m.block));
+    if tile.is_some() { report.bad_bytes = Some(0); }
+    let (mut reference, mut buf) = (vec![0u8; block], vec![0u8; block]);
+    let t0 = Instant::now();
+    let mut sampler = Sampler::new(t0);
+    let mut lat = Vec::new();
+    let mut pos = 0;
+    while pos < end {
+        let len = block.min((end - pos) as usize);
+        let t = Instant::now();
+        crate::file::read_exact_at(&f, &mut buf[..len], pos)?;
+        lat.push(t.elapsed().as_secs_f64());
+        if let Some(tile) = &tile {
+            crate::runner::fill_pattern(tile, pos, &mut reference[..len]);
+        }
+        let expected = if len == block { m.block_hash } else { m.tail_hash };
+        if tile.is_none() && pos == 0 && len == block && Some(fnv1a(&buf)) == m.block_hash {
+            // An intact first block is the reference the rest is compared with byte for byte.
+            reference.copy_from_slice(&buf);
+            report.bad_bytes = Some(0);
+        }
+        let bad = match report.bad_bytes.as_mut() {
+            Some(total) => {
+                let n = buf[..len].iter().zip(&reference[..len]).filter(|(a, b)| a != b).count() as u64;
+                *total += n;
+                n > 0
+            }
+            None => Some(fnv1a(&buf[..len])) != expected,
+        };
+        if bad { report.bad_blocks.push(pos); }
+        pos += len as u64;
+        sampler.tick(pos, &lat);
+        progress.update(&Update { phase: Phase::Verify, done: pos, total: end, start: t0, samples: sampler.samples(), latencies: &lat })?;
+    }
+    progress.finish(Phase::Verify)?;
+    report.bytes = pos;
+    report.seconds = t0.elapsed().as_secs_f64();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoProgress;
+    use crate::runner::{fill_pattern, pattern_block};
+
+    fn round_trip(m: &Manifest) -> Option<Manifest> {
+        Manifest::from_json(&json::parse(&m.to_json().to_string()).unwrap())
+    }
+
+    /// A test file of `bytes` bytes as a run with `seed` and `block` leaves it, and its manifest.
+    fn kept_file(name: &str, seed: u64, block: u64, bytes: u64) -> (PathBuf, Manifest) {
+        let path = std::env::temp_dir().join(format!("crabwise-manifest-{}-{name}.tmp", std::process::id()));
+        let mut data = vec![0u8; bytes as usize];
+        fill_pattern(&pattern_block(seed, block), 0, &mut data);
+        std::fs::write(&path, data).unwrap();
+        (path, Manifest::new(seed, block, bytes))
+    }
+
+    #[test]
+    fn manifests_round_trip() {
+        let m = Manifest::new(u64::MAX - 5, 1 << 20, (3 << 20) + 17);
+        assert_eq!(round_trip(&m), Some(m));
+        let v1 = Manifest { seed: None, block_hash: Some(0xdead_beef_0000_0001), tail_hash: Some(42), ..Manifest::new(0, 4096, 5000) };
+        assert_eq!(round_trip(&v1), Some(v1));
+    }
+
+    #[test]
+    fn malformed_manifests_are_rejected() {
+        let parse = |s: &str| Manifest::from_json(&json::parse(s).unwrap());
+        assert!(parse(r#"{"version":2,"bytes":10,"block_bytes":0,"seed":"01"}"#).is_none());
+        assert!(parse(r#"{"version":1,"bytes":10,"block_bytes":4096}"#).is_none());
+        assert!(parse(r#"{"version":2,"block_bytes":4096,"seed":"01"}"#).is_none());
+    }
+
+    #[test]
+    fn intact_file_verifies() {
+        let (path, m) = kept_file("intact", 9, 64 << 10, (256 << 10) + 100);
+        let r = verify(&path, &m, &mut NoProgress).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(r.ok(), "{r:?}");
+        assert_eq!((r.bytes, r.bad_bytes), (m.bytes, Some(0)));
+    }
+
+    #[test]
+    fn moved_and_damaged_blocks_are_found() {
+        let block = 64 << 10;
+        let (path, m) = kept_file("moved", 9, block, 8 * block);
+        let mut data = std::fs::read(&path).unwrap();
+        // Block 1's data at block 5, as from a drive wrapping its addresses, and one flipped byte in block 6.
+        data.copy_within(block as usize..2 * block as usize, 5 * block as usize);
+        data[6 * block as usize + 10] ^= 1;
+        std::fs::write(&path, &data[..7 * block as usize]).unwrap();
+        let r = verify(&path, &m, &mut NoProgress).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(r.bad_blocks, [5 * block, 6 * block]);
+        assert_eq!(r.length_mismatch, Some(7 * block));
+        assert!(!r.ok());
+    }
+
+    #[test]
+    fn version_1_files_still_verify() {
+        let block = 4096;
+        let tile = pattern_block(2, block);
+        let path = std::env::temp_dir().join(format!("crabwise-manifest-{}-v1.tmp", std::process::id()));
+        let mut data = tile.repeat(10);
+        data.extend_from_slice(&tile[..100]);
+        data[3 * block as usize] ^= 0xff;
+        std::fs::write(&path, &data).unwrap();
+        let m = Manifest {
+            seed: None, block_hash: Some(fnv1a(&tile)), tail_hash: Some(fnv1a(&tile[..100])),
+            ..Manifest::new(0, block, data.len() as u64)
+        };
+        let r = verify(&path, &m, &mut NoProgress).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(r.bad_blocks, [3 * block]);
+        assert_eq!(r.bad_bytes, Some(1));
+    }
+}
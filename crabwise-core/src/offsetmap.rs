@@ -0,0 +1,72 @@
+//! Throughput per region of the test file, so slow stretches of flash show up by position
+//! instead of being averaged into one number.
+
+use crate::json::Value;
+use crate::stats::{mbs, percentile};
+
+/// Regions a map is split into (1% of the file each), fewer when the file has fewer blocks.
+pub const REGIONS: usize = 100;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OffsetMap {
+    /// Bytes covered; region `i` starts at [`region_start(i)`](OffsetMap::region_start).
+    pub bytes: u64,
+    /// MB/s per region, front to back.
+    pub mb_s: Vec<f64>,
+}
+
+impl OffsetMap {
+    /// Splits `total` bytes into `regions` equal parts and times each from `(offset, length,
+    /// seconds)` per I/O; an I/O counts toward the region holding its first byte. I/Os from
+    /// several passes over the same file add up.
+    pub fn build(total: u64, regions: usize, ios: impl IntoIterator<Item = (u64, u64, f64)>) -> OffsetMap {
+        let n = regions.max(1);
+        let (mut bytes, mut secs) = (vec![0u64; n], vec![0f64; n]);
+        for (off, len, s) in ios {
+            let i = ((off as u128 * n as u128 / total.max(1) as u128) as usize).min(n - 1);
+            bytes[i] += len;
+            secs[i] += s;
+        }
+        let mb_s = bytes.iter().zip(&secs).map(|(&b, &s)| if s > 0.0 { mbs(b as u128, s) } else { 0.0 }).collect();
+        OffsetMap { bytes: total, mb_s }
+    }
+
+    /// Byte offset where region `i` starts.
+    pub fn region_start(&self, i: usize) -> u64 {
+        (self.bytes as u128 * i as u128 / self.mb_s.len().max(1) as u128) as u64
+    }
+
+    /// `(region, MB/s)` of the slowest region.
+    pub fn slowest(&self) -> Option<(usize, f64)> {
+        self.mb_s.iter().copied().enumerate().min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// `(region, MB/s)` of the fastest region.
+    pub fn fastest(&self) -> Option<(usize, f64)> {
+        self.mb_s.iter().copied().enumerate().max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    pub fn median(&self) -> f64 {
+        percentile(&self.mb_s, 50.0)
+    }
+
+    /// `(percent of the file, MB/s)` at the start of each region, for charts.
+    pub fn points(&self) -> Vec<(f64, f64)> {
+        let n = self.mb_s.len().max(1) as f64;
+        self.mb_s.iter().enumerate().map(|(i, &v)| (i as f64 * 100.0 / n, v)).collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("bytes", self.bytes.into()),
+            ("mb_s", Value::Array(self.mb_s.iter().map(|&v| v.into()).collect())),
+        ])
+    }
+
+    pub fn from_json(v: &Value) -> Option<OffsetMap> {
+        Some(OffsetMap {
+            bytes: v.get("bytes")?.as_u64()?,
+            mb_s: v.get("mb_s")?.as_array()?.iter().filter_map(Value::as_f64).collect(),
+        })
+    }
+}
@@ -0,0 +1,196 @@
+//! Parsers for the human-friendly sizes, rates and durations accepted on the command line.
+
+use crate::config::ReadPattern;
+use crate::device::DeviceSpec;
+use crate::guard::AbortBelow;
+use crate::wipe::WipePattern;
+
+/// Parses a size such as `1G`, `1.5G`, `512MiB`, `2GB` or `64K` into bytes.
+///
+/// Bare `K`/`M`/`G`/`T` and the `KiB`…`TiB` forms are binary (base 1024); `KB`…`TB` are
+/// decimal (base 1000), matching how drives are labelled. The suffix is case-insensitive.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    const FORMS: &str = "expected e.g. 512M, 1.5G, 4KiB or 2GB";
+    let t = s.trim();
+    let split = t.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(t.len());
+    let (num, unit) = t.split_at(split);
+    let n: f64 = num.parse().map_err(|_| format!("invalid size '{s}' ({FORMS})"))?;
+    let mult: f64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KIB" => 1024.0,
+        "M" | "MIB" => 1024.0 * 1024.0,
+        "G" | "GIB" => 1024.0 * 1024.0 * 1024.0,
+        "T" | "TIB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        other => return Err(format!("invalid size '{s}' (unknown unit '{other}'; {FORMS})")),
+    };
+    let bytes = n * mult;
+    if bytes >= u64::MAX as f64 { return Err(format!("size '{s}' is too large")); }
+    Ok(bytes.round() as u64)
+}
+
+/// Parses a throughput such as `100MBps`, `100MB/s`, `800Mbps` or `95MiB/s` into bytes per second.
+/// An upper-case `B` means bytes and a lower-case `b` means bits; the prefix is case-insensitive.
+pub fn parse_rate(s: &str) -> Result<f64, String> {
+    let t = s.trim();
+    let split = t.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(t.len());
+    let (num, unit) = t.split_at(split);
+    let n: f64 = num.parse().map_err(|_| format!("invalid rate '{s}' (expected e.g. 100MBps or 800Mbps)"))?;
+    let unit = unit.trim();
+    let unit = unit.strip_suffix("/s").or_else(|| unit.strip_suffix("ps")).unwrap_or(unit);
+    let (prefix, bits) = match unit.strip_suffix('B') {
+        Some(p) => (p, false),
+        None => match unit.strip_suffix('b') {
+            Some(p) => (p, true),
+            None => return Err(format!("invalid rate '{s}' (missing B/s or b/s unit)")),
+        },
+    };
+    let mult = match prefix.to_ascii_uppercase().as_str() {
+        "" => 1.0,
+        "K" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "KI" => 1024.0,
+        "MI" => 1024.0 * 1024.0,
+        "GI" => 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(format!("invalid rate '{s}' (unknown prefix '{prefix}')")),
+    };
+    let bytes = n * mult;
+    Ok(if bits { bytes / 8.0 } else { bytes })
+}
+
+/// Parses a read pattern: `seq`, `reverse` or `stride:<n>` (every n-th block, n >= 1).
+pub fn parse_read_pattern(s: &str) -> Result<ReadPattern, String> {
+    let t = s.trim().to_ascii_lowercase();
+    match t.as_str() {
+        "seq" | "sequential" => Ok(ReadPattern::Sequential),
+        "reverse" => Ok(ReadPattern::Reverse),
+        _ => match t.strip_prefix("stride:").map(str::parse::<u64>) {
+            Some(Ok(n)) if n > 0 => Ok(ReadPattern::Stride(n)),
+            _ => Err(format!("invalid read pattern '{s}' (expected seq, reverse or stride:<n>)")),
+        },
+    }
+}
+
+/// Parses a wipe pattern: `zero`, `ones`, `random`, or one byte value such as `0xAA`.
+pub fn parse_wipe_pattern(s: &str) -> Result<WipePattern, String> {
+    let t = s.trim().to_ascii_lowercase();
+    match t.as_str() {
+        "zero" | "zeros" => Ok(WipePattern::Zero),
+        "ones" => Ok(WipePattern::Ones),
+        "random" => Ok(WipePattern::Random),
+        _ => match t.strip_prefix("0x").map(|h| u8::from_str_radix(h, 16)) {
+            Some(Ok(b)) => Ok(WipePattern::Byte(b)),
+            _ => Err(format!("invalid wipe pattern '{s}' (expected zero, ones, random or a byte like 0xAA)")),
+        },
+    }
+}
+
+/// Parses a duration such as `50ms`, `1.5s` or `250us` into seconds.
+pub fn parse_duration(s: &str) -> Result<f64, String> {
+    let t = s.trim();
+    let split = t.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(t.len());
+    let (num, unit) = t.split_at(split);
+    let n: f64 = num.parse().map_err(|_| format!("invalid duration '{s}' (expected e.g. 50ms or 2s)"))?;
+    let mult = match unit.trim() {
+        "us" => 1e-6,
+        "ms" => 1e-3,
+        "" | "s" => 1.0,
+        "m" | "min" => 60.0,
+        "h" => 3600.0,
+        other => return Err(format!("invalid duration '{s}' (unknown unit '{other}')")),
+    };
+    Ok(n * mult)
+}
+
+/// Parses an abort floor, `RATE@DURATION` such as `5MBps@30s`: give up once throughput stays
+/// under the rate for the whole duration.
+pub fn parse_abort_below(s: &str) -> Result<AbortBelow, String> {
+    let (rate, window) = s.split_once('@').ok_or(format!("invalid floor '{s}' (expected RATE@DURATION, e.g. 5MBps@30s)"))?;
+    let window = parse_duration(window)?;
+    if window <= 0.0 { return Err(format!("invalid floor '{s}' (the duration must be greater than 0)")); }
+    Ok(AbortBelow { floor: parse_rate(rate)?, window })
+}
+
+/// Parses a `--device` name: `serial:<serial>`, a drive letter such as `E:`, or a label.
+pub fn parse_device(s: &str) -> Result<DeviceSpec, String> {
+    let t = s.trim();
+    if let Some(serial) = t.strip_prefix("serial:") {
+        if serial.trim().is_empty() { return Err(format!("invalid device '{s}' (serial: needs a serial number)")); }
+        return Ok(DeviceSpec::Serial(serial.trim().to_string()));
+    }
+    let letter = t.trim_end_matches('\\');
+    if let [c, b':'] = letter.as_bytes() {
+        if c.is_ascii_alphabetic() { return Ok(DeviceSpec::Letter(c.to_ascii_uppercase() as char)); }
+    }
+    if t.is_empty() { return Err("invalid device '' (expected a label, serial:<serial> or a drive letter like E:)".to_string()); }
+    Ok(DeviceSpec::Label(t.to_string()))
+}
+
+/// Parses a comma-separated list of queue depths such as `1,2,4,8`, each at least 1.
+pub fn parse_queue_depths(s: &str) -> Result<Vec<u32>, String> {
+    s.split(',').map(|d| match d.trim().parse::<u32>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("invalid queue depth '{}' in '{s}' (expected e.g. 1,2,4,8)", d.trim())),
+    }).collect()
+}
+
+/// Parses a result tag, `key=value`; the key may not be empty or contain whitespace.
+pub fn parse_tag(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.trim().is_empty() && !k.trim().contains(char::is_whitespace) => Ok((k.trim().to_string(), v.trim().to_string())),
+        _ => Err(format!("invalid tag '{s}' (expected key=value, e.g. port=front-usb-c)")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("64K"), Ok(64 << 10));
+        assert_eq!(parse_size("512mib"), Ok(512 << 20));
+        assert_eq!(parse_size("1.5G"), Ok(3 << 29));
+        assert_eq!(parse_size("2GB"), Ok(2_000_000_000));
+        assert_eq!(parse_size(" 1 TiB "), Ok(1 << 40));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("12X").is_err());
+        assert!(parse_size("-1G").is_err());
+        assert!(parse_size("99999999T").is_err());
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("2"), Ok(2.0));
+        assert_eq!(parse_duration("50ms"), Ok(0.05));
+        assert_eq!(parse_duration("250us"), Ok(0.000_25));
+        assert_eq!(parse_duration("1.5s"), Ok(1.5));
+        assert_eq!(parse_duration("10min"), Ok(600.0));
+        assert_eq!(parse_duration("2h"), Ok(7200.0));
+        assert!(parse_duration("ms").is_err());
+        assert!(parse_duration("3d").is_err());
+    }
+
+    #[test]
+    fn rates() {
+        assert_eq!(parse_rate("100MBps"), Ok(100e6));
+        assert_eq!(parse_rate("100MB/s"), Ok(100e6));
+        assert_eq!(parse_rate("800Mbps"), Ok(100e6));
+        assert_eq!(parse_rate("1MiB/s"), Ok(1024.0 * 1024.0));
+        assert!(parse_rate("100M").is_err());
+    }
+
+    #[test]
+    fn read_patterns() {
+        assert_eq!(parse_read_pattern("seq"), Ok(ReadPattern::Sequential));
+        assert_eq!(parse_read_pattern("Reverse"), Ok(ReadPattern::Reverse));
+        assert_eq!(parse_read_pattern("stride:4"), Ok(ReadPattern::Stride(4)));
+        assert!(parse_read_pattern("stride:0").is_err());
+        assert!(parse_read_pattern("shuffled").is_err());
+    }
+}
@@ -0,0 +1,119 @@
+//! Progress callbacks from the write/read loops.
+
+use crate::result::BenchResult;
+use crate::sampler::Sample;
+use std::io;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    CompressionCheck,
+    BlockProbe,
+    Write,
+    Read,
+    CachedRead,
+    RandomWrite,
+    RandomRead,
+    Sustained,
+    ShuffledRead,
+    Access,
+    ParallelWrite,
+    ParallelRead,
+    SmallWrite,
+    SmallRead,
+    Fsync,
+    DirtyWrite,
+    TrimmedWrite,
+    Scan,
+    Wipe,
+    WipeVerify,
+    Verify,
+    Replay,
+    Copy,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::CompressionCheck => "Compression check",
+            Phase::BlockProbe => "Tuning block size",
+            Phase::Write => "Writing",
+            Phase::Read => "Reading",
+            Phase::CachedRead => "Cached read",
+            Phase::RandomWrite => "Random write",
+            Phase::RandomRead => "Random read",
+            Phase::Sustained => "Sustained write",
+            Phase::ShuffledRead => "Shuffled read",
+            Phase::Access => "Access time",
+            Phase::ParallelWrite => "Parallel write",
+            Phase::ParallelRead => "Parallel read",
+            Phase::SmallWrite => "Small-file write",
+            Phase::SmallRead => "Small-file read",
+            Phase::Fsync => "Write+fsync",
+            Phase::DirtyWrite => "Dirty rewrite",
+            Phase::TrimmedWrite => "Trimmed rewrite",
+            Phase::Scan => "Scanning",
+            Phase::Wipe => "Wiping",
+            Phase::WipeVerify => "Verifying wipe",
+            Phase::Verify => "Verifying",
+            Phase::Replay => "Replaying trace",
+            Phase::Copy => "Copying",
+        }
+    }
+
+    /// Lower-case identifier used in machine-readable output.
+    pub fn id(self) -> &'static str {
+        match self {
+            Phase::CompressionCheck => "compression_check",
+            Phase::BlockProbe => "block_probe",
+            Phase::Write => "write",
+            Phase::Read => "read",
+            Phase::CachedRead => "cached_read",
+            Phase::RandomWrite => "random_write",
+            Phase::RandomRead => "random_read",
+            Phase::Sustained => "sustained",
+            Phase::ShuffledRead => "shuffled_read",
+            Phase::Access => "access",
+            Phase::ParallelWrite => "parallel_write",
+            Phase::ParallelRead => "parallel_read",
+            Phase::SmallWrite => "small_write",
+            Phase::SmallRead => "small_read",
+            Phase::Fsync => "fsync",
+            Phase::DirtyWrite => "dirty_write",
+            Phase::TrimmedWrite => "trimmed_write",
+            Phase::Scan => "scan",
+            Phase::Wipe => "wipe",
+            Phase::WipeVerify => "wipe_verify",
+            Phase::Verify => "verify",
+            Phase::Replay => "replay",
+            Phase::Copy => "copy",
+        }
+    }
+}
+
+/// Snapshot handed to a `Progress` after every block.
+pub struct Update<'a> {
+    pub phase: Phase,
+    pub done: u64,
+    pub total: u64,
+    pub start: Instant,
+    pub samples: &'a [Sample],
+    /// Per-block latencies (seconds) so far in this phase.
+    pub latencies: &'a [f64],
+}
+
+/// Receives progress from the benchmark loops. Returning an error aborts the run.
+pub trait Progress {
+    fn update(&mut self, u: &Update) -> io::Result<()>;
+    fn finish(&mut self, phase: Phase) -> io::Result<()>;
+    /// Called once with the final result, before the runner returns it.
+    fn done(&mut self, _result: &BenchResult) -> io::Result<()> { Ok(()) }
+}
+
+/// Ignores all progress.
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn update(&mut self, _u: &Update) -> io::Result<()> { Ok(()) }
+    fn finish(&mut self, _phase: Phase) -> io::Result<()> { Ok(()) }
+}
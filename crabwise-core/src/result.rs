@@ -0,0 +1,521 @@
+//! Benchmark results and their machine-readable form (one JSON object per run).
+//!
+//! Records are appended as JSON lines with `--record FILE` and consumed by
+//! `crabwise report`.
+
+use crate::config::ReadPattern;
+use crate::cpu::CpuUsage;
+use crate::extents::Fragmentation;
+use crate::device::DeviceInfo;
+use crate::host::HostInfo;
+use crate::transform::Transform;
+use crate::tune::BlockTune;
+use crate::usbpower::UsbPower;
+use crate::json::{self, Value};
+use crate::offsetmap::OffsetMap;
+use crate::parse::parse_read_pattern;
+use crate::sampler::Sample;
+use crate::stats::percentile;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Measurements for one phase (write or read).
+#[derive(Debug, Clone, Default)]
+pub struct PhaseStats {
+    pub bytes: u64,
+    pub seconds: f64,
+    /// Individual write/read (or open) calls timed; one latency sample each.
+    pub ops: u64,
+    pub latency_avg_ms: f64,
+    pub latency_p99_ms: f64,
+    /// Per-interval throughput samples (see `sampler`).
+    pub samples: Vec<Sample>,
+    /// Host CPU load while the phase ran; `None` in records from before it was measured.
+    pub cpu: Option<CpuUsage>,
+}
+
+impl PhaseStats {
+    /// Throughput in decimal MB/s.
+    pub fn mb_s(&self) -> f64 { crate::stats::mbs(self.bytes as u128, self.seconds) }
+    /// Throughput in decimal Mbps.
+    pub fn mbps(&self) -> f64 { crate::stats::mbps(self.bytes as u128, self.seconds) }
+    /// Operations per second, given the size of each operation.
+    pub fn iops(&self, io_bytes: u64) -> f64 {
+        if self.seconds > 0.0 { self.bytes as f64 / io_bytes as f64 / self.seconds } else { 0.0 }
+    }
+    /// Calls per second actually made, whatever their size.
+    pub fn ops_per_s(&self) -> f64 {
+        if self.seconds > 0.0 { self.ops as f64 / self.seconds } else { 0.0 }
+    }
+    /// 100% minus the coefficient of variation of the per-second throughput samples, floored
+    /// at 0; `None` with fewer than two samples.
+    pub fn consistency_pct(&self) -> Option<f64> {
+        if self.samples.len() < 2 { return None; }
+        let rates: Vec<f64> = self.samples.iter().map(|s| s.mb_s).collect();
+        let m = crate::stats::mean(&rates);
+        if m <= 0.0 { return None; }
+        Some(((1.0 - crate::stats::std_dev(&rates) / m) * 100.0).max(0.0))
+    }
+    /// Slowest per-second throughput sample in MB/s; `None` without samples.
+    pub fn worst_sample_mb_s(&self) -> Option<f64> {
+        self.samples.iter().map(|s| s.mb_s).reduce(f64::min)
+    }
+    /// Samples as `(seconds, MB/s)` chart points.
+    pub fn points(&self) -> Vec<(f64, f64)> { self.samples.iter().map(|s| (s.t, s.mb_s)).collect() }
+}
+
+/// The compression pre-check: the same amount written as zeros and as unique random data.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CompressionCheck {
+    pub zero_mb_s: f64,
+    pub random_mb_s: f64,
+}
+
+impl CompressionCheck {
+    /// Zero-fill speed as a multiple of the random-data speed above which the controller is
+    /// taken to compress or deduplicate.
+    pub const SUSPECT_RATIO: f64 = 1.5;
+
+    pub fn ratio(&self) -> f64 {
+        if self.random_mb_s > 0.0 { self.zero_mb_s / self.random_mb_s } else { 0.0 }
+    }
+
+    /// Whether zeros wrote so much faster that the controller is likely shrinking them.
+    pub fn suspected(&self) -> bool {
+        self.ratio() >= Self::SUSPECT_RATIO
+    }
+
+    fn to_json(self) -> Value {
+        Value::object(vec![
+            ("zero_mb_s", self.zero_mb_s.into()),
+            ("random_mb_s", self.random_mb_s.into()),
+            ("ratio", self.ratio().into()),
+            ("suspected", self.suspected().into()),
+        ])
+    }
+
+    fn from_json(v: &Value) -> Option<CompressionCheck> {
+        Some(CompressionCheck { zero_mb_s: v.get("zero_mb_s")?.as_f64()?, random_mb_s: v.get("random_mb_s")?.as_f64()? })
+    }
+}
+
+/// Distribution of one kind of operation's latency, for stages where the tail is the result.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyStats {
+    pub ops: u64,
+    /// Bytes written or read by each operation.
+    pub io_bytes: u64,
+    pub seconds: f64,
+    pub avg_ms: f64,
+    pub min_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub p999_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// Summarizes per-operation latencies given in seconds.
+    pub fn from_secs(lat: &[f64], io_bytes: u64, seconds: f64) -> LatencyStats {
+        let ms = |p: f64| percentile(lat, p) * 1e3;
+        LatencyStats {
+            ops: lat.len() as u64,
+            io_bytes,
+            seconds,
+            avg_ms: crate::stats::mean(lat) * 1e3,
+            min_ms: lat.iter().copied().reduce(f64::min).unwrap_or(0.0) * 1e3,
+            p50_ms: ms(50.0),
+            p90_ms: ms(90.0),
+            p99_ms: ms(99.0),
+            p999_ms: ms(99.9),
+            max_ms: lat.iter().copied().fold(0.0, f64::max) * 1e3,
+        }
+    }
+
+    /// Operations per second, one after another.
+    pub fn ops_per_s(&self) -> f64 {
+        if self.seconds > 0.0 { self.ops as f64 / self.seconds } else { 0.0 }
+    }
+
+    fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("ops", self.ops.into()),
+            ("io_bytes", self.io_bytes.into()),
+            ("seconds", self.seconds.into()),
+            ("ops_per_s", self.ops_per_s().into()),
+            ("avg_ms", self.avg_ms.into()),
+            ("min_ms", self.min_ms.into()),
+            ("p50_ms", self.p50_ms.into()),
+            ("p90_ms", self.p90_ms.into()),
+            ("p99_ms", self.p99_ms.into()),
+            ("p999_ms", self.p999_ms.into()),
+            ("max_ms", self.max_ms.into()),
+        ])
+    }
+
+    fn from_json(v: &Value) -> Option<LatencyStats> {
+        let ms = |k: &str| v.get(k).and_then(Value::as_f64).unwrap_or(0.0);
+        Some(LatencyStats {
+            ops: v.get("ops")?.as_u64()?,
+            io_bytes: v.get("io_bytes").and_then(Value::as_u64).unwrap_or(0),
+            seconds: ms("seconds"),
+            avg_ms: ms("avg_ms"),
+            min_ms: ms("min_ms"),
+            p50_ms: ms("p50_ms"),
+            p90_ms: ms("p90_ms"),
+            p99_ms: ms("p99_ms"),
+            p999_ms: ms("p999_ms"),
+            max_ms: ms("max_ms"),
+        })
+    }
+}
+
+/// Everything measured in one benchmark run.
+#[derive(Debug, Clone, Default)]
+pub struct BenchResult {
+    pub timestamp: String,
+    pub session: Option<String>,
+    /// `key=value` labels given with the run, in the order given.
+    pub tags: Vec<(String, String)>,
+    pub target_dir: String,
+    pub test_file: String,
+    pub device: Option<DeviceInfo>,
+    /// The machine the run was performed on; `None` in records from before it was stored.
+    pub host: Option<HostInfo>,
+    /// Stable name of the physical drive (see [`Identity::fingerprint`](crate::device::Identity::fingerprint)),
+    /// for grouping runs of one unit across mount points.
+    pub fingerprint: Option<String>,
+    /// Current the drive's USB device asks for and its port supplies; `None` off USB.
+    pub usb_power: Option<UsbPower>,
+    /// Encryption and compression the host applies on the target's volume.
+    pub transforms: Vec<Transform>,
+    pub size_bytes: u64,
+    pub block_bytes: u64,
+    /// The probes `block_bytes` was picked from, when the block size was auto-tuned.
+    pub block_tune: Option<BlockTune>,
+    /// Sequential passes aggregated into `write` and `read`.
+    pub iterations: u32,
+    /// Whether the test file could be preallocated before the write passes; `None` when
+    /// preallocation wasn't asked for.
+    pub preallocated: Option<bool>,
+    /// How the test file was laid out on disk after the first write pass; `None` where the
+    /// filesystem or OS doesn't tell.
+    pub fragmentation: Option<Fragmentation>,
+    /// Zero-fill versus random-data write speed, when the compression check ran.
+    pub compression: Option<CompressionCheck>,
+    pub write: PhaseStats,
+    pub read: PhaseStats,
+    /// Block order of the `read` pass.
+    pub read_pattern: ReadPattern,
+    /// 4 KiB random stages, when requested.
+    pub random_write: Option<PhaseStats>,
+    pub random_read: Option<PhaseStats>,
+    /// 4 KiB random reads at each queue depth of the sweep, in the order run; empty when not requested.
+    pub qd_sweep: Vec<(u32, PhaseStats)>,
+    /// Test files written and read at once in `parallel_write`/`parallel_read` (1 when not run).
+    pub files: u32,
+    /// Aggregate throughput across all `files`, when more than one was requested.
+    pub parallel_write: Option<PhaseStats>,
+    pub parallel_read: Option<PhaseStats>,
+    /// Every block of the test file read once in shuffled order, when requested.
+    pub shuffled_read: Option<PhaseStats>,
+    /// A sequential read served from the OS cache, next to `read` from the device.
+    pub cached_read: Option<PhaseStats>,
+    /// Time-limited sequential write, when requested.
+    pub sustained: Option<PhaseStats>,
+    /// Small-file stages (see `runner::SMALL_FILE_BYTES`), when requested.
+    pub small_write: Option<PhaseStats>,
+    pub small_read: Option<PhaseStats>,
+    /// Open+close of an existing small file: one metadata round trip each. Only the latency fields apply.
+    pub round_trip: Option<PhaseStats>,
+    /// Single-block reads at random offsets in the test file, one at a time, when requested.
+    pub access: Option<LatencyStats>,
+    /// Small appends each followed by an fsync, when requested; one latency sample per write+fsync.
+    pub fsync: Option<LatencyStats>,
+    /// Whether the device accepts TRIM/discard; `None` when that can't be told.
+    pub discard: Option<bool>,
+    /// Bytes the filesystem reported trimmed after the run, when a TRIM was requested and succeeded.
+    pub trimmed_bytes: Option<u64>,
+    /// The test file rewritten over just-freed blocks, then again after a TRIM, when compared.
+    pub dirty_write: Option<PhaseStats>,
+    pub trimmed_write: Option<PhaseStats>,
+    /// What the corruption check found changed in the filesystem's metadata; `None` when it
+    /// wasn't requested or the raw device couldn't be read.
+    pub metadata_changes: Option<Vec<String>>,
+    /// Write speed per region of the test file, when `offset_map` was on.
+    pub write_map: Option<OffsetMap>,
+    /// Read speed per region of the test file, when `offset_map` was on.
+    pub read_map: Option<OffsetMap>,
+    /// Bytes that did not read back as written; `None` when verification was off.
+    pub verify_mismatches: Option<u64>,
+    /// Threshold (and verify) violations; `None` when neither was requested.
+    pub threshold_failures: Option<Vec<String>>,
+}
+
+impl DeviceInfo {
+    fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("name", self.name.as_str().into()),
+            ("mount_point", self.mount_point.as_str().into()),
+            ("file_system", self.file_system.as_str().into()),
+            ("total_bytes", self.total_bytes.into()),
+            ("available_bytes", self.available_bytes.into()),
+            ("removable", self.removable.into()),
+        ])
+    }
+
+    fn from_json(v: &Value) -> Option<DeviceInfo> {
+        Some(DeviceInfo {
+            name: str_field(v, "name"),
+            mount_point: str_field(v, "mount_point"),
+            file_system: str_field(v, "file_system"),
+            total_bytes: v.get("total_bytes")?.as_u64()?,
+            available_bytes: v.get("available_bytes").and_then(Value::as_u64).unwrap_or(0),
+            removable: v.get("removable").and_then(Value::as_bool).unwrap_or(false),
+        })
+    }
+}
+
+impl PhaseStats {
+    fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("bytes", self.bytes.into()),
+            ("seconds", self.seconds.into()),
+            ("mb_s", self.mb_s().into()),
+            ("mbps", self.mbps().into()),
+            ("ops", self.ops.into()),
+            ("iops", self.ops_per_s().into()),
+            ("consistency_pct", self.consistency_pct().into()),
+            ("worst_1s_mb_s", self.worst_sample_mb_s().into()),
+            ("cpu", self.cpu.map(|c| Value::object(vec![
+                ("process_pct", c.process_pct.into()),
+                ("system_pct", c.system_pct.into()),
+                ("busiest_core_pct", c.busiest_core_pct.into()),
+            ])).unwrap_or(Value::Null)),
+            ("latency_avg_ms", self.latency_avg_ms.into()),
+            ("latency_p99_ms", self.latency_p99_ms.into()),
+            ("samples", Value::Array(self.samples.iter().map(|s| [s.t, s.mb_s].into_iter().chain(s.latency_ms).collect::<Vec<_>>().into()).collect())),
+        ])
+    }
+
+    fn from_json(v: &Value) -> Option<PhaseStats> {
+        Some(PhaseStats {
+            bytes: v.get("bytes")?.as_u64()?,
+            seconds: v.get("seconds")?.as_f64()?,
+            ops: v.get("ops").and_then(Value::as_u64).unwrap_or(0),
+            latency_avg_ms: v.get("latency_avg_ms").and_then(Value::as_f64).unwrap_or(0.0),
+            latency_p99_ms: v.get("latency_p99_ms").and_then(Value::as_f64).unwrap_or(0.0),
+            samples: v.get("samples").and_then(Value::as_array).unwrap_or_default().iter().filter_map(|p| {
+                let p = p.as_array()?;
+                Some(Sample { t: p.first()?.as_f64()?, mb_s: p.get(1)?.as_f64()?, latency_ms: p.get(2).and_then(Value::as_f64) })
+            }).collect(),
+            cpu: v.get("cpu").filter(|c| !matches!(c, Value::Null)).map(|c| CpuUsage {
+                process_pct: c.get("process_pct").and_then(Value::as_f64).unwrap_or(0.0),
+                system_pct: c.get("system_pct").and_then(Value::as_f64).unwrap_or(0.0),
+                busiest_core_pct: c.get("busiest_core_pct").and_then(Value::as_f64).unwrap_or(0.0),
+            }),
+        })
+    }
+}
+
+fn opt_phase(p: &Option<PhaseStats>) -> Value {
+    p.as_ref().map(PhaseStats::to_json).unwrap_or(Value::Null)
+}
+
+fn str_field(v: &Value, key: &str) -> String {
+    v.get(key).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+impl BenchResult {
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("timestamp", self.timestamp.as_str().into()),
+            ("session", self.session.clone().into()),
+            ("tags", Value::object(self.tags.iter().map(|(k, v)| (k.as_str(), v.as_str().into())).collect())),
+            ("target_dir", self.target_dir.as_str().into()),
+            ("test_file", self.test_file.as_str().into()),
+            ("device", self.device.as_ref().map(DeviceInfo::to_json).unwrap_or(Value::Null)),
+            ("host", self.host.as_ref().map(HostInfo::to_json).unwrap_or(Value::Null)),
+            ("fingerprint", self.fingerprint.clone().into()),
+            ("usb_power", self.usb_power.as_ref().map(UsbPower::to_json).unwrap_or(Value::Null)),
+            ("transforms", self.transforms.iter().map(|t| t.id()).collect::<Vec<_>>().into()),
+            ("size_bytes", self.size_bytes.into()),
+            ("block_bytes", self.block_bytes.into()),
+            ("block_tune", self.block_tune.as_ref().map(BlockTune::to_json).unwrap_or(Value::Null)),
+            ("iterations", u64::from(self.iterations).into()),
+            ("preallocated", self.preallocated.into()),
+            ("fragmentation", self.fragmentation.as_ref().map(Fragmentation::to_json).unwrap_or(Value::Null)),
+            ("compression", self.compression.map(CompressionCheck::to_json).unwrap_or(Value::Null)),
+            ("write", self.write.to_json()),
+            ("read", self.read.to_json()),
+            ("read_pattern", self.read_pattern.to_string().into()),
+            ("random_write", opt_phase(&self.random_write)),
+            ("random_read", opt_phase(&self.random_read)),
+            ("qd_sweep", Value::Array(self.qd_sweep.iter().map(|(qd, p)| Value::object(vec![
+                ("queue_depth", u64::from(*qd).into()),
+                ("random_read", p.to_json()),
+            ])).collect())),
+            ("files", u64::from(self.files).into()),
+            ("parallel_write", opt_phase(&self.parallel_write)),
+            ("parallel_read", opt_phase(&self.parallel_read)),
+            ("shuffled_read", opt_phase(&self.shuffled_read)),
+            ("cached_read", opt_phase(&self.cached_read)),
+            ("random_penalty_pct", self.random_penalty_pct().into()),
+            ("sustained", opt_phase(&self.sustained)),
+            ("small_write", opt_phase(&self.small_write)),
+            ("small_read", opt_phase(&self.small_read)),
+            ("round_trip", opt_phase(&self.round_trip)),
+            ("access", self.access.as_ref().map(LatencyStats::to_json).unwrap_or(Value::Null)),
+            ("fsync", self.fsync.as_ref().map(LatencyStats::to_json).unwrap_or(Value::Null)),
+            ("discard", self.discard.into()),
+            ("trimmed_bytes", self.trimmed_bytes.into()),
+            ("dirty_write", opt_phase(&self.dirty_write)),
+            ("trimmed_write", opt_phase(&self.trimmed_write)),
+            ("trim_gain_pct", self.trim_gain_pct().into()),
+            ("metadata_changes", self.metadata_changes.clone().into()),
+            ("write_map", self.write_map.as_ref().map(OffsetMap::to_json).unwrap_or(Value::Null)),
+            ("read_map", self.read_map.as_ref().map(OffsetMap::to_json).unwrap_or(Value::Null)),
+            ("verify_mismatches", self.verify_mismatches.into()),
+            ("threshold_failures", self.threshold_failures.clone().into()),
+        ])
+    }
+
+    pub fn from_json(v: &Value) -> Option<BenchResult> {
+        let mut r = BenchResult {
+            timestamp: str_field(v, "timestamp"),
+            session: v.get("session").and_then(Value::as_str).map(str::to_string),
+            tags: match v.get("tags") {
+                Some(Value::Object(pairs)) => pairs.iter().filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string()))).collect(),
+                _ => Vec::new(),
+            },
+            target_dir: str_field(v, "target_dir"),
+            test_file: str_field(v, "test_file"),
+            device: v.get("device").and_then(DeviceInfo::from_json),
+            host: v.get("host").and_then(HostInfo::from_json),
+            fingerprint: v.get("fingerprint").and_then(Value::as_str).map(str::to_string),
+            usb_power: v.get("usb_power").and_then(UsbPower::from_json),
+            transforms: v.get("transforms").and_then(Value::as_array).unwrap_or_default()
+                .iter().filter_map(|t| Transform::from_id(t.as_str()?)).collect(),
+            size_bytes: v.get("size_bytes")?.as_u64()?,
+            block_bytes: v.get("block_bytes")?.as_u64()?,
+            block_tune: v.get("block_tune").and_then(BlockTune::from_json),
+            iterations: v.get("iterations").and_then(Value::as_u64).unwrap_or(1) as u32,
+            preallocated: v.get("preallocated").and_then(Value::as_bool),
+            fragmentation: v.get("fragmentation").and_then(Fragmentation::from_json),
+            compression: v.get("compression").and_then(CompressionCheck::from_json),
+            write: PhaseStats::from_json(v.get("write")?)?,
+            read: PhaseStats::from_json(v.get("read")?)?,
+            read_pattern: v.get("read_pattern").and_then(Value::as_str).and_then(|s| parse_read_pattern(s).ok()).unwrap_or_default(),
+            random_write: v.get("random_write").and_then(PhaseStats::from_json),
+            random_read: v.get("random_read").and_then(PhaseStats::from_json),
+            qd_sweep: v.get("qd_sweep").and_then(Value::as_array).unwrap_or_default().iter().filter_map(|p| {
+                Some((p.get("queue_depth")?.as_u64()? as u32, PhaseStats::from_json(p.get("random_read")?)?))
+            }).collect(),
+            files: v.get("files").and_then(Value::as_u64).unwrap_or(1) as u32,
+            parallel_write: v.get("parallel_write").and_then(PhaseStats::from_json),
+            parallel_read: v.get("parallel_read").and_then(PhaseStats::from_json),
+            shuffled_read: v.get("shuffled_read").and_then(PhaseStats::from_json),
+            cached_read: v.get("cached_read").and_then(PhaseStats::from_json),
+            sustained: v.get("sustained").and_then(PhaseStats::from_json),
+            small_write: v.get("small_write").and_then(PhaseStats::from_json),
+            small_read: v.get("small_read").and_then(PhaseStats::from_json),
+            round_trip: v.get("round_trip").and_then(PhaseStats::from_json),
+            access: v.get("access").and_then(LatencyStats::from_json),
+            fsync: v.get("fsync").and_then(LatencyStats::from_json),
+            discard: v.get("discard").and_then(Value::as_bool),
+            trimmed_bytes: v.get("trimmed_bytes").and_then(Value::as_u64),
+            dirty_write: v.get("dirty_write").and_then(PhaseStats::from_json),
+            trimmed_write: v.get("trimmed_write").and_then(PhaseStats::from_json),
+            metadata_changes: v.get("metadata_changes").and_then(Value::as_array).map(|a| {
+                a.iter().filter_map(Value::as_str).map(str::to_string).collect()
+            }),
+            write_map: v.get("write_map").and_then(OffsetMap::from_json),
+            read_map: v.get("read_map").and_then(OffsetMap::from_json),
+            verify_mismatches: v.get("verify_mismatches").and_then(Value::as_u64),
+            threshold_failures: v.get("threshold_failures").and_then(Value::as_array).map(|a| {
+                a.iter().filter_map(Value::as_str).map(str::to_string).collect()
+            }),
+        };
+        // Records from before `ops` was stored: the sequential passes used one call per block.
+        for p in [&mut r.write, &mut r.read] {
+            if p.ops == 0 && r.block_bytes > 0 { p.ops = p.bytes.div_ceil(r.block_bytes); }
+        }
+        Some(r)
+    }
+
+    /// Aggregate parallel throughput as a multiple of the single-stream write and read.
+    pub fn parallel_scaling(&self) -> Option<(f64, f64)> {
+        let (w, r) = (self.parallel_write.as_ref()?, self.parallel_read.as_ref()?);
+        let ratio = |p: &PhaseStats, single: &PhaseStats| if single.mb_s() > 0.0 { p.mb_s() / single.mb_s() } else { 0.0 };
+        Some((ratio(w, &self.write), ratio(r, &self.read)))
+    }
+
+    /// Best IOPS of the queue-depth sweep as a multiple of its shallowest depth's, with the
+    /// depth it was reached at; `None` with fewer than two depths.
+    pub fn qd_scaling(&self) -> Option<(f64, u32)> {
+        let (base_qd, base) = self.qd_sweep.iter().min_by_key(|(qd, _)| *qd)?;
+        let (best_qd, best) = self.qd_sweep.iter().filter(|(qd, _)| qd != base_qd).max_by(|a, b| a.1.ops_per_s().total_cmp(&b.1.ops_per_s()))?;
+        (base.ops_per_s() > 0.0).then(|| (best.ops_per_s() / base.ops_per_s(), *best_qd))
+    }
+
+    /// How much slower the shuffled read was than the sequential one, in percent
+    /// (negative if it was faster); `None` without a shuffled read.
+    pub fn random_penalty_pct(&self) -> Option<f64> {
+        let s = self.shuffled_read.as_ref()?;
+        let seq = self.read.mb_s();
+        (seq > 0.0).then(|| (1.0 - s.mb_s() / seq) * 100.0)
+    }
+
+    /// How many times faster the cached read was than the device read; `None` without one.
+    pub fn cache_speedup(&self) -> Option<f64> {
+        let c = self.cached_read.as_ref()?;
+        (self.read.mb_s() > 0.0).then(|| c.mb_s() / self.read.mb_s())
+    }
+
+    /// How much faster the rewrite after a TRIM was than the one over dirty blocks, in percent
+    /// (negative if it was slower); `None` without the comparison.
+    pub fn trim_gain_pct(&self) -> Option<f64> {
+        let (d, t) = (self.dirty_write.as_ref()?, self.trimmed_write.as_ref()?);
+        (d.mb_s() > 0.0).then(|| (t.mb_s() / d.mb_s() - 1.0) * 100.0)
+    }
+
+    /// The tags as `key=value` pairs separated by spaces; empty without tags.
+    pub fn tags_text(&self) -> String {
+        self.tags.iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Human-friendly name for tables and charts.
+    pub fn label(&self) -> String {
+        match &self.session {
+            Some(s) if !s.is_empty() => s.clone(),
+            _ => self.timestamp.clone(),
+        }
+    }
+}
+
+/// Appends `record` as a single JSON line to `path`.
+pub fn append(path: &Path, record: &BenchResult) -> io::Result<()> {
+    let mut f = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(f, "{}", record.to_json())?;
+    f.sync_all()
+}
+
+/// Loads every record from a JSON-lines file (a single JSON array is accepted too).
+pub fn load(path: &Path) -> io::Result<Vec<BenchResult>> {
+    let text = std::fs::read_to_string(path)?;
+    let bad = |line: usize, e: String| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {}", path.display(), line, e))
+    };
+    if text.trim_start().starts_with('[') {
+        let v = json::parse(&text).map_err(|e| bad(1, e))?;
+        return Ok(v.as_array().unwrap_or_default().iter().filter_map(BenchResult::from_json).collect());
+    }
+    let mut out = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() { continue; }
+        let v = json::parse(line).map_err(|e| bad(i + 1, e))?;
+        out.push(BenchResult::from_json(&v).ok_or_else(|| bad(i + 1, "not a crabwise result record".into()))?);
+    }
+    Ok(out)
+}
@@ -0,0 +1,1175 @@
+//! The write-then-read benchmark loop, plus the optional random, verify and sustained stages.
+
+use crate::artifacts;
+use crate::backend::{FileBackend, StorageBackend};
+use crate::config::{BenchConfig, ReadPattern, VECTORED_BLOCKS};
+use crate::cpu::{CpuMeter, CpuUsage};
+use crate::device::{device_info, fingerprint, DeviceInfo};
+use crate::extents::Fragmentation;
+use crate::fscheck::{snapshot, Snapshot};
+use crate::guard::AbortGuard;
+use crate::host::host_info;
+use crate::file::{evict, open_read, open_write};
+use crate::manifest::Manifest;
+use crate::offsetmap::{OffsetMap, REGIONS as OFFSET_REGIONS};
+use crate::progress::{Phase, Progress, Update};
+use crate::result::{BenchResult, CompressionCheck, LatencyStats, PhaseStats};
+use crate::sampler::{Sample, Sampler};
+use crate::stage::random_read_sweep;
+use crate::stats::{mbs, mean, percentile};
+use crate::transform::transforms;
+use crate::trim::{discard_supported, trim};
+use crate::tune::{tune_block, BlockTune};
+use crate::usbpower::{usb_power, UsbPower};
+use chrono::Local;
+use rand::{rngs::SmallRng, seq::SliceRandom, Rng, RngCore, SeedableRng};
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// I/O size of the random test.
+pub const RANDOM_IO_BYTES: usize = 4096;
+/// The random test stops after this many operations per direction...
+pub const RANDOM_MAX_OPS: u64 = 16_384;
+/// ...or after this many seconds, whichever comes first.
+pub const RANDOM_TIME_LIMIT_S: f64 = 10.0;
+/// Bytes written by each of the compression check's four writes (zeros, random, random, zeros).
+pub const COMPRESSION_CHECK_BYTES: u64 = 16 * 1024 * 1024;
+/// Reads timed by the access-time test...
+pub const ACCESS_OPS: u64 = 1000;
+/// ...or its time limit, whichever comes first.
+pub const ACCESS_TIME_LIMIT_S: f64 = 10.0;
+/// Seconds the queue-depth sweep spends at each depth.
+pub const QD_SWEEP_SECONDS: f64 = 5.0;
+/// Size of each file in the small-file stage.
+pub const SMALL_FILE_BYTES: usize = 64 * 1024;
+/// Open+close operations timed for the round-trip latency.
+pub const ROUND_TRIP_OPS: usize = 100;
+/// Size of each append in the fsync stage, a typical database or log record.
+pub const FSYNC_IO_BYTES: usize = 4096;
+/// Buffers in flight in the sequential write: one being written while the others are refilled.
+const WRITE_BUFFERS: usize = 2;
+/// Below this block size the hand-off between threads costs more than it hides.
+const PIPELINE_MIN_BLOCK: u64 = 64 * 1024;
+/// How often the concurrent stage reports progress while its workers run.
+const PARALLEL_POLL: Duration = Duration::from_millis(100);
+/// Free space left untouched by the sustained write.
+const SUSTAINED_MARGIN: u64 = 64 * 1024 * 1024;
+
+pub struct BenchRunner {
+    config: BenchConfig,
+    /// The probes that picked `config.block`, when it was auto-tuned.
+    block_tune: Option<BlockTune>,
+}
+
+/// Running totals for one phase across iterations.
+#[derive(Default)]
+struct PhaseAcc {
+    bytes: u64,
+    seconds: f64,
+    latencies: Vec<f64>,
+    samples: Vec<Sample>,
+    cpu: Vec<(f64, CpuUsage)>,
+}
+
+impl PhaseAcc {
+    /// Appends one pass; its samples are shifted so the timeline stays continuous.
+    fn add(&mut self, bytes: u64, seconds: f64, latencies: Vec<f64>, samples: Vec<Sample>, cpu: CpuUsage) {
+        self.cpu.push((seconds, cpu));
+        let offset = self.seconds;
+        self.samples.extend(samples.into_iter().map(|s| Sample { t: s.t + offset, ..s }));
+        self.bytes += bytes;
+        self.seconds += seconds;
+        self.latencies.extend(latencies);
+    }
+
+    fn stats(self) -> PhaseStats {
+        PhaseStats {
+            bytes: self.bytes,
+            seconds: self.seconds,
+            ops: self.latencies.len() as u64,
+            latency_avg_ms: mean(&self.latencies) * 1e3,
+            latency_p99_ms: percentile(&self.latencies, 99.0) * 1e3,
+            samples: self.samples,
+            cpu: CpuUsage::weighted(&self.cpu),
+        }
+    }
+}
+
+/// Bytes of test file per offset stamp: each 4 KiB holds its own offset, so no two are alike.
+pub(crate) const STAMP_EVERY: u64 = 4096;
+
+/// The block of pseudo-random bytes a test file made with `seed` is tiled with.
+pub(crate) fn pattern_block(seed: u64, block: u64) -> Vec<u8> {
+    let mut buf = vec![0u8; block as usize];
+    SmallRng::seed_from_u64(seed).fill_bytes(&mut buf);
+    buf
+}
+
+/// Copies the test pattern starting at `pos` into `out`: `block` tiled from offset 0, with the
+/// first 8 bytes of every [`STAMP_EVERY`] bytes XORed with a tag of their offset. A drive that
+/// returns one block's data for another (aliased addresses, reordered blocks) then fails the compare.
+pub(crate) fn fill_pattern(block: &[u8], pos: u64, out: &mut [u8]) {
+    let mut off = (pos % block.len() as u64) as usize;
+    let mut i = 0;
+    while i < out.len() {
+        let take = (block.len() - off).min(out.len() - i);
+        out[i..i + take].copy_from_slice(&block[off..off + take]);
+        i += take;
+        off = 0;
+    }
+    let end = pos + out.len() as u64;
+    let mut unit = pos - pos % STAMP_EVERY;
+    while unit < end {
+        let tag = (unit / STAMP_EVERY + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15).to_le_bytes();
+        for (at, b) in (unit..).zip(tag).filter(|&(at, _)| at >= pos && at < end) {
+            out[(at - pos) as usize] ^= b;
+        }
+        unit += STAMP_EVERY;
+    }
+}
+
+/// Counts bytes in `data` (read from `pos`) that differ from the test pattern; `expected` is
+/// scratch space at least as long as `data`.
+fn mismatches(block: &[u8], pos: u64, data: &[u8], expected: &mut [u8]) -> u64 {
+    let expected = &mut expected[..data.len()];
+    fill_pattern(block, pos, expected);
+    if data == expected { 0 } else { data.iter().zip(expected.iter()).filter(|(a, b)| a != b).count() as u64 }
+}
+
+/// Sequential write of `cfg.size` bytes with [`WRITE_BUFFERS`] buffers in rotation: a scoped I/O
+/// thread writes one while this thread refills the next and reports progress, so rendering and
+/// buffer preparation overlap the device instead of stalling it. Latency is the `write` call alone.
+/// Smaller blocks are written inline, and `cfg.vectored` writes [`VECTORED_BLOCKS`] blocks per call,
+/// each block getting an equal share of the call's time as its latency. Returns the bytes written.
+fn sequential_write(
+    backend: &mut dyn StorageBackend, pattern: &[u8], cfg: &BenchConfig, progress: &mut dyn Progress,
+    t0: Instant, sampler: &mut Sampler, lat: &mut Vec<f64>,
+) -> io::Result<u64> {
+    let (block, total) = (pattern.len() as u64, cfg.size);
+    if cfg.vectored {
+        let mut written = 0;
+        let mut bufs = vec![0u8; block as usize * VECTORED_BLOCKS];
+        while written < total {
+            let len = (total - written).min(bufs.len() as u64) as usize;
+            fill_pattern(pattern, written, &mut bufs[..len]);
+            let slices: Vec<IoSlice> = bufs[..len].chunks(block as usize).map(IoSlice::new).collect();
+            let blocks = slices.len();
+            let len = len as u64;
+            let op = Instant::now();
+            backend.write_vectored(&slices)?;
+            let secs = op.elapsed().as_secs_f64();
+            lat.extend(std::iter::repeat_n(secs / blocks as f64, blocks));
+            written += len;
+            sampler.tick(written, lat);
+            progress.update(&Update { phase: Phase::Write, done: written, total, start: t0, samples: sampler.samples(), latencies: lat })?;
+        }
+        return Ok(written);
+    }
+    if block < PIPELINE_MIN_BLOCK {
+        let (mut written, mut buf) = (0, vec![0u8; block as usize]);
+        while written < total {
+            let len = block.min(total - written) as usize;
+            fill_pattern(pattern, written, &mut buf[..len]);
+            let op = Instant::now();
+            backend.write(&buf[..len])?;
+            lat.push(op.elapsed().as_secs_f64());
+            written += len as u64;
+            sampler.tick(written, lat);
+            progress.update(&Update { phase: Phase::Write, done: written, total, start: t0, samples: sampler.samples(), latencies: lat })?;
+        }
+        return Ok(written);
+    }
+    let (full_tx, full_rx) = mpsc::sync_channel::<(Vec<u8>, usize)>(WRITE_BUFFERS);
+    let (free_tx, free_rx) = mpsc::channel::<(Vec<u8>, usize, f64)>();
+    std::thread::scope(|s| {
+        let io = s.spawn(move || -> io::Result<()> {
+            for (buf, len) in full_rx {
+                let op = Instant::now();
+                backend.write(&buf[..len])?;
+                if free_tx.send((buf, len, op.elapsed().as_secs_f64())).is_err() { break; }
+            }
+            Ok(())
+        });
+        let (mut queued, mut written) = (0u64, 0u64);
+        let mut spare: Vec<Vec<u8>> = (0..WRITE_BUFFERS).map(|_| vec![0u8; block as usize]).collect();
+        let mut outcome = Ok(());
+        while written < total {
+            // Keep every idle buffer queued; the block at `queued` holds the pattern for that offset.
+            while let (true, Some(mut buf)) = (queued < total, spare.pop()) {
+                let len = block.min(total - queued) as usize;
+                fill_pattern(pattern, queued, &mut buf[..len]);
+                if full_tx.send((buf, len)).is_err() { break; }
+                queued += len as u64;
+            }
+            let Ok((buf, len, secs)) = free_rx.recv() else { break }; // the I/O thread failed
+            spare.push(buf);
+            lat.push(secs);
+            written += len as u64;
+            sampler.tick(written, lat);
+            outcome = progress.update(&Update {
+                phase: Phase::Write, done: written, total, start: t0,
+                samples: sampler.samples(), latencies: lat,
+            });
+            if outcome.is_err() { break; }
+        }
+        drop(full_tx);
+        drop(free_rx);
+        io.join().map_err(|_| io::Error::other("write thread panicked"))??;
+        outcome.map(|_| written)
+    })
+}
+
+/// One worker of the concurrent stage: writes (and syncs) or reads `bytes` of `path` in
+/// `pattern`-sized blocks, adding to `done` as it goes. Returns the per-block latencies and when it finished.
+fn stream_file(path: &Path, write: bool, bytes: u64, pattern: &[u8], direct: bool, done: &AtomicU64) -> io::Result<(Vec<f64>, Instant)> {
+    let mut lat = Vec::new();
+    let mut pos = 0;
+    if write {
+        let mut f = open_write(path, direct)?;
+        let mut buf = vec![0u8; pattern.len()];
+        while pos < bytes {
+            fill_pattern(pattern, pos, &mut buf);
+            let op = Instant::now();
+            f.write_all(&buf)?;
+            lat.push(op.elapsed().as_secs_f64());
+            pos += pattern.len() as u64;
+            done.fetch_add(pattern.len() as u64, Ordering::Relaxed);
+        }
+        f.sync_all()?;
+    } else {
+        let mut f = open_read(path, direct)?;
+        let mut buf = vec![0u8; pattern.len()];
+        while pos < bytes {
+            let op = Instant::now();
+            f.read_exact(&mut buf)?;
+            lat.push(op.elapsed().as_secs_f64());
+            pos += buf.len() as u64;
+            done.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        }
+    }
+    Ok((lat, Instant::now()))
+}
+
+/// A metadata snapshot for the corruption check; `None`, with a warning, when it can't be taken.
+fn metadata_snapshot(device: Option<&DeviceInfo>) -> Option<Snapshot> {
+    let Some(device) = device else {
+        crate::warn!("corruption check skipped: the target's volume wasn't found");
+        return None;
+    };
+    match snapshot(device) {
+        Ok(Some(s)) => Some(s),
+        Ok(None) => {
+            crate::warn!("corruption check skipped: {} holds no FAT, exFAT, NTFS or ext filesystem", device.name);
+            None
+        }
+        Err(e) => {
+            crate::warn!("corruption check skipped: {e} (reading the raw device usually needs root)");
+            None
+        }
+    }
+}
+
+/// Progress total for time-limited stages: projects where `done` will be when the limit is hit.
+pub(crate) fn projected(done: u64, elapsed: f64, limit_s: f64, cap: u64) -> u64 {
+    if elapsed <= 0.0 { return cap; }
+    ((done as f64 * limit_s / elapsed) as u64).max(done).min(cap)
+}
+
+impl BenchRunner {
+    pub fn new(config: BenchConfig) -> BenchRunner {
+        BenchRunner { config, block_tune: None }
+    }
+
+    pub fn config(&self) -> &BenchConfig {
+        &self.config
+    }
+
+    /// Path of the temporary test file.
+    pub fn test_path(&self) -> PathBuf {
+        self.config.test_path()
+    }
+
+    /// Writes then reads back the test file, reporting each block to `progress`.
+    ///
+    /// The test file is removed afterwards unless `keep_file` is set, also when the run
+    /// fails or is stopped partway. Threshold and verify failures are recorded in the
+    /// result, not returned as errors.
+    /// A kept file also gets a [`manifest`](crate::manifest) so `crabwise verify` can check it later.
+    pub fn run(&self, progress: &mut dyn Progress) -> io::Result<BenchResult> {
+        std::fs::create_dir_all(&self.config.target_dir)?;
+        let path = self.test_path();
+        let result = self.run_with(&mut FileBackend::new(path.clone()), progress);
+        if result.is_err() && !self.config.keep_file {
+            let _ = std::fs::remove_file(&path);
+        }
+        let result = result?;
+        if self.config.keep_file {
+            let saved = std::fs::metadata(&path).and_then(|m| Manifest::new(self.config.seed, result.block_bytes, m.len()).save(&path));
+            if let Err(e) = saved { crate::warn!("could not write the manifest for {}: {e}", path.display()); }
+        }
+        Ok(result)
+    }
+
+    /// The block of pseudo-random bytes every test file is tiled with.
+    fn pattern(&self, block: u64) -> Vec<u8> {
+        pattern_block(self.config.seed, block)
+    }
+
+    /// Like [`run`](Self::run), but against an arbitrary storage backend.
+    pub fn run_with(&self, backend: &mut dyn StorageBackend, progress: &mut dyn Progress) -> io::Result<BenchResult> {
+        let cfg = &self.config;
+        cfg.validate()?;
+        if cfg.auto_block {
+            let tune = tune_block(&cfg.target_dir, cfg.size, cfg.direct, progress)?;
+            let config = BenchConfig { block: tune.chosen, auto_block: false, ..cfg.clone() };
+            return BenchRunner { config, block_tune: Some(tune) }.run_with(backend, progress);
+        }
+        let mut guard;
+        let progress: &mut dyn Progress = match cfg.abort_below {
+            Some(limit) => { guard = AbortGuard::new(progress, limit); &mut guard }
+            None => progress,
+        };
+        let (total, block) = (cfg.size, cfg.block);
+        // The volume holding a directory target; a device node given as the target has none.
+        let device = if cfg.target_dir.is_dir() { device_info(&cfg.target_dir) } else { None };
+        let host = host_info(device.as_ref());
+        let usb_power = device.as_ref().and_then(usb_power);
+        if let Some(p) = usb_power.filter(UsbPower::short) {
+            let what = if p.rotational { "bus-powered hard drive" } else { "drive" };
+            crate::warn!("the {what} asks for {} mA but its port supplies {} mA; use a powered hub, a Y-cable or a port on the computer itself",
+                p.declared_ma, p.available_ma.unwrap_or_default());
+        }
+        let transforms = device.as_ref().map(|d| transforms(d, &cfg.target_dir)).unwrap_or_default();
+        for t in &transforms { crate::warn!("{}", t.note()); }
+        let before = if cfg.corruption_check { metadata_snapshot(device.as_ref()) } else { None };
+
+        let buf = self.pattern(block);
+        let compression = if cfg.compression_check { Some(self.compression_check(progress)?) } else { None };
+        if compression.is_some_and(|c| c.suspected()) {
+            crate::warn!("controller appears to compress/deduplicate — results with non-random data are unreliable");
+        }
+
+        let (mut write, mut read) = (PhaseAcc::default(), PhaseAcc::default());
+        let mut bad_bytes = 0;
+        // (offset, length, seconds) of every sequential write and each read, for the offset maps.
+        let mut write_ios: Vec<(u64, u64, f64)> = Vec::new();
+        let mut read_ios: Vec<(u64, u64, f64)> = Vec::new();
+        crate::info!(
+            "benchmark {}: {} bytes in {}-byte blocks, {} pass(es), {}{}", backend.describe(), total, block,
+            cfg.iterations.max(1), if cfg.direct { "direct" } else { "buffered" }, if cfg.vectored { ", vectored" } else { "" }
+        );
+        if !cfg.tags.is_empty() {
+            crate::info!("tags: {}", cfg.tags.iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>().join(" "));
+        }
+        let mut preallocated = None;
+        let mut fragmentation = None;
+        for pass in 1..=cfg.iterations.max(1) {
+            // -------- WRITE --------
+            backend.open_write(block as usize, cfg.direct)?;
+            if cfg.preallocate {
+                let ok = match backend.preallocate(total) {
+                    Ok(()) => true,
+                    Err(e) if e.kind() == io::ErrorKind::StorageFull => {
+                        let _ = backend.cleanup();
+                        return Err(io::Error::new(e.kind(), format!("not enough free space for the {total}-byte test file ({e})")));
+                    }
+                    Err(e) => {
+                        if preallocated.is_none() { crate::warn!("could not preallocate {}: {e}; the write pass includes allocation", backend.describe()); }
+                        false
+                    }
+                };
+                preallocated = Some(ok);
+            }
+            let mut write_lat: Vec<f64> = Vec::new();
+            let cpu = CpuMeter::start();
+            let t0 = Instant::now();
+            let mut sampler = Sampler::new(t0);
+            let written = sequential_write(backend, &buf, cfg, progress, t0, &mut sampler, &mut write_lat)?;
+            backend.sync()?;
+            // `direct` is O_SYNC, which still reads through the page cache; without this the read
+            // pass would time RAM. The cached figure comes from `cached_read`'s own passes.
+            backend.evict()?;
+            progress.finish(Phase::Write)?;
+            if pass == 1 {
+                fragmentation = match backend.fragmentation() {
+                    Ok(f) => Some(f),
+                    Err(e) => { crate::debug!("layout of {} unknown: {e}", backend.describe()); None }
+                };
+                if let Some(f) = fragmentation.filter(Fragmentation::likely_affects) {
+                    crate::warn!("the test file is in {}; sequential reads include the seeks between them", f.summary());
+                }
+            }
+            crate::info!("pass {pass} write: {written} bytes in {:.3} s", t0.elapsed().as_secs_f64());
+            if cfg.offset_map {
+                // Blocks go out front to back, so the i-th latency is the block at i * block.
+                write_ios.extend(write_lat.iter().enumerate().map(|(i, &s)| {
+                    let off = i as u64 * block;
+                    (off, block.min(written.saturating_sub(off)), s)
+                }));
+            }
+            let samples = sampler.finish(written, &write_lat);
+            write.add(written, t0.elapsed().as_secs_f64(), write_lat, samples, cpu.stop());
+
+            // -------- READ --------
+            // Non-sequential patterns go through positional reads, one block per call.
+            let mut offsets = match cfg.read_pattern {
+                ReadPattern::Sequential => {
+                    backend.open_read(block as usize, cfg.direct)?;
+                    None
+                }
+                pattern => {
+                    backend.open_random(cfg.direct)?;
+                    Some(pattern.offsets(written, block).into_iter())
+                }
+            };
+            // Vectored reads fill VECTORED_BLOCKS block-sized slices of one buffer, so the bytes stay contiguous.
+            let batch = if cfg.vectored && offsets.is_none() { VECTORED_BLOCKS } else { 1 };
+            let mut read_buf = vec![0u8; block as usize * batch];
+            let mut expected = if cfg.verify { vec![0u8; read_buf.len()] } else { Vec::new() };
+            let mut read_total: u64 = 0;
+            let mut read_lat: Vec<f64> = Vec::new();
+            // Time spent comparing is not device time; it is taken out of the read duration.
+            let mut verify_secs = 0.0;
+            let cpu = CpuMeter::start();
+            let t1 = Instant::now();
+            let mut sampler = Sampler::new(t1);
+            loop {
+                let op = Instant::now();
+                let (pos, n) = match offsets.as_mut() {
+                    None if batch > 1 => {
+                        let mut slices: Vec<IoSliceMut> = read_buf.chunks_mut(block as usize).map(IoSliceMut::new).collect();
+                        (read_total, backend.read_vectored(&mut slices)?)
+                    }
+                    None => (read_total, backend.read(&mut read_buf)?),
+                    Some(it) => {
+                        let Some((off, len)) = it.next() else { break };
+                        backend.read_at(&mut read_buf[..len], off)?;
+                        (off, len)
+                    }
+                };
+                if n == 0 { break; }
+                let blocks = n.div_ceil(block as usize);
+                let secs = op.elapsed().as_secs_f64();
+                read_lat.extend(std::iter::repeat_n(secs / blocks as f64, blocks));
+                if cfg.offset_map { read_ios.push((pos, n as u64, secs)); }
+                if cfg.verify {
+                    let v = Instant::now();
+                    bad_bytes += mismatches(&buf, pos, &read_buf[..n], &mut expected);
+                    verify_secs += v.elapsed().as_secs_f64();
+                }
+                read_total += n as u64;
+                sampler.tick(read_total, &read_lat);
+                progress.update(&Update {
+                    phase: Phase::Read, done: read_total, total, start: t1,
+                    samples: sampler.samples(), latencies: &read_lat,
+                })?;
+            }
+            progress.finish(Phase::Read)?;
+            let read_secs = t1.elapsed().as_secs_f64() - verify_secs;
+            // A short read-back is as much a verify failure as corrupted bytes.
+            if cfg.verify { bad_bytes += written.saturating_sub(read_total); }
+            crate::info!("pass {pass} read ({}): {read_total} bytes in {read_secs:.3} s (verify took {verify_secs:.3} s)", cfg.read_pattern);
+            let samples = sampler.finish(read_total, &read_lat);
+            read.add(read_total, read_secs, read_lat, samples, cpu.stop());
+        }
+
+        let cached_read = if cfg.cached_read { Some(self.cached_read(backend, progress)?) } else { None };
+
+        let (random_write, random_read) = if cfg.random && total >= RANDOM_IO_BYTES as u64 {
+            let (w, r) = self.random_test(backend, &buf, progress)?;
+            (Some(w), Some(r))
+        } else {
+            (None, None)
+        };
+
+        let (parallel_write, parallel_read) = if cfg.files > 1 {
+            let (w, r) = self.parallel_test(&buf, progress)?;
+            (Some(w), Some(r))
+        } else {
+            (None, None)
+        };
+
+        let access = if cfg.access_time && total >= RANDOM_IO_BYTES as u64 {
+            Some(self.access_test(backend, progress)?)
+        } else {
+            None
+        };
+
+        // Positional reads from several threads need a shared file handle, not the backend.
+        let qd_sweep = if !cfg.sweep_qd.is_empty() && total >= RANDOM_IO_BYTES as u64 {
+            random_read_sweep(&self.test_path(), total, RANDOM_IO_BYTES as u64, &cfg.sweep_qd, QD_SWEEP_SECONDS, cfg.direct, progress)?
+        } else {
+            Vec::new()
+        };
+
+        let shuffled_read = if cfg.shuffled && total >= block {
+            Some(self.shuffled_read(backend, progress)?)
+        } else {
+            None
+        };
+
+        let sustained = match cfg.sustained {
+            Some(limit) => {
+                let cap = device.as_ref()
+                    .map(|d| (d.available_bytes + total).saturating_sub(SUSTAINED_MARGIN))
+                    .unwrap_or(total.saturating_mul(16))
+                    .max(block);
+                Some(self.timed_write(backend, &buf, Phase::Sustained, limit, cap, progress)?)
+            }
+            None => None,
+        };
+
+        let (small_write, small_read, round_trip) = if cfg.small_files > 0 {
+            let (w, r, rt) = self.small_file_test(&buf, progress)?;
+            (Some(w), Some(r), Some(rt))
+        } else {
+            (None, None, None)
+        };
+
+        let fsync = if cfg.fsync_ops > 0 { Some(self.fsync_test(&buf, progress)?) } else { None };
+
+        let (dirty_write, trimmed_write) = match cfg.trim_compare {
+            true => self.trim_compare(backend, &buf, progress)?.unzip(),
+            false => (None, None),
+        };
+
+        let mut result = BenchResult {
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            session: None,
+            tags: cfg.tags.clone(),
+            target_dir: cfg.target_dir.display().to_string(),
+            test_file: backend.describe(),
+            fingerprint: device.as_ref().and_then(fingerprint),
+            usb_power,
+            transforms,
+            host: Some(host),
+            device,
+            size_bytes: total,
+            block_bytes: block,
+            block_tune: self.block_tune.clone(),
+            iterations: cfg.iterations.max(1),
+            preallocated,
+            fragmentation,
+            compression,
+            write: write.stats(),
+            read: read.stats(),
+            read_pattern: cfg.read_pattern,
+            random_write,
+            random_read,
+            qd_sweep,
+            files: cfg.files.max(1),
+            parallel_write,
+            parallel_read,
+            shuffled_read,
+            cached_read,
+            sustained,
+            small_write,
+            small_read,
+            round_trip,
+            access,
+            fsync,
+            discard: discard_supported(&cfg.target_dir),
+            trimmed_bytes: None,
+            dirty_write,
+            trimmed_write,
+            metadata_changes: None,
+            write_map: cfg.offset_map.then(|| OffsetMap::build(total, OFFSET_REGIONS.min(total.div_ceil(block) as usize), write_ios)),
+            read_map: cfg.offset_map.then(|| OffsetMap::build(total, OFFSET_REGIONS.min(total.div_ceil(block) as usize), read_ios)),
+            verify_mismatches: cfg.verify.then_some(bad_bytes),
+            threshold_failures: None,
+        };
+        let mut failures = if cfg.thresholds.is_empty() { None } else { Some(cfg.thresholds.check(&result)) };
+        if bad_bytes > 0 {
+            failures.get_or_insert_with(Vec::new)
+                .push(format!("verify: {bad_bytes} byte(s) read back differ from what was written"));
+        }
+        result.threshold_failures = failures;
+        if !cfg.keep_file {
+            let _ = backend.cleanup();
+            if cfg.trim {
+                match trim(&cfg.target_dir) {
+                    Ok(n) => result.trimmed_bytes = Some(n),
+                    Err(e) => crate::warn!("TRIM of {} failed: {e}", cfg.target_dir.display()),
+                }
+            }
+        }
+        if let Some(before) = &before {
+            // Taken after cleanup and TRIM, which touch the metadata too.
+            if let Some(after) = metadata_snapshot(result.device.as_ref()) {
+                let changes = before.changes(&after);
+                for c in &changes {
+                    result.threshold_failures.get_or_insert_with(Vec::new).push(format!("metadata: {c}"));
+                }
+                result.metadata_changes = Some(changes);
+            }
+        }
+        progress.done(&result)?;
+        Ok(result)
+    }
+
+    /// 4 KiB writes then reads at random aligned offsets inside the test file.
+    fn random_test(
+        &self, backend: &mut dyn StorageBackend, pattern: &[u8], progress: &mut dyn Progress,
+    ) -> io::Result<(PhaseStats, PhaseStats)> {
+        let slots = self.config.size / RANDOM_IO_BYTES as u64;
+        let cap = RANDOM_MAX_OPS * RANDOM_IO_BYTES as u64;
+        let mut rng = SmallRng::seed_from_u64(0x4B_0FF5E7);
+        let mut io_buf = vec![0u8; RANDOM_IO_BYTES];
+        backend.open_random(self.config.direct)?;
+
+        let mut out = Vec::new();
+        for phase in [Phase::RandomWrite, Phase::RandomRead] {
+            if phase == Phase::RandomRead {
+                // The random writes and earlier passes leave the file cached; the reads must reach the device.
+                backend.sync()?;
+                backend.evict()?;
+            }
+            let mut lat: Vec<f64> = Vec::new();
+            let mut done: u64 = 0;
+            let cpu = CpuMeter::start();
+            let t0 = Instant::now();
+            let mut sampler = Sampler::new(t0);
+            while done < cap && t0.elapsed().as_secs_f64() < RANDOM_TIME_LIMIT_S {
+                let off = rng.random_range(0..slots) * RANDOM_IO_BYTES as u64;
+                let op = Instant::now();
+                if phase == Phase::RandomWrite {
+                    // Rewrite the pattern that belongs here so the file stays verifiable.
+                    fill_pattern(pattern, off, &mut io_buf);
+                    backend.write_at(&io_buf, off)?;
+                } else {
+                    backend.read_at(&mut io_buf, off)?;
+                }
+                lat.push(op.elapsed().as_secs_f64());
+                done += RANDOM_IO_BYTES as u64;
+                sampler.tick(done, &lat);
+                let total = projected(done, t0.elapsed().as_secs_f64(), RANDOM_TIME_LIMIT_S, cap);
+                progress.update(&Update { phase, done, total, start: t0, samples: sampler.samples(), latencies: &lat })?;
+            }
+            if phase == Phase::RandomWrite { backend.sync()?; }
+            progress.finish(phase)?;
+            crate::info!("{}: {} ops in {:.3} s", phase.id(), done / RANDOM_IO_BYTES as u64, t0.elapsed().as_secs_f64());
+            let mut acc = PhaseAcc::default();
+            let samples = sampler.finish(done, &lat);
+            acc.add(done, t0.elapsed().as_secs_f64(), lat, samples, cpu.stop());
+            out.push(acc.stats());
+        }
+        let read = out.pop().unwrap_or_default();
+        Ok((out.pop().unwrap_or_default(), read))
+    }
+
+    /// Writes `COMPRESSION_CHECK_BYTES` of zeros, then of unique random data, twice in
+    /// mirrored order so neither kind gets the empty write cache, syncing after each. A
+    /// controller that compresses or deduplicates writes the zeros far faster. The random
+    /// data is fresh per block: the benchmark's own tiled pattern could itself deduplicate.
+    fn compression_check(&self, progress: &mut dyn Progress) -> io::Result<CompressionCheck> {
+        let cfg = &self.config;
+        let path = cfg.target_dir.join(artifacts::name("-compress.tmp"));
+        let block = cfg.block.min(COMPRESSION_CHECK_BYTES) as usize;
+        let mut rng = SmallRng::seed_from_u64(0xC0_4F2E55);
+        let mut data = vec![0u8; block];
+        let total = 4 * COMPRESSION_CHECK_BYTES;
+        let (mut zero_secs, mut random_secs) = (0.0, 0.0);
+        let mut done = 0;
+        let t0 = Instant::now();
+        let mut sampler = Sampler::new(t0);
+        let mut lat: Vec<f64> = Vec::new();
+        let ran = (|| -> io::Result<()> {
+            for zeros in [true, false, false, true] {
+                let mut f = open_write(&path, cfg.direct)?;
+                // Device time only: generating the random data is not the drive's cost.
+                let mut secs = 0.0;
+                let mut written = 0;
+                while written < COMPRESSION_CHECK_BYTES {
+                    let n = block.min((COMPRESSION_CHECK_BYTES - written) as usize);
+                    if zeros { data[..n].fill(0) } else { rng.fill_bytes(&mut data[..n]) }
+                    let op = Instant::now();
+                    f.write_all(&data[..n])?;
+                    let op_secs = op.elapsed().as_secs_f64();
+                    lat.push(op_secs);
+                    secs += op_secs;
+                    written += n as u64;
+                    done += n as u64;
+                    sampler.tick(done, &lat);
+                    progress.update(&Update { phase: Phase::CompressionCheck, done, total, start: t0, samples: sampler.samples(), latencies: &lat })?;
+                }
+                let t = Instant::now();
+                f.sync_all()?;
+                secs += t.elapsed().as_secs_f64();
+                if zeros { zero_secs += secs } else { random_secs += secs }
+            }
+            Ok(())
+        })();
+        let _ = std::fs::remove_file(&path);
+        ran?;
+        progress.finish(Phase::CompressionCheck)?;
+        let rate = |secs: f64| if secs > 0.0 { mbs(2 * COMPRESSION_CHECK_BYTES as u128, secs) } else { 0.0 };
+        let check = CompressionCheck { zero_mb_s: rate(zero_secs), random_mb_s: rate(random_secs) };
+        crate::info!("compression check: zeros {:.1} MB/s, random {:.1} MB/s ({:.2}x)", check.zero_mb_s, check.random_mb_s, check.ratio());
+        Ok(check)
+    }
+
+    /// HDTune-style access time: with the test file out of the cache, reads one
+    /// `RANDOM_IO_BYTES` block at a time at random aligned offsets across it, so each read
+    /// pays the device's full seek or lookup. Stops after `ACCESS_OPS` reads or
+    /// `ACCESS_TIME_LIMIT_S`, whichever comes first.
+    fn access_test(&self, backend: &mut dyn StorageBackend, progress: &mut dyn Progress) -> io::Result<LatencyStats> {
+        let slots = self.config.size / RANDOM_IO_BYTES as u64;
+        let cap = ACCESS_OPS * RANDOM_IO_BYTES as u64;
+        // Not the random test's seed, so the two don't visit the same blocks.
+        let mut rng = SmallRng::seed_from_u64(0xACCE55);
+        let mut io_buf = vec![0u8; RANDOM_IO_BYTES];
+        backend.evict()?;
+        backend.open_random(self.config.direct)?;
+        let mut lat: Vec<f64> = Vec::new();
+        let mut done: u64 = 0;
+        let t0 = Instant::now();
+        let mut sampler = Sampler::new(t0);
+        while done < cap && t0.elapsed().as_secs_f64() < ACCESS_TIME_LIMIT_S {
+            let off = rng.random_range(0..slots) * RANDOM_IO_BYTES as u64;
+            let op = Instant::now();
+            backend.read_at(&mut io_buf, off)?;
+            lat.push(op.elapsed().as_secs_f64());
+            done += RANDOM_IO_BYTES as u64;
+            sampler.tick(done, &lat);
+            let total = projected(done, t0.elapsed().as_secs_f64(), ACCESS_TIME_LIMIT_S, cap);
+            progress.update(&Update { phase: Phase::Access, done, total, start: t0, samples: sampler.samples(), latencies: &lat })?;
+        }
+        progress.finish(Phase::Access)?;
+        let secs = t0.elapsed().as_secs_f64();
+        crate::info!("access: {} reads in {secs:.3} s", lat.len());
+        Ok(LatencyStats::from_secs(&lat, RANDOM_IO_BYTES as u64, secs))
+    }
+
+    /// Creates `small_files` files of `SMALL_FILE_BYTES`, reads them back, then times open+close
+    /// round trips on them. Each file's create/open-to-close time is one latency sample.
+    fn small_file_test(&self, pattern: &[u8], progress: &mut dyn Progress) -> io::Result<(PhaseStats, PhaseStats, PhaseStats)> {
+        let cfg = &self.config;
+        let dir = cfg.target_dir.join(artifacts::name("-small"));
+        std::fs::create_dir_all(&dir)?;
+        let paths: Vec<PathBuf> = (0..cfg.small_files).map(|i| dir.join(format!("{i:06}.tmp"))).collect();
+        let total = paths.len() as u64 * SMALL_FILE_BYTES as u64;
+        let mut data = vec![0u8; SMALL_FILE_BYTES];
+        fill_pattern(pattern, 0, &mut data);
+
+        // One progress line per file would swamp a terminal; ~64 updates per phase is plenty.
+        let every = (paths.len() / 64).max(1);
+        let mut out = Vec::new();
+        for phase in [Phase::SmallWrite, Phase::SmallRead] {
+            if phase == Phase::SmallRead && cfg.drop_cache {
+                for p in &paths { evict(p)?; }
+            }
+            let mut lat: Vec<f64> = Vec::new();
+            let mut done: u64 = 0;
+            let cpu = CpuMeter::start();
+            let t0 = Instant::now();
+            let mut sampler = Sampler::new(t0);
+            for (i, p) in paths.iter().enumerate() {
+                let op = Instant::now();
+                if phase == Phase::SmallWrite {
+                    open_write(p, cfg.direct)?.write_all(&data)?;
+                } else {
+                    open_read(p, cfg.direct)?.read_exact(&mut data)?;
+                }
+                lat.push(op.elapsed().as_secs_f64());
+                done += SMALL_FILE_BYTES as u64;
+                sampler.tick(done, &lat);
+                if (i + 1) % every == 0 || i + 1 == paths.len() {
+                    progress.update(&Update { phase, done, total, start: t0, samples: sampler.samples(), latencies: &lat })?;
+                }
+            }
+            progress.finish(phase)?;
+            crate::info!("{}: {} files in {:.3} s", phase.id(), paths.len(), t0.elapsed().as_secs_f64());
+            let mut acc = PhaseAcc::default();
+            let samples = sampler.finish(done, &lat);
+            acc.add(done, t0.elapsed().as_secs_f64(), lat, samples, cpu.stop());
+            out.push(acc.stats());
+        }
+
+        let mut lat = Vec::with_capacity(ROUND_TRIP_OPS);
+        let cpu = CpuMeter::start();
+        let t0 = Instant::now();
+        for p in paths.iter().cycle().take(ROUND_TRIP_OPS) {
+            let op = Instant::now();
+            drop(open_read(p, cfg.direct)?);
+            lat.push(op.elapsed().as_secs_f64());
+        }
+        let mut rt = PhaseAcc::default();
+        rt.add(0, t0.elapsed().as_secs_f64(), lat, Vec::new(), cpu.stop());
+        crate::info!("round trip: {ROUND_TRIP_OPS} open+close in {:.3} s", t0.elapsed().as_secs_f64());
+
+        if !cfg.keep_file { let _ = std::fs::remove_dir_all(&dir); }
+        let read = out.pop().unwrap_or_default();
+        Ok((out.pop().unwrap_or_default(), read, rt.stats()))
+    }
+
+    /// Appends `fsync_ops` blocks of `FSYNC_IO_BYTES` to a fresh file, calling fsync
+    /// (FlushFileBuffers on Windows, F_FULLFSYNC on macOS) after each, the way a database
+    /// commits its log. Each write+fsync is one latency sample.
+    fn fsync_test(&self, pattern: &[u8], progress: &mut dyn Progress) -> io::Result<LatencyStats> {
+        let cfg = &self.config;
+        let path = cfg.target_dir.join(artifacts::name("-fsync.tmp"));
+        let mut data = vec![0u8; FSYNC_IO_BYTES];
+        let total = u64::from(cfg.fsync_ops) * FSYNC_IO_BYTES as u64;
+        let mut f = open_write(&path, cfg.direct)?;
+        let mut lat: Vec<f64> = Vec::with_capacity(cfg.fsync_ops as usize);
+        let mut done: u64 = 0;
+        let t0 = Instant::now();
+        let mut sampler = Sampler::new(t0);
+        let every = (cfg.fsync_ops as usize / 64).max(1);
+        let ran = (|| -> io::Result<()> {
+            for i in 0..cfg.fsync_ops as usize {
+                fill_pattern(pattern, done, &mut data);
+                let op = Instant::now();
+                f.write_all(&data)?;
+                f.sync_all()?;
+                lat.push(op.elapsed().as_secs_f64());
+                done += FSYNC_IO_BYTES as u64;
+                sampler.tick(done, &lat);
+                if (i + 1) % every == 0 || i + 1 == cfg.fsync_ops as usize {
+                    progress.update(&Update { phase: Phase::Fsync, done, total, start: t0, samples: sampler.samples(), latencies: &lat })?;
+                }
+            }
+            Ok(())
+        })();
+        drop(f);
+        if !cfg.keep_file { let _ = std::fs::remove_file(&path); }
+        ran?;
+        progress.finish(Phase::Fsync)?;
+        let secs = t0.elapsed().as_secs_f64();
+        crate::info!("fsync: {} write+fsync in {secs:.3} s", lat.len());
+        Ok(LatencyStats::from_secs(&lat, FSYNC_IO_BYTES as u64, secs))
+    }
+
+    /// Writes, then reads back, `files` test files at once from one thread each, with `size`
+    /// split evenly between them. Throughput is the aggregate; latencies are per block across all files.
+    fn parallel_test(&self, pattern: &[u8], progress: &mut dyn Progress) -> io::Result<(PhaseStats, PhaseStats)> {
+        let cfg = &self.config;
+        let per_file = (cfg.size / u64::from(cfg.files) / cfg.block).max(1) * cfg.block;
+        let paths: Vec<PathBuf> = (0..cfg.files).map(|i| cfg.target_dir.join(artifacts::name(&format!(".{i}.tmp")))).collect();
+        let total = per_file * paths.len() as u64;
+        let mut out = Vec::new();
+        for phase in [Phase::ParallelWrite, Phase::ParallelRead] {
+            if phase == Phase::ParallelRead && cfg.drop_cache {
+                for p in &paths { evict(p)?; }
+            }
+            let done = AtomicU64::new(0);
+            let cpu = CpuMeter::start();
+            let t0 = Instant::now();
+            let mut sampler = Sampler::new(t0);
+            // Timed to the last worker's finish, not to the poll that noticed it.
+            let ran = std::thread::scope(|s| -> io::Result<(Vec<f64>, Instant)> {
+                let workers: Vec<_> = paths.iter()
+                    .map(|p| {
+                        let done = &done;
+                        s.spawn(move || stream_file(p, phase == Phase::ParallelWrite, per_file, pattern, cfg.direct, done))
+                    })
+                    .collect();
+                while !workers.iter().all(|w| w.is_finished()) {
+                    std::thread::sleep(PARALLEL_POLL);
+                    let d = done.load(Ordering::Relaxed);
+                    sampler.tick(d, &[]);
+                    progress.update(&Update { phase, done: d, total, start: t0, samples: sampler.samples(), latencies: &[] })?;
+                }
+                let (mut lat, mut end) = (Vec::new(), t0);
+                for w in workers {
+                    let (l, e) = w.join().map_err(|_| io::Error::other("parallel worker panicked"))??;
+                    lat.extend(l);
+                    end = end.max(e);
+                }
+                Ok((lat, end))
+            });
+            let (lat, end) = match ran {
+                Ok(ran) => ran,
+                Err(e) => {
+                    for p in &paths { let _ = std::fs::remove_file(p); }
+                    return Err(e);
+                }
+            };
+            let (done, secs) = (done.into_inner(), (end - t0).as_secs_f64());
+            progress.finish(phase)?;
+            crate::info!("{}: {} files, {done} bytes in {secs:.3} s", phase.id(), paths.len());
+            let mut acc = PhaseAcc::default();
+            acc.add(done, secs, lat, sampler.finish(done, &[]), cpu.stop());
+            out.push(acc.stats());
+        }
+        if !cfg.keep_file {
+            for p in &paths { let _ = std::fs::remove_file(p); }
+        }
+        let read = out.pop().unwrap_or_default();
+        Ok((out.pop().unwrap_or_default(), read))
+    }
+
+    /// Reads the test file twice through the OS cache: the first pass loads it, the second is
+    /// timed. Files larger than the free RAM only partly fit, so the result falls toward the device read.
+    fn cached_read(&self, backend: &mut dyn StorageBackend, progress: &mut dyn Progress) -> io::Result<PhaseStats> {
+        let (size, block) = (self.config.size, self.config.block);
+        let total = size * 2;
+        let mut read_buf = vec![0u8; block as usize];
+        let t0 = Instant::now();
+        let mut loaded: u64 = 0;
+        backend.open_read(block as usize, false)?;
+        loop {
+            let n = backend.read(&mut read_buf)?;
+            if n == 0 { break; }
+            loaded += n as u64;
+            progress.update(&Update { phase: Phase::CachedRead, done: loaded, total, start: t0, samples: &[], latencies: &[] })?;
+        }
+        backend.open_read(block as usize, false)?;
+        let mut lat: Vec<f64> = Vec::new();
+        let mut done: u64 = 0;
+        let cpu = CpuMeter::start();
+        let t1 = Instant::now();
+        let mut sampler = Sampler::new(t1);
+        loop {
+            let op = Instant::now();
+            let n = backend.read(&mut read_buf)?;
+            if n == 0 { break; }
+            lat.push(op.elapsed().as_secs_f64());
+            done += n as u64;
+            sampler.tick(done, &lat);
+            progress.update(&Update {
+                phase: Phase::CachedRead, done: loaded + done, total, start: t0,
+                samples: sampler.samples(), latencies: &lat,
+            })?;
+        }
+        progress.finish(Phase::CachedRead)?;
+        crate::info!("cached read: {done} bytes in {:.3} s after loading {loaded} bytes", t1.elapsed().as_secs_f64());
+        let mut acc = PhaseAcc::default();
+        let samples = sampler.finish(done, &lat);
+        acc.add(done, t1.elapsed().as_secs_f64(), lat, samples, cpu.stop());
+        Ok(acc.stats())
+    }
+
+    /// Reads every whole block of the test file once, in a random permutation, so read-ahead
+    /// and controller prefetch can't help; compared with the sequential read it gives the random penalty.
+    fn shuffled_read(&self, backend: &mut dyn StorageBackend, progress: &mut dyn Progress) -> io::Result<PhaseStats> {
+        let (total, block) = (self.config.size, self.config.block);
+        let mut order: Vec<u64> = (0..total / block).collect();
+        order.shuffle(&mut SmallRng::seed_from_u64(0x5_4FF1E));
+        let total = order.len() as u64 * block;
+        // The passes before this one left the file in the OS cache; the permutation must hit the device.
+        backend.evict()?;
+        backend.open_random(self.config.direct)?;
+        let mut read_buf = vec![0u8; block as usize];
+        let mut lat: Vec<f64> = Vec::with_capacity(order.len());
+        let mut done: u64 = 0;
+        let cpu = CpuMeter::start();
+        let t0 = Instant::now();
+        let mut sampler = Sampler::new(t0);
+        for i in order {
+            let op = Instant::now();
+            backend.read_at(&mut read_buf, i * block)?;
+            lat.push(op.elapsed().as_secs_f64());
+            done += block;
+            sampler.tick(done, &lat);
+            progress.update(&Update { phase: Phase::ShuffledRead, done, total, start: t0, samples: sampler.samples(), latencies: &lat })?;
+        }
+        progress.finish(Phase::ShuffledRead)?;
+        crate::info!("shuffled read: {} blocks in {:.3} s", lat.len(), t0.elapsed().as_secs_f64());
+        let mut acc = PhaseAcc::default();
+        let samples = sampler.finish(done, &lat);
+        acc.add(done, t0.elapsed().as_secs_f64(), lat, samples, cpu.stop());
+        Ok(acc.stats())
+    }
+
+    /// Rewrites the test file over the blocks it just freed, then again after a TRIM of the
+    /// filesystem's free space. `None` (with a warning) when the TRIM itself fails, e.g. without root.
+    fn trim_compare(
+        &self, backend: &mut dyn StorageBackend, buf: &[u8], progress: &mut dyn Progress,
+    ) -> io::Result<Option<(PhaseStats, PhaseStats)>> {
+        let total = self.config.size;
+        backend.cleanup()?;
+        let dirty = self.timed_write(backend, buf, Phase::DirtyWrite, f64::INFINITY, total, progress)?;
+        backend.cleanup()?;
+        match trim(&self.config.target_dir) {
+            Ok(n) => crate::info!("trimmed {n} bytes before the trimmed rewrite"),
+            Err(e) => {
+                crate::warn!("TRIM comparison skipped: TRIM of {} failed: {e}", self.config.target_dir.display());
+                return Ok(None);
+            }
+        }
+        let trimmed = self.timed_write(backend, buf, Phase::TrimmedWrite, f64::INFINITY, total, progress)?;
+        Ok(Some((dirty, trimmed)))
+    }
+
+    /// Writes the test pattern sequentially as `phase` for `limit_s` seconds (or until `cap`
+    /// bytes); the sustained write uses it to expose cache exhaustion.
+    fn timed_write(
+        &self, backend: &mut dyn StorageBackend, pattern: &[u8], phase: Phase, limit_s: f64, cap: u64, progress: &mut dyn Progress,
+    ) -> io::Result<PhaseStats> {
+        let block = pattern.len() as u64;
+        let mut buf = vec![0u8; pattern.len()];
+        backend.open_write(pattern.len(), self.config.direct)?;
+        let mut written: u64 = 0;
+        let mut lat: Vec<f64> = Vec::new();
+        let cpu = CpuMeter::start();
+        let t0 = Instant::now();
+        let mut sampler = Sampler::new(t0);
+        while written < cap && t0.elapsed().as_secs_f64() < limit_s {
+            let n = block.min(cap - written) as usize;
+            // Stamped for its offset, so a kept file still verifies.
+            fill_pattern(pattern, written, &mut buf[..n]);
+            let op = Instant::now();
+            backend.write(&buf[..n])?;
+            lat.push(op.elapsed().as_secs_f64());
+            written += n as u64;
+            sampler.tick(written, &lat);
+            let total = projected(written, t0.elapsed().as_secs_f64(), limit_s, cap);
+            progress.update(&Update {
+                phase, done: written, total, start: t0,
+                samples: sampler.samples(), latencies: &lat,
+            })?;
+        }
+        backend.sync()?;
+        progress.finish(phase)?;
+        crate::info!("{}: {written} bytes in {:.3} s", phase.id(), t0.elapsed().as_secs_f64());
+        let mut acc = PhaseAcc::default();
+        let samples = sampler.finish(written, &lat);
+        acc.add(written, t0.elapsed().as_secs_f64(), lat, samples, cpu.stop());
+        Ok(acc.stats())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+    use crate::progress::NoProgress;
+
+    fn config(size: u64, block: u64) -> BenchConfig {
+        // Kept, so the backend still holds the file for the test to look at.
+        BenchConfig { size, block, verify: true, keep_file: true, ..BenchConfig::new("unused") }
+    }
+
+    /// Runs `cfg` in memory and checks the file it left is the stamped pattern, read back intact.
+    fn run_and_check(cfg: BenchConfig) -> BenchResult {
+        let (size, block, seed) = (cfg.size, cfg.block, cfg.seed);
+        let mut backend = MemoryBackend::new();
+        let result = BenchRunner::new(cfg).run_with(&mut backend, &mut NoProgress).unwrap();
+        let mut expected = vec![0u8; size as usize];
+        fill_pattern(&pattern_block(seed, block), 0, &mut expected);
+        assert!(backend.data() == expected.as_slice(), "the file isn't the test pattern");
+        assert_eq!(result.write.bytes, size);
+        assert_eq!(result.read.bytes, size);
+        assert_eq!(result.verify_mismatches, Some(0));
+        result
+    }
+
+    #[test]
+    fn pipelined_write_reads_back_intact() {
+        let r = run_and_check(config(4 << 20, 1 << 20));
+        assert_eq!(r.write.ops, 4);
+    }
+
+    #[test]
+    fn small_block_and_vectored_writes_match_the_pipelined_pattern() {
+        run_and_check(config(1 << 20, 16 << 10));
+        run_and_check(BenchConfig { vectored: true, ..config(1 << 20, 16 << 10) });
+        // A size that isn't a whole number of blocks or batches.
+        run_and_check(BenchConfig { vectored: true, ..config((1 << 20) + 5000, 4096) });
+    }
+
+    #[test]
+    fn non_sequential_reads_verify() {
+        run_and_check(BenchConfig { read_pattern: ReadPattern::Reverse, ..config(1 << 20, 64 << 10) });
+        run_and_check(BenchConfig { read_pattern: ReadPattern::Stride(3), ..config(1 << 20, 64 << 10) });
+    }
+
+    #[test]
+    fn random_writes_keep_the_file_verifiable() {
+        let r = run_and_check(BenchConfig { random: true, ..config(1 << 20, 64 << 10) });
+        assert!(r.random_write.is_some_and(|p| p.bytes > 0));
+        assert!(r.random_read.is_some_and(|p| p.bytes > 0));
+    }
+
+    /// Verifies what `backend` holds against the manifest a kept run of `cfg` would save.
+    fn verify_kept(backend: &MemoryBackend, cfg: &BenchConfig, name: &str) -> crate::manifest::VerifyReport {
+        let path = std::env::temp_dir().join(format!("crabwise-runner-{}-{name}.tmp", std::process::id()));
+        std::fs::write(&path, backend.data()).unwrap();
+        let m = Manifest::new(cfg.seed, cfg.block, backend.data().len() as u64);
+        let r = crate::manifest::verify(&path, &m, &mut NoProgress).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        r
+    }
+
+    #[test]
+    fn kept_sustained_file_verifies() {
+        let cfg = BenchConfig { sustained: Some(10.0), ..config(1 << 20, 64 << 10) };
+        let mut backend = MemoryBackend::new();
+        let r = BenchRunner::new(cfg.clone()).run_with(&mut backend, &mut NoProgress).unwrap();
+        // Without a device to size it, the fill stops at 16 times the test file.
+        assert_eq!(r.sustained.map(|p| p.bytes), Some(16 << 20));
+        let report = verify_kept(&backend, &cfg, "sustained");
+        assert!(report.ok(), "{report:?}");
+    }
+
+    #[test]
+    fn file_rewritten_by_the_trim_comparison_verifies() {
+        // The pass the TRIM comparison rewrites the test file with; the TRIM itself needs root.
+        let cfg = config(1 << 20, 64 << 10);
+        let runner = BenchRunner::new(cfg.clone());
+        let mut backend = MemoryBackend::new();
+        let pattern = runner.pattern(cfg.block);
+        runner.timed_write(&mut backend, &pattern, Phase::TrimmedWrite, f64::INFINITY, cfg.size, &mut NoProgress).unwrap();
+        assert_eq!(backend.data().len(), 1 << 20);
+        let report = verify_kept(&backend, &cfg, "trim-compare");
+        assert!(report.ok(), "{report:?}");
+    }
+
+    #[test]
+    fn iterations_add_up() {
+        let r = BenchRunner::new(BenchConfig { iterations: 3, ..config(1 << 20, 256 << 10) })
+            .run_with(&mut MemoryBackend::new(), &mut NoProgress).unwrap();
+        assert_eq!(r.write.bytes, 3 << 20);
+        assert_eq!(r.read.bytes, 3 << 20);
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        assert!(BenchRunner::new(config(1 << 20, 0)).run_with(&mut MemoryBackend::new(), &mut NoProgress).is_err());
+    }
+
+    #[test]
+    fn fill_pattern_is_the_same_however_it_is_split() {
+        let tile = pattern_block(7, 10_000);
+        let mut whole = vec![0u8; 50_000];
+        fill_pattern(&tile, 0, &mut whole);
+        for split in [1, 7, 4096, 4100, 10_000, 33_333] {
+            let mut parts = vec![0u8; 50_000];
+            let (a, b) = parts.split_at_mut(split);
+            fill_pattern(&tile, 0, a);
+            fill_pattern(&tile, split as u64, b);
+            assert!(parts == whole, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn every_4k_of_the_pattern_differs() {
+        // One tile per 4 KiB: without the stamp, every unit would be identical.
+        let tile = pattern_block(1, STAMP_EVERY);
+        let mut data = vec![0u8; 16 * STAMP_EVERY as usize];
+        fill_pattern(&tile, 0, &mut data);
+        let units: Vec<&[u8]> = data.chunks(STAMP_EVERY as usize).collect();
+        for (i, a) in units.iter().enumerate() {
+            assert!(units[i + 1..].iter().all(|b| a != b), "unit {i} repeats");
+        }
+    }
+
+    #[test]
+    fn mismatches_catch_moved_blocks() {
+        let block = 64 << 10;
+        let tile = pattern_block(3, block);
+        let mut data = vec![0u8; 4 * block as usize];
+        fill_pattern(&tile, 0, &mut data);
+        let mut scratch = vec![0u8; data.len()];
+        assert_eq!(mismatches(&tile, 0, &data, &mut scratch), 0);
+        // Block 1 returned for block 3, as a drive wrapping its addresses would.
+        let (head, tail) = data.split_at_mut(3 * block as usize);
+        tail.copy_from_slice(&head[block as usize..2 * block as usize]);
+        assert!(mismatches(&tile, 0, &data, &mut scratch) > 0);
+        assert_eq!(mismatches(&tile, 0, &data[..3 * block as usize], &mut scratch), 0);
+    }
+}
@@ -0,0 +1,74 @@
+//! Per-interval throughput sampling during a phase.
+
+use std::time::Instant;
+
+/// Seconds between throughput samples.
+pub const SAMPLE_INTERVAL_S: f64 = 1.0;
+/// Weight of each new sample in [`smoothed`].
+pub const SMOOTHING: f64 = 0.3;
+
+/// One throughput sample: `t` is seconds since phase start at the end of the interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub t: f64,
+    pub mb_s: f64,
+    /// Mean latency of the I/Os that completed in the interval; `None` where the phase times
+    /// no individual I/Os or none completed.
+    pub latency_ms: Option<f64>,
+}
+
+/// Turns a running byte counter into fixed-interval throughput samples.
+pub struct Sampler {
+    start: Instant,
+    last_t: f64,
+    last_bytes: u64,
+    /// Latencies already counted into an earlier sample.
+    last_ops: usize,
+    samples: Vec<Sample>,
+}
+
+impl Sampler {
+    pub fn new(start: Instant) -> Self {
+        Sampler { start, last_t: 0.0, last_bytes: 0, last_ops: 0, samples: Vec::new() }
+    }
+
+    /// Records `done` (total bytes so far) and `latencies` (every I/O latency of the phase so
+    /// far, in seconds); emits a sample once an interval has elapsed.
+    /// Returns true when a new sample was added.
+    pub fn tick(&mut self, done: u64, latencies: &[f64]) -> bool {
+        let now = self.start.elapsed().as_secs_f64();
+        if now - self.last_t < SAMPLE_INTERVAL_S { return false; }
+        self.push(now, done, latencies);
+        true
+    }
+
+    /// Flushes the trailing partial interval (if it is long enough to be meaningful).
+    pub fn finish(&mut self, done: u64, latencies: &[f64]) -> Vec<Sample> {
+        let now = self.start.elapsed().as_secs_f64();
+        if done > self.last_bytes && (now - self.last_t >= SAMPLE_INTERVAL_S / 10.0 || self.samples.is_empty()) {
+            self.push(now, done, latencies);
+        }
+        std::mem::take(&mut self.samples)
+    }
+
+    pub fn samples(&self) -> &[Sample] { &self.samples }
+
+    fn push(&mut self, now: f64, done: u64, latencies: &[f64]) {
+        let dt = now - self.last_t;
+        let mb_s = if dt > 0.0 { (done - self.last_bytes) as f64 / 1_000_000f64 / dt } else { 0.0 };
+        let new = latencies.get(self.last_ops..).unwrap_or_default();
+        let latency_ms = (!new.is_empty()).then(|| crate::stats::mean(new) * 1e3);
+        self.samples.push(Sample { t: now, mb_s, latency_ms });
+        self.last_t = now;
+        self.last_bytes = done;
+        self.last_ops = latencies.len();
+    }
+}
+
+/// Exponentially smoothed MB/s over `samples`: the last few seconds count most, so a stall
+/// shows within a sample or two, but one odd interval doesn't swing it. `None` before the
+/// first sample.
+pub fn smoothed(samples: &[Sample]) -> Option<f64> {
+    let (first, rest) = samples.split_first()?;
+    Some(rest.iter().fold(first.mb_s, |rate, s| rate + SMOOTHING * (s.mb_s - rate)))
+}
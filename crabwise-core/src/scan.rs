@@ -0,0 +1,362 @@
+//! Read-only surface scan: reads a device or file end to end without writing and
+//! records the regions that fail to read or read abnormally slowly. [`free_space`] narrows
+//! the scan to the blocks of a mounted filesystem that no file occupies.
+
+use crate::file::{evict, open_read, read_exact_at};
+use crate::json::Value;
+use crate::offsetmap::{OffsetMap, REGIONS};
+use crate::progress::{Phase, Progress, Update};
+use crate::sampler::{Sample, Sampler};
+use crate::stats::mbs;
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Granularity a failed block is re-read at, to narrow down which part of it is bad.
+pub const SECTOR: u64 = 4096;
+
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    /// Block device, partition or file to read.
+    pub path: PathBuf,
+    /// Bytes per read call.
+    pub block: u64,
+    /// Where the scan starts.
+    pub offset: u64,
+    /// Bytes to scan from `offset`; `None` scans to the end.
+    pub length: Option<u64>,
+    /// A block taking longer than this (seconds) is recorded as slow.
+    pub slow: f64,
+    /// When not empty, only these `(offset, length)` ranges are read, in order, instead of
+    /// `offset` and `length` (see [`free_space`]).
+    pub extents: Vec<(u64, u64)>,
+}
+
+impl ScanConfig {
+    pub fn new(path: impl Into<PathBuf>) -> ScanConfig {
+        ScanConfig { path: path.into(), block: 1024 * 1024, offset: 0, length: None, slow: 0.5, extents: Vec::new() }
+    }
+}
+
+/// Filesystems whose file extents aren't offsets on one device: their free space can't be mapped.
+const UNMAPPABLE: [&str; 3] = ["btrfs", "zfs", "bcachefs"];
+
+/// The free space of the filesystem mounted under some directory, as ranges of its device.
+#[derive(Debug, Clone)]
+pub struct FreeSpace {
+    /// The filesystem's device, e.g. `/dev/sdb1`.
+    pub device: PathBuf,
+    pub mount_point: PathBuf,
+    pub file_system: String,
+    /// `(offset, length)` on `device` of every range no file or directory occupies, in order.
+    pub extents: Vec<(u64, u64)>,
+    /// Files and directories mapped.
+    pub files: u64,
+    /// Those whose layout couldn't be read; their blocks count as free and are scanned too.
+    pub unmapped: u64,
+}
+
+impl FreeSpace {
+    pub fn bytes(&self) -> u64 {
+        self.extents.iter().map(|&(_, len)| len).sum()
+    }
+}
+
+/// Maps the free space of the filesystem `dir` is on by reading the layout (`FIEMAP`) of
+/// every file and directory on it and taking what's left of its device. Filesystem metadata
+/// such as the journal and inode tables isn't a file, so it falls in the free space and is
+/// read too; that only costs time, since the scan never writes. Linux only; needs read access
+/// to the device and every directory, so in practice root.
+pub fn free_space(dir: &Path) -> io::Result<FreeSpace> {
+    if !cfg!(target_os = "linux") {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "free-space scans need Linux (FIEMAP)"));
+    }
+    let info = crate::device::device_info(dir)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no mounted filesystem contains {}", dir.display())))?;
+    if UNMAPPABLE.contains(&info.file_system.as_str()) {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} maps files to its own address space, not to offsets on {}", info.file_system, info.name)));
+    }
+    let device = PathBuf::from(&info.name);
+    let mount_point = PathBuf::from(&info.mount_point);
+    // The volume list leaves out filesystems without a device (tmpfs), so `dir` may be on one
+    // of those under the volume found: the device must be the one `dir` is really on.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let on = std::fs::metadata(dir)?.dev();
+        if !std::fs::metadata(&device).is_ok_and(|m| m.rdev() == on) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} isn't on a block device crabwise can read", dir.display())));
+        }
+    }
+    let mut dev = open_read(&device, false).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", device.display())))?;
+    let end = dev.seek(SeekFrom::End(0))?;
+    let mut used = Vec::new();
+    let (mut files, mut unmapped) = (0, 0);
+    walk(&mount_point, &mut |path| {
+        files += 1;
+        match crate::extents::runs(path) {
+            Ok(runs) => used.extend(runs),
+            Err(e) => {
+                unmapped += 1;
+                crate::debug!("no layout for {}: {e}", path.display());
+            }
+        }
+    })?;
+    if files > 0 && unmapped == files {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} on {} doesn't report where its files are (FIEMAP)", info.file_system, info.name)));
+    }
+    let extents = complement(used, end);
+    crate::info!("free space of {} ({}): {} extents after mapping {files} files ({unmapped} unmapped)", info.name, info.file_system, extents.len());
+    Ok(FreeSpace { device, mount_point, file_system: info.file_system, extents, files, unmapped })
+}
+
+/// Calls `f` for `root` and everything under it on the same filesystem, not following symlinks.
+fn walk(root: &Path, f: &mut dyn FnMut(&Path)) -> io::Result<()> {
+    #[cfg(unix)]
+    use std::os::unix::fs::MetadataExt;
+    #[cfg(unix)]
+    let fs = std::fs::metadata(root)?.dev();
+    let mut dirs = vec![root.to_path_buf()];
+    f(root);
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            crate::debug!("can't list {}; its files count as free space", dir.display());
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else { continue };
+            #[cfg(unix)]
+            if meta.dev() != fs { continue; }
+            if meta.is_dir() || meta.is_file() {
+                f(&entry.path());
+            }
+            if meta.is_dir() { dirs.push(entry.path()); }
+        }
+    }
+    Ok(())
+}
+
+/// The parts of `0..end` that none of the `(offset, length)` runs in `used` cover, in order.
+fn complement(mut used: Vec<(u64, u64)>, end: u64) -> Vec<(u64, u64)> {
+    used.sort_unstable();
+    let mut free = Vec::new();
+    let mut pos = 0;
+    for (start, len) in used {
+        if start > pos { free.push((pos, start.min(end) - pos)); }
+        pos = pos.max(start + len);
+        if pos >= end { return free; }
+    }
+    if pos < end { free.push((pos, end - pos)); }
+    free
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// The read returned an error.
+    Unreadable,
+    /// The read succeeded but took longer than [`ScanConfig::slow`].
+    Slow,
+}
+
+impl RegionKind {
+    pub fn id(self) -> &'static str {
+        match self {
+            RegionKind::Unreadable => "unreadable",
+            RegionKind::Slow => "slow",
+        }
+    }
+}
+
+/// A run of adjacent bytes that failed the same way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub offset: u64,
+    pub len: u64,
+    pub kind: RegionKind,
+    /// Slowest read in the region, in milliseconds.
+    pub worst_ms: f64,
+    /// The first error seen, for unreadable regions.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanReport {
+    pub path: PathBuf,
+    pub offset: u64,
+    /// Bytes attempted, including unreadable ones.
+    pub bytes: u64,
+    pub seconds: f64,
+    pub block: u64,
+    pub regions: Vec<Region>,
+    pub samples: Vec<Sample>,
+    /// Ranges read when the scan was limited to [`ScanConfig::extents`]; 0 for one range.
+    pub extents: usize,
+    /// Read speed per 1% of the scanned range, front to back; for extents, of the bytes
+    /// read laid end to end.
+    pub map: OffsetMap,
+    /// Median and slowest block read, in milliseconds.
+    pub latency_median_ms: f64,
+    pub latency_max_ms: f64,
+}
+
+impl ScanReport {
+    pub fn mb_s(&self) -> f64 {
+        if self.seconds > 0.0 { mbs(self.bytes as u128, self.seconds) } else { 0.0 }
+    }
+
+    /// Total bytes in regions of `kind`.
+    pub fn bytes_of(&self, kind: RegionKind) -> u64 {
+        self.regions.iter().filter(|r| r.kind == kind).map(|r| r.len).sum()
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("path", self.path.display().to_string().into()),
+            ("offset", self.offset.into()),
+            ("bytes", self.bytes.into()),
+            ("seconds", self.seconds.into()),
+            ("block_bytes", self.block.into()),
+            ("extents", (self.extents as u64).into()),
+            ("mb_s", self.mb_s().into()),
+            ("latency_median_ms", self.latency_median_ms.into()),
+            ("latency_max_ms", self.latency_max_ms.into()),
+            ("unreadable_bytes", self.bytes_of(RegionKind::Unreadable).into()),
+            ("slow_bytes", self.bytes_of(RegionKind::Slow).into()),
+            ("map", self.map.to_json()),
+            ("regions", Value::Array(self.regions.iter().map(|r| Value::object(vec![
+                ("offset", r.offset.into()),
+                ("len", r.len.into()),
+                ("kind", r.kind.id().into()),
+                ("worst_ms", r.worst_ms.into()),
+                ("error", r.error.clone().into()),
+            ])).collect())),
+        ])
+    }
+}
+
+/// Adds `len` bytes at `offset` to `regions`, extending the last region when it is adjacent and of the same kind.
+fn push_region(regions: &mut Vec<Region>, offset: u64, len: u64, kind: RegionKind, ms: f64, error: Option<String>) {
+    if let Some(r) = regions.last_mut().filter(|r| r.kind == kind && r.offset + r.len == offset) {
+        r.len += len;
+        r.worst_ms = r.worst_ms.max(ms);
+        return;
+    }
+    regions.push(Region { offset, len, kind, worst_ms: ms, error });
+}
+
+/// Reads `cfg.path` from `cfg.offset` to the end (or `cfg.length`), or only `cfg.extents`,
+/// never writing to it.
+///
+/// A block that fails to read is re-read in [`SECTOR`]-sized pieces so only the bad
+/// sectors are recorded, and the scan carries on past it. Errors opening the target or
+/// finding its size are returned; read errors are recorded in the report.
+pub fn scan(cfg: &ScanConfig, progress: &mut dyn Progress) -> io::Result<ScanReport> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    if cfg.block == 0 { return Err(invalid("scan block size must be greater than 0".into())); }
+    let mut file = open_read(&cfg.path, false)?;
+    let ranges = if cfg.extents.is_empty() {
+        let end = match cfg.length {
+            Some(len) => cfg.offset + len,
+            // Block devices report a zero length in their metadata but seek to their real end.
+            None => match file.seek(SeekFrom::End(0))? {
+                0 => file.metadata()?.len(),
+                n => n,
+            },
+        };
+        if end <= cfg.offset {
+            return Err(invalid(format!("nothing to scan in {} (size unknown or offset past the end; pass a length)", cfg.path.display())));
+        }
+        vec![(cfg.offset, end - cfg.offset)]
+    } else {
+        cfg.extents.clone()
+    };
+    // Cached pages would hide the device's real behaviour.
+    if let Err(e) = evict(&cfg.path) { crate::debug!("evict {}: {e}", cfg.path.display()); }
+    crate::info!("scan {}: {} range(s) from byte {} in {}-byte blocks", cfg.path.display(), ranges.len(), ranges[0].0, cfg.block);
+
+    let total: u64 = ranges.iter().map(|&(_, len)| len).sum();
+    let mut buf = vec![0u8; cfg.block as usize];
+    let mut regions = Vec::new();
+    let mut lat = Vec::new();
+    // (bytes scanned before it, length, seconds) per block, for the offset map.
+    let mut ios = Vec::new();
+    let t0 = Instant::now();
+    let mut sampler = Sampler::new(t0);
+    let mut done = 0;
+    'ranges: for &(start, range) in &ranges {
+        let (mut pos, end) = (start, start + range);
+        while pos < end {
+            let len = cfg.block.min(end - pos);
+            let t = Instant::now();
+            let res = read_exact_at(&file, &mut buf[..len as usize], pos);
+            let secs = t.elapsed().as_secs_f64();
+            match res {
+                Ok(()) if secs > cfg.slow => push_region(&mut regions, pos, len, RegionKind::Slow, secs * 1e3, None),
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    crate::warn!("{} ended at or before byte {}", cfg.path.display(), pos + len);
+                    break 'ranges;
+                }
+                Err(e) => {
+                    crate::debug!("read error at {pos}: {e}; retrying in {SECTOR}-byte pieces");
+                    let mut sub = pos;
+                    while sub < pos + len {
+                        let n = SECTOR.min(pos + len - sub);
+                        let t = Instant::now();
+                        if let Err(e) = read_exact_at(&file, &mut buf[..n as usize], sub) {
+                            push_region(&mut regions, sub, n, RegionKind::Unreadable, t.elapsed().as_secs_f64() * 1e3, Some(e.to_string()));
+                        }
+                        sub += n;
+                    }
+                }
+            }
+            lat.push(secs);
+            ios.push((done, len, secs));
+            pos += len;
+            done += len;
+            sampler.tick(done, &lat);
+            progress.update(&Update { phase: Phase::Scan, done, total, start: t0, samples: sampler.samples(), latencies: &lat })?;
+        }
+    }
+    let seconds = t0.elapsed().as_secs_f64();
+    progress.finish(Phase::Scan)?;
+    let bytes = done;
+    Ok(ScanReport {
+        path: cfg.path.clone(),
+        offset: cfg.offset,
+        bytes,
+        seconds,
+        block: cfg.block,
+        regions,
+        samples: sampler.finish(bytes, &lat),
+        extents: if cfg.extents.is_empty() { 0 } else { ranges.len() },
+        map: OffsetMap::build(bytes, REGIONS.min(bytes.div_ceil(cfg.block) as usize), ios),
+        latency_median_ms: crate::stats::percentile(&lat, 50.0) * 1e3,
+        latency_max_ms: lat.iter().copied().fold(0.0, f64::max) * 1e3,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complement_of_used_runs() {
+        assert_eq!(complement(vec![], 100), [(0, 100)]);
+        assert_eq!(complement(vec![(40, 10), (0, 10), (45, 20)], 100), [(10, 30), (65, 35)]);
+        assert_eq!(complement(vec![(0, 50), (50, 50)], 100), []);
+        // Runs reaching past the end of the device.
+        assert_eq!(complement(vec![(90, 20), (200, 5)], 100), [(0, 90)]);
+    }
+
+    #[test]
+    fn scan_reads_only_the_extents() {
+        let path = std::env::temp_dir().join(format!("crabwise-scan-{}.tmp", std::process::id()));
+        std::fs::write(&path, vec![0u8; 64 << 10]).unwrap();
+        let cfg = ScanConfig { block: 4096, extents: vec![(0, 8192), (32 << 10, 10_000)], ..ScanConfig::new(&path) };
+        let r = scan(&cfg, &mut crate::progress::NoProgress).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((r.bytes, r.extents), (18_192, 2));
+        assert_eq!(r.bytes_of(RegionKind::Unreadable), 0);
+    }
+}
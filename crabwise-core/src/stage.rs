@@ -0,0 +1,353 @@
+//! Generic benchmark stages: one access pattern, block size and queue depth against one target.
+//!
+//! Job files are a list of these. Unlike [`BenchRunner`](crate::BenchRunner), a stage can
+//! keep several I/Os in flight: `queue_depth` worker threads share one handle and issue
+//! positional reads/writes, which is how a synchronous API emulates a deeper queue.
+
+use crate::artifacts;
+use crate::cpu::CpuMeter;
+use crate::file::{open_read, open_read_write, open_write, read_exact_at, write_all_at};
+use crate::json::Value;
+use crate::progress::{Phase, Progress, Update};
+use crate::result::PhaseStats;
+use crate::runner::{fill_pattern, projected};
+use crate::sampler::Sampler;
+use crate::stats::{mean, percentile};
+use crate::thresholds::Thresholds;
+use rand::{rngs::SmallRng, Rng, RngCore, SeedableRng};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often the coordinating thread reports progress while workers run.
+const POLL_EVERY: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    Sequential,
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Write,
+    Read,
+    /// A write pass followed by a read pass.
+    ReadWrite,
+}
+
+impl Pattern {
+    pub fn id(self) -> &'static str {
+        match self { Pattern::Sequential => "sequential", Pattern::Random => "random" }
+    }
+}
+
+impl Mode {
+    pub fn id(self) -> &'static str {
+        match self { Mode::Write => "write", Mode::Read => "read", Mode::ReadWrite => "readwrite" }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Pattern, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "sequential" | "seq" => Ok(Pattern::Sequential),
+            "random" | "rand" => Ok(Pattern::Random),
+            _ => Err(format!("unknown pattern '{s}' (expected sequential or random)")),
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Mode, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "write" => Ok(Mode::Write),
+            "read" => Ok(Mode::Read),
+            "readwrite" | "rw" => Ok(Mode::ReadWrite),
+            _ => Err(format!("unknown mode '{s}' (expected write, read or readwrite)")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Stage {
+    pub name: String,
+    /// Directory holding the stage's test file; stages on the same target share it.
+    pub target_dir: PathBuf,
+    pub pattern: Pattern,
+    pub mode: Mode,
+    pub block: u64,
+    /// Size of the region (test file) the stage works within.
+    pub size: u64,
+    /// Concurrent I/Os in flight.
+    pub queue_depth: u32,
+    /// Run for this many seconds instead of a single pass over `size`.
+    pub duration: Option<f64>,
+    /// Issue I/Os no faster than this many bytes per second, like a camera or a stream
+    /// (`None` = as fast as the device goes).
+    pub rate: Option<f64>,
+    pub direct: bool,
+    pub thresholds: Thresholds,
+}
+
+impl Stage {
+    /// Sequential write+read of 1 GiB in 4 MiB blocks at queue depth 1.
+    pub fn new(name: impl Into<String>, target_dir: impl Into<PathBuf>) -> Stage {
+        Stage {
+            name: name.into(),
+            target_dir: target_dir.into(),
+            pattern: Pattern::Sequential,
+            mode: Mode::ReadWrite,
+            block: crate::config::DEFAULT_BLOCK,
+            size: crate::config::DEFAULT_SIZE,
+            queue_depth: 1,
+            duration: None,
+            rate: None,
+            direct: true,
+            thresholds: Thresholds::default(),
+        }
+    }
+
+    pub fn test_path(&self) -> PathBuf {
+        self.target_dir.join(artifacts::name(".tmp"))
+    }
+
+    pub fn validate(&self) -> io::Result<()> {
+        let invalid = |m: String| Err(io::Error::new(io::ErrorKind::InvalidInput, format!("stage '{}': {m}", self.name)));
+        if self.block == 0 || self.size < self.block { return invalid("block must be >0 and <= size".into()); }
+        if self.queue_depth == 0 { return invalid("queue_depth must be at least 1".into()); }
+        if self.duration.is_some_and(|d| d <= 0.0) { return invalid("duration must be positive".into()); }
+        if self.rate.is_some_and(|r| r <= 0.0) { return invalid("rate must be positive".into()); }
+        Ok(())
+    }
+
+    fn phase(&self, write: bool) -> Phase {
+        match (self.pattern, write) {
+            (Pattern::Sequential, true) => Phase::Write,
+            (Pattern::Sequential, false) => Phase::Read,
+            (Pattern::Random, true) => Phase::RandomWrite,
+            (Pattern::Random, false) => Phase::RandomRead,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StageResult {
+    pub name: String,
+    pub target_dir: String,
+    pub test_file: String,
+    pub pattern: Pattern,
+    pub mode: Mode,
+    pub block_bytes: u64,
+    pub size_bytes: u64,
+    pub queue_depth: u32,
+    pub write: Option<PhaseStats>,
+    pub read: Option<PhaseStats>,
+    /// `None` when the stage had no thresholds.
+    pub threshold_failures: Option<Vec<String>>,
+}
+
+impl StageResult {
+    pub fn to_json(&self) -> Value {
+        let phase = |p: &Option<PhaseStats>| p.as_ref().map(|p| Value::object(vec![
+            ("bytes", p.bytes.into()),
+            ("seconds", p.seconds.into()),
+            ("mb_s", p.mb_s().into()),
+            ("iops", p.iops(self.block_bytes).into()),
+            ("latency_avg_ms", p.latency_avg_ms.into()),
+            ("latency_p99_ms", p.latency_p99_ms.into()),
+        ])).unwrap_or(Value::Null);
+        Value::object(vec![
+            ("name", self.name.as_str().into()),
+            ("target_dir", self.target_dir.as_str().into()),
+            ("test_file", self.test_file.as_str().into()),
+            ("pattern", self.pattern.id().into()),
+            ("mode", self.mode.id().into()),
+            ("block_bytes", self.block_bytes.into()),
+            ("size_bytes", self.size_bytes.into()),
+            ("queue_depth", u64::from(self.queue_depth).into()),
+            ("write", phase(&self.write)),
+            ("read", phase(&self.read)),
+            ("threshold_failures", self.threshold_failures.clone().into()),
+        ])
+    }
+}
+
+/// Runs one stage. The test file is left in place so later stages on the same target can read it.
+pub fn run_stage(stage: &Stage, progress: &mut dyn Progress) -> io::Result<StageResult> {
+    stage.validate()?;
+    std::fs::create_dir_all(&stage.target_dir)?;
+    let path = stage.test_path();
+
+    let mut rng = SmallRng::seed_from_u64(0x5EED_CAFE);
+    let mut tile = vec![0u8; stage.block as usize];
+    rng.fill_bytes(&mut tile);
+
+    let mut write = None;
+    if stage.mode != Mode::Read {
+        let f = open_write(&path, stage.direct)?;
+        f.set_len(stage.size)?;
+        write = Some(pass(stage, &f, true, &tile, progress)?);
+    }
+    let mut read = None;
+    if stage.mode != Mode::Write {
+        if std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0) < stage.size {
+            prefill(stage, &tile)?;
+        }
+        // Just written by this stage, an earlier one or the prefill: read it from the device, not the cache.
+        if let Err(e) = crate::file::evict(&path) { crate::debug!("evict {}: {e}", path.display()); }
+        let f = open_read(&path, stage.direct)?;
+        read = Some(pass(stage, &f, false, &tile, progress)?);
+    }
+    let failures = stage.thresholds.check_phases(write.as_ref(), read.as_ref());
+    Ok(StageResult {
+        name: stage.name.clone(),
+        target_dir: stage.target_dir.display().to_string(),
+        test_file: path.display().to_string(),
+        pattern: stage.pattern,
+        mode: stage.mode,
+        block_bytes: stage.block,
+        size_bytes: stage.size,
+        queue_depth: stage.queue_depth,
+        write,
+        read,
+        threshold_failures: (!stage.thresholds.is_empty()).then_some(failures),
+    })
+}
+
+/// Random `block`-sized reads of the existing file at `path` for `seconds` at each of
+/// `depths`, for the benchmark's queue-depth sweep. The file is dropped from the cache
+/// before each depth so every one starts cold.
+pub(crate) fn random_read_sweep(
+    path: &Path, size: u64, block: u64, depths: &[u32], seconds: f64, direct: bool, progress: &mut dyn Progress,
+) -> io::Result<Vec<(u32, PhaseStats)>> {
+    let file = open_read(path, direct)?;
+    let mut out = Vec::with_capacity(depths.len());
+    for &qd in depths {
+        let stage = Stage {
+            name: format!("qd{qd}"),
+            pattern: Pattern::Random,
+            mode: Mode::Read,
+            block,
+            size,
+            queue_depth: qd,
+            duration: Some(seconds),
+            direct,
+            ..Stage::new("", path.parent().unwrap_or(path))
+        };
+        stage.validate()?;
+        if let Err(e) = crate::file::evict(path) { crate::debug!("evict {}: {e}", path.display()); }
+        out.push((qd, pass(&stage, &file, false, &[], progress)?));
+    }
+    Ok(out)
+}
+
+/// Untimed sequential write so a read-only stage has data to read.
+fn prefill(stage: &Stage, tile: &[u8]) -> io::Result<()> {
+    let mut f = open_read_write(&stage.test_path(), false).or_else(|_| open_write(&stage.test_path(), false))?;
+    let mut pos = 0;
+    let mut buf = vec![0u8; stage.block as usize];
+    while pos < stage.size {
+        let n = stage.block.min(stage.size - pos) as usize;
+        fill_pattern(tile, pos, &mut buf[..n]);
+        f.write_all(&buf[..n])?;
+        pos += n as u64;
+    }
+    f.sync_all()
+}
+
+/// One timed pass with `queue_depth` workers; the calling thread reports progress.
+fn pass(stage: &Stage, file: &File, write: bool, tile: &[u8], progress: &mut dyn Progress) -> io::Result<PhaseStats> {
+    let block = stage.block;
+    let slots = stage.size / block;
+    let ops_limit = if stage.duration.is_some() { u64::MAX } else { slots };
+    let phase = stage.phase(write);
+
+    let next = AtomicU64::new(0);
+    let done = AtomicU64::new(0);
+    let stop = AtomicBool::new(false);
+    let latencies = Mutex::new(Vec::<f64>::new());
+    let cpu = CpuMeter::start();
+    let start = Instant::now();
+    let mut sampler = Sampler::new(start);
+
+    let outcome = std::thread::scope(|s| {
+        let (next, done, stop, latencies) = (&next, &done, &stop, &latencies);
+        let workers: Vec<_> = (0..stage.queue_depth).map(|w| s.spawn(move || -> io::Result<()> {
+            let mut rng = SmallRng::seed_from_u64(0x4B_0FF5E7 ^ u64::from(w));
+            let mut buf = vec![0u8; block as usize];
+            while !stop.load(Ordering::Relaxed) {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= ops_limit { break; }
+                // Paced stages start I/O i no earlier than its place in the stream.
+                if let Some(wait) = stage.rate.and_then(|r| Duration::try_from_secs_f64((i * block) as f64 / r - start.elapsed().as_secs_f64()).ok()) {
+                    std::thread::sleep(wait);
+                }
+                if stage.duration.is_some_and(|d| start.elapsed().as_secs_f64() >= d) { break; }
+                let slot = match stage.pattern { Pattern::Sequential => i % slots, Pattern::Random => rng.random_range(0..slots) };
+                let off = slot * block;
+                let op = Instant::now();
+                let r = if write {
+                    fill_pattern(tile, off, &mut buf);
+                    write_all_at(file, &buf, off)
+                } else {
+                    read_exact_at(file, &mut buf, off)
+                };
+                if let Err(e) = r {
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+                latencies.lock().unwrap_or_else(|p| p.into_inner()).push(op.elapsed().as_secs_f64());
+                done.fetch_add(block, Ordering::Relaxed);
+            }
+            Ok(())
+        })).collect();
+
+        let mut result = Ok(());
+        while !workers.iter().all(|h| h.is_finished()) {
+            std::thread::sleep(POLL_EVERY);
+            let d = done.load(Ordering::Relaxed);
+            let lat = latencies.lock().unwrap_or_else(|p| p.into_inner());
+            sampler.tick(d, &lat);
+            let total = match stage.duration {
+                Some(limit) => projected(d, start.elapsed().as_secs_f64(), limit, u64::MAX),
+                None => slots * block,
+            };
+            let u = Update { phase, done: d, total, start, samples: sampler.samples(), latencies: &lat };
+            if let Err(e) = progress.update(&u) {
+                stop.store(true, Ordering::Relaxed);
+                result = Err(e);
+                break;
+            }
+        }
+        for h in workers {
+            match h.join() {
+                Ok(r) => result = result.and(r),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+        result
+    });
+    outcome?;
+    if write { file.sync_all()?; }
+    let seconds = start.elapsed().as_secs_f64();
+    crate::info!("stage '{}' {}: {} bytes at QD {} in {seconds:.3} s", stage.name, phase.id(), done.load(Ordering::Relaxed), stage.queue_depth);
+    progress.finish(phase)?;
+    let bytes = done.into_inner();
+    let latencies = latencies.into_inner().unwrap_or_else(|p| p.into_inner());
+    Ok(PhaseStats {
+        bytes,
+        seconds,
+        ops: latencies.len() as u64,
+        latency_avg_ms: mean(&latencies) * 1e3,
+        latency_p99_ms: percentile(&latencies, 99.0) * 1e3,
+        samples: sampler.finish(bytes, &latencies),
+        cpu: Some(cpu.stop()),
+    })
+}
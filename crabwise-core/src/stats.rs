@@ -0,0 +1,75 @@
+//! Throughput and latency arithmetic shared by the runner and its frontends.
+
+/// Throughput in decimal megabits per second.
+pub fn mbps(bytes: u128, dur_s: f64) -> f64 { (bytes as f64 * 8.0) / 1_000_000f64 / dur_s }
+/// Throughput in decimal megabytes per second.
+pub fn mbs(bytes: u128, dur_s: f64) -> f64 { (bytes as f64) / 1_000_000f64 / dur_s }
+
+/// Returns the `p`th percentile (0..=100) of `samples` using nearest-rank; 0.0 if empty.
+pub fn percentile(samples: &[f64], p: f64) -> f64 {
+    if samples.is_empty() { return 0.0; }
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+pub fn mean(samples: &[f64]) -> f64 {
+    if samples.is_empty() { 0.0 } else { samples.iter().sum::<f64>() / samples.len() as f64 }
+}
+
+/// Population standard deviation; 0.0 if empty.
+pub fn std_dev(samples: &[f64]) -> f64 {
+    let m = mean(samples);
+    mean(&samples.iter().map(|s| (s - m).powi(2)).collect::<Vec<_>>()).sqrt()
+}
+
+/// Least-squares line through `points` as `(slope, intercept)`; `None` with fewer than two distinct x values.
+pub fn linear_fit(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    if points.len() < 2 { return None; }
+    let (mx, my) = (points.iter().map(|p| p.0).sum::<f64>() / n, points.iter().map(|p| p.1).sum::<f64>() / n);
+    let sxx: f64 = points.iter().map(|p| (p.0 - mx).powi(2)).sum();
+    if sxx == 0.0 { return None; }
+    let sxy: f64 = points.iter().map(|p| (p.0 - mx) * (p.1 - my)).sum();
+    let slope = sxy / sxx;
+    Some((slope, my - slope * mx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_is_nearest_rank() {
+        let samples: Vec<f64> = (1..=100).rev().map(f64::from).collect();
+        assert_eq!(percentile(&samples, 50.0), 50.0);
+        assert_eq!(percentile(&samples, 99.0), 99.0);
+        assert_eq!(percentile(&samples, 100.0), 100.0);
+        assert_eq!(percentile(&samples, 0.0), 1.0);
+        assert_eq!(percentile(&[3.0, 1.0, 2.0], 50.0), 2.0);
+        assert_eq!(percentile(&[], 99.0), 0.0);
+    }
+
+    #[test]
+    fn mean_and_std_dev() {
+        assert_eq!(mean(&[]), 0.0);
+        assert_eq!(mean(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]), 5.0);
+        assert_eq!(std_dev(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]), 2.0);
+    }
+
+    #[test]
+    fn linear_fit_recovers_a_line() {
+        let points: Vec<(f64, f64)> = (0..10).map(|x| (f64::from(x), 3.0 - 0.5 * f64::from(x))).collect();
+        let (slope, intercept) = linear_fit(&points).unwrap();
+        assert!((slope + 0.5).abs() < 1e-12 && (intercept - 3.0).abs() < 1e-12);
+        assert_eq!(linear_fit(&[(1.0, 2.0)]), None);
+        assert_eq!(linear_fit(&[(1.0, 2.0), (1.0, 5.0)]), None);
+    }
+
+    #[test]
+    fn rates_are_decimal() {
+        assert_eq!(mbs(5_000_000, 2.0), 2.5);
+        assert_eq!(mbps(5_000_000, 2.0), 20.0);
+    }
+}
@@ -0,0 +1,74 @@
+//! Pass/fail limits checked against a finished run.
+
+use crate::result::{BenchResult, PhaseStats};
+
+/// Optional limits; throughput in bytes per second, latency in seconds (p99 per block).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Thresholds {
+    pub min_write: Option<f64>,
+    pub min_read: Option<f64>,
+    pub max_write_latency: Option<f64>,
+    pub max_read_latency: Option<f64>,
+}
+
+impl Thresholds {
+    pub fn is_empty(&self) -> bool {
+        self.min_write.is_none() && self.min_read.is_none()
+            && self.max_write_latency.is_none() && self.max_read_latency.is_none()
+    }
+
+    /// Describes every limit `r` misses; empty when all are met.
+    pub fn check(&self, r: &BenchResult) -> Vec<String> {
+        self.check_phases(Some(&r.write), Some(&r.read))
+    }
+
+    /// Like `check`, for runs that measured only one direction; limits for a missing phase are skipped.
+    pub fn check_phases(&self, write: Option<&PhaseStats>, read: Option<&PhaseStats>) -> Vec<String> {
+        let mut failures: Vec<String> = Vec::new();
+        if let (Some(min), Some(w)) = (self.min_write, write) {
+            if w.mb_s() * 1e6 < min { failures.push(format!("write {:.2} MB/s is below minimum {:.2} MB/s", w.mb_s(), min / 1e6)); }
+        }
+        if let (Some(min), Some(r)) = (self.min_read, read) {
+            if r.mb_s() * 1e6 < min { failures.push(format!("read {:.2} MB/s is below minimum {:.2} MB/s", r.mb_s(), min / 1e6)); }
+        }
+        if let (Some(max), Some(w)) = (self.max_write_latency, write) {
+            if w.latency_p99_ms / 1e3 > max { failures.push(format!("write p99 latency {:.2} ms exceeds maximum {:.2} ms", w.latency_p99_ms, max * 1e3)); }
+        }
+        if let (Some(max), Some(r)) = (self.max_read_latency, read) {
+            if r.latency_p99_ms / 1e3 > max { failures.push(format!("read p99 latency {:.2} ms exceeds maximum {:.2} ms", r.latency_p99_ms, max * 1e3)); }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(mb_s: f64, p99_ms: f64) -> PhaseStats {
+        PhaseStats { bytes: (mb_s * 1e6) as u64, seconds: 1.0, latency_p99_ms: p99_ms, ..PhaseStats::default() }
+    }
+
+    #[test]
+    fn no_limits_pass_everything() {
+        let t = Thresholds::default();
+        assert!(t.is_empty());
+        assert!(t.check_phases(Some(&phase(0.0, 1e6)), Some(&phase(0.0, 1e6))).is_empty());
+    }
+
+    #[test]
+    fn each_missed_limit_is_reported() {
+        let t = Thresholds { min_write: Some(10e6), min_read: Some(20e6), max_write_latency: Some(0.05), max_read_latency: Some(0.01) };
+        assert!(!t.is_empty());
+        assert!(t.check_phases(Some(&phase(10.0, 50.0)), Some(&phase(25.0, 5.0))).is_empty());
+        let failures = t.check_phases(Some(&phase(9.0, 60.0)), Some(&phase(19.0, 11.0)));
+        assert_eq!(failures.len(), 4, "{failures:?}");
+        assert!(failures[0].starts_with("write 9.00 MB/s is below"));
+    }
+
+    #[test]
+    fn limits_for_a_missing_phase_are_skipped() {
+        let t = Thresholds { min_write: Some(10e6), min_read: Some(20e6), ..Thresholds::default() };
+        assert_eq!(t.check_phases(None, Some(&phase(1.0, 0.0))), ["read 1.00 MB/s is below minimum 20.00 MB/s"]);
+    }
+}
@@ -0,0 +1,309 @@
+//! Replaying a recorded I/O trace against a test file: an application's own mix of reads and
+//! writes, sizes and offsets, instead of a synthetic sequential stream.
+//!
+//! Three text formats are read, told apart by their lines:
+//!
+//! - crabwise's CSV: `time,op,offset,length[,phase]`, with the time in seconds, `op` one of
+//!   `r`/`read`/`w`/`write`, offsets and lengths in bytes (or `4K`-style sizes), and an
+//!   optional phase name. A header line and `#` comments are skipped.
+//! - `blkparse` output, from `blktrace -d /dev/sdX -o - | blkparse -i -`: the `D` (issued)
+//!   events, or the `Q` (queued) ones when the trace has no `D`.
+//! - ftrace `block_rq_issue` events, e.g. from `/sys/kernel/tracing/trace`.
+//!
+//! Block traces carry no phases, so they are split wherever the device sat idle for
+//! [`PHASE_GAP_SECS`] or longer.
+
+use crate::file::{evict, open_read_write, read_exact_at, write_all_at};
+use crate::json::Value;
+use crate::parse::parse_size;
+use crate::progress::{Phase, Progress, Update};
+use crate::result::LatencyStats;
+use crate::runner::fill_pattern;
+use crate::sampler::Sampler;
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// An idle stretch this long starts a new phase in a block trace.
+pub const PHASE_GAP_SECS: f64 = 1.0;
+/// Bytes per sector in blktrace and ftrace offsets and lengths.
+const SECTOR: u64 = 512;
+/// Size of the pattern tile the test file is filled with.
+const TILE: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Read,
+    Write,
+}
+
+/// One recorded I/O.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceIo {
+    /// Seconds since the start of the trace.
+    pub t: f64,
+    pub op: Op,
+    pub offset: u64,
+    pub len: u64,
+    /// Index into [`Trace::phases`].
+    pub phase: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    /// `csv`, `blktrace` or `ftrace`.
+    pub format: &'static str,
+    pub phases: Vec<String>,
+    pub ios: Vec<TraceIo>,
+}
+
+impl Trace {
+    /// Bytes from the lowest offset touched to the end of the highest I/O.
+    pub fn span(&self) -> u64 {
+        let lo = self.ios.iter().map(|io| io.offset).min().unwrap_or(0);
+        let hi = self.ios.iter().map(|io| io.offset + io.len).max().unwrap_or(0);
+        hi - lo
+    }
+
+    /// The trace's length in seconds.
+    pub fn duration(&self) -> f64 {
+        self.ios.iter().map(|io| io.t).fold(0.0, f64::max)
+    }
+}
+
+/// Parses a trace in any of the supported formats; errors name the offending line.
+pub fn parse(text: &str) -> Result<Trace, String> {
+    let lines = || text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#'));
+    let mut trace = if lines().any(|l| l.contains("block_rq_issue:")) {
+        let ios = lines().filter(|l| l.contains("block_rq_issue:")).filter_map(ftrace_line).collect();
+        Trace { format: "ftrace", ios, ..Trace::default() }
+    } else if lines().next().is_some_and(|l| l.split(',').count() >= 4) {
+        // blkparse's `8,16` device field has one comma; a CSV line has at least three.
+        return csv(lines());
+    } else {
+        let events: Vec<(&str, TraceIo)> = lines().filter_map(blkparse_line).collect();
+        let action = if events.iter().any(|(a, _)| *a == "D") { "D" } else { "Q" };
+        Trace { format: "blktrace", ios: events.into_iter().filter(|(a, _)| *a == action).map(|(_, io)| io).collect(), ..Trace::default() }
+    };
+    if trace.ios.is_empty() { return Err(format!("no reads or writes found in the {} trace", trace.format)); }
+    trace.ios.sort_by(|a, b| a.t.total_cmp(&b.t));
+    let t0 = trace.ios[0].t;
+    let mut last = t0;
+    let mut phase = 0;
+    for io in &mut trace.ios {
+        if io.t - last >= PHASE_GAP_SECS { phase += 1; }
+        last = io.t;
+        io.t -= t0;
+        io.phase = phase;
+    }
+    trace.phases = (1..=phase + 1).map(|i| format!("phase {i}")).collect();
+    Ok(trace)
+}
+
+fn op_of(rwbs: &str) -> Option<Op> {
+    // Discards (`D`) have no data to replay; flushes are dropped by the callers' zero length check.
+    if rwbs.contains('D') { return None; }
+    if rwbs.contains('W') { Some(Op::Write) } else if rwbs.contains('R') { Some(Op::Read) } else { None }
+}
+
+/// `  8,16   1       3     0.000012345  4242  D   W 2048 + 8 [app]`
+fn blkparse_line(line: &str) -> Option<(&str, TraceIo)> {
+    let f: Vec<&str> = line.split_whitespace().collect();
+    if f.len() < 10 || !f[0].contains(',') || f[8] != "+" { return None; }
+    let action = f[5];
+    let op = op_of(f[6])?;
+    let len = f[9].parse::<u64>().ok()? * SECTOR;
+    if len == 0 { return None; }
+    Some((action, TraceIo { t: f[3].parse().ok()?, op, offset: f[7].parse::<u64>().ok()? * SECTOR, len, phase: 0 }))
+}
+
+/// `app-4242 [001] ..... 1234.567890: block_rq_issue: 8,16 W 4096 () 2048 + 8 [app]`; older
+/// kernels leave out the byte count.
+fn ftrace_line(line: &str) -> Option<TraceIo> {
+    let (head, event) = line.split_once("block_rq_issue:")?;
+    let t = head.split_whitespace().last()?.trim_end_matches(':').parse().ok()?;
+    let f: Vec<&str> = event.split_whitespace().collect();
+    let plus = f.iter().position(|&w| w == "+")?;
+    let op = op_of(f.get(1)?)?;
+    let len = f.get(plus + 1)?.parse::<u64>().ok()? * SECTOR;
+    if len == 0 || plus == 0 { return None; }
+    Some(TraceIo { t, op, offset: f[plus - 1].parse::<u64>().ok()? * SECTOR, len, phase: 0 })
+}
+
+fn csv<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Trace, String> {
+    let mut trace = Trace { format: "csv", ..Trace::default() };
+    for (n, line) in lines.enumerate() {
+        let f: Vec<&str> = line.split(',').map(str::trim).collect();
+        if n == 0 && f[0].parse::<f64>().is_err() { continue; } // header
+        let bad = |m: &str| format!("trace line {}: {m}: {line}", n + 1);
+        if f.len() < 4 { return Err(bad("expected time,op,offset,length[,phase]")); }
+        let t = f[0].parse::<f64>().ok().filter(|t| t.is_finite() && *t >= 0.0).ok_or_else(|| bad("bad time"))?;
+        let op = match f[1].to_ascii_lowercase().as_str() {
+            "r" | "read" => Op::Read,
+            "w" | "write" => Op::Write,
+            _ => return Err(bad("op must be r or w")),
+        };
+        let offset = parse_size(f[2]).map_err(|e| bad(&e))?;
+        let len = parse_size(f[3]).map_err(|e| bad(&e))?;
+        if len == 0 { return Err(bad("length must be positive")); }
+        let name = f.get(4).filter(|p| !p.is_empty()).copied().unwrap_or("trace");
+        let phase = match trace.phases.iter().position(|p| p == name) {
+            Some(i) => i,
+            None => { trace.phases.push(name.to_string()); trace.phases.len() - 1 }
+        };
+        trace.ios.push(TraceIo { t, op, offset, len, phase });
+    }
+    if trace.ios.is_empty() { return Err("the CSV trace holds no I/O".into()); }
+    trace.ios.sort_by(|a, b| a.t.total_cmp(&b.t));
+    let t0 = trace.ios[0].t;
+    for io in &mut trace.ios { io.t -= t0; }
+    Ok(trace)
+}
+
+/// How to replay.
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayOptions {
+    /// Size of the test file. Offsets are taken relative to the lowest one in the trace and
+    /// wrap around within it, so a trace of a whole disk fits a smaller file.
+    pub region: u64,
+    /// Keep the trace's timing, waiting out its idle time, rather than issuing each I/O as
+    /// soon as the last completes.
+    pub paced: bool,
+    /// Synchronous / uncached I/O, as for the benchmark.
+    pub direct: bool,
+}
+
+/// The figures for one phase of a replay.
+#[derive(Debug, Clone, Default)]
+pub struct PhaseReplay {
+    pub name: String,
+    /// Wall time from the phase's first I/O to the end of its last.
+    pub seconds: f64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    /// Latency of the reads and writes; `None` when the phase had none.
+    pub reads: Option<LatencyStats>,
+    pub writes: Option<LatencyStats>,
+}
+
+impl PhaseReplay {
+    pub fn ops(&self) -> u64 {
+        self.reads.as_ref().map_or(0, |s| s.ops) + self.writes.as_ref().map_or(0, |s| s.ops)
+    }
+
+    /// Bytes moved either way per second, in decimal MB/s.
+    pub fn mb_s(&self) -> f64 {
+        crate::stats::mbs(u128::from(self.read_bytes + self.write_bytes), self.seconds)
+    }
+
+    pub fn iops(&self) -> f64 {
+        if self.seconds > 0.0 { self.ops() as f64 / self.seconds } else { 0.0 }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let lat = |s: &Option<LatencyStats>| s.as_ref().map(|s| Value::object(vec![
+            ("ops", s.ops.into()),
+            ("avg_ms", s.avg_ms.into()),
+            ("p50_ms", s.p50_ms.into()),
+            ("p99_ms", s.p99_ms.into()),
+            ("max_ms", s.max_ms.into()),
+        ])).unwrap_or(Value::Null);
+        Value::object(vec![
+            ("name", self.name.as_str().into()),
+            ("seconds", self.seconds.into()),
+            ("read_bytes", self.read_bytes.into()),
+            ("write_bytes", self.write_bytes.into()),
+            ("mb_s", self.mb_s().into()),
+            ("iops", self.iops().into()),
+            ("reads", lat(&self.reads)),
+            ("writes", lat(&self.writes)),
+        ])
+    }
+}
+
+/// Fills a `region`-byte file at `path`, drops it from the cache, then issues every I/O of
+/// `trace` against it one at a time, in order. The file is left in place.
+pub fn replay(trace: &Trace, path: &Path, opts: ReplayOptions, progress: &mut dyn Progress) -> io::Result<Vec<PhaseReplay>> {
+    let largest = trace.ios.iter().map(|io| io.len).max().unwrap_or(0);
+    if largest > opts.region {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("the trace has a {largest}-byte I/O, larger than the {}-byte test file", opts.region)));
+    }
+    let mut tile = vec![0u8; TILE];
+    SmallRng::seed_from_u64(0x7ACE).fill_bytes(&mut tile);
+    fill(path, opts.region, &tile, progress)?;
+    evict(path)?;
+
+    let f = open_read_write(path, opts.direct)?;
+    let base = trace.ios.iter().map(|io| io.offset).min().unwrap_or(0);
+    let mut buf = vec![0u8; largest as usize];
+    let total: u64 = trace.ios.iter().map(|io| io.len).sum();
+    let (mut done, mut lat) = (0u64, Vec::with_capacity(trace.ios.len()));
+    let start = Instant::now();
+    let mut sampler = Sampler::new(start);
+    // (start, end) seconds of each I/O since `start`, for the phases' wall time.
+    let mut spans = Vec::with_capacity(trace.ios.len());
+    for io in &trace.ios {
+        if opts.paced {
+            if let Some(wait) = Duration::try_from_secs_f64(io.t - start.elapsed().as_secs_f64()).ok().filter(|d| !d.is_zero()) {
+                std::thread::sleep(wait);
+            }
+        }
+        // Wrap into the file, pulling an I/O that would run off the end back inside it.
+        let at = ((io.offset - base) % opts.region).min(opts.region - io.len);
+        let buf = &mut buf[..io.len as usize];
+        let t = Instant::now();
+        match io.op {
+            Op::Read => read_exact_at(&f, buf, at)?,
+            Op::Write => {
+                fill_pattern(&tile, at, buf);
+                write_all_at(&f, buf, at)?;
+            }
+        }
+        let secs = t.elapsed().as_secs_f64();
+        let begun = t.duration_since(start).as_secs_f64();
+        spans.push((begun, begun + secs));
+        lat.push(secs);
+        done += io.len;
+        sampler.tick(done, &lat);
+        progress.update(&Update { phase: Phase::Replay, done, total, start, samples: sampler.samples(), latencies: &lat })?;
+    }
+    f.sync_all()?;
+    progress.finish(Phase::Replay)?;
+
+    Ok(trace.phases.iter().enumerate().map(|(p, name)| {
+        let mut r = PhaseReplay { name: name.clone(), ..PhaseReplay::default() };
+        let (mut reads, mut writes) = (Vec::new(), Vec::new());
+        let (mut first, mut last) = (f64::MAX, 0.0f64);
+        for (i, io) in trace.ios.iter().enumerate().filter(|(_, io)| io.phase == p) {
+            first = first.min(spans[i].0);
+            last = last.max(spans[i].1);
+            match io.op {
+                Op::Read => { r.read_bytes += io.len; reads.push(lat[i]); }
+                Op::Write => { r.write_bytes += io.len; writes.push(lat[i]); }
+            }
+        }
+        r.seconds = (last - first).max(0.0);
+        let stats = |l: &[f64], bytes: u64| (!l.is_empty()).then(|| LatencyStats::from_secs(l, bytes / l.len() as u64, l.iter().sum()));
+        r.reads = stats(&reads, r.read_bytes);
+        r.writes = stats(&writes, r.write_bytes);
+        r
+    }).collect())
+}
+
+/// Writes the test file front to back so every read in the trace finds data.
+fn fill(path: &Path, region: u64, tile: &[u8], progress: &mut dyn Progress) -> io::Result<()> {
+    let f = crate::file::open_write(path, false)?;
+    let mut buf = vec![0u8; TILE];
+    let start = Instant::now();
+    let mut pos = 0;
+    while pos < region {
+        let n = (TILE as u64).min(region - pos) as usize;
+        fill_pattern(tile, pos, &mut buf[..n]);
+        write_all_at(&f, &buf[..n], pos)?;
+        pos += n as u64;
+        progress.update(&Update { phase: Phase::Write, done: pos, total: region, start, samples: &[], latencies: &[] })?;
+    }
+    f.sync_all()?;
+    progress.finish(Phase::Write)
+}
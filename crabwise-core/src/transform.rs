@@ -0,0 +1,136 @@
+//! Encryption and compression the host applies between the benchmark and the drive. An
+//! encrypted stick spends host CPU on every block, and a compressing filesystem writes fewer
+//! bytes than the benchmark does, so such a result can't be compared with a plain volume's.
+
+use crate::device::DeviceInfo;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    BitLocker,
+    /// FileVault or another encrypted APFS volume.
+    FileVault,
+    /// dm-crypt, usually LUKS.
+    Luks,
+    NtfsCompression,
+    BtrfsCompression,
+}
+
+impl Transform {
+    /// Identifier used in the result JSON.
+    pub fn id(self) -> &'static str {
+        match self {
+            Transform::BitLocker => "bitlocker",
+            Transform::FileVault => "filevault",
+            Transform::Luks => "luks",
+            Transform::NtfsCompression => "ntfs_compression",
+            Transform::BtrfsCompression => "btrfs_compression",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Transform> {
+        [Transform::BitLocker, Transform::FileVault, Transform::Luks, Transform::NtfsCompression, Transform::BtrfsCompression]
+            .into_iter()
+            .find(|t| t.id() == id)
+    }
+
+    /// e.g. `volume is BitLocker-encrypted — throughput includes host-side crypto`.
+    pub fn note(self) -> &'static str {
+        match self {
+            Transform::BitLocker => "volume is BitLocker-encrypted — throughput includes host-side crypto",
+            Transform::FileVault => "volume is APFS-encrypted (FileVault) — throughput includes host-side crypto",
+            Transform::Luks => "volume is LUKS/dm-crypt-encrypted — throughput includes host-side crypto",
+            Transform::NtfsCompression => "NTFS compression is on — the drive stores fewer bytes than were written",
+            Transform::BtrfsCompression => "btrfs compression is on — the drive stores fewer bytes than were written",
+        }
+    }
+}
+
+/// The transforms applied to files written in `dir` on the volume `info`; empty when none
+/// are found or the system doesn't tell.
+pub fn transforms(info: &DeviceInfo, dir: &Path) -> Vec<Transform> {
+    let found = detect(info, dir);
+    for t in &found { crate::debug!("{}: {}", info.name, t.note()); }
+    found
+}
+
+/// dm-crypt shows up as a device-mapper target with a `CRYPT-` UUID, anywhere in the stack of
+/// devices under the volume (LVM on LUKS, LUKS on a partition); cryptsetup's BitLocker support
+/// is `CRYPT-BITLK-`. Compression is a mount option on btrfs and a directory flag
+/// (`chattr +c`) on btrfs and ntfs3.
+#[cfg(target_os = "linux")]
+fn detect(info: &DeviceInfo, dir: &Path) -> Vec<Transform> {
+    fn crypt(dir: &Path, depth: u32) -> Option<Transform> {
+        let uuid = std::fs::read_to_string(dir.join("dm").join("uuid")).unwrap_or_default();
+        if let Some(kind) = uuid.strip_prefix("CRYPT-") {
+            return Some(if kind.starts_with("BITLK") { Transform::BitLocker } else { Transform::Luks });
+        }
+        if depth == 0 { return None; }
+        std::fs::read_dir(dir.join("slaves")).ok()?.flatten()
+            .find_map(|e| crypt(&std::fs::canonicalize(e.path()).ok()?, depth - 1))
+    }
+    let mut found = Vec::new();
+    // `/dev/mapper/NAME` is a symlink to the `dm-N` node that `/sys/class/block` knows.
+    let node = std::fs::canonicalize(&info.name).unwrap_or_else(|_| info.name.clone().into());
+    let sys = node.file_name().and_then(|n| std::fs::canonicalize(Path::new("/sys/class/block").join(n)).ok());
+    if let Some(t) = sys.and_then(|d| crypt(&d, 4)) { found.push(t); }
+    // The last entry for the mount point is the one on top.
+    let options = std::fs::read_to_string("/proc/self/mounts").unwrap_or_default().lines().rev()
+        .map(|l| l.split(' ').collect::<Vec<_>>())
+        .find(|f| f.len() > 3 && f[1] == info.mount_point)
+        .map(|f| f[3].to_string())
+        .unwrap_or_default();
+    let mount_compress = options.split(',').any(|o| o.starts_with("compress") && !o.ends_with("=no") && !o.ends_with("=none"));
+    if info.file_system == "btrfs" && (mount_compress || dir_compressed(dir)) {
+        found.push(Transform::BtrfsCompression);
+    } else if info.file_system.starts_with("ntfs") && dir_compressed(dir) {
+        found.push(Transform::NtfsCompression);
+    }
+    found
+}
+
+/// `FS_COMPR_FL` on the directory, which new files inherit.
+#[cfg(target_os = "linux")]
+fn dir_compressed(dir: &Path) -> bool {
+    use std::os::fd::AsRawFd;
+    const FS_IOC_GETFLAGS: libc::c_ulong = 0x8008_6601;
+    const FS_COMPR_FL: libc::c_long = 0x4;
+    let Ok(f) = std::fs::File::open(dir) else { return false };
+    let mut flags: libc::c_long = 0;
+    unsafe { libc::ioctl(f.as_raw_fd(), FS_IOC_GETFLAGS as _, &mut flags) == 0 && flags & FS_COMPR_FL != 0 }
+}
+
+/// `diskutil info` says `FileVault: Yes` for an encrypted APFS volume, and `Encrypted: Yes`
+/// for one that is not the boot volume.
+#[cfg(target_os = "macos")]
+fn detect(info: &DeviceInfo, _dir: &Path) -> Vec<Transform> {
+    let Ok(out) = std::process::Command::new("diskutil").args(["info", &info.mount_point]).output() else { return Vec::new() };
+    let text = String::from_utf8_lossy(&out.stdout);
+    let yes = |key: &str| text.lines().any(|l| l.trim().strip_prefix(key).is_some_and(|v| v.trim().starts_with("Yes")));
+    if yes("FileVault:") || yes("Encrypted:") { vec![Transform::FileVault] } else { Vec::new() }
+}
+
+/// BitLocker status from the shell's `System.Volume.BitLockerProtection` property, which
+/// unlike `manage-bde` needs no elevation: 1 (on), 3 (encrypting) and 5 (decrypting) mean
+/// the data is encrypted and unlocked. Compression is the directory's compressed attribute.
+#[cfg(target_os = "windows")]
+fn detect(info: &DeviceInfo, dir: &Path) -> Vec<Transform> {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_COMPRESSED: u32 = 0x800;
+    let mut found = Vec::new();
+    let drive = info.mount_point.trim_end_matches('\\');
+    let script = format!("(New-Object -ComObject Shell.Application).NameSpace('{drive}\\').Self.ExtendedProperty('System.Volume.BitLockerProtection')");
+    let status = std::process::Command::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", &script]).output()
+        .ok()
+        .and_then(|o| String::from_utf8_lossy(&o.stdout).trim().parse::<u32>().ok());
+    if matches!(status, Some(1 | 3 | 5)) { found.push(Transform::BitLocker); }
+    if std::fs::metadata(dir).is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_COMPRESSED != 0) {
+        found.push(Transform::NtfsCompression);
+    }
+    found
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn detect(_info: &DeviceInfo, _dir: &Path) -> Vec<Transform> {
+    Vec::new()
+}
@@ -0,0 +1,57 @@
+//! TRIM/discard: whether the device under a directory accepts it, and issuing one on demand.
+//!
+//! Detection reads `queue/discard_max_bytes` from sysfs and trimming uses the `FITRIM` ioctl
+//! that `fstrim` issues, so both are Linux-only; elsewhere support is unknown and a TRIM
+//! request fails with [`io::ErrorKind::Unsupported`].
+
+#[cfg(target_os = "linux")]
+use crate::device::{device_info, sys_block_dir};
+use std::io;
+use std::path::Path;
+
+/// Whether the block device holding `dir` accepts discards; `None` when that can't be told.
+pub fn discard_supported(dir: &Path) -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        let disk = sys_block_dir(&device_info(dir)?)?;
+        let max: u64 = std::fs::read_to_string(disk.join("queue/discard_max_bytes")).ok()?.trim().parse().ok()?;
+        Some(max > 0)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = dir;
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct FstrimRange {
+    start: u64,
+    len: u64,
+    minlen: u64,
+}
+
+/// `_IOWR('X', 121, struct fstrim_range)`, which the libc crate doesn't export.
+#[cfg(target_os = "linux")]
+const FITRIM: u64 = 0xC018_5879;
+
+/// Discards the free space of the filesystem holding `dir`, like `fstrim`; returns the bytes
+/// the filesystem reports trimmed. Needs root (`CAP_SYS_ADMIN`) on Linux.
+pub fn trim(dir: &Path) -> io::Result<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::fd::AsRawFd;
+        let f = std::fs::File::open(dir)?;
+        let mut range = FstrimRange { start: 0, len: u64::MAX, minlen: 0 };
+        let rc = unsafe { libc::ioctl(f.as_raw_fd(), FITRIM as _, &mut range) };
+        if rc != 0 { return Err(io::Error::last_os_error()); }
+        crate::debug!("FITRIM {}: {} bytes", dir.display(), range.len);
+        Ok(range.len)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = dir;
+        Err(io::Error::new(io::ErrorKind::Unsupported, "TRIM on demand is only implemented on Linux"))
+    }
+}
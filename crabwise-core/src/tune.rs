@@ -0,0 +1,123 @@
+//! Block size auto-tuning: short sequential write+read probes across a range of block sizes,
+//! picking the smallest one past which throughput stops improving. Most drives level off
+//! somewhere between 512 KiB and 16 MiB, and the 4 MiB default is only right for some.
+
+use crate::json::Value;
+use crate::progress::{Phase, Progress, Update};
+use crate::stage::{run_stage, Mode, Stage};
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+/// Block sizes probed, smallest first.
+pub const CANDIDATES: [u64; 9] = [64 << 10, 128 << 10, 256 << 10, 512 << 10, 1 << 20, 2 << 20, 4 << 20, 8 << 20, 16 << 20];
+/// Bytes each probe writes and reads back (or the test size, if smaller).
+pub const PROBE_BYTES: u64 = 64 << 20;
+/// A block within this share of the best probe's throughput counts as on the plateau.
+const PLATEAU: f64 = 0.95;
+
+/// One probe's figures.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BlockProbe {
+    pub block: u64,
+    pub write_mb_s: f64,
+    pub read_mb_s: f64,
+}
+
+impl BlockProbe {
+    /// Throughput of the write and read together (their harmonic mean), which is what the
+    /// choice is made on.
+    pub fn mb_s(&self) -> f64 {
+        let (w, r) = (self.write_mb_s, self.read_mb_s);
+        if w + r > 0.0 { 2.0 * w * r / (w + r) } else { 0.0 }
+    }
+}
+
+/// The probes and the block size picked from them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockTune {
+    pub probes: Vec<BlockProbe>,
+    pub chosen: u64,
+}
+
+impl BlockTune {
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("chosen", self.chosen.into()),
+            ("probes", Value::Array(self.probes.iter().map(|p| Value::object(vec![
+                ("block", p.block.into()),
+                ("write_mb_s", p.write_mb_s.into()),
+                ("read_mb_s", p.read_mb_s.into()),
+            ])).collect())),
+        ])
+    }
+
+    pub fn from_json(v: &Value) -> Option<BlockTune> {
+        Some(BlockTune {
+            chosen: v.get("chosen")?.as_u64()?,
+            probes: v.get("probes").and_then(Value::as_array).unwrap_or_default().iter().filter_map(|p| Some(BlockProbe {
+                block: p.get("block")?.as_u64()?,
+                write_mb_s: p.get("write_mb_s")?.as_f64()?,
+                read_mb_s: p.get("read_mb_s")?.as_f64()?,
+            })).collect(),
+        })
+    }
+}
+
+/// Reports every probe's blocks as one [`Phase::BlockProbe`] running over all of them.
+struct Probing<'a> {
+    inner: &'a mut dyn Progress,
+    start: Instant,
+    offset: u64,
+    total: u64,
+}
+
+impl Progress for Probing<'_> {
+    fn update(&mut self, u: &Update) -> io::Result<()> {
+        self.inner.update(&Update { phase: Phase::BlockProbe, done: self.offset + u.done, total: self.total, start: self.start, ..*u })
+    }
+
+    fn finish(&mut self, _phase: Phase) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The smallest block on the plateau: within [`PLATEAU`] of the fastest probe.
+fn choose(probes: &[BlockProbe]) -> Option<u64> {
+    let best = probes.iter().map(BlockProbe::mb_s).fold(0.0, f64::max);
+    probes.iter().find(|p| p.mb_s() >= best * PLATEAU).map(|p| p.block)
+}
+
+/// Probes each of [`CANDIDATES`] that fits in `size` with a sequential write and read of
+/// up to [`PROBE_BYTES`] in `dir`, and picks the block size for the main run.
+pub fn tune_block(dir: &Path, size: u64, direct: bool, progress: &mut dyn Progress) -> io::Result<BlockTune> {
+    let bytes = size.min(PROBE_BYTES);
+    let blocks: Vec<u64> = CANDIDATES.into_iter().filter(|&b| b <= bytes).collect();
+    if blocks.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("--auto-block needs a size of at least {} bytes", CANDIDATES[0])));
+    }
+    let mut probing = Probing { inner: progress, start: Instant::now(), offset: 0, total: 2 * bytes * blocks.len() as u64 };
+    let mut probes = Vec::new();
+    let mut path = None;
+    let outcome = blocks.iter().try_for_each(|&block| {
+        // Whole blocks only, so every probe moves the same bytes give or take one block.
+        let stage = Stage { mode: Mode::ReadWrite, block, size: bytes / block * block, direct, ..Stage::new("block-probe", dir) };
+        path = Some(stage.test_path());
+        let r = run_stage(&stage, &mut probing)?;
+        probing.offset += 2 * bytes;
+        // Stages report on a timer, so the last blocks of a probe may not have been shown.
+        let mark = Update { phase: Phase::BlockProbe, done: probing.offset, total: probing.total, start: probing.start, samples: &[], latencies: &[] };
+        probing.inner.update(&mark)?;
+        let mb_s = |p: &Option<crate::PhaseStats>| p.as_ref().map_or(0.0, |p| p.mb_s());
+        let probe = BlockProbe { block, write_mb_s: mb_s(&r.write), read_mb_s: mb_s(&r.read) };
+        crate::info!("block probe {block}: write {:.1} MB/s, read {:.1} MB/s", probe.write_mb_s, probe.read_mb_s);
+        probes.push(probe);
+        Ok::<_, io::Error>(())
+    });
+    if let Some(p) = path { let _ = std::fs::remove_file(p); }
+    outcome?;
+    probing.inner.finish(Phase::BlockProbe)?;
+    let chosen = choose(&probes).unwrap_or(blocks[0]);
+    crate::info!("auto block size: {chosen} bytes");
+    Ok(BlockTune { probes, chosen })
+}
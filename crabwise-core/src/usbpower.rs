@@ -0,0 +1,126 @@
+//! How much current a USB drive asks for and how much its port can give. A bus-powered hard
+//! drive on a port or hub that can't supply it spins up slowly, stalls and drops writes, which
+//! looks like a bad drive rather than a bad port.
+
+use crate::device::DeviceInfo;
+use crate::json::Value;
+
+/// Per-port budget in mA by the USB spec: a USB 2.0 port, a USB 3.x port, and the ports of a
+/// bus-powered USB 2.0 and 3.x hub.
+const USB2_PORT_MA: u32 = 500;
+const USB3_PORT_MA: u32 = 900;
+const USB2_BUS_HUB_MA: u32 = 100;
+const USB3_BUS_HUB_MA: u32 = 150;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsbPower {
+    /// What the active configuration asks of the bus (`bMaxPower`), in mA.
+    pub declared_ma: u32,
+    /// What the port supplies, in mA: on Linux the spec's budget for the link and the hub
+    /// above it, on macOS the `Current Available` the system reports. `None` when unknown.
+    pub available_ma: Option<u32>,
+    /// The device reports its own power supply, so the port's budget doesn't matter.
+    pub self_powered: bool,
+    /// A spinning disk, which needs its peak current at spin-up (Linux only).
+    pub rotational: bool,
+}
+
+impl UsbPower {
+    /// Bus-powered and asking for more than the port supplies.
+    pub fn short(&self) -> bool {
+        !self.self_powered && self.available_ma.is_some_and(|a| self.declared_ma > a)
+    }
+
+    /// e.g. `896 mA declared, 500 mA available (bus-powered hard drive)`.
+    pub fn summary(&self) -> String {
+        let available = self.available_ma.map(|a| format!(", {a} mA available")).unwrap_or_default();
+        let supply = if self.self_powered { "self-powered" } else { "bus-powered" };
+        let kind = if self.rotational { " hard drive" } else { "" };
+        format!("{} mA declared{available} ({supply}{kind})", self.declared_ma)
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("declared_ma", (self.declared_ma as u64).into()),
+            ("available_ma", self.available_ma.map(u64::from).into()),
+            ("self_powered", self.self_powered.into()),
+            ("rotational", self.rotational.into()),
+        ])
+    }
+
+    pub fn from_json(v: &Value) -> Option<UsbPower> {
+        let flag = |k: &str| v.get(k).and_then(Value::as_bool).unwrap_or(false);
+        Some(UsbPower {
+            declared_ma: v.get("declared_ma")?.as_u64()? as u32,
+            available_ma: v.get("available_ma").and_then(Value::as_u64).map(|a| a as u32),
+            self_powered: flag("self_powered"),
+            rotational: flag("rotational"),
+        })
+    }
+}
+
+/// The power figures of the USB device behind `info`; `None` off USB or where the OS
+/// doesn't tell (Windows).
+pub fn usb_power(info: &DeviceInfo) -> Option<UsbPower> {
+    let p = read(info)?;
+    crate::debug!("{}: USB power {}", info.name, p.summary());
+    Some(p)
+}
+
+/// The USB device is the first directory above the disk with a `bMaxPower`; its parent is
+/// the hub it is plugged into (`usbN` for a root hub).
+#[cfg(target_os = "linux")]
+fn read(info: &DeviceInfo) -> Option<UsbPower> {
+    let text = |p: &std::path::Path| std::fs::read_to_string(p).ok().map(|s| s.trim().to_string());
+    let disk = crate::device::sys_block_dir(info)?;
+    let mut dir = std::fs::canonicalize(disk.join("device")).ok()?;
+    while !dir.join("bMaxPower").exists() {
+        if !dir.pop() || dir == std::path::Path::new("/sys/devices") { return None; }
+    }
+    // "896mA"
+    let declared_ma = text(&dir.join("bMaxPower"))?.trim_end_matches("mA").parse().ok()?;
+    let self_powered_at = |d: &std::path::Path| text(&d.join("bmAttributes"))
+        .and_then(|a| u8::from_str_radix(&a, 16).ok())
+        .is_some_and(|a| a & 0x40 != 0);
+    let usb3 = text(&dir.join("speed")).and_then(|s| s.parse::<u32>().ok()).is_some_and(|s| s >= 5000);
+    let hub = dir.parent()?;
+    let root = hub.file_name().is_some_and(|n| n.to_string_lossy().starts_with("usb"));
+    let available_ma = match (root || self_powered_at(hub), usb3) {
+        (true, false) => USB2_PORT_MA,
+        (true, true) => USB3_PORT_MA,
+        (false, false) => USB2_BUS_HUB_MA,
+        (false, true) => USB3_BUS_HUB_MA,
+    };
+    Some(UsbPower {
+        declared_ma,
+        available_ma: Some(available_ma),
+        self_powered: self_powered_at(&dir),
+        rotational: text(&disk.join("queue").join("rotational")).as_deref() == Some("1"),
+    })
+}
+
+/// `system_profiler SPUSBDataType` lists each device as a `Name:` heading followed by
+/// indented fields, including `Current Available (mA)` and `Current Required (mA)`.
+#[cfg(target_os = "macos")]
+fn read(info: &DeviceInfo) -> Option<UsbPower> {
+    let name = crate::macos::volume(std::path::Path::new(&info.mount_point))?.usb_name?;
+    let out = std::process::Command::new("system_profiler").arg("SPUSBDataType").output().ok()?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    let heading = format!("{name}:");
+    let head = text.lines().find(|l| l.trim() == heading)?;
+    let indent = head.len() - head.trim_start().len();
+    let field = |key: &str| text.lines()
+        .skip_while(|l| l.trim() != heading).skip(1)
+        .take_while(|l| l.trim().is_empty() || l.len() - l.trim_start().len() > indent)
+        .find_map(|l| l.trim().strip_prefix(key)?.trim().parse::<u32>().ok());
+    Some(UsbPower {
+        declared_ma: field("Current Required (mA):")?,
+        available_ma: field("Current Available (mA):"),
+        ..UsbPower::default()
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read(_info: &DeviceInfo) -> Option<UsbPower> {
+    None
+}
@@ -0,0 +1,320 @@
+//! Overwriting a whole device, or a filesystem's free space, with a fixed pattern and
+//! reading it back to prove the overwrite landed.
+
+use crate::artifacts;
+use crate::device::device_info;
+use crate::file::{evict, open_existing_write, open_read, open_write, read_exact_at};
+use crate::json::Value;
+use crate::progress::{Phase, Progress, Update};
+use crate::sampler::Sampler;
+use crate::stats::mbs;
+use rand::rngs::SmallRng;
+use rand::{RngCore, SeedableRng};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WipePattern {
+    /// All zero bytes.
+    #[default]
+    Zero,
+    /// All 0xFF bytes.
+    Ones,
+    /// Every byte set to this value.
+    Byte(u8),
+    /// Seeded pseudo-random data, regenerated for verification.
+    Random,
+}
+
+impl WipePattern {
+    fn fill(self, rng: &mut SmallRng, buf: &mut [u8]) {
+        match self {
+            WipePattern::Zero => buf.fill(0),
+            WipePattern::Ones => buf.fill(0xFF),
+            WipePattern::Byte(b) => buf.fill(b),
+            WipePattern::Random => rng.fill_bytes(buf),
+        }
+    }
+}
+
+impl fmt::Display for WipePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WipePattern::Zero => f.write_str("zero"),
+            WipePattern::Ones => f.write_str("ones"),
+            WipePattern::Byte(b) => write!(f, "0x{b:02X}"),
+            WipePattern::Random => f.write_str("random"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WipeConfig {
+    /// A block device or file to overwrite in place, or a directory whose free space is filled.
+    pub path: PathBuf,
+    pub pattern: WipePattern,
+    /// Bytes per write/read call.
+    pub block: u64,
+    /// Read everything back and compare it to the pattern.
+    pub verify: bool,
+}
+
+impl WipeConfig {
+    pub fn new(path: impl Into<PathBuf>) -> WipeConfig {
+        WipeConfig { path: path.into(), pattern: WipePattern::Zero, block: 4 * 1024 * 1024, verify: true }
+    }
+
+    /// Whether `path` is a directory, i.e. only its filesystem's free space is wiped.
+    pub fn free_space(&self) -> bool {
+        self.path.is_dir()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WipeReport {
+    /// What was written: the device or file, or the first filler file for a free-space wipe.
+    pub target: PathBuf,
+    pub free_space: bool,
+    pub pattern: WipePattern,
+    pub bytes: u64,
+    pub write_seconds: f64,
+    /// `None` when verification was off.
+    pub verify_seconds: Option<f64>,
+    /// Bytes that read back differently from the pattern.
+    pub mismatches: u64,
+    /// Offset of the first differing byte.
+    pub first_mismatch: Option<u64>,
+}
+
+impl WipeReport {
+    pub fn write_mb_s(&self) -> f64 {
+        if self.write_seconds > 0.0 { mbs(self.bytes as u128, self.write_seconds) } else { 0.0 }
+    }
+
+    pub fn verify_mb_s(&self) -> Option<f64> {
+        self.verify_seconds.map(|s| if s > 0.0 { mbs(self.bytes as u128, s) } else { 0.0 })
+    }
+
+    pub fn verified(&self) -> bool {
+        self.verify_seconds.is_some() && self.mismatches == 0
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("target", self.target.display().to_string().into()),
+            ("free_space", self.free_space.into()),
+            ("pattern", self.pattern.to_string().into()),
+            ("bytes", self.bytes.into()),
+            ("write_seconds", self.write_seconds.into()),
+            ("write_mb_s", self.write_mb_s().into()),
+            ("verify_seconds", self.verify_seconds.into()),
+            ("verify_mb_s", self.verify_mb_s().into()),
+            ("mismatches", self.mismatches.into()),
+            ("first_mismatch", self.first_mismatch.into()),
+        ])
+    }
+}
+
+/// The seed behind [`WipePattern::Random`]; fixed so the verify pass can regenerate the stream.
+const RANDOM_SEED: u64 = 0x57_1BE0;
+
+/// Overwrites `cfg.path` and, with `cfg.verify`, reads it all back.
+///
+/// A device or file is overwritten from offset 0 to its end. For a directory filler files
+/// are written until the filesystem is full, verified, and removed again, which covers the
+/// free space but not blocks the filesystem or the drive's controller keep to themselves.
+/// There is one filler unless the filesystem caps the size of a file (4 GiB on FAT32); the
+/// next one is started whenever a write comes back "file too large".
+pub fn wipe(cfg: &WipeConfig, progress: &mut dyn Progress) -> io::Result<WipeReport> {
+    if cfg.block == 0 { return Err(io::Error::new(io::ErrorKind::InvalidInput, "wipe block size must be greater than 0")); }
+    let free_space = cfg.free_space();
+    let mut fillers = Vec::new();
+    let outcome = wipe_target(cfg, free_space, &mut fillers, progress);
+    for f in &fillers {
+        if let Err(e) = std::fs::remove_file(f) { crate::warn!("could not remove {}: {e}", f.display()); }
+    }
+    outcome
+}
+
+/// The `i`th filler file of a free-space wipe of `dir`.
+fn filler(dir: &Path, i: usize) -> PathBuf {
+    dir.join(artifacts::name(&format!("-wipe.{i}.tmp")))
+}
+
+/// Wipes `cfg.path`; a free-space wipe records each filler it creates in `fillers` as it goes,
+/// so they are all removed however this ends.
+fn wipe_target(cfg: &WipeConfig, free_space: bool, fillers: &mut Vec<PathBuf>, progress: &mut dyn Progress) -> io::Result<WipeReport> {
+    let target = if free_space { filler(&cfg.path, 0) } else { cfg.path.clone() };
+    let (mut file, end) = if free_space {
+        let f = open_write(&target, false)?;
+        fillers.push(target.clone());
+        (f, None)
+    } else {
+        let mut f = open_existing_write(&target, false)?;
+        // Block devices report a zero length in their metadata but seek to their real end.
+        let end = match f.seek(SeekFrom::End(0))? {
+            0 => f.metadata()?.len(),
+            n => n,
+        };
+        if end == 0 { return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{}: size unknown or empty", target.display()))); }
+        f.seek(SeekFrom::Start(0))?;
+        (f, Some(end))
+    };
+    crate::info!("wipe {} with {} ({})", target.display(), cfg.pattern, if free_space { "free space" } else { "whole target" });
+    let mut buf = vec![0u8; cfg.block as usize];
+    let t0 = Instant::now();
+    let bytes = if free_space {
+        let mut next = || {
+            let path = filler(&cfg.path, fillers.len());
+            crate::info!("file size limit reached; continuing in {}", path.display());
+            let f = open_write(&path, false)?;
+            fillers.push(path);
+            Ok(f)
+        };
+        write_pass(cfg, &mut file, end, &mut buf, progress, Some(&mut next))?
+    } else {
+        write_pass(cfg, &mut file, end, &mut buf, progress, None)?
+    };
+    file.sync_all()?;
+    let write_seconds = t0.elapsed().as_secs_f64();
+    drop(file);
+    let mut report = WipeReport {
+        target, free_space, pattern: cfg.pattern, bytes, write_seconds,
+        verify_seconds: None, mismatches: 0, first_mismatch: None,
+    };
+    if cfg.verify {
+        let paths = if free_space { fillers.clone() } else { vec![report.target.clone()] };
+        let mut parts = Vec::new();
+        for path in &paths {
+            if let Err(e) = evict(path) { crate::debug!("evict {}: {e}", path.display()); }
+            let file = open_read(path, false)?;
+            let len = if free_space { file.metadata()?.len() } else { bytes };
+            parts.push((file, len));
+        }
+        let (bad, first, secs) = verify_pass(cfg, &parts, bytes, &mut buf, progress)?;
+        report.verify_seconds = Some(secs);
+        report.mismatches = bad;
+        report.first_mismatch = first;
+    }
+    Ok(report)
+}
+
+/// Writes the pattern until `end`, or until the filesystem is full when there is none; returns
+/// the bytes written. When a write is refused as too large for one file, `next_file` closes
+/// this one and the rest goes into the file it opens.
+fn write_pass(
+    cfg: &WipeConfig, file: &mut File, end: Option<u64>, buf: &mut [u8], progress: &mut dyn Progress,
+    mut next_file: Option<&mut dyn FnMut() -> io::Result<File>>,
+) -> io::Result<u64> {
+    let mut rng = SmallRng::seed_from_u64(RANDOM_SEED);
+    // A free-space wipe can only estimate its total from what the filesystem reports free.
+    let total = end.unwrap_or_else(|| device_info(&cfg.path).map_or(0, |d| d.available_bytes));
+    let t0 = Instant::now();
+    let mut sampler = Sampler::new(t0);
+    let mut lat = Vec::new();
+    let mut pos = 0;
+    // Bytes in the current file; a fresh one that is already too large can't take any more.
+    let mut in_file = 0;
+    let mut full = false;
+    while !full && end.is_none_or(|e| pos < e) {
+        let len = end.map_or(cfg.block, |e| cfg.block.min(e - pos)) as usize;
+        cfg.pattern.fill(&mut rng, &mut buf[..len]);
+        let t = Instant::now();
+        let mut done = 0;
+        while done < len {
+            match file.write(&buf[done..len]) {
+                Ok(0) => { full = true; break; }
+                Ok(n) => { done += n; in_file += n as u64; }
+                Err(e) if end.is_none() && e.kind() == io::ErrorKind::StorageFull => { full = true; break; }
+                Err(e) if e.kind() == io::ErrorKind::FileTooLarge && in_file > 0 => match next_file.as_mut() {
+                    Some(next) => {
+                        file.sync_all()?;
+                        *file = next()?;
+                        in_file = 0;
+                    }
+                    None => return Err(e),
+                },
+                Err(e) => return Err(e),
+            }
+        }
+        pos += done as u64;
+        lat.push(t.elapsed().as_secs_f64());
+        sampler.tick(pos, &lat);
+        progress.update(&Update { phase: Phase::Wipe, done: pos, total: total.max(pos), start: t0, samples: sampler.samples(), latencies: &lat })?;
+    }
+    if full && end.is_some() { return Err(io::Error::new(io::ErrorKind::WriteZero, format!("{} stopped accepting data at byte {pos}", cfg.path.display()))); }
+    file.flush()?;
+    progress.finish(Phase::Wipe)?;
+    Ok(pos)
+}
+
+/// Reads `buf.len()` bytes at `pos` of `parts` (files with their lengths) laid end to end.
+fn read_parts(parts: &[(File, u64)], buf: &mut [u8], mut pos: u64) -> io::Result<()> {
+    let mut done = 0;
+    for (file, len) in parts {
+        if done == buf.len() { break; }
+        if pos >= *len { pos -= len; continue; }
+        let n = ((len - pos) as usize).min(buf.len() - done);
+        read_exact_at(file, &mut buf[done..done + n], pos)?;
+        done += n;
+        pos = 0;
+    }
+    if done < buf.len() { return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "wiped data is shorter than what was written")); }
+    Ok(())
+}
+
+fn verify_pass(cfg: &WipeConfig, parts: &[(File, u64)], bytes: u64, buf: &mut [u8], progress: &mut dyn Progress) -> io::Result<(u64, Option<u64>, f64)> {
+    let mut rng = SmallRng::seed_from_u64(RANDOM_SEED);
+    let mut want = vec![0u8; buf.len()];
+    let t0 = Instant::now();
+    let mut sampler = Sampler::new(t0);
+    let mut lat = Vec::new();
+    let (mut pos, mut bad, mut first) = (0, 0, None);
+    while pos < bytes {
+        let len = cfg.block.min(bytes - pos) as usize;
+        cfg.pattern.fill(&mut rng, &mut want[..len]);
+        let t = Instant::now();
+        read_parts(parts, &mut buf[..len], pos)?;
+        lat.push(t.elapsed().as_secs_f64());
+        if buf[..len] != want[..len] {
+            let diff: Vec<usize> = buf[..len].iter().zip(&want[..len]).enumerate().filter(|(_, (a, b))| a != b).map(|(i, _)| i).collect();
+            first = first.or(diff.first().map(|&i| pos + i as u64));
+            bad += diff.len() as u64;
+        }
+        pos += len as u64;
+        sampler.tick(pos, &lat);
+        progress.update(&Update { phase: Phase::WipeVerify, done: pos, total: bytes, start: t0, samples: sampler.samples(), latencies: &lat })?;
+    }
+    progress.finish(Phase::WipeVerify)?;
+    Ok((bad, first, t0.elapsed().as_secs_f64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoProgress;
+
+    #[test]
+    fn verify_runs_on_across_fillers() {
+        let cfg = WipeConfig { pattern: WipePattern::Random, block: 4096, ..WipeConfig::new(std::env::temp_dir()) };
+        let mut data = vec![0u8; 5 * 4096];
+        cfg.pattern.fill(&mut SmallRng::seed_from_u64(RANDOM_SEED), &mut data);
+        // Split mid-block, where a write came back "file too large".
+        let (mut parts, mut paths) = (Vec::new(), Vec::new());
+        for (i, chunk) in [&data[..6000], &data[6000..]].into_iter().enumerate() {
+            let path = std::env::temp_dir().join(format!("crabwise-wipe-{}-{i}.tmp", std::process::id()));
+            std::fs::write(&path, chunk).unwrap();
+            parts.push((File::open(&path).unwrap(), chunk.len() as u64));
+            paths.push(path);
+        }
+        let mut buf = vec![0u8; 4096];
+        let (bad, first, _) = verify_pass(&cfg, &parts, data.len() as u64, &mut buf, &mut NoProgress).unwrap();
+        assert_eq!((bad, first), (0, None));
+        assert!(verify_pass(&cfg, &parts[..1], data.len() as u64, &mut buf, &mut NoProgress).is_err());
+        drop(parts);
+        for p in paths { std::fs::remove_file(p).unwrap(); }
+    }
+}
@@ -0,0 +1,87 @@
+/*
+ * C API for the crabwise benchmark engine (crabwise-ffi).
+ *
+ * Link against libcrabwise (crabwise.dll / libcrabwise.so / libcrabwise.dylib, or the
+ * static libcrabwise.a / crabwise.lib). Mirrors crabwise-ffi/src/lib.rs; keep the two in step
+ * and bump CRABWISE_ABI_VERSION for any change a caller built against an older header
+ * would get wrong.
+ */
+#ifndef CRABWISE_H
+#define CRABWISE_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CRABWISE_ABI_VERSION 1
+
+#define CRABWISE_OK 0
+/* A bad argument: a null pointer, a target that isn't UTF-8, a block larger than the size. */
+#define CRABWISE_ERR_INVALID 1
+/* The benchmark failed with an I/O error. */
+#define CRABWISE_ERR_IO 2
+/* The progress callback asked to stop. */
+#define CRABWISE_ERR_ABORTED 3
+/* A bug in crabwise; the message says where. */
+#define CRABWISE_ERR_PANIC 4
+
+/* What to benchmark; fill with crabwise_config_init and change what you need. */
+typedef struct CrabwiseConfig {
+    /* Directory on the device under test, UTF-8 and NUL-terminated; borrowed for the call. */
+    const char *target_dir;
+    uint64_t size;
+    uint64_t block;
+    uint32_t iterations;
+    /* Booleans: 0 is off, anything else on. */
+    int direct;
+    int random;
+    int verify;
+    int keep_file;
+} CrabwiseConfig;
+
+/* The headline figures of a run, plus the whole record as JSON (the same as a line of
+ * --record). Release with crabwise_result_free. */
+typedef struct CrabwiseResult {
+    double write_mb_s;
+    double read_mb_s;
+    double write_seconds;
+    double read_seconds;
+    double write_p99_ms;
+    double read_p99_ms;
+    /* 4 KiB random IOPS; 0 when the random stage didn't run. */
+    double random_write_iops;
+    double random_read_iops;
+    /* Mismatched bytes found by verify; -1 when it was off. */
+    int64_t verify_mismatches;
+    /* NUL-terminated, owned by the result. */
+    char *json;
+} CrabwiseResult;
+
+/* Called after every block: the phase ("write", "read", "random_write", ...), bytes done of
+ * the phase's total, and the average rate so far. Return non-zero to stop the run. */
+typedef int (*CrabwiseProgressFn)(void *user, const char *phase, uint64_t done, uint64_t total, double mb_s);
+
+/* The CRABWISE_ABI_VERSION the library was built with; compare it with the header's. */
+uint32_t crabwise_abi_version(void);
+
+/* The message of the last failed call on this thread; empty if none. Valid until the next call. */
+const char *crabwise_last_error(void);
+
+/* Fills config with the defaults the command line uses, with no target. */
+void crabwise_config_init(CrabwiseConfig *config);
+
+/* Runs the benchmark described by config on the calling thread, which also receives the
+ * progress callbacks; progress may be NULL. On success fills out and returns CRABWISE_OK;
+ * otherwise returns an error code, leaves out untouched and sets crabwise_last_error. */
+int crabwise_run(const CrabwiseConfig *config, CrabwiseProgressFn progress, void *user, CrabwiseResult *out);
+
+/* Releases what crabwise_run allocated in result and clears it; safe to call twice. */
+void crabwise_result_free(CrabwiseResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CRABWISE_H */
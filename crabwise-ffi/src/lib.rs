@@ -0,0 +1,215 @@
+//! C API over `crabwise-core`, for embedding the benchmark in programs that aren't written in
+//! Rust. The declarations are in `include/crabwise.h`; build the crate as a shared or static
+//! library and link against it.
+//!
+//! The ABI is versioned by [`CRABWISE_ABI_VERSION`]: fields are only ever appended to the
+//! structs, and anything that would break a caller compiled against an older header bumps it.
+
+use crabwise_core::{BenchConfig, BenchResult, BenchRunner, Phase, Progress, Update};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::io;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+pub const CRABWISE_ABI_VERSION: u32 = 1;
+
+pub const CRABWISE_OK: c_int = 0;
+/// A bad argument: a null pointer, a target that isn't UTF-8, a block larger than the size.
+pub const CRABWISE_ERR_INVALID: c_int = 1;
+/// The benchmark failed with an I/O error.
+pub const CRABWISE_ERR_IO: c_int = 2;
+/// The progress callback asked to stop.
+pub const CRABWISE_ERR_ABORTED: c_int = 3;
+/// A bug in crabwise; the message says where.
+pub const CRABWISE_ERR_PANIC: c_int = 4;
+
+/// What to benchmark; fill with [`crabwise_config_init`] and change what you need.
+#[repr(C)]
+pub struct CrabwiseConfig {
+    /// Directory on the device under test, UTF-8 and NUL-terminated; borrowed for the call.
+    pub target_dir: *const c_char,
+    pub size: u64,
+    pub block: u64,
+    pub iterations: u32,
+    /// Booleans: 0 is off, anything else on.
+    pub direct: c_int,
+    pub random: c_int,
+    pub verify: c_int,
+    pub keep_file: c_int,
+}
+
+/// The headline figures of a run, plus the whole record as JSON (the same as a line of
+/// `--record`). Release with [`crabwise_result_free`].
+#[repr(C)]
+pub struct CrabwiseResult {
+    pub write_mb_s: f64,
+    pub read_mb_s: f64,
+    pub write_seconds: f64,
+    pub read_seconds: f64,
+    pub write_p99_ms: f64,
+    pub read_p99_ms: f64,
+    /// 4 KiB random IOPS; 0 when the random stage didn't run.
+    pub random_write_iops: f64,
+    pub random_read_iops: f64,
+    /// Mismatched bytes found by `verify`; -1 when it was off.
+    pub verify_mismatches: i64,
+    /// NUL-terminated, owned by the result.
+    pub json: *mut c_char,
+}
+
+/// Called after every block: the phase (`"write"`, `"read"`, `"random_write"`, ...), bytes done
+/// of the phase's total, and the average rate so far. Return non-zero to stop the run.
+pub type CrabwiseProgressFn = Option<unsafe extern "C" fn(user: *mut c_void, phase: *const c_char, done: u64, total: u64, mb_s: f64) -> c_int>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_error(msg: impl Into<String>) {
+    let msg = CString::new(msg.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = msg);
+}
+
+/// The [`CRABWISE_ABI_VERSION`] the library was built with; compare it with the header's.
+#[no_mangle]
+pub extern "C" fn crabwise_abi_version() -> u32 {
+    CRABWISE_ABI_VERSION
+}
+
+/// The message of the last failed call on this thread; empty if none. Valid until the next call.
+#[no_mangle]
+pub extern "C" fn crabwise_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
+}
+
+/// Fills `config` with the defaults the command line uses, with no target.
+///
+/// # Safety
+/// `config` must be null or point to a writable `CrabwiseConfig`.
+#[no_mangle]
+pub unsafe extern "C" fn crabwise_config_init(config: *mut CrabwiseConfig) {
+    let Some(config) = (unsafe { config.as_mut() }) else { return };
+    let d = BenchConfig::new("");
+    *config = CrabwiseConfig {
+        target_dir: ptr::null(),
+        size: d.size,
+        block: d.block,
+        iterations: d.iterations,
+        direct: d.direct.into(),
+        random: d.random.into(),
+        verify: d.verify.into(),
+        keep_file: d.keep_file.into(),
+    };
+}
+
+struct Callback {
+    f: CrabwiseProgressFn,
+    user: *mut c_void,
+    phase: Option<(Phase, CString)>,
+}
+
+impl Progress for Callback {
+    fn update(&mut self, u: &Update) -> io::Result<()> {
+        let Some(f) = self.f else { return Ok(()) };
+        if self.phase.as_ref().is_none_or(|(p, _)| *p != u.phase) {
+            self.phase = Some((u.phase, CString::new(u.phase.id()).expect("phase ids have no NUL")));
+        }
+        let name = self.phase.as_ref().map_or(ptr::null(), |(_, s)| s.as_ptr());
+        let mb_s = crabwise_core::stats::mbs(u.done as u128, u.start.elapsed().as_secs_f64());
+        if unsafe { f(self.user, name, u.done, u.total, mb_s) } != 0 {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "stopped by the progress callback"));
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _phase: Phase) -> io::Result<()> { Ok(()) }
+}
+
+fn bench_config(c: &CrabwiseConfig) -> Result<BenchConfig, String> {
+    if c.target_dir.is_null() { return Err("target_dir is null".into()); }
+    let dir = unsafe { CStr::from_ptr(c.target_dir) }.to_str().map_err(|_| "target_dir isn't UTF-8".to_string())?;
+    let mut config = BenchConfig::new(dir);
+    config.size = c.size;
+    config.block = c.block;
+    config.iterations = c.iterations.max(1);
+    config.direct = c.direct != 0;
+    config.random = c.random != 0;
+    config.verify = c.verify != 0;
+    config.keep_file = c.keep_file != 0;
+    Ok(config)
+}
+
+fn c_result(r: &BenchResult) -> CrabwiseResult {
+    let iops = |p: &Option<crabwise_core::PhaseStats>| p.as_ref().map_or(0.0, |p| p.iops(crabwise_core::runner::RANDOM_IO_BYTES as u64));
+    let json = CString::new(r.to_json().to_string()).unwrap_or_default();
+    CrabwiseResult {
+        write_mb_s: r.write.mb_s(),
+        read_mb_s: r.read.mb_s(),
+        write_seconds: r.write.seconds,
+        read_seconds: r.read.seconds,
+        write_p99_ms: r.write.latency_p99_ms,
+        read_p99_ms: r.read.latency_p99_ms,
+        random_write_iops: iops(&r.random_write),
+        random_read_iops: iops(&r.random_read),
+        verify_mismatches: r.verify_mismatches.map_or(-1, |m| m as i64),
+        json: json.into_raw(),
+    }
+}
+
+/// Runs the benchmark described by `config` on the calling thread, which also receives the
+/// progress callbacks; `progress` may be null. On success fills `out` and returns
+/// `CRABWISE_OK`; otherwise returns an error code, leaves `out` untouched and sets
+/// [`crabwise_last_error`].
+///
+/// # Safety
+/// `config` must point to a valid `CrabwiseConfig` whose `target_dir` is a NUL-terminated
+/// string, and `out` to a writable `CrabwiseResult`.
+#[no_mangle]
+pub unsafe extern "C" fn crabwise_run(config: *const CrabwiseConfig, progress: CrabwiseProgressFn, user: *mut c_void, out: *mut CrabwiseResult) -> c_int {
+    let (Some(config), Some(out)) = (unsafe { config.as_ref() }, unsafe { out.as_mut() }) else {
+        set_error("config and out must not be null");
+        return CRABWISE_ERR_INVALID;
+    };
+    let run = catch_unwind(AssertUnwindSafe(|| {
+        let config = bench_config(config).map_err(|e| (CRABWISE_ERR_INVALID, e))?;
+        let mut cb = Callback { f: progress, user, phase: None };
+        BenchRunner::new(config).run(&mut cb).map_err(|e| {
+            let code = match e.kind() {
+                io::ErrorKind::Interrupted => CRABWISE_ERR_ABORTED,
+                io::ErrorKind::InvalidInput => CRABWISE_ERR_INVALID,
+                _ => CRABWISE_ERR_IO,
+            };
+            (code, e.to_string())
+        })
+    }));
+    match run {
+        Ok(Ok(r)) => {
+            *out = c_result(&r);
+            set_error("");
+            CRABWISE_OK
+        }
+        Ok(Err((code, msg))) => {
+            set_error(msg);
+            code
+        }
+        Err(panic) => {
+            let msg = panic.downcast_ref::<&str>().map(|s| s.to_string()).or_else(|| panic.downcast_ref::<String>().cloned());
+            set_error(format!("internal error: {}", msg.unwrap_or_default()));
+            CRABWISE_ERR_PANIC
+        }
+    }
+}
+
+/// Releases what `crabwise_run` allocated in `result` and clears it; safe to call twice.
+///
+/// # Safety
+/// `result` must be null or point to a `CrabwiseResult` filled by `crabwise_run`.
+#[no_mangle]
+pub unsafe extern "C" fn crabwise_result_free(result: *mut CrabwiseResult) {
+    let Some(r) = (unsafe { result.as_mut() }) else { return };
+    if !r.json.is_null() {
+        drop(unsafe { CString::from_raw(r.json) });
+        r.json = ptr::null_mut();
+    }
+}
@@ -0,0 +1,206 @@
+//! `crabwise burnin`: writes and verifies the test region over and over, tracking
+//! throughput drift and verification failures, and ends with an endurance report.
+
+use crate::progress::LineProgress;
+use crate::{units, SyncMode};
+use chrono::Local;
+use crabwise_core::json::Value as Json;
+use crabwise_core::parse::parse_size;
+use crabwise_core::result;
+use crabwise_core::stats::linear_fit;
+use crabwise_core::{BenchConfig, BenchRunner};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+#[derive(clap::Args, Debug)]
+pub struct BurninArgs {
+    /// Directory on the drive to exercise
+    target_dir: PathBuf,
+
+    /// Write/verify cycles to run
+    #[arg(long, value_name="N", default_value_t=50)]
+    cycles: u32,
+
+    /// Size of the test region rewritten each cycle
+    #[arg(short='s', long, default_value="1G", value_parser=parse_size)]
+    size: u64,
+
+    /// Block size
+    #[arg(short='b', long, default_value="4M", value_parser=parse_size)]
+    block: u64,
+
+    /// I/O mode, as for the benchmark [default: direct]
+    #[arg(long, value_enum)]
+    sync: Option<SyncMode>,
+
+    /// Print a drift/failure summary every N cycles (0 = only at the end)
+    #[arg(long, value_name="N", default_value_t=10)]
+    summary_every: u32,
+
+    /// Stop at the first cycle that fails verification
+    #[arg(long)]
+    stop_on_fail: bool,
+
+    /// Append each cycle's result as a JSON line to FILE (input for `crabwise report`)
+    #[arg(long, value_name="FILE")]
+    record: Option<PathBuf>,
+
+    /// Also write the endurance report to FILE (.json, or Markdown otherwise)
+    #[arg(short='o', long, value_name="FILE")]
+    out: Option<PathBuf>,
+}
+
+struct Cycle {
+    write: f64,
+    read: f64,
+    /// Bytes that read back differently from what the cycle wrote.
+    mismatches: u64,
+}
+
+/// Runs the cycles; `Ok(false)` when any cycle failed verification.
+pub fn run(b: BurninArgs) -> io::Result<bool> {
+    if b.cycles == 0 { return Err(io::Error::new(io::ErrorKind::InvalidInput, "--cycles must be at least 1")); }
+    let mut config = BenchConfig::new(&b.target_dir);
+    config.size = b.size;
+    config.block = b.block;
+    config.direct = b.sync.unwrap_or(SyncMode::Direct) == SyncMode::Direct;
+    config.verify = true;
+    crate::say!("Burn-in on {}: {} cycles of {} in {} blocks", b.target_dir.display(), b.cycles, units::size(b.size), units::size(b.block));
+    let start = Instant::now();
+    let mut cycles: Vec<Cycle> = Vec::new();
+    let mut outcome = Ok(());
+    for i in 1..=b.cycles {
+        crate::say!("\n[{}] Cycle {i}/{}", Local::now().format("%Y-%m-%d %H:%M:%S"), b.cycles);
+        // A fresh pattern every cycle, so data left by the previous one can't pass verification.
+        config.seed = 0x5EED_CAFE ^ u64::from(i).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let mut rec = match BenchRunner::new(config.clone()).run(&mut LineProgress { enabled: true }) {
+            Ok(rec) => rec,
+            Err(e) => {
+                outcome = Err(io::Error::new(e.kind(), format!("cycle {i}: {e}")));
+                break;
+            }
+        };
+        let mismatches = rec.verify_mismatches.unwrap_or(0);
+        rec.session = Some(format!("burn-in cycle {i}"));
+        if let Some(path) = &b.record { result::append(path, &rec)?; }
+        let verdict = if mismatches == 0 { "verify OK".to_string() } else { format!("VERIFY FAILED ({mismatches} bytes differ)") };
+        crate::say!("  write {}, read {}, {verdict}", units::rate(rec.write.mb_s()), units::rate(rec.read.mb_s()));
+        cycles.push(Cycle { write: rec.write.mb_s(), read: rec.read.mb_s(), mismatches });
+        if mismatches > 0 && b.stop_on_fail { break; }
+        if b.summary_every > 0 && i % b.summary_every == 0 && i < b.cycles { print_progress(&cycles); }
+    }
+    if !cycles.is_empty() {
+        print_report(&cycles, b.cycles, b.size, start.elapsed().as_secs_f64());
+        if let Some(out) = &b.out {
+            let doc = match out.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+                Some("json") => format!("{}\n", to_json(&b.target_dir, &cycles, b.size)),
+                _ => render_markdown(&b.target_dir, &cycles, b.size),
+            };
+            std::fs::write(out, doc)?;
+            println!("Wrote endurance report to {}", out.display());
+        }
+    }
+    outcome?;
+    Ok(failed(&cycles).is_empty())
+}
+
+fn failed(cycles: &[Cycle]) -> Vec<usize> {
+    cycles.iter().enumerate().filter(|(_, c)| c.mismatches > 0).map(|(i, _)| i + 1).collect()
+}
+
+/// Change from the first cycle to the last, in percent.
+fn drift(v: &[f64]) -> f64 {
+    match (v.first(), v.last()) {
+        (Some(&first), Some(&last)) if first > 0.0 => (last - first) / first * 100.0,
+        _ => 0.0,
+    }
+}
+
+/// Fitted change per cycle, in MB/s.
+fn trend(v: &[f64]) -> Option<f64> {
+    let pts: Vec<(f64, f64)> = v.iter().enumerate().map(|(i, &y)| (i as f64, y)).collect();
+    linear_fit(&pts).map(|(s, _)| s)
+}
+
+fn series(cycles: &[Cycle]) -> [(&'static str, Vec<f64>); 2] {
+    [("Write", cycles.iter().map(|c| c.write).collect()), ("Read", cycles.iter().map(|c| c.read).collect())]
+}
+
+fn fmt_failed(f: &[usize]) -> String {
+    f.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")
+}
+
+fn print_progress(cycles: &[Cycle]) {
+    let label = units::label();
+    crate::say!("\n── After {} cycles ──", cycles.len());
+    for (name, v) in series(cycles) {
+        let t = trend(&v).map(|s| format!(", trend {:+.2} {label}/cycle", units::scale(s))).unwrap_or_default();
+        crate::say!("  {:<6} {:+.1}% since cycle 1{t}", format!("{name}:"), drift(&v));
+    }
+    let f = failed(cycles);
+    if f.is_empty() { crate::say!("  Verify: no failures"); } else { crate::say!("  Verify: {} failed (cycle {})", f.len(), fmt_failed(&f)); }
+}
+
+fn print_report(cycles: &[Cycle], planned: u32, size: u64, secs: f64) {
+    let label = units::label();
+    crate::say!("\n{}", "═".repeat(48));
+    crate::say!("{:^48}", "Endurance Report");
+    crate::say!("{}", "═".repeat(48));
+    crate::say!("{:<8} {} of {planned}", "Cycles:", cycles.len());
+    crate::say!("{:<8} {} in {:.1} min", "Written:", units::size(size * cycles.len() as u64), secs / 60.0);
+    for (name, v) in series(cycles) {
+        let min = v.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = v.iter().copied().fold(0.0, f64::max);
+        let t = trend(&v).map(|s| format!(", trend {:+.2}/cycle", units::scale(s))).unwrap_or_default();
+        crate::say!("{:<8} min {:.2} / max {:.2} {label}, first→last {:+.1}%{t}", format!("{name}:"), units::scale(min), units::scale(max), drift(&v));
+    }
+    let f = failed(cycles);
+    if f.is_empty() {
+        crate::say!("{:<8} every cycle read back intact", "Verify:");
+    } else {
+        let bytes: u64 = cycles.iter().map(|c| c.mismatches).sum();
+        crate::say!("{:<8} {} cycle(s) failed: {} ({bytes} bytes differ in total)", "Verify:", f.len(), fmt_failed(&f));
+    }
+    crate::say!("{:<8} {}", "Result:", if f.is_empty() { "PASS" } else { "FAIL" });
+}
+
+fn to_json(target: &Path, cycles: &[Cycle], size: u64) -> Json {
+    let [(_, w), (_, r)] = series(cycles);
+    Json::object(vec![
+        ("target", target.display().to_string().into()),
+        ("size_bytes", size.into()),
+        ("cycles", Json::Array(cycles.iter().map(|c| Json::object(vec![
+            ("write_mb_s", c.write.into()),
+            ("read_mb_s", c.read.into()),
+            ("verify_mismatches", c.mismatches.into()),
+        ])).collect())),
+        ("write_drift_pct", drift(&w).into()),
+        ("read_drift_pct", drift(&r).into()),
+        ("write_trend_mb_s_per_cycle", trend(&w).into()),
+        ("read_trend_mb_s_per_cycle", trend(&r).into()),
+        ("failed_cycles", Json::Array(failed(cycles).into_iter().map(Json::from).collect())),
+        ("pass", failed(cycles).is_empty().into()),
+    ])
+}
+
+fn render_markdown(target: &Path, cycles: &[Cycle], size: u64) -> String {
+    let label = units::label();
+    let mut m = String::new();
+    let _ = writeln!(m, "# Endurance report: {}\n", target.display());
+    let _ = writeln!(m, "{} cycles of {}.\n", cycles.len(), units::size(size));
+    let _ = writeln!(m, "| | First ({label}) | Last ({label}) | Drift | Trend per cycle |\n|---|---:|---:|---:|---:|");
+    for (name, v) in series(cycles) {
+        let t = trend(&v).map(|s| format!("{:+.2}", units::scale(s))).unwrap_or_else(|| "—".to_string());
+        let _ = writeln!(m, "| {name} | {:.2} | {:.2} | {:+.1}% | {t} |", units::scale(v[0]), units::scale(v[v.len() - 1]), drift(&v));
+    }
+    let f = failed(cycles);
+    let _ = writeln!(m, "\n**Result: {}**", if f.is_empty() { "PASS".to_string() } else { format!("FAIL (cycle {})", fmt_failed(&f)) });
+    let _ = writeln!(m, "\n| Cycle | Write {label} | Read {label} | Verify |\n|---:|---:|---:|---|");
+    for (i, c) in cycles.iter().enumerate() {
+        let v = if c.mismatches == 0 { "OK".to_string() } else { format!("{} bytes differ", c.mismatches) };
+        let _ = writeln!(m, "| {} | {:.2} | {:.2} | {v} |", i + 1, units::scale(c.write), units::scale(c.read));
+    }
+    m
+}
@@ -0,0 +1,288 @@
+//! Throughput line charts (over time, or over offset in the test file) rendered to SVG or PNG
+//! without external dependencies.
+
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+pub struct Series<'a> {
+    pub name: &'a str,
+    pub color: (u8, u8, u8),
+    /// (x, y) points; x is seconds (or percent of the test file), y is MB/s.
+    pub points: &'a [(f64, f64)],
+}
+
+/// X-axis labels; the PNG font only has the glyphs these need.
+pub const TIME_AXIS: &str = "Time (s)";
+pub const OFFSET_AXIS: &str = "Offset (%)";
+
+pub const WRITE_COLOR: (u8, u8, u8) = (0xd9, 0x53, 0x4f);
+pub const READ_COLOR: (u8, u8, u8) = (0x33, 0x7a, 0xb7);
+
+const W: usize = 800;
+const H: usize = 360;
+const LEFT: f64 = 70.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 40.0;
+const BOTTOM: f64 = 50.0;
+
+/// Rounds `step` up to 1, 2 or 5 times a power of ten.
+fn nice_step(range: f64, ticks: usize) -> f64 {
+    let raw = (range / ticks as f64).max(f64::MIN_POSITIVE);
+    let mag = 10f64.powf(raw.log10().floor());
+    let norm = raw / mag;
+    let nice = if norm <= 1.0 { 1.0 } else if norm <= 2.0 { 2.0 } else if norm <= 5.0 { 5.0 } else { 10.0 };
+    nice * mag
+}
+
+fn fmt_tick(v: f64, step: f64) -> String {
+    if step >= 1.0 { format!("{v:.0}") } else { format!("{v:.1}") }
+}
+
+struct Layout {
+    x_max: f64,
+    y_max: f64,
+    x_step: f64,
+    y_step: f64,
+}
+
+impl Layout {
+    fn new(series: &[Series]) -> Layout {
+        let pts = series.iter().flat_map(|s| s.points.iter());
+        let (x_max, y_max) = pts.fold((1.0f64, 1.0f64), |(xm, ym), &(x, y)| (xm.max(x), ym.max(y)));
+        let y_step = nice_step(y_max * 1.1, 5);
+        let x_step = nice_step(x_max, 8);
+        Layout { x_max: (x_max / x_step).ceil() * x_step, y_max: (y_max * 1.1 / y_step).ceil() * y_step, x_step, y_step }
+    }
+    fn px(&self, x: f64) -> f64 { LEFT + x / self.x_max * (W as f64 - LEFT - RIGHT) }
+    fn py(&self, y: f64) -> f64 { H as f64 - BOTTOM - y / self.y_max * (H as f64 - TOP - BOTTOM) }
+    fn x_ticks(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..=(self.x_max / self.x_step).round() as usize).map(|i| i as f64 * self.x_step)
+    }
+    fn y_ticks(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..=(self.y_max / self.y_step).round() as usize).map(|i| i as f64 * self.y_step)
+    }
+}
+
+/// Renders the series as a standalone SVG document.
+pub fn line_chart_svg(title: &str, x_label: &str, series: &[Series]) -> String {
+    let l = Layout::new(series);
+    let mut s = String::new();
+    let _ = write!(s, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{W}" height="{H}" viewBox="0 0 {W} {H}" font-family="sans-serif" font-size="12">"#);
+    s.push_str(r#"<rect width="100%" height="100%" fill="white"/>"#);
+    let _ = write!(s, r#"<text x="{}" y="22" font-size="15" text-anchor="middle">{}</text>"#, W / 2, title.replace('&', "&amp;").replace('<', "&lt;"));
+    for y in l.y_ticks() {
+        let py = l.py(y);
+        let _ = write!(s, r##"<line x1="{LEFT}" y1="{py:.1}" x2="{:.1}" y2="{py:.1}" stroke="#e5e5e5"/>"##, W as f64 - RIGHT);
+        let _ = write!(s, r#"<text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"#, LEFT - 6.0, py + 4.0, fmt_tick(y, l.y_step));
+    }
+    for x in l.x_ticks() {
+        let px = l.px(x);
+        let _ = write!(s, r#"<text x="{px:.1}" y="{:.1}" text-anchor="middle">{}</text>"#, H as f64 - BOTTOM + 16.0, fmt_tick(x, l.x_step));
+    }
+    let _ = write!(
+        s,
+        r##"<line x1="{LEFT}" y1="{b:.1}" x2="{r:.1}" y2="{b:.1}" stroke="#333"/><line x1="{LEFT}" y1="{TOP}" x2="{LEFT}" y2="{b:.1}" stroke="#333"/>"##,
+        b = H as f64 - BOTTOM, r = W as f64 - RIGHT
+    );
+    let _ = write!(s, r#"<text x="{:.1}" y="{}" text-anchor="middle">{x_label}</text>"#, (LEFT + W as f64 - RIGHT) / 2.0, H - 12);
+    let _ = write!(s, r#"<text x="16" y="{:.1}" text-anchor="middle" transform="rotate(-90 16 {:.1})">MB/s</text>"#, H as f64 / 2.0, H as f64 / 2.0);
+    for (i, ser) in series.iter().enumerate() {
+        let (r, g, b) = ser.color;
+        let pts: Vec<String> = ser.points.iter().map(|&(x, y)| format!("{:.1},{:.1}", l.px(x), l.py(y))).collect();
+        let _ = write!(s, r##"<polyline fill="none" stroke="#{r:02x}{g:02x}{b:02x}" stroke-width="2" points="{}"/>"##, pts.join(" "));
+        let lx = W as f64 - RIGHT - 150.0 + i as f64 * 75.0;
+        let _ = write!(s, r##"<rect x="{lx:.1}" y="{}" width="12" height="12" fill="#{r:02x}{g:02x}{b:02x}"/><text x="{:.1}" y="{}">{}</text>"##, TOP - 4.0 - 12.0, lx + 16.0, TOP - 6.0, ser.name);
+    }
+    s.push_str("</svg>");
+    s
+}
+
+/// Writes the chart to `path`, choosing SVG or PNG from the extension.
+pub fn write_chart(path: &Path, title: &str, x_label: &str, series: &[Series]) -> io::Result<()> {
+    match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("svg") => std::fs::write(path, line_chart_svg(title, x_label, series)),
+        Some("png") => std::fs::write(path, line_chart_png(x_label, series)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "chart file must end in .svg or .png")),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PNG rendering
+
+struct Canvas {
+    px: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Canvas { Canvas { px: vec![0xff; W * H * 3] } }
+
+    fn set(&mut self, x: i64, y: i64, c: (u8, u8, u8)) {
+        if x < 0 || y < 0 || x >= W as i64 || y >= H as i64 { return; }
+        let i = (y as usize * W + x as usize) * 3;
+        self.px[i..i + 3].copy_from_slice(&[c.0, c.1, c.2]);
+    }
+
+    fn rect(&mut self, x: f64, y: f64, w: f64, h: f64, c: (u8, u8, u8)) {
+        for yy in y.round() as i64..(y + h).round() as i64 {
+            for xx in x.round() as i64..(x + w).round() as i64 { self.set(xx, yy, c); }
+        }
+    }
+
+    fn line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, thick: i64, c: (u8, u8, u8)) {
+        let (mut x, mut y) = (x0.round() as i64, y0.round() as i64);
+        let (x1, y1) = (x1.round() as i64, y1.round() as i64);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = (if x < x1 { 1 } else { -1 }, if y < y1 { 1 } else { -1 });
+        let mut err = dx + dy;
+        loop {
+            for ox in 0..thick { for oy in 0..thick { self.set(x + ox, y + oy, c); } }
+            if x == x1 && y == y1 { break; }
+            let e2 = 2 * err;
+            if e2 >= dy { err += dy; x += sx; }
+            if e2 <= dx { err += dx; y += sy; }
+        }
+    }
+
+    /// Draws `text` with the built-in 5x7 font; `(x, y)` is the top-left corner.
+    fn text(&mut self, x: f64, y: f64, text: &str, c: (u8, u8, u8)) {
+        for (i, ch) in text.chars().enumerate() {
+            let rows = glyph(ch);
+            for (ry, row) in rows.iter().enumerate() {
+                for cx in 0..5 {
+                    if row & (0b10000 >> cx) != 0 {
+                        self.set(x as i64 + i as i64 * 6 + cx, y as i64 + ry as i64, c);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn text_width(s: &str) -> f64 { s.chars().count() as f64 * 6.0 }
+
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        '.' => [0, 0, 0, 0, 0, 0b01100, 0b01100],
+        '/' => [0, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0],
+        '%' => [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+        '(' => [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+        ')' => [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+        '-' => [0, 0, 0, 0b11111, 0, 0, 0],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+        'a' => [0, 0, 0b01110, 0b00001, 0b01111, 0b10001, 0b01111],
+        'd' => [0b00001, 0b00001, 0b01101, 0b10011, 0b10001, 0b10001, 0b01111],
+        'e' => [0, 0, 0b01110, 0b10001, 0b11111, 0b10000, 0b01110],
+        'f' => [0b00110, 0b01001, 0b01000, 0b11100, 0b01000, 0b01000, 0b01000],
+        'i' => [0b00100, 0, 0b01100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'm' => [0, 0, 0b11010, 0b10101, 0b10101, 0b10001, 0b10001],
+        'r' => [0, 0, 0b10110, 0b11001, 0b10000, 0b10000, 0b10000],
+        's' => [0, 0, 0b01110, 0b10000, 0b01110, 0b00001, 0b11110],
+        't' => [0b01000, 0b01000, 0b11100, 0b01000, 0b01000, 0b01001, 0b00110],
+        _ => [0; 7],
+    }
+}
+
+/// Renders the series as a PNG image (no title; axes, ticks and legend only).
+pub fn line_chart_png(x_label: &str, series: &[Series]) -> Vec<u8> {
+    let l = Layout::new(series);
+    let mut c = Canvas::new();
+    let (grid, axis, ink) = ((0xe5, 0xe5, 0xe5), (0x33, 0x33, 0x33), (0x22, 0x22, 0x22));
+    for y in l.y_ticks() {
+        let py = l.py(y);
+        c.line(LEFT, py, W as f64 - RIGHT, py, 1, grid);
+        let label = fmt_tick(y, l.y_step);
+        c.text(LEFT - 6.0 - text_width(&label), py - 3.0, &label, ink);
+    }
+    for x in l.x_ticks() {
+        let label = fmt_tick(x, l.x_step);
+        c.text(l.px(x) - text_width(&label) / 2.0, H as f64 - BOTTOM + 8.0, &label, ink);
+    }
+    c.line(LEFT, H as f64 - BOTTOM, W as f64 - RIGHT, H as f64 - BOTTOM, 1, axis);
+    c.line(LEFT, TOP, LEFT, H as f64 - BOTTOM, 1, axis);
+    c.text((LEFT + W as f64 - RIGHT - text_width(x_label)) / 2.0, H as f64 - 20.0, x_label, ink);
+    c.text(8.0, TOP - 16.0, "MB/s", ink);
+    for (i, ser) in series.iter().enumerate() {
+        for w in ser.points.windows(2) {
+            c.line(l.px(w[0].0), l.py(w[0].1), l.px(w[1].0), l.py(w[1].1), 2, ser.color);
+        }
+        if let [only] = ser.points {
+            c.rect(l.px(only.0) - 2.0, l.py(only.1) - 2.0, 4.0, 4.0, ser.color);
+        }
+        let lx = W as f64 - RIGHT - 150.0 + i as f64 * 75.0;
+        c.rect(lx, TOP - 20.0, 10.0, 10.0, ser.color);
+        c.text(lx + 14.0, TOP - 19.0, ser.name, ink);
+    }
+    encode_png(W as u32, H as u32, &c.px)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 { crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 }; }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &x in chunk { a += x as u32; b += a; }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encodes 8-bit RGB pixels as a PNG using stored (uncompressed) deflate blocks.
+fn encode_png(w: u32, h: u32, rgb: &[u8]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity((w as usize * 3 + 1) * h as usize);
+    for row in rgb.chunks(w as usize * 3) {
+        raw.push(0); // filter: none
+        raw.extend_from_slice(row);
+    }
+    let mut z = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(65535).collect();
+    for (i, b) in blocks.iter().enumerate() {
+        z.push(u8::from(i == blocks.len() - 1));
+        z.extend_from_slice(&(b.len() as u16).to_le_bytes());
+        z.extend_from_slice(&(!(b.len() as u16)).to_le_bytes());
+        z.extend_from_slice(b);
+    }
+    z.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&w.to_be_bytes());
+    ihdr.extend_from_slice(&h.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &ihdr);
+    chunk(&mut out, b"IDAT", &z);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
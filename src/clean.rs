@@ -0,0 +1,41 @@
+//! `crabwise clean`: removes test files and directories that crashed or killed runs left behind.
+
+use crate::units;
+use crabwise_core::artifacts;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct CleanArgs {
+    /// Directory to sweep (the one benchmarks were pointed at, e.g. the drive's root)
+    dir: PathBuf,
+
+    /// List what would be removed without deleting anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub fn run(c: CleanArgs) -> io::Result<()> {
+    let stale = artifacts::leftovers(&c.dir);
+    if stale.is_empty() {
+        println!("No crabwise leftovers in {}", c.dir.display());
+        return Ok(());
+    }
+    let (mut removed, mut freed) = (0, 0);
+    for a in &stale {
+        if c.dry_run {
+            println!("would remove {} ({})", a.path.display(), units::size(a.bytes));
+            continue;
+        }
+        match artifacts::remove(a) {
+            Ok(()) => {
+                println!("removed {} ({})", a.path.display(), units::size(a.bytes));
+                removed += 1;
+                freed += a.bytes;
+            }
+            Err(e) => eprintln!("{}: {e}", a.path.display()),
+        }
+    }
+    if !c.dry_run { println!("Removed {removed} of {} item(s), freeing {}", stale.len(), units::size(freed)); }
+    Ok(())
+}
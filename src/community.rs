@@ -0,0 +1,155 @@
+//! Opt-in community results: `--submit` uploads an anonymized record, `crabwise lookup` fetches medians.
+//!
+//! The service is whatever `--results-service`, `results_service` in the config file, or
+//! `CRABWISE_RESULTS_SERVICE` points at. It takes `POST {service}/v1/results` with the
+//! record below and answers `GET {service}/v1/lookup?vendor_id=&product_id=&model=` with
+//! `{"model", "count", "write_mb_s": {"median", ...}, "read_mb_s": {"median", ...}}`.
+
+use crate::{config, http, units, Args};
+use crabwise_core::device::{device_info, identity, Identity};
+use crabwise_core::json::{self, Value};
+use crabwise_core::{warn, BenchResult};
+use std::io;
+use std::path::PathBuf;
+
+/// Environment variable naming the results service when neither flag nor config does.
+const SERVICE_ENV: &str = "CRABWISE_RESULTS_SERVICE";
+
+#[derive(clap::Args, Debug)]
+pub struct LookupArgs {
+    /// Look up the drive mounted here (its USB IDs and model are read from the OS)
+    target_dir: Option<PathBuf>,
+
+    /// Look up by model string instead
+    #[arg(long, conflicts_with="target_dir")]
+    model: Option<String>,
+
+    /// Look up by USB vendor:product ID in hex (e.g., 0781:5581)
+    #[arg(long, value_name="VID:PID", value_parser=parse_usb_id, conflicts_with="target_dir")]
+    usb_id: Option<(u16, u16)>,
+
+    /// Results service base URL [default: results_service from the config file, or $CRABWISE_RESULTS_SERVICE]
+    #[arg(long, value_name="URL")]
+    results_service: Option<String>,
+
+    /// Read results_service from this config file instead of the default location
+    #[arg(long, value_name="FILE")]
+    config: Option<PathBuf>,
+}
+
+fn parse_usb_id(s: &str) -> Result<(u16, u16), String> {
+    let bad = || format!("invalid USB ID '{s}' (expected VID:PID in hex, e.g. 0781:5581)");
+    let (v, p) = s.split_once(':').ok_or_else(bad)?;
+    Ok((u16::from_str_radix(v, 16).map_err(|_| bad())?, u16::from_str_radix(p, 16).map_err(|_| bad())?))
+}
+
+/// The flag (already layered over the config file), then the environment.
+pub fn service(flag: Option<&str>) -> io::Result<String> {
+    flag.map(str::to_string)
+        .or_else(|| std::env::var(SERVICE_ENV).ok().filter(|s| !s.is_empty()))
+        .map(|s| s.trim_end_matches('/').to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "no results service configured (use --results-service, results_service in config.toml, or ${SERVICE_ENV})"
+        )))
+}
+
+fn hex(id: Option<u16>) -> Value {
+    id.map(|i| Value::String(format!("{i:04x}"))).unwrap_or(Value::Null)
+}
+
+/// The submitted record: hardware model and test parameters only — no serial, hostname, paths or session names.
+pub fn anonymized(rec: &BenchResult, id: &Identity) -> Value {
+    let phase = |p: &Option<crabwise_core::PhaseStats>| p.as_ref().map(|p| p.mb_s().into()).unwrap_or(Value::Null);
+    let io = crabwise_core::runner::RANDOM_IO_BYTES as u64;
+    let dev = rec.device.as_ref();
+    Value::object(vec![
+        ("date", rec.timestamp.get(..10).unwrap_or_default().into()),
+        ("crabwise_version", env!("CARGO_PKG_VERSION").into()),
+        ("os", std::env::consts::OS.into()),
+        ("vendor_id", hex(id.vendor_id)),
+        ("product_id", hex(id.product_id)),
+        ("vendor", id.vendor.clone().into()),
+        ("model", id.model.clone().into()),
+        ("file_system", dev.map(|d| d.file_system.as_str().into()).unwrap_or(Value::Null)),
+        ("capacity_bytes", dev.map(|d| d.total_bytes.into()).unwrap_or(Value::Null)),
+        ("size_bytes", rec.size_bytes.into()),
+        ("block_bytes", rec.block_bytes.into()),
+        ("iterations", u64::from(rec.iterations).into()),
+        ("write_mb_s", rec.write.mb_s().into()),
+        ("read_mb_s", rec.read.mb_s().into()),
+        ("random_write_iops", rec.random_write.as_ref().map(|p| p.iops(io).into()).unwrap_or(Value::Null)),
+        ("random_read_iops", rec.random_read.as_ref().map(|p| p.iops(io).into()).unwrap_or(Value::Null)),
+        ("sustained_mb_s", phase(&rec.sustained)),
+    ])
+}
+
+fn lookup_url(service: &str, id: &Identity) -> String {
+    let mut q = Vec::new();
+    if let (Some(v), Some(p)) = (id.vendor_id, id.product_id) { q.push(format!("vendor_id={v:04x}&product_id={p:04x}")); }
+    if let Some(m) = &id.model { q.push(format!("model={}", http::encode(m))); }
+    format!("{service}/v1/lookup?{}", q.join("&"))
+}
+
+fn fetch_medians(service: &str, id: &Identity) -> io::Result<Value> {
+    let body = http::fetch("GET", &lookup_url(service, id), None)?;
+    json::parse(body.trim()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("results service: {e}")))
+}
+
+fn median(v: &Value, key: &str) -> Option<f64> {
+    v.get(key).and_then(|p| p.get("median")).and_then(Value::as_f64)
+}
+
+fn describe(id: &Identity) -> String {
+    let ids = match (id.vendor_id, id.product_id) { (Some(v), Some(p)) => format!(" [{v:04x}:{p:04x}]"), _ => String::new() };
+    let name = [id.vendor.as_deref(), id.model.as_deref()].into_iter().flatten().collect::<Vec<_>>().join(" ");
+    format!("{}{ids}", if name.is_empty() { "unknown model" } else { &name })
+}
+
+/// `--submit`: uploads the record, then compares it with the community medians. Failures only warn.
+pub fn submit(flag: Option<&str>, rec: &BenchResult) {
+    let outcome = (|| -> io::Result<()> {
+        let service = service(flag)?;
+        let id = rec.device.as_ref().map(identity).unwrap_or_default();
+        if id.model.is_none() && id.vendor_id.is_none() {
+            return Err(io::Error::other("drive model could not be identified; nothing useful to submit"));
+        }
+        http::fetch("POST", &format!("{service}/v1/results"), Some(&anonymized(rec, &id).to_string()))?;
+        eprintln!("Submitted anonymized result for {} to {service}", describe(&id));
+        let m = fetch_medians(&service, &id)?;
+        let count = m.get("count").and_then(Value::as_u64).unwrap_or(0);
+        for (name, mine, key) in [("write", rec.write.mb_s(), "write_mb_s"), ("read", rec.read.mb_s(), "read_mb_s")] {
+            if let Some(med) = median(&m, key).filter(|&x| x > 0.0) {
+                eprintln!("  {name}: {} vs community median {} ({:+.0}%, {count} results)", units::rate(mine), units::rate(med), (mine / med - 1.0) * 100.0);
+            }
+        }
+        Ok(())
+    })();
+    if let Err(e) = outcome { warn!("submit: {e}"); }
+}
+
+pub fn lookup(args: LookupArgs) -> io::Result<()> {
+    let mut a = Args { config: args.config.clone(), results_service: args.results_service.clone(), ..Args::default() };
+    config::apply(&mut a)?;
+    let service = service(a.results_service.as_deref())?;
+    let id = match (&args.target_dir, &args.model, args.usb_id) {
+        (Some(dir), _, _) => {
+            let info = device_info(dir).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}: no mounted volume found", dir.display())))?;
+            identity(&info)
+        }
+        (None, None, None) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "give a target directory, --model or --usb-id")),
+        (None, model, ids) => Identity { vendor_id: ids.map(|i| i.0), product_id: ids.map(|i| i.1), model: model.clone(), ..Identity::default() },
+    };
+    if id.model.is_none() && id.vendor_id.is_none() {
+        return Err(io::Error::other("drive model could not be identified; try --model or --usb-id"));
+    }
+    let m = fetch_medians(&service, &id)?;
+    let count = m.get("count").and_then(Value::as_u64).unwrap_or(0);
+    let model = m.get("model").and_then(Value::as_str).map(str::to_string).unwrap_or_else(|| describe(&id));
+    println!("{model}: {count} community result(s)");
+    for (name, key) in [("Write", "write_mb_s"), ("Read", "read_mb_s")] {
+        let Some(p) = m.get(key) else { continue };
+        let f = |k: &str| p.get(k).and_then(Value::as_f64).map(|x| format!("{:.2}", units::scale(x))).unwrap_or_else(|| "—".into());
+        crate::say!("  {:<6} median {:>9} {}  (p25 {}, p75 {})", format!("{name}:"), f("median"), units::label(), f("p25"), f("p75"));
+    }
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! `config.toml` defaults, overridden by command-line flags.
+//!
+//! Settings are flat `key = value` lines (see `toml` for the supported syntax) plus
+//! a `[thresholds]` table whose keys match the `--min-*`/`--max-*` flags.
+
+use crate::toml::{self, bad, Value};
+use crate::{i18n, notify, units, Args, Engine, Format, SyncMode};
+use clap::ValueEnum;
+use crabwise_core::debug;
+use crabwise_core::parse::{parse_abort_below, parse_device, parse_duration, parse_queue_depths, parse_rate, parse_read_pattern, parse_size, parse_tag};
+use std::io;
+use std::path::PathBuf;
+
+/// Location used when `--config` is not given: `$XDG_CONFIG_HOME/crabwise/config.toml`,
+/// `~/.config/crabwise/config.toml`, or `%APPDATA%\crabwise\config.toml` on Windows.
+pub fn default_path() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(not(target_os = "windows"))]
+    let base = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+    base.map(|b| b.join("crabwise").join("config.toml"))
+}
+
+/// Fills every setting not given on the command line from the config file.
+///
+/// An explicit `--config` must exist; the default location is optional.
+pub fn apply(args: &mut Args) -> io::Result<()> {
+    let (path, required) = match &args.config {
+        Some(p) => (p.clone(), true),
+        None => match default_path() {
+            Some(p) => (p, false),
+            None => return Ok(()),
+        },
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(t) => t,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+            debug!("no config file at {}", path.display());
+            return Ok(());
+        }
+        Err(e) => return Err(io::Error::new(e.kind(), format!("{}: {e}", path.display()))),
+    };
+    debug!("reading config file {}", path.display());
+    for e in toml::parse(&path, &text)? {
+        let key = if e.table.is_empty() { e.key } else { format!("{}.{}", e.table, e.key) };
+        set(args, &key, e.value).map_err(|msg| bad(&path, e.line, msg))?;
+    }
+    Ok(())
+}
+
+fn set(args: &mut Args, key: &str, value: Value) -> Result<(), String> {
+    fn pick<T: ValueEnum>(v: Value) -> Result<T, String> {
+        let s = v.text();
+        T::from_str(&s, true).map_err(|_| format!("invalid value '{s}'"))
+    }
+    match key {
+        "size" => { let s = value.text(); parse_size(&s)?; args.size.get_or_insert(s); }
+        "block" => { let s = value.text(); parse_size(&s)?; args.block.get_or_insert(s); }
+        "sync" => { if args.sync.is_none() { args.sync = Some(pick::<SyncMode>(value)?); } }
+        "engine" => { if args.engine.is_none() { args.engine = Some(pick::<Engine>(value)?); } }
+        "format" => { if args.format.is_none() { args.format = Some(pick::<Format>(value)?); } }
+        "read_pattern" => { if args.read_pattern.is_none() { args.read_pattern = Some(parse_read_pattern(&value.text())?); } }
+        "log" => { args.log.get_or_insert(PathBuf::from(value.text())); }
+        "record" => { args.record.get_or_insert(PathBuf::from(value.text())); }
+        "chart" => { args.chart.get_or_insert(PathBuf::from(value.text())); }
+        "notify" => { if args.notify.is_empty() { args.notify.push(notify::parse_notify(&value.text())?); } }
+        "units" => units::set_default(pick::<units::Units>(value)?),
+        "lang" => i18n::set_default(pick::<i18n::Lang>(value)?),
+        "results_service" => { args.results_service.get_or_insert(value.text()); }
+        "keep" => args.keep |= value.flag()?,
+        "allow_internal" => args.allow_internal |= value.flag()?,
+        "auto" => args.auto |= value.flag()?,
+        "device" => { if args.device.is_none() && args.target_dir.is_none() { args.device = Some(parse_device(&value.text())?); } }
+        "network" => args.network |= value.flag()?,
+        "random" => args.random |= value.flag()?,
+        "verify" => args.verify |= value.flag()?,
+        "shuffled" => args.shuffled |= value.flag()?,
+        "cached_read" => args.cached_read |= value.flag()?,
+        "preallocate" => args.preallocate |= value.flag()?,
+        "auto_block" => args.auto_block |= value.flag()?,
+        "trim" => args.trim |= value.flag()?,
+        "trim_compare" => args.trim_compare |= value.flag()?,
+        "corruption_check" => args.corruption_check |= value.flag()?,
+        "compression_check" => args.compression_check |= value.flag()?,
+        "offset_map" => args.offset_map |= value.flag()?,
+        "offset_chart" => { args.offset_chart.get_or_insert(PathBuf::from(value.text())); }
+        "samples" => { args.samples.get_or_insert(PathBuf::from(value.text())); }
+        "iterations" => {
+            if args.iterations.is_none() {
+                let s = value.text();
+                args.iterations = Some(s.parse().ok().filter(|&n: &u32| n > 0).ok_or(format!("invalid iteration count '{s}'"))?);
+            }
+        }
+        "files" => {
+            if args.files.is_none() {
+                let s = value.text();
+                args.files = Some(s.parse().ok().filter(|&n: &u32| n > 0).ok_or(format!("invalid file count '{s}'"))?);
+            }
+        }
+        "access_time" => args.access_time |= value.flag()?,
+        "sweep_qd" => { if args.sweep_qd.is_empty() { args.sweep_qd = parse_queue_depths(&value.text())?; } }
+        "fsync" => {
+            if args.fsync.is_none() {
+                let s = value.text();
+                args.fsync = Some(s.parse().ok().filter(|&n: &u32| n > 0).ok_or(format!("invalid fsync count '{s}'"))?);
+            }
+        }
+        "sustained" => { if args.sustained.is_none() { args.sustained = Some(parse_duration(&value.text())?); } }
+        "abort_below" => { if args.abort_below.is_none() { args.abort_below = Some(parse_abort_below(&value.text())?); } }
+        "thresholds.min_write" => { if args.min_write.is_none() { args.min_write = Some(parse_rate(&value.text())?); } }
+        "thresholds.min_read" => { if args.min_read.is_none() { args.min_read = Some(parse_rate(&value.text())?); } }
+        "thresholds.max_write_latency" => { if args.max_write_latency.is_none() { args.max_write_latency = Some(parse_duration(&value.text())?); } }
+        "thresholds.max_read_latency" => { if args.max_read_latency.is_none() { args.max_read_latency = Some(parse_duration(&value.text())?); } }
+        k if k.starts_with("tags.") => {
+            let tag = parse_tag(&format!("{}={}", &k["tags.".len()..], value.text()))?;
+            if !args.tag.iter().any(|(t, _)| *t == tag.0) { args.tag.push(tag); }
+        }
+        other => return Err(format!("unknown setting '{other}'")),
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! `crabwise copybench --from A --to B`: streams a test file from one device to another,
+//! checks the copy, and says which side limited it, for "how long will offloading this card
+//! to that disk take?".
+
+use crate::progress::LineProgress;
+use crate::{units, SyncMode};
+use crabwise_core::copy::{self, Bottleneck, CopyOptions};
+use crabwise_core::parse::parse_size;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct CopybenchArgs {
+    /// Directory on the device to copy from
+    #[arg(long, value_name="DIR")]
+    from: PathBuf,
+    /// Directory on the device to copy to
+    #[arg(long, value_name="DIR")]
+    to: PathBuf,
+
+    /// Bytes to copy
+    #[arg(short='s', long, default_value="1G", value_parser=parse_size)]
+    size: u64,
+
+    /// Block size of each read and write
+    #[arg(short='b', long, default_value="4M", value_parser=parse_size)]
+    block: u64,
+
+    /// I/O mode, as for the benchmark [default: direct]
+    #[arg(long, value_enum)]
+    sync: Option<SyncMode>,
+
+    /// Estimate how long copying this much would take [default: the data on the source volume]
+    #[arg(long, value_name="SIZE", value_parser=parse_size)]
+    estimate: Option<u64>,
+
+    /// Keep the test files on both devices
+    #[arg(long)]
+    keep: bool,
+}
+
+/// Runs the copy and prints the figures; returns false when the copy doesn't match the source.
+pub fn run(a: CopybenchArgs) -> io::Result<bool> {
+    if a.block == 0 || a.block > a.size {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "--block must be >0 and <= --size"));
+    }
+    let opts = CopyOptions {
+        from: a.from.clone(),
+        to: a.to.clone(),
+        size: a.size,
+        block: a.block,
+        direct: a.sync.unwrap_or(SyncMode::Direct) == SyncMode::Direct,
+    };
+    println!(
+        "Copying {} from {} to {} in {} blocks, after measuring each side alone",
+        units::size(a.size / a.block * a.block), a.from.display(), a.to.display(), units::size(a.block)
+    );
+    std::fs::create_dir_all(&a.from)?;
+    std::fs::create_dir_all(&a.to)?;
+    let outcome = copy::copy_bench(&opts, &mut LineProgress { enabled: true });
+    if !a.keep {
+        for p in copy::test_paths(&opts) { let _ = std::fs::remove_file(p); }
+    }
+    let r = outcome?;
+
+    println!();
+    println!("Source read:       {}", units::rate_w(r.source_read_mb_s, 9));
+    println!("Destination write: {}", units::rate_w(r.dest_write_mb_s, 9));
+    println!("Copy:              {} in {:.1} s, {:.0}% of the slower side", units::rate_w(r.copy_mb_s(), 9), r.copy_seconds, r.efficiency() * 100.0);
+    let verified = r.mismatches == 0;
+    if verified {
+        println!("Verify:            copy matches the source");
+    } else {
+        println!("Verify:            {} bytes differ from the source", r.mismatches);
+    }
+    let verdict = match r.bottleneck() {
+        Bottleneck::Source => format!("the source ({}); a faster destination won't speed this up", a.from.display()),
+        Bottleneck::Destination => format!("the destination ({}); a faster source won't speed this up", a.to.display()),
+        Bottleneck::Both => "both sides about equally".to_string(),
+        Bottleneck::Shared => "neither side alone: the copy runs slower than each device by itself; they likely share a hub, \
+            controller or bus, so connect them to separate ports".to_string(),
+    };
+    println!("Bottleneck:        {verdict}");
+
+    let used = crabwise_core::device::device_info(&a.from).map(|i| i.total_bytes.saturating_sub(i.available_bytes));
+    if let Some(bytes) = a.estimate.or(used).filter(|&b| b > 0) {
+        let what = if a.estimate.is_some() { String::new() } else { " (the data on the source volume)".to_string() };
+        println!("Copying {}{what} would take about {}", units::size(bytes), span(bytes as f64 / 1e6 / r.copy_mb_s()));
+    }
+    Ok(verified)
+}
+
+/// e.g. `45 s`, `12 min`, `3 h 05 min`.
+fn span(seconds: f64) -> String {
+    let s = seconds.round() as u64;
+    if s < 60 { format!("{s} s") } else if s < 3600 { format!("{} min", (s + 30) / 60) } else { format!("{} h {:02} min", s / 3600, s / 60 % 60) }
+}
@@ -0,0 +1,135 @@
+//! Guards in front of modes that overwrite a whole device or fill a filesystem: the user types
+//! what identifies the drive, dd-style, instead of answering y/N, and a device with mounted
+//! filesystems is refused unless `--force-unmount` unmounts them first.
+
+use crate::prompt_line;
+use crabwise_core::device::{identity, mounted_on, DeviceInfo};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// Whether `path` is a device node (`/dev/sdb`, `/dev/rdisk4`, `\\.\PhysicalDrive2`) rather
+/// than a file or directory; device nodes are resolved through symlinks such as
+/// `/dev/disk/by-id/...`.
+pub fn device_node(path: &Path) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        let ty = std::fs::metadata(path).ok()?.file_type();
+        (ty.is_block_device() || ty.is_char_device()).then(|| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+    }
+    #[cfg(windows)]
+    { path.to_string_lossy().starts_with(r"\\.\").then(|| path.to_path_buf()) }
+}
+
+/// What the user has to type for `device`: the drive's serial number when the OS reports
+/// one, else its name (`sdb`); with a description of which it is.
+fn expected(device: &Path) -> (String, &'static str) {
+    let info = DeviceInfo { name: device.display().to_string(), ..DeviceInfo::default() };
+    match identity(&info).serial {
+        Some(serial) => (serial, "the drive's serial number"),
+        None => (device.file_name().map_or_else(|| info.name.clone(), |n| n.to_string_lossy().to_string()), "the device name"),
+    }
+}
+
+/// A name for the drive in the warning, e.g. `SanDisk Ultra`.
+fn model(device: &Path) -> Option<String> {
+    let id = identity(&DeviceInfo { name: device.display().to_string(), ..DeviceInfo::default() });
+    let words: Vec<String> = [id.vendor, id.model].into_iter().flatten().collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Warns that `action` is about to happen and asks the user to type what identifies `device`
+/// (or `word` when the target is a file on a filesystem). `yes` is the answer given up front
+/// with `--yes NAME`, for scripts; it has to match just the same. Without `yes`, this fails
+/// when `prompt` is off (a run started over HTTP or by `watch`) or there is no terminal to
+/// answer on. The errors say what would have been accepted, as the prompt does.
+pub fn confirm_typed(action: &str, device: Option<&Path>, word: &str, yes: Option<&str>, prompt: bool) -> io::Result<()> {
+    let (want, what) = match device {
+        Some(d) => expected(d),
+        None => (word.to_string(), "the word"),
+    };
+    if let Some(given) = yes {
+        if given == want { return Ok(()); }
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("this would {action}; --yes {given} is not {what}, confirm with --yes {want}")));
+    }
+    if !prompt {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("this would {action}; confirm it up front with --yes {want}")));
+    }
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("this would {action}; confirm with --yes {want} without a terminal")));
+    }
+    eprintln!("\nWARNING: this will {action}.");
+    if let Some(m) = device.and_then(model) { eprintln!("Drive: {m}"); }
+    if prompt_line(&format!("Type {what}, {want}, to continue"))? == want { Ok(()) } else {
+        Err(io::Error::new(io::ErrorKind::Interrupted, "not confirmed: what was typed doesn't match"))
+    }
+}
+
+/// Refuses a device with mounted filesystems; with `force_unmount`, unmounts them first.
+pub fn ensure_unmounted(device: &Path, force_unmount: bool) -> io::Result<()> {
+    let mounted = mounted_on(device);
+    if mounted.is_empty() { return Ok(()); }
+    let list = mounted.iter().map(|m| format!("{} on {}", m.name, m.mount_point)).collect::<Vec<_>>().join(", ");
+    if !force_unmount {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "{} has mounted filesystems ({list}); unmount them first or pass --force-unmount", device.display()
+        )));
+    }
+    crate::say!("Unmounting {list}...");
+    unmount(device, &mounted)?;
+    let left = mounted_on(device);
+    if !left.is_empty() {
+        let list = left.iter().map(|m| m.mount_point.as_str()).collect::<Vec<_>>().join(", ");
+        return Err(io::Error::other(format!("{} is still mounted at {list}", device.display())));
+    }
+    Ok(())
+}
+
+fn unmount(device: &Path, mounted: &[DeviceInfo]) -> io::Result<()> {
+    let run = |cmd: &str, args: &[&str]| -> io::Result<()> {
+        let out = std::process::Command::new(cmd).args(args).output()?;
+        if out.status.success() { return Ok(()); }
+        Err(io::Error::other(format!("{cmd} {}: {}", args.join(" "), String::from_utf8_lossy(&out.stderr).trim())))
+    };
+    if cfg!(target_os = "macos") {
+        return run("diskutil", &["unmountDisk", &device.display().to_string()]);
+    }
+    if cfg!(target_os = "linux") {
+        for m in mounted { run("umount", &[&m.mount_point])?; }
+        return Ok(());
+    }
+    Err(io::Error::new(io::ErrorKind::Unsupported, "--force-unmount isn't supported on this system; take the volumes offline first"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_device_nodes_name_a_drive() {
+        assert_eq!(device_node(&std::env::temp_dir()), None);
+        assert_eq!(device_node(Path::new("/nonexistent/sdz")), None);
+        #[cfg(unix)]
+        assert_eq!(device_node(Path::new("/dev/null")), Some(PathBuf::from("/dev/null")));
+    }
+
+    #[test]
+    fn yes_has_to_match() {
+        assert!(confirm_typed("fill it", None, "FILL", Some("FILL"), false).is_ok());
+        let err = confirm_typed("fill it", None, "FILL", Some("fill"), true).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "this would fill it; --yes fill is not the word, confirm with --yes FILL");
+        let err = confirm_typed("fill it", None, "WIPE", None, false).unwrap_err();
+        assert_eq!(err.to_string(), "this would fill it; confirm it up front with --yes WIPE");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_device_is_confirmed_by_name_when_it_has_no_serial() {
+        let null = Path::new("/dev/null");
+        assert_eq!(expected(null), ("null".to_string(), "the device name"));
+        assert!(confirm_typed("wipe it", Some(null), "WIPE", Some("null"), false).is_ok());
+        assert!(confirm_typed("wipe it", Some(null), "WIPE", Some("WIPE"), false).is_err(), "the word doesn't do for a device");
+        assert!(ensure_unmounted(null, false).is_ok());
+    }
+}
@@ -0,0 +1,239 @@
+//! `crabwise diag`: collects what a bug report needs into one tar archive to attach to an
+//! issue — platform, device descriptors, recent results with their raw samples, the session
+//! log, the config file and the kernel's messages about USB and storage.
+//!
+//! Everything is anonymized before it is written: the hostname, home directory, mount point,
+//! serial number and WWN are replaced with placeholders, session names, tags and drive
+//! fingerprints are dropped, and notification and results-service URLs are redacted.
+
+use crate::{config, samples};
+use crabwise_core::device::{self, device_info, identity, DeviceInfo};
+use crabwise_core::host::host_info;
+use crabwise_core::usbpower::usb_power;
+use crabwise_core::{result, BenchResult};
+use chrono::Local;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Kernel log lines kept: the last this many that mention USB or storage.
+const KERNEL_LINES: usize = 400;
+
+/// Words that mark a kernel message as being about USB, a disk or a filesystem on one.
+const KERNEL_WORDS: &[&str] = &[
+    "usb", "uas", "xhci", "ehci", "scsi", "mmc", "sd ", "i/o error", "blk_update", "buffer i/o",
+    "ntfs", "exfat", "fat-fs", "ext4", "btrfs", "f2fs", "hfs", "apfs",
+];
+
+#[derive(clap::Args, Debug)]
+pub struct DiagArgs {
+    /// Directory on the drive the report is about (its descriptors and crabwise.log are included)
+    target_dir: Option<PathBuf>,
+
+    /// Results file written with --record; its latest runs and their samples are included
+    #[arg(long, value_name="FILE")]
+    record: Option<PathBuf>,
+
+    /// Session log to include instead of crabwise.log in the target directory
+    #[arg(long, value_name="FILE")]
+    log: Option<PathBuf>,
+
+    /// How many of the latest recorded runs to include
+    #[arg(long, value_name="N", default_value_t=5)]
+    runs: usize,
+
+    /// Archive to write [default: crabwise-diag-YYYYMMDD-HHMMSS.tar]
+    #[arg(short='o', long, value_name="FILE")]
+    out: Option<PathBuf>,
+}
+
+pub fn run(d: DiagArgs) -> io::Result<()> {
+    let info = d.target_dir.as_deref().and_then(device_info);
+    let scrub = Scrubber::new(info.as_ref(), d.target_dir.as_deref());
+    let mut files: Vec<(&str, String)> = vec![("platform.txt", platform(info.as_ref()))];
+    if let Some(i) = &info { files.push(("device.txt", describe(i))); }
+
+    let runs = match &d.record {
+        Some(p) => result::load(p)?,
+        None => Vec::new(),
+    };
+    let latest: Vec<BenchResult> = runs.into_iter().rev().take(d.runs).rev().map(anonymize).collect();
+    if !latest.is_empty() {
+        let lines: String = latest.iter().map(|r| format!("{}\n", r.to_json())).collect();
+        files.push(("runs.jsonl", lines));
+    }
+    if let Some(r) = latest.last() { files.push(("samples.csv", samples::render(r))); }
+
+    let log = d.log.clone().or_else(|| d.target_dir.as_ref().map(|t| t.join(crate::LOG_FILE_NAME)));
+    if let Some(text) = log.and_then(|p| std::fs::read_to_string(p).ok()) { files.push(("session.log", session_log(&text))); }
+    if let Some(text) = config::default_path().and_then(|p| std::fs::read_to_string(p).ok()) { files.push(("config.toml", config_file(&text))); }
+    files.push(("kernel.log", kernel_log(info.as_ref())));
+    files.push(("README.txt", readme(&files)));
+
+    let out = d.out.unwrap_or_else(|| PathBuf::from(Local::now().format("crabwise-diag-%Y%m%d-%H%M%S.tar").to_string()));
+    let mut tar = Vec::new();
+    for (name, text) in &files {
+        tar_entry(&mut tar, &format!("crabwise-diag/{name}"), scrub.apply(text).as_bytes());
+    }
+    tar.extend([0u8; 1024]);
+    std::fs::File::create(&out)?.write_all(&tar)?;
+    println!("Wrote {} ({} file(s)); attach it to the issue.", out.display(), files.len());
+    println!("Identifiers were replaced with placeholders, but look through it before posting: `tar -tvf {}`", out.display());
+    Ok(())
+}
+
+fn platform(info: Option<&DeviceInfo>) -> String {
+    let h = host_info(info);
+    let mut t = String::new();
+    let _ = writeln!(t, "crabwise {}", h.crabwise_version);
+    let _ = writeln!(t, "os: {}", h.os.as_deref().unwrap_or(std::env::consts::OS));
+    let _ = writeln!(t, "kernel: {}", h.kernel.as_deref().unwrap_or("unknown"));
+    let _ = writeln!(t, "arch: {}", h.arch);
+    if let Some(c) = &h.controller { let _ = writeln!(t, "controller: {c}"); }
+    if let Some(p) = h.power { let _ = writeln!(t, "power: {}", p.id()); }
+    t
+}
+
+fn describe(info: &DeviceInfo) -> String {
+    let id = identity(info);
+    let mut t = String::new();
+    let _ = writeln!(t, "name: {}", info.name);
+    let _ = writeln!(t, "file_system: {}", info.file_system);
+    let _ = writeln!(t, "capacity_bytes: {}", info.total_bytes);
+    let _ = writeln!(t, "available_bytes: {}", info.available_bytes);
+    let _ = writeln!(t, "removable: {}", info.removable);
+    if let Some(b) = device::bus(info) { let _ = writeln!(t, "bus: {b}"); }
+    if let (Some(v), Some(p)) = (id.vendor_id, id.product_id) { let _ = writeln!(t, "usb_id: {v:04x}:{p:04x}"); }
+    if let Some(v) = &id.vendor { let _ = writeln!(t, "vendor: {v}"); }
+    if let Some(m) = &id.model { let _ = writeln!(t, "model: {m}"); }
+    if let Some(p) = usb_power(info) { let _ = writeln!(t, "usb_power: {}", p.summary()); }
+    for p in device::partition_alignment(info) {
+        let _ = writeln!(t, "partition {}: starts at {} bytes{}", p.name, p.start_bytes, if p.aligned() { "" } else { " (misaligned)" });
+    }
+    for (k, v) in device::descriptors(info) { let _ = writeln!(t, "{k}: {v}"); }
+    t
+}
+
+/// The record without what identifies the user or the drive: session names, tags, paths, the
+/// hostname and the fingerprint (which contains the serial).
+fn anonymize(mut r: BenchResult) -> BenchResult {
+    r.session = None;
+    r.tags.clear();
+    r.target_dir = "<target>".into();
+    r.test_file = Path::new(&r.test_file).file_name().map(|n| format!("<target>/{}", n.to_string_lossy())).unwrap_or_default();
+    if let Some(d) = &mut r.device { d.mount_point = "<mount>".into(); }
+    if let Some(h) = &mut r.host { h.hostname = None; }
+    r.fingerprint = None;
+    r
+}
+
+/// Log lines are `session | read | write | IOPS | timestamp | drive=... tags`; only the
+/// figures and the timestamp are kept.
+fn session_log(text: &str) -> String {
+    text.lines().map(|l| {
+        let cols: Vec<&str> = l.split(" | ").collect();
+        match cols.get(1..5) {
+            Some(keep) => format!("{}\n", keep.join(" | ")),
+            None => format!("{l}\n"),
+        }
+    }).collect()
+}
+
+/// The config file with the values of keys that hold URLs (webhooks can carry tokens) redacted.
+fn config_file(text: &str) -> String {
+    text.lines().map(|l| match l.split_once('=') {
+        Some((k, _)) if matches!(k.trim(), "notify" | "results_service") => format!("{} = \"<redacted>\"\n", k.trim()),
+        _ => format!("{l}\n"),
+    }).collect()
+}
+
+/// The kernel's recent messages about USB and storage (`dmesg`, else the journal, on Linux;
+/// the unified log on macOS).
+fn kernel_log(info: Option<&DeviceInfo>) -> String {
+    let run = |cmd: &str, args: &[&str]| std::process::Command::new(cmd).args(args).output().ok()
+        .filter(|o| o.status.success() && !o.stdout.is_empty())
+        .map(|o| String::from_utf8_lossy(&o.stdout).to_string());
+    let text = if cfg!(target_os = "linux") {
+        run("dmesg", &["-T"]).or_else(|| run("journalctl", &["-k", "-b", "--no-pager"]))
+    } else if cfg!(target_os = "macos") {
+        run("log", &["show", "--last", "1h", "--style", "syslog", "--predicate", "process == \"kernel\""])
+    } else {
+        None
+    };
+    let Some(text) = text else { return "not collected: the kernel log is not readable here (try as root)\n".into() };
+    let disk = info.and_then(|i| Path::new(&i.name).file_name()).map(|n| n.to_string_lossy().trim_end_matches(char::is_numeric).to_string());
+    let relevant = |l: &str| {
+        let l = l.to_ascii_lowercase();
+        KERNEL_WORDS.iter().any(|w| l.contains(w)) || disk.as_deref().is_some_and(|d| !d.is_empty() && l.contains(d))
+    };
+    let lines: Vec<&str> = text.lines().filter(|l| relevant(l)).collect();
+    let mut out: String = lines[lines.len().saturating_sub(KERNEL_LINES)..].iter().map(|l| format!("{l}\n")).collect();
+    if out.is_empty() { out = "no USB or storage messages\n".into(); }
+    out
+}
+
+fn readme(files: &[(&str, String)]) -> String {
+    let mut t = String::from("crabwise diagnostic bundle, created ");
+    let _ = writeln!(t, "{}\n", Local::now().format("%Y-%m-%d %H:%M:%S"));
+    t.push_str("The hostname, home directory, mount point, serial number and WWN were replaced with\n");
+    t.push_str("<host>, <home>, <mount>, <serial> and <wwn>. Session names, tags and drive fingerprints\n");
+    t.push_str("were dropped, and URLs in the config file redacted.\n\nFiles:\n");
+    for (name, _) in files { let _ = writeln!(t, "  {name}"); }
+    t
+}
+
+/// Replaces identifying strings in the bundle's text with placeholders.
+struct Scrubber {
+    /// Longest first, so a mount point is replaced before the home directory it may sit in.
+    replace: Vec<(String, &'static str)>,
+}
+
+impl Scrubber {
+    fn new(info: Option<&DeviceInfo>, target: Option<&Path>) -> Scrubber {
+        let env = |k: &str| std::env::var(k).ok();
+        let id = info.map(identity).unwrap_or_default();
+        let mut replace: Vec<(String, &'static str)> = [
+            (sysinfo::System::host_name(), "<host>"),
+            (env("HOME").or_else(|| env("USERPROFILE")), "<home>"),
+            (info.map(|i| i.mount_point.clone()), "<mount>"),
+            (target.map(|t| t.display().to_string()), "<target>"),
+            (id.serial, "<serial>"),
+            (id.wwn, "<wwn>"),
+        ].into_iter().filter_map(|(s, p)| s.filter(|s| s.len() > 1 && s != "/").map(|s| (s, p))).collect();
+        replace.sort_by_key(|(s, _)| std::cmp::Reverse(s.len()));
+        Scrubber { replace }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let mut t = self.replace.iter().fold(text.to_string(), |t, (s, p)| t.replace(s.as_str(), p));
+        // Other devices' serials show up in the kernel log as `SerialNumber: XYZ`.
+        if t.contains("SerialNumber: ") {
+            t = t.lines().map(|l| match l.split_once("SerialNumber: ") {
+                Some((head, _)) => format!("{head}SerialNumber: <serial>\n"),
+                None => format!("{l}\n"),
+            }).collect();
+        }
+        t
+    }
+}
+
+/// Appends one regular file to a ustar archive: a 512-byte header, then the data padded to
+/// a multiple of 512 bytes.
+fn tar_entry(tar: &mut Vec<u8>, name: &str, data: &[u8]) {
+    let mut h = [0u8; 512];
+    let field = |h: &mut [u8; 512], at: usize, value: &[u8]| h[at..at + value.len()].copy_from_slice(value);
+    field(&mut h, 0, &name.as_bytes()[..name.len().min(100)]);
+    field(&mut h, 100, b"0000644\0");
+    field(&mut h, 108, b"0000000\0");
+    field(&mut h, 116, b"0000000\0");
+    field(&mut h, 124, format!("{:011o}\0", data.len()).as_bytes());
+    field(&mut h, 136, format!("{:011o}\0", Local::now().timestamp().max(0)).as_bytes());
+    field(&mut h, 148, b"        ");
+    h[156] = b'0';
+    field(&mut h, 257, b"ustar\x0000");
+    let sum: u32 = h.iter().map(|&b| u32::from(b)).sum();
+    field(&mut h, 148, format!("{sum:06o}\0 ").as_bytes());
+    tar.extend_from_slice(&h);
+    tar.extend_from_slice(data);
+    tar.resize(tar.len().next_multiple_of(512), 0);
+}
@@ -0,0 +1,87 @@
+_crabwise() {
+    local cur prev cmd i
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+    cmd=crabwise
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "$cmd,${COMP_WORDS[i]}" in
+            crabwise,report) cmd=crabwise__report ;;
+            crabwise,completions) cmd=crabwise__completions ;;
+            crabwise,help) cmd=crabwise__help ;;
+            crabwise__help,report) cmd=crabwise__help__report ;;
+            crabwise__help,completions) cmd=crabwise__help__completions ;;
+            crabwise__help,help) cmd=crabwise__help__help ;;
+        esac
+    done
+    case "$cmd" in
+        crabwise)
+            case "$prev" in
+                -s|--size) COMPREPLY=(); return ;;
+                --sync) COMPREPLY=($(compgen -W 'direct buffered' -- "$cur")); return ;;
+            esac
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W '-s --size --sync --keep -h --help' -- "$cur"))
+            else
+                COMPREPLY=($(compgen -W 'report completions help' -- "$cur"))
+                COMPREPLY+=($(compgen -f -- "$cur"))
+            fi
+            ;;
+        crabwise__report)
+            case "$prev" in
+                -o|--out) COMPREPLY=($(compgen -f -- "$cur")); return ;;
+            esac
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W '-o --out -h --help' -- "$cur"))
+            else
+                COMPREPLY=($(compgen -W '' -- "$cur"))
+                COMPREPLY+=($(compgen -f -- "$cur"))
+            fi
+            ;;
+        crabwise__completions)
+            case "$prev" in
+            esac
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W '-h --help' -- "$cur"))
+            else
+                COMPREPLY=($(compgen -W 'bash zsh' -- "$cur"))
+            fi
+            ;;
+        crabwise__help)
+            case "$prev" in
+            esac
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W '' -- "$cur"))
+            else
+                COMPREPLY=($(compgen -W 'report completions help' -- "$cur"))
+            fi
+            ;;
+        crabwise__help__report)
+            case "$prev" in
+            esac
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W '' -- "$cur"))
+            else
+                COMPREPLY=($(compgen -W '' -- "$cur"))
+            fi
+            ;;
+        crabwise__help__completions)
+            case "$prev" in
+            esac
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W '' -- "$cur"))
+            else
+                COMPREPLY=($(compgen -W '' -- "$cur"))
+            fi
+            ;;
+        crabwise__help__help)
+            case "$prev" in
+            esac
+            if [[ "$cur" == -* ]]; then
+                COMPREPLY=($(compgen -W '' -- "$cur"))
+            else
+                COMPREPLY=($(compgen -W '' -- "$cur"))
+            fi
+            ;;
+    esac
+}
+complete -o filenames -F _crabwise crabwise
@@ -0,0 +1,14 @@
+complete -c crabwise -n '__fish_use_subcommand' -f -a report -d 'Summarize recorded runs'
+complete -c crabwise -n '__fish_use_subcommand' -f -a completions -d 'Print a completion script'
+complete -c crabwise -n '__fish_use_subcommand' -f -a help -d 'Print this message or the help of the given subcommand(s)'
+complete -c crabwise -n '__fish_use_subcommand' -s s -l size -x -d 'Test file size'
+complete -c crabwise -n '__fish_use_subcommand' -l sync -x -a 'direct buffered' -d 'How writes reach the drive'
+complete -c crabwise -n '__fish_use_subcommand' -l keep -d 'Keep the test file'\''s data [for verify]'
+complete -c crabwise -n '__fish_use_subcommand' -s h -l help -d 'Print help'
+complete -c crabwise -n '__fish_seen_subcommand_from report' -s o -l out -r -F -d 'Write it to FILE'
+complete -c crabwise -n '__fish_seen_subcommand_from report' -s h -l help -d 'Print help'
+complete -c crabwise -n '__fish_seen_subcommand_from completions' -f -a 'bash zsh'
+complete -c crabwise -n '__fish_seen_subcommand_from completions' -s h -l help -d 'Print help'
+complete -c crabwise -n '__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from report completions help' -f -a report -d 'Summarize recorded runs'
+complete -c crabwise -n '__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from report completions help' -f -a completions -d 'Print a completion script'
+complete -c crabwise -n '__fish_seen_subcommand_from help; and not __fish_seen_subcommand_from report completions help' -f -a help -d 'Print this message or the help of the given subcommand(s)'
@@ -0,0 +1,54 @@
+Register-ArgumentCompleter -Native -CommandName crabwise -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $commands = @('crabwise', 'crabwise;report', 'crabwise;completions', 'crabwise;help', 'crabwise;help;report', 'crabwise;help;completions', 'crabwise;help;help')
+    $path = 'crabwise'
+    $prev = ''
+    foreach ($e in $commandAst.CommandElements | Select-Object -Skip 1) {
+        if ($e.Extent.StartOffset -ge $cursorPosition) { break }
+        $t = $e.ToString()
+        if ($commands -contains "$path;$t") { $path = "$path;$t" }
+        $prev = $t
+    }
+    $results = switch ("$path|$prev") {
+        'crabwise|--sync' { @('direct', 'buffered') | ForEach-Object { [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_) } }
+        default {
+            switch ($path) {
+                'crabwise' {
+                    [System.Management.Automation.CompletionResult]::new('report', 'report', 'ParameterValue', 'Summarize recorded runs')
+                    [System.Management.Automation.CompletionResult]::new('completions', 'completions', 'ParameterValue', 'Print a completion script')
+                    [System.Management.Automation.CompletionResult]::new('help', 'help', 'ParameterValue', 'Print this message or the help of the given subcommand(s)')
+                    [System.Management.Automation.CompletionResult]::new('-s', '-s', 'ParameterName', 'Test file size')
+                    [System.Management.Automation.CompletionResult]::new('--size', '--size', 'ParameterName', 'Test file size')
+                    [System.Management.Automation.CompletionResult]::new('--sync', '--sync', 'ParameterName', 'How writes reach the drive')
+                    [System.Management.Automation.CompletionResult]::new('--keep', '--keep', 'ParameterName', 'Keep the test file''s data [for verify]')
+                    [System.Management.Automation.CompletionResult]::new('-h', '-h', 'ParameterName', 'Print help')
+                    [System.Management.Automation.CompletionResult]::new('--help', '--help', 'ParameterName', 'Print help')
+                }
+                'crabwise;report' {
+                    [System.Management.Automation.CompletionResult]::new('-o', '-o', 'ParameterName', 'Write it to FILE')
+                    [System.Management.Automation.CompletionResult]::new('--out', '--out', 'ParameterName', 'Write it to FILE')
+                    [System.Management.Automation.CompletionResult]::new('-h', '-h', 'ParameterName', 'Print help')
+                    [System.Management.Automation.CompletionResult]::new('--help', '--help', 'ParameterName', 'Print help')
+                }
+                'crabwise;completions' {
+                    [System.Management.Automation.CompletionResult]::new('bash', 'bash', 'ParameterValue', 'bash')
+                    [System.Management.Automation.CompletionResult]::new('zsh', 'zsh', 'ParameterValue', 'zsh')
+                    [System.Management.Automation.CompletionResult]::new('-h', '-h', 'ParameterName', 'Print help')
+                    [System.Management.Automation.CompletionResult]::new('--help', '--help', 'ParameterName', 'Print help')
+                }
+                'crabwise;help' {
+                    [System.Management.Automation.CompletionResult]::new('report', 'report', 'ParameterValue', 'Summarize recorded runs')
+                    [System.Management.Automation.CompletionResult]::new('completions', 'completions', 'ParameterValue', 'Print a completion script')
+                    [System.Management.Automation.CompletionResult]::new('help', 'help', 'ParameterValue', 'Print this message or the help of the given subcommand(s)')
+                }
+                'crabwise;help;report' {
+                }
+                'crabwise;help;completions' {
+                }
+                'crabwise;help;help' {
+                }
+            }
+        }
+    }
+    $results | Where-Object { $_.CompletionText -like "$wordToComplete*" }
+}
@@ -0,0 +1,66 @@
+//! The previous run on the same drive, so the results box can show how far a device moved
+//! since then (wear, a slower port or cable) without comparing logs by hand.
+//!
+//! Drives are matched by fingerprint: the `--record` file is searched first, since it holds
+//! exact figures, then the session log's `drive=...` column.
+
+use crabwise_core::{result, BenchResult};
+use std::path::Path;
+
+/// Changes smaller than this (in percent) are shown as unchanged.
+const SAME_PCT: f64 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct Previous {
+    /// `YYYY-MM-DD` of the earlier run.
+    pub date: String,
+    pub write_mb_s: f64,
+    pub read_mb_s: f64,
+}
+
+/// The latest earlier run of `rec`'s drive in `record` or the session log at `log`.
+pub fn previous(rec: &BenchResult, record: Option<&Path>, log: &Path) -> Option<Previous> {
+    let fp = rec.fingerprint.as_deref()?;
+    record.and_then(|p| from_record(p, fp)).or_else(|| from_log(log, fp))
+}
+
+fn from_record(path: &Path, fingerprint: &str) -> Option<Previous> {
+    let r = result::load(path).ok()?.into_iter().rfind(|r| r.fingerprint.as_deref() == Some(fingerprint))?;
+    Some(Previous { date: date(&r.timestamp), write_mb_s: r.write.mb_s(), read_mb_s: r.read.mb_s() })
+}
+
+/// Log lines are `session | read | write | IOPS | timestamp | drive=... tags`, rates in
+/// whatever `--units` was in effect.
+fn from_log(path: &Path, fingerprint: &str) -> Option<Previous> {
+    let text = std::fs::read_to_string(path).ok()?;
+    text.lines().rev().find_map(|line| {
+        let cols: Vec<&str> = line.split(" | ").map(str::trim).collect();
+        let drive = cols.get(5)?.split_whitespace().find_map(|w| w.strip_prefix("drive="))?;
+        if drive != fingerprint { return None; }
+        Some(Previous { date: date(cols.get(4)?), write_mb_s: rate(cols.get(2)?)?, read_mb_s: rate(cols.get(1)?)? })
+    })
+}
+
+/// A logged rate back in decimal MB/s: `42.10 Mbps`, `41.27 MB/s` or `39.36 MiB/s`, with an
+/// optional `(...)` second unit after it.
+fn rate(s: &str) -> Option<f64> {
+    let mut words = s.split_whitespace();
+    let n: f64 = words.next()?.parse().ok()?;
+    match words.next()? {
+        "Mbps" => Some(n / 8.0),
+        "MB/s" => Some(n),
+        "MiB/s" => Some(n * 1024.0 * 1024.0 / 1e6),
+        _ => None,
+    }
+}
+
+fn date(timestamp: &str) -> String {
+    timestamp.split([' ', 'T']).next().unwrap_or(timestamp).to_string()
+}
+
+/// `▼ 31%`, `▲ 4%` or `= 0%` going from `before` to `now`; `None` without an earlier figure.
+pub fn change(now: f64, before: f64) -> Option<String> {
+    if before <= 0.0 { return None; }
+    let pct = (now - before) / before * 100.0;
+    Some(if pct.abs() < SAME_PCT { "= 0%".to_string() } else if pct < 0.0 { format!("▼ {:.0}%", -pct) } else { format!("▲ {pct:.0}%") })
+}
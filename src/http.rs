@@ -0,0 +1,178 @@
+//! Just enough HTTP/1.1 for the local API: one request per connection, answered with `Connection: close`.
+//!
+//! Outgoing requests go through `curl`, which every supported OS ships, so HTTPS
+//! needs no TLS stack in the binary.
+
+use crabwise_core::json::Value;
+use crabwise_core::{debug, info};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+/// Request bodies are small JSON objects; anything bigger is refused.
+const MAX_BODY: usize = 64 * 1024;
+
+pub struct Request {
+    pub method: String,
+    /// Path without the query string.
+    pub path: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+pub fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut r = BufReader::new(stream);
+    let mut line = String::new();
+    r.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else { return Err(invalid("malformed request line")) };
+    let (method, path) = (method.to_string(), target.split('?').next().unwrap_or("/").to_string());
+
+    let (mut content_type, mut len) = (None, 0usize);
+    loop {
+        line.clear();
+        if r.read_line(&mut line)? == 0 { return Err(invalid("connection closed in headers")); }
+        let h = line.trim_end();
+        if h.is_empty() { break; }
+        let Some((name, value)) = h.split_once(':') else { continue };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => len = value.trim().parse().map_err(|_| invalid("bad Content-Length"))?,
+            "content-type" => content_type = Some(value.trim().to_ascii_lowercase()),
+            _ => {}
+        }
+    }
+    if len > MAX_BODY { return Err(invalid("request body too large")); }
+    let mut body = vec![0u8; len];
+    r.read_exact(&mut body)?;
+    Ok(Request { method, path, content_type, body })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        415 => "Unsupported Media Type",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Writes the status line and headers; without a length the body runs until the connection closes.
+pub fn head(out: &mut impl Write, status: u16, content_type: &str, len: Option<usize>) -> io::Result<()> {
+    write!(out, "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nCache-Control: no-store\r\nConnection: close\r\n", reason(status))?;
+    if let Some(n) = len { write!(out, "Content-Length: {n}\r\n")?; }
+    out.write_all(b"\r\n")
+}
+
+pub fn respond(out: &mut impl Write, status: u16, content_type: &str, body: &[u8]) -> io::Result<()> {
+    head(out, status, content_type, Some(body.len()))?;
+    out.write_all(body)?;
+    out.flush()
+}
+
+pub fn json(out: &mut impl Write, status: u16, v: &Value) -> io::Result<()> {
+    respond(out, status, "application/json", format!("{v}\n").as_bytes())
+}
+
+pub fn error(out: &mut impl Write, status: u16, msg: impl Into<String>) -> io::Result<()> {
+    json(out, status, &Value::object(vec![("error", Value::String(msg.into()))]))
+}
+
+/// Accepts connections forever, handling each on its own thread.
+pub fn serve<F>(listener: TcpListener, handler: F) -> io::Result<()>
+where
+    F: Fn(Request, &mut TcpStream) -> io::Result<()> + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        let handler = Arc::clone(&handler);
+        std::thread::spawn(move || {
+            // Clients that hang up or send garbage only cost their own connection.
+            let _ = match read_request(&stream) {
+                Ok(req) => {
+                    info!("{} {} from {}", req.method, req.path, stream.peer_addr().map(|a| a.to_string()).unwrap_or_default());
+                    handler(req, &mut stream)
+                }
+                Err(e) => error(&mut stream, 400, e.to_string()),
+            };
+        });
+    }
+    Ok(())
+}
+
+/// Sends a request with an optional JSON body and returns the response body; HTTP errors are errors.
+pub fn fetch(method: &str, url: &str, json_body: Option<&str>) -> io::Result<String> {
+    debug!("{method} {url} via curl");
+    let mut cmd = Command::new("curl");
+    cmd.args(["-fsS", "-m", "30", "-X", method]);
+    if json_body.is_some() { cmd.args(["-H", "Content-Type: application/json", "--data-binary", "@-"]); }
+    let mut child = cmd.arg(url).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("HTTP requests need curl on PATH ({e})")))?;
+    if let (Some(body), Some(mut stdin)) = (json_body, child.stdin.take()) { stdin.write_all(body.as_bytes())?; }
+    let out = child.wait_with_output()?;
+    if !out.status.success() {
+        let msg = String::from_utf8_lossy(&out.stderr).trim().trim_start_matches("curl: ").to_string();
+        return Err(io::Error::other(format!("{method} {url} failed: {msg}")));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).to_string())
+}
+
+/// Percent-encodes a query-string component.
+pub fn encode(s: &str) -> String {
+    s.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b => format!("%{b:02X}"),
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Passes `raw` through a loopback connection to [`read_request`].
+    fn request(raw: &[u8]) -> io::Result<Request> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(raw).unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        read_request(&listener.accept().unwrap().0)
+    }
+
+    #[test]
+    fn reads_a_request() {
+        let r = request(b"POST /runs?x=1 HTTP/1.1\r\nHost: localhost\r\nContent-Type: Application/JSON\r\ncontent-length: 2\r\n\r\n{}").unwrap();
+        assert_eq!((r.method.as_str(), r.path.as_str(), r.content_type.as_deref(), &r.body[..]), ("POST", "/runs", Some("application/json"), &b"{}"[..]));
+        assert!(request(b"GET /runs HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap().body.is_empty());
+    }
+
+    #[test]
+    fn refuses_malformed_requests() {
+        let err = |raw: &[u8]| request(raw).err().map(|e| e.to_string());
+        assert_eq!(err(b"\r\n"), Some("malformed request line".to_string()));
+        assert_eq!(err(b"GET /runs HTTP/1.1\r\nHost: x\r\n"), Some("connection closed in headers".to_string()));
+        assert_eq!(err(b"POST /runs HTTP/1.1\r\nContent-Length: lots\r\n\r\n"), Some("bad Content-Length".to_string()));
+        assert_eq!(err(format!("POST /runs HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1).as_bytes()), Some("request body too large".to_string()));
+    }
+
+    #[test]
+    fn writes_a_json_error() {
+        let mut out = Vec::new();
+        error(&mut out, 409, "busy").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "HTTP/1.1 409 Conflict\r\nContent-Type: application/json\r\nCache-Control: no-store\r\nConnection: close\r\nContent-Length: 17\r\n\r\n{\"error\":\"busy\"}\n");
+    }
+
+    #[test]
+    fn encodes_query_components() {
+        assert_eq!(encode("/media/usb stick~1"), "%2Fmedia%2Fusb%20stick~1");
+        assert_eq!(encode("ü"), "%C3%BC");
+    }
+}
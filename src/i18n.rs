@@ -0,0 +1,119 @@
+//! `--lang`: translations of the interactive text (banner, prompts, results box).
+//!
+//! Messages are looked up by their English text in gettext-style catalogs under `locales/`,
+//! compiled into the binary; anything a catalog lacks stays English. Logs, JSON, JUnit,
+//! reports and the session log are never translated, so scripts can keep parsing them.
+
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// Deutsch
+    De,
+    /// Español
+    Es,
+}
+
+/// 0 means not chosen yet (detected from the environment); otherwise `Lang as u8 + 1`.
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+/// Selects the language for the rest of the process (`--lang`).
+pub fn set(l: Lang) {
+    LANG.store(l as u8 + 1, Ordering::Relaxed);
+}
+
+/// Selects the language unless `--lang` already did (the config file's `lang`).
+pub fn set_default(l: Lang) {
+    let _ = LANG.compare_exchange(0, l as u8 + 1, Ordering::Relaxed, Ordering::Relaxed);
+}
+
+pub fn get() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        0 => detect(),
+        2 => Lang::De,
+        3 => Lang::Es,
+        _ => Lang::En,
+    }
+}
+
+/// The language from `LC_ALL`, `LC_MESSAGES` or `LANG` (e.g. `de_DE.UTF-8`), where bundled.
+fn detect() -> Lang {
+    static DETECTED: OnceLock<Lang> = OnceLock::new();
+    *DETECTED.get_or_init(|| {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|v| std::env::var(v).ok())
+            .find(|v| !v.is_empty())
+            .unwrap_or_default();
+        let code = locale.split(['_', '.', '-', '@']).next().unwrap_or_default().to_ascii_lowercase();
+        Lang::from_str(&code, true).unwrap_or_default()
+    })
+}
+
+/// Reads a catalog: pairs of `msgid "..."` / `msgstr "..."` lines, `#` comments.
+fn parse_po(text: &str) -> HashMap<String, String> {
+    let unquote = |s: &str| s.trim().strip_prefix('"').and_then(|s| s.strip_suffix('"'))
+        .map(|s| s.replace("\\n", "\n").replace("\\\"", "\""));
+    let mut out = HashMap::new();
+    let mut id = None;
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            id = unquote(rest);
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            if let (Some(k), Some(v)) = (id.take(), unquote(rest)) {
+                if !v.is_empty() { out.insert(k, v); }
+            }
+        }
+    }
+    out
+}
+
+fn catalog(l: Lang) -> Option<&'static HashMap<String, String>> {
+    static DE: OnceLock<HashMap<String, String>> = OnceLock::new();
+    static ES: OnceLock<HashMap<String, String>> = OnceLock::new();
+    match l {
+        Lang::En => None,
+        Lang::De => Some(DE.get_or_init(|| parse_po(include_str!("../locales/de.po")))),
+        Lang::Es => Some(ES.get_or_init(|| parse_po(include_str!("../locales/es.po")))),
+    }
+}
+
+/// `msg` in the current language.
+pub fn t(msg: &'static str) -> &'static str {
+    catalog(get()).and_then(|c| c.get(msg)).map(String::as_str).unwrap_or(msg)
+}
+
+/// Replaces each `{}` in `template` with the next of `args`.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() { out.push_str(first); }
+    for (i, part) in parts.enumerate() {
+        if let Some(a) = args.get(i) { out.push_str(&a.to_string()); }
+        out.push_str(part);
+    }
+    out
+}
+
+/// Whether `answer` to a `[y/N]` prompt means yes; English `y`/`yes` always count.
+pub fn is_yes(answer: &str) -> bool {
+    let a = answer.trim().to_lowercase();
+    matches!(a.as_str(), "y" | "yes") || match get() {
+        Lang::En => false,
+        Lang::De => matches!(a.as_str(), "j" | "ja"),
+        Lang::Es => matches!(a.as_str(), "s" | "si" | "sí"),
+    }
+}
+
+/// Translated message, with `{}` placeholders filled from the arguments.
+#[macro_export]
+macro_rules! tr {
+    ($msg:literal) => { $crate::i18n::t($msg) };
+    ($msg:literal, $($arg:expr),+ $(,)?) => { $crate::i18n::fill($crate::i18n::t($msg), &[$(&$arg as &dyn std::fmt::Display),+]) };
+}
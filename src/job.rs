@@ -0,0 +1,315 @@
+//! `crabwise run JOB.toml`: executes the stages of a job file in order and prints a combined report.
+//!
+//! ```toml
+//! [job]
+//! name = "USB acceptance"
+//! target = "/media/usb"      # default for every stage; --target overrides
+//! size = "1G"                # any stage key here becomes a default
+//!
+//! [[stage]]
+//! name = "seq-write"
+//! mode = "write"             # write | read | readwrite
+//! block = "4M"
+//! min-write = "20MBps"
+//!
+//! [[stage]]
+//! name = "rand-read-qd4"
+//! pattern = "random"         # sequential | random
+//! mode = "read"
+//! block = "4K"
+//! queue-depth = 4
+//! duration = "30s"
+//!
+//! [[stage]]
+//! name = "paced-write"
+//! mode = "write"
+//! block = "1M"
+//! rate = "4MBps"             # pace the I/O, like a camera recording
+//! duration = "60s"
+//! ```
+
+use crate::progress::LineProgress;
+use crate::toml::{self, bad, Value};
+use crate::units;
+use crabwise_core::json::Value as Json;
+use crabwise_core::parse::{parse_duration, parse_rate, parse_size};
+use crabwise_core::{run_stage, PhaseStats, Stage, StageResult};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args, Debug)]
+pub struct RunArgs {
+    /// Job file describing the stages to run
+    job: PathBuf,
+
+    /// Target directory for stages that don't name one (overrides the job's `target`)
+    #[arg(long, value_name="DIR")]
+    target: Option<PathBuf>,
+
+    /// Also write the combined report to FILE (.json, .md/.markdown, or HTML otherwise)
+    #[arg(short='o', long, value_name="FILE")]
+    out: Option<PathBuf>,
+
+    /// Keep the test files when the job finishes
+    #[arg(long)]
+    keep: bool,
+}
+
+struct Job {
+    name: String,
+    stages: Vec<Stage>,
+}
+
+/// Runs the job; returns true when every stage met its thresholds.
+pub fn run(args: RunArgs) -> io::Result<bool> {
+    let job = load(&args.job, args.target.as_deref())?;
+    println!("Job: {} ({} stage(s))", job.name, job.stages.len());
+    let mut results = Vec::new();
+    let outcome = (|| -> io::Result<()> {
+        for (i, st) in job.stages.iter().enumerate() {
+            crate::say!(
+                "\n[{}/{}] {} — {} {}, {} blocks, QD {}{}",
+                i + 1, job.stages.len(), st.name, st.pattern.id(), st.mode.id(), fmt_size(st.block), st.queue_depth,
+                st.duration.map(|d| format!(", {d:.0}s")).unwrap_or_default()
+            );
+            let r = run_stage(st, &mut LineProgress { enabled: true })?;
+            for (name, p) in [("write", &r.write), ("read", &r.read)] {
+                if let Some(p) = p {
+                    println!("  {:<6} {}  {:>9.0} IOPS  p99 {:>8.2} ms", name, units::rate_w(p.mb_s(), 9), p.iops(r.block_bytes), p.latency_p99_ms);
+                }
+            }
+            for f in r.threshold_failures.iter().flatten() { println!("  FAIL: {f}"); }
+            results.push(r);
+        }
+        Ok(())
+    })();
+    if !args.keep {
+        let mut paths: Vec<PathBuf> = job.stages.iter().map(Stage::test_path).collect();
+        paths.dedup();
+        for p in paths { let _ = std::fs::remove_file(p); }
+    }
+    outcome?;
+
+    println!("\n{}", render_text(&job.name, &results));
+    if let Some(out) = &args.out {
+        let ext = out.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let doc = match ext.as_deref() {
+            Some("json") => format!("{}\n", Json::object(vec![
+                ("job", job.name.as_str().into()),
+                ("stages", Json::Array(results.iter().map(StageResult::to_json).collect())),
+            ])),
+            Some("md" | "markdown") => render_markdown(&job.name, &results),
+            _ => render_html(&job.name, &results),
+        };
+        std::fs::write(out, doc)?;
+        println!("Wrote job report to {}", out.display());
+    }
+    Ok(results.iter().all(|r| r.threshold_failures.as_ref().is_none_or(|f| f.is_empty())))
+}
+
+fn load(path: &Path, target: Option<&Path>) -> io::Result<Job> {
+    let text = std::fs::read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    let mut name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let mut defaults = Stage::new("", target.map(Path::to_path_buf).unwrap_or_default());
+    let mut target_set = target.is_some();
+    let mut stages: Vec<(usize, Stage, bool)> = Vec::new();
+    for e in toml::parse(path, &text)? {
+        match e.table.as_str() {
+            "job" if e.key == "name" => name = e.value.text(),
+            "job" if e.key == "target" => {
+                if target.is_none() { defaults.target_dir = PathBuf::from(e.value.text()); }
+                target_set = true;
+            }
+            "job" => set(&mut defaults, &e.key, e.value).map_err(|m| bad(path, e.line, m))?,
+            "stage" => {
+                if e.index == stages.len() {
+                    let mut st = defaults.clone();
+                    st.name = format!("stage-{}", e.index + 1);
+                    stages.push((e.line, st, target_set));
+                }
+                let (_, st, has_target) = &mut stages[e.index];
+                if e.key == "target" {
+                    st.target_dir = PathBuf::from(e.value.text());
+                    *has_target = true;
+                } else {
+                    set(st, &e.key, e.value).map_err(|m| bad(path, e.line, m))?;
+                }
+            }
+            "" => return Err(bad(path, e.line, format!("'{}' must be inside [job] or [[stage]]", e.key))),
+            other => return Err(bad(path, e.line, format!("unknown table [{other}]"))),
+        }
+    }
+    if stages.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{}: no [[stage]] entries", path.display())));
+    }
+    let mut out = Vec::new();
+    for (line, st, has_target) in stages {
+        if !has_target {
+            return Err(bad(path, line, format!("stage '{}' has no target (set one in [job], the stage, or pass --target)", st.name)));
+        }
+        st.validate().map_err(|e| bad(path, line, e.to_string()))?;
+        out.push(st);
+    }
+    Ok(Job { name, stages: out })
+}
+
+fn set(st: &mut Stage, key: &str, value: Value) -> Result<(), String> {
+    match key {
+        "name" => st.name = value.text(),
+        "pattern" => st.pattern = value.text().parse()?,
+        "mode" => st.mode = value.text().parse()?,
+        "block" => st.block = parse_size(&value.text())?,
+        "size" => st.size = parse_size(&value.text())?,
+        "queue_depth" => {
+            let s = value.text();
+            st.queue_depth = s.parse().map_err(|_| format!("invalid queue depth '{s}'"))?;
+        }
+        "duration" => st.duration = Some(parse_duration(&value.text())?),
+        "rate" => st.rate = Some(parse_rate(&value.text())?),
+        "sync" => st.direct = match value.text().as_str() {
+            "direct" => true,
+            "buffered" => false,
+            other => return Err(format!("invalid sync '{other}' (expected direct or buffered)")),
+        },
+        "min_write" => st.thresholds.min_write = Some(parse_rate(&value.text())?),
+        "min_read" => st.thresholds.min_read = Some(parse_rate(&value.text())?),
+        "max_write_latency" => st.thresholds.max_write_latency = Some(parse_duration(&value.text())?),
+        "max_read_latency" => st.thresholds.max_read_latency = Some(parse_duration(&value.text())?),
+        other => return Err(format!("unknown stage setting '{other}'")),
+    }
+    Ok(())
+}
+
+fn fmt_size(b: u64) -> String {
+    match b {
+        b if b >= 1 << 30 && b % (1 << 30) == 0 => format!("{}G", b >> 30),
+        b if b >= 1 << 20 && b % (1 << 20) == 0 => format!("{}M", b >> 20),
+        b if b >= 1 << 10 && b % (1 << 10) == 0 => format!("{}K", b >> 10),
+        b => format!("{b}B"),
+    }
+}
+
+fn verdict(r: &StageResult) -> &'static str {
+    match &r.threshold_failures {
+        None => "—",
+        Some(f) if f.is_empty() => "PASS",
+        Some(_) => "FAIL",
+    }
+}
+
+/// One row per measured direction: (stage, pattern, mode, block, QD, stats).
+fn rows(results: &[StageResult]) -> Vec<(&StageResult, &'static str, &PhaseStats)> {
+    results.iter().flat_map(|r| {
+        [("write", &r.write), ("read", &r.read)].into_iter().filter_map(move |(d, p)| p.as_ref().map(|p| (r, d, p)))
+    }).collect()
+}
+
+fn render_text(name: &str, results: &[StageResult]) -> String {
+    let mut t = String::new();
+    let _ = writeln!(t, "=== {name} ===");
+    let _ = writeln!(t, "{:<20} {:<10} {:<5} {:>6} {:>3} {:>10} {:>10} {:>10}  Result", "Stage", "Pattern", "Dir", "Block", "QD", units::label(), "IOPS", "p99 ms");
+    for (r, dir, p) in rows(results) {
+        let _ = writeln!(
+            t, "{:<20} {:<10} {:<5} {:>6} {:>3} {:>10.2} {:>10.0} {:>10.2}  {}",
+            r.name, r.pattern.id(), dir, fmt_size(r.block_bytes), r.queue_depth, units::scale(p.mb_s()), p.iops(r.block_bytes), p.latency_p99_ms, verdict(r)
+        );
+    }
+    t
+}
+
+fn render_markdown(name: &str, results: &[StageResult]) -> String {
+    let mut m = String::new();
+    let _ = writeln!(m, "# {name}\n");
+    let _ = writeln!(m, "| Stage | Pattern | Direction | Block | QD | {} | IOPS | Latency avg (ms) | Latency p99 (ms) | Result |\n|---|---|---|---:|---:|---:|---:|---:|---:|---|", units::label());
+    for (r, dir, p) in rows(results) {
+        let _ = writeln!(
+            m, "| {} | {} | {dir} | {} | {} | {:.2} | {:.0} | {:.2} | {:.2} | {} |",
+            r.name.replace('|', "\\|"), r.pattern.id(), fmt_size(r.block_bytes), r.queue_depth,
+            units::scale(p.mb_s()), p.iops(r.block_bytes), p.latency_avg_ms, p.latency_p99_ms, verdict(r)
+        );
+    }
+    for r in results {
+        for f in r.threshold_failures.iter().flatten() { let _ = writeln!(m, "\n- **FAIL** ({}): {f}", r.name); }
+    }
+    m
+}
+
+fn render_html(name: &str, results: &[StageResult]) -> String {
+    let esc = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let mut h = String::new();
+    let _ = write!(
+        h,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{t}</title><style>\
+body{{font-family:sans-serif;margin:2em;color:#222}}table{{border-collapse:collapse}}\
+th,td{{border:1px solid #ccc;padding:4px 10px}}th{{background:#f2f2f2}}td.num{{text-align:right}}\
+</style></head><body>\n<h1>{t}</h1>\n<table><tr><th>Stage</th><th>Pattern</th><th>Direction</th><th>Block</th><th>QD</th><th>{u}</th><th>IOPS</th><th>Latency avg (ms)</th><th>Latency p99 (ms)</th><th>Result</th></tr>\n",
+        t = esc(name), u = units::label()
+    );
+    for (r, dir, p) in rows(results) {
+        let _ = writeln!(
+            h,
+            "<tr><td>{}</td><td>{}</td><td>{dir}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.2}</td><td class=\"num\">{:.0}</td><td class=\"num\">{:.2}</td><td class=\"num\">{:.2}</td><td>{}</td></tr>",
+            esc(&r.name), r.pattern.id(), fmt_size(r.block_bytes), r.queue_depth,
+            units::scale(p.mb_s()), p.iops(r.block_bytes), p.latency_avg_ms, p.latency_p99_ms, verdict(r)
+        );
+    }
+    h.push_str("</table>\n");
+    for r in results {
+        for f in r.threshold_failures.iter().flatten() { let _ = writeln!(h, "<p><b>FAIL</b> ({}): {}</p>", esc(&r.name), esc(f)); }
+    }
+    h.push_str("</body></html>\n");
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crabwise_core::stage::{Mode, Pattern};
+
+    /// Loads `text` as a job file named `name.toml`.
+    fn load_text(name: &str, text: &str, target: Option<&Path>) -> io::Result<Job> {
+        let path = std::env::temp_dir().join(format!("crabwise-job-{}-{name}.toml", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let job = load(&path, target);
+        std::fs::remove_file(&path).unwrap();
+        job
+    }
+
+    #[test]
+    fn stages_inherit_job_defaults() {
+        let job = load_text("defaults", "[job]\nname = \"USB acceptance\"\ntarget = \"/media/usb\"\nsize = \"64M\"\nsync = \"buffered\"\n\n\
+            [[stage]]\nmode = \"write\"\nmin-write = \"20MBps\"\n\n\
+            [[stage]]\nname = \"rr\"\npattern = \"random\"\nblock = \"4K\"\nqueue-depth = 4\nduration = \"30s\"\ntarget = \"/media/other\"\n", None).unwrap();
+        assert_eq!(job.name, "USB acceptance");
+        let [first, second] = &job.stages[..] else { panic!("expected two stages") };
+        assert_eq!((first.name.as_str(), first.mode, first.size, first.direct), ("stage-1", Mode::Write, 64 << 20, false));
+        assert_eq!(first.target_dir, Path::new("/media/usb"));
+        assert_eq!(first.thresholds.min_write, Some(20e6));
+        assert_eq!((second.name.as_str(), second.pattern, second.block, second.queue_depth), ("rr", Pattern::Random, 4096, 4));
+        assert_eq!((second.size, second.duration), (64 << 20, Some(30.0)));
+        assert_eq!(second.target_dir, Path::new("/media/other"));
+    }
+
+    #[test]
+    fn target_flag_and_file_name() {
+        let job = load_text("nightly", "[job]\ntarget = \"/media/usb\"\n[[stage]]\nmode = \"read\"\n", Some(Path::new("/mnt/x"))).unwrap();
+        assert!(job.name.ends_with("-nightly"), "{}", job.name);
+        assert_eq!(job.stages[0].target_dir, Path::new("/mnt/x"));
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let err = |text: &str| load_text("bad", text, None).err().map(|e| e.to_string()).unwrap_or_default();
+        assert!(err("[[stage]]\nmode = \"write\"\n").ends_with(":2: stage 'stage-1' has no target (set one in [job], the stage, or pass --target)"));
+        assert!(err("[job]\ntarget = \"/m\"\n[[stage]]\nspeed = 3\n").ends_with(":4: unknown stage setting 'speed'"));
+        assert!(err("size = \"1G\"\n").ends_with(":1: 'size' must be inside [job] or [[stage]]"));
+        assert!(err("[job]\ntarget = \"/m\"\n").ends_with(": no [[stage]] entries"));
+        assert!(err("[job]\ntarget = \"/m\"\n[[stage]]\nsync = \"fast\"\n").contains("invalid sync 'fast'"));
+    }
+
+    #[test]
+    fn block_sizes_are_shown_in_the_largest_whole_unit() {
+        assert_eq!([fmt_size(1 << 30), fmt_size(4 << 20), fmt_size(4096), fmt_size(1536), fmt_size(512)], ["1G", "4M", "4K", "1536B", "512B"]);
+    }
+}
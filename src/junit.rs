@@ -0,0 +1,137 @@
+//! JUnit XML for CI (`--format junit`): each measured phase and each configured check is a
+//! test case, so Jenkins and GitLab show a run like a test suite.
+
+use crabwise_core::{BenchResult, PhaseStats, Thresholds};
+use std::fmt::Write;
+
+fn xml_escape(s: &str) -> String {
+    s.chars().filter(|c| !c.is_control() || *c == '\n' || *c == '\t').collect::<String>()
+        .replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn phases(r: &BenchResult) -> Vec<(&'static str, &PhaseStats)> {
+    let mut v = vec![("write", &r.write), ("read", &r.read)];
+    let optional = [("random_write", &r.random_write), ("random_read", &r.random_read), ("parallel_write", &r.parallel_write),
+        ("parallel_read", &r.parallel_read), ("shuffled_read", &r.shuffled_read), ("cached_read", &r.cached_read), ("sustained", &r.sustained),
+        ("small_write", &r.small_write), ("small_read", &r.small_read), ("round_trip", &r.round_trip),
+        ("dirty_write", &r.dirty_write), ("trimmed_write", &r.trimmed_write)];
+    v.extend(optional.into_iter().filter_map(|(n, p)| p.as_ref().map(|p| (n, p))));
+    v
+}
+
+struct Case {
+    class: &'static str,
+    name: String,
+    seconds: f64,
+    out: Option<String>,
+    /// Failure messages; empty when the case passed.
+    failures: Vec<String>,
+}
+
+/// The run as one `<testsuite>`; `thresholds` are the limits it was checked against.
+pub fn render(r: &BenchResult, thresholds: &Thresholds) -> String {
+    let mut cases: Vec<Case> = phases(r).into_iter().map(|(name, p)| Case {
+        class: "crabwise.phase",
+        name: name.to_string(),
+        seconds: p.seconds,
+        out: Some(format!("{:.2} MB/s, {} bytes in {} ops, latency avg {:.3} ms / p99 {:.3} ms", p.mb_s(), p.bytes, p.ops, p.latency_avg_ms, p.latency_p99_ms)),
+        failures: Vec::new(),
+    }).collect();
+    for (qd, p) in &r.qd_sweep {
+        cases.push(Case {
+            class: "crabwise.phase",
+            name: format!("random_read_qd{qd}"),
+            seconds: p.seconds,
+            out: Some(format!("{:.0} IOPS at queue depth {qd}, latency avg {:.3} ms / p99 {:.3} ms", p.ops_per_s(), p.latency_avg_ms, p.latency_p99_ms)),
+            failures: Vec::new(),
+        });
+    }
+    if let Some(l) = &r.access {
+        cases.push(Case {
+            class: "crabwise.phase",
+            name: "access".into(),
+            seconds: l.seconds,
+            out: Some(format!("{} x {} bytes random read, latency avg {:.3} ms / p50 {:.3} ms / p99 {:.3} ms / max {:.3} ms",
+                l.ops, l.io_bytes, l.avg_ms, l.p50_ms, l.p99_ms, l.max_ms)),
+            failures: Vec::new(),
+        });
+    }
+    if let Some(l) = &r.fsync {
+        cases.push(Case {
+            class: "crabwise.phase",
+            name: "fsync".into(),
+            seconds: l.seconds,
+            out: Some(format!("{} x {} bytes write+fsync, {:.0}/s, latency p50 {:.3} ms / p99 {:.3} ms / p99.9 {:.3} ms / max {:.3} ms",
+                l.ops, l.io_bytes, l.ops_per_s(), l.p50_ms, l.p99_ms, l.p999_ms, l.max_ms)),
+            failures: Vec::new(),
+        });
+    }
+    // One case per limit, each checked on its own so a miss lands on the right case.
+    let limits = [
+        ("min_write", Thresholds { min_write: thresholds.min_write, ..Thresholds::default() }),
+        ("min_read", Thresholds { min_read: thresholds.min_read, ..Thresholds::default() }),
+        ("max_write_latency", Thresholds { max_write_latency: thresholds.max_write_latency, ..Thresholds::default() }),
+        ("max_read_latency", Thresholds { max_read_latency: thresholds.max_read_latency, ..Thresholds::default() }),
+    ];
+    for (name, t) in limits.into_iter().filter(|(_, t)| !t.is_empty()) {
+        cases.push(Case { class: "crabwise.threshold", name: name.to_string(), seconds: 0.0, out: None, failures: t.check(r) });
+    }
+    if let Some(bad) = r.verify_mismatches {
+        let failures = if bad > 0 { vec![format!("{bad} byte(s) read back differ from what was written")] } else { Vec::new() };
+        cases.push(Case { class: "crabwise.check", name: "verify".into(), seconds: 0.0, out: None, failures });
+    }
+    if let Some(c) = &r.compression {
+        let out = format!("zeros {:.2} MB/s vs random {:.2} MB/s ({:.2}x)", c.zero_mb_s, c.random_mb_s, c.ratio());
+        cases.push(Case { class: "crabwise.check", name: "compression".into(), seconds: 0.0, out: Some(out), failures: Vec::new() });
+    }
+    if let Some(changes) = &r.metadata_changes {
+        cases.push(Case { class: "crabwise.check", name: "metadata".into(), seconds: 0.0, out: None, failures: changes.clone() });
+    }
+
+    let failed = cases.iter().filter(|c| !c.failures.is_empty()).count();
+    let total: f64 = cases.iter().map(|c| c.seconds).sum();
+    let timestamp = r.timestamp.replacen(' ', "T", 1);
+    let mut x = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(x, "<testsuites name=\"crabwise\" tests=\"{}\" failures=\"{failed}\" time=\"{total:.3}\">", cases.len());
+    let _ = writeln!(x, "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failed}\" errors=\"0\" skipped=\"0\" time=\"{total:.3}\" timestamp=\"{}\">",
+        xml_escape(&format!("crabwise {}", r.target_dir)), cases.len(), xml_escape(&timestamp));
+    x.push_str("    <properties>\n");
+    let mut props = vec![("target_dir", r.target_dir.clone()), ("size_bytes", r.size_bytes.to_string()), ("block_bytes", r.block_bytes.to_string())];
+    if let Some(d) = &r.device { props.push(("device", d.name.clone())); props.push(("file_system", d.file_system.clone())); }
+    if let Some(f) = &r.fingerprint { props.push(("fingerprint", f.clone())); }
+    if !r.transforms.is_empty() { props.push(("transforms", r.transforms.iter().map(|t| t.id()).collect::<Vec<_>>().join(","))); }
+    if r.block_tune.is_some() { props.push(("auto_block", "true".into())); }
+    if let Some(ok) = r.preallocated { props.push(("preallocated", ok.to_string())); }
+    if let Some(f) = &r.fragmentation { props.push(("fragments", f.fragments.to_string())); }
+    if let Some(h) = &r.host {
+        props.extend([("hostname", &h.hostname), ("os", &h.os), ("kernel", &h.kernel), ("controller", &h.controller)]
+            .into_iter().filter_map(|(k, v)| v.clone().map(|v| (k, v))));
+        if let Some(p) = h.power { props.push(("power", p.id().to_string())); }
+        props.push(("crabwise_version", h.crabwise_version.clone()));
+    }
+    if let Some(p) = &r.usb_power {
+        props.push(("usb_power_declared_ma", p.declared_ma.to_string()));
+        if let Some(a) = p.available_ma { props.push(("usb_power_available_ma", a.to_string())); }
+    }
+    props.extend(r.tags.iter().map(|(k, v)| ("tag", format!("{k}={v}"))));
+    for (k, v) in props {
+        let _ = writeln!(x, "      <property name=\"{k}\" value=\"{}\"/>", xml_escape(&v));
+    }
+    x.push_str("    </properties>\n");
+    for c in &cases {
+        let _ = write!(x, "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"", c.class, xml_escape(&c.name), c.seconds);
+        if c.out.is_none() && c.failures.is_empty() {
+            x.push_str("/>\n");
+            continue;
+        }
+        x.push_str(">\n");
+        if !c.failures.is_empty() {
+            let _ = writeln!(x, "      <failure message=\"{}\" type=\"{}\">{}</failure>",
+                xml_escape(&c.failures[0]), c.name, xml_escape(&c.failures.join("\n")));
+        }
+        if let Some(out) = &c.out { let _ = writeln!(x, "      <system-out>{}</system-out>", xml_escape(out)); }
+        x.push_str("    </testcase>\n");
+    }
+    x.push_str("  </testsuite>\n</testsuites>\n");
+    x
+}
@@ -0,0 +1,114 @@
+//! Known-device database: rated speeds for common drives, used to put a result in context.
+//!
+//! A built-in list (`devices.toml`) is compiled in; a `devices.toml` next to the user's
+//! `config.toml` is checked first, so entries there add to or override it.
+
+use crate::toml::{self, bad, Value};
+use crabwise_core::device::{identity, Identity};
+use crabwise_core::parse::parse_rate;
+use crabwise_core::{warn, BenchResult};
+use std::io;
+use std::path::{Path, PathBuf};
+
+const BUILTIN: &str = include_str!("devices.toml");
+
+/// Below this fraction of the rated speed a drive is flagged as underperforming.
+const SLOW_FRACTION: f64 = 0.6;
+/// Above this fraction the measurement is probably hitting a cache, not the flash.
+const FAST_FRACTION: f64 = 1.15;
+
+#[derive(Debug, Clone, Default)]
+struct Known {
+    name: String,
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    /// Lower-cased substring of the model string.
+    model: String,
+    /// Rated sequential throughput, bytes per second.
+    read: Option<f64>,
+    write: Option<f64>,
+    /// Rated 4 KiB random IOPS.
+    random_read_iops: Option<f64>,
+    random_write_iops: Option<f64>,
+}
+
+impl Known {
+    fn matches(&self, id: &Identity) -> bool {
+        if self.vendor_id.is_some() && self.vendor_id != id.vendor_id { return false; }
+        if self.product_id.is_some() { return self.product_id == id.product_id; }
+        !self.model.is_empty() && id.model.as_deref().is_some_and(|m| m.to_ascii_lowercase().contains(&self.model))
+    }
+}
+
+fn load(path: &Path, text: &str) -> io::Result<Vec<Known>> {
+    let mut out: Vec<Known> = Vec::new();
+    for e in toml::parse(path, text)? {
+        if e.table != "device" { return Err(bad(path, e.line, format!("'{}' must be inside [[device]]", e.key))); }
+        if e.index == out.len() { out.push(Known::default()); }
+        let k = &mut out[e.index];
+        let hex = |v: Value| { let s = v.text(); u16::from_str_radix(&s, 16).map_err(|_| format!("invalid USB ID '{s}' (expected hex, e.g. 0781)")) };
+        let iops = |v: Value| { let s = v.text(); s.parse::<f64>().map_err(|_| format!("invalid IOPS '{s}'")) };
+        let r = match e.key.as_str() {
+            "name" => { k.name = e.value.text(); Ok(()) }
+            "model" => { k.model = e.value.text().to_ascii_lowercase(); Ok(()) }
+            "vendor_id" => hex(e.value).map(|v| k.vendor_id = Some(v)),
+            "product_id" => hex(e.value).map(|v| k.product_id = Some(v)),
+            "read" => parse_rate(&e.value.text()).map(|v| k.read = Some(v)),
+            "write" => parse_rate(&e.value.text()).map(|v| k.write = Some(v)),
+            "random_read_iops" => iops(e.value).map(|v| k.random_read_iops = Some(v)),
+            "random_write_iops" => iops(e.value).map(|v| k.random_write_iops = Some(v)),
+            other => Err(format!("unknown device setting '{other}'")),
+        };
+        r.map_err(|m| bad(path, e.line, m))?;
+    }
+    Ok(out)
+}
+
+fn user_path() -> Option<PathBuf> {
+    crate::config::default_path().map(|p| p.with_file_name("devices.toml"))
+}
+
+/// User entries first, then the built-in list.
+fn database() -> io::Result<Vec<Known>> {
+    let mut db = Vec::new();
+    if let Some(path) = user_path() {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => db = load(&path, &text)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(io::Error::new(e.kind(), format!("{}: {e}", path.display()))),
+        }
+    }
+    db.extend(load(Path::new("devices.toml (built-in)"), BUILTIN)?);
+    Ok(db)
+}
+
+/// One-line verdict comparing `rec` with the drive's rated speeds, if the drive is in the database.
+pub fn annotate(rec: &BenchResult) -> Option<String> {
+    let id = identity(rec.device.as_ref()?);
+    let db = match database() {
+        Ok(db) => db,
+        Err(e) => { warn!("known devices: {e}"); return None; }
+    };
+    let k = db.into_iter().find(|k| k.matches(&id))?;
+    let io = crabwise_core::runner::RANDOM_IO_BYTES as u64;
+    // (measured / rated) per direction the database has a rating for.
+    let mut ratios: Vec<(&str, f64)> = Vec::new();
+    for (name, rated, got) in [("write", k.write, rec.write.mb_s() * 1e6), ("read", k.read, rec.read.mb_s() * 1e6)] {
+        if let Some(r) = rated.filter(|&r| r > 0.0) { ratios.push((name, got / r)); }
+    }
+    for (name, rated, got) in [("random write", k.random_write_iops, &rec.random_write), ("random read", k.random_read_iops, &rec.random_read)] {
+        if let (Some(r), Some(p)) = (rated.filter(|&r| r > 0.0), got) { ratios.push((name, p.iops(io) / r)); }
+    }
+    let rated = [("read", k.read), ("write", k.write)].iter()
+        .filter_map(|(n, r)| r.map(|r| format!("{n} {:.0}", r / 1e6)))
+        .collect::<Vec<_>>().join(" / ");
+    let (slowest, low) = ratios.iter().copied().min_by(|a, b| a.1.total_cmp(&b.1))?;
+    let high = ratios.iter().map(|r| r.1).fold(0.0, f64::max);
+    Some(if low < SLOW_FRACTION {
+        format!("{slowest} is {:.0}% below rated for {} ({rated} MB/s) — possible counterfeit or bad link (USB 2 port/cable?)", (1.0 - low) * 100.0, k.name)
+    } else if high > FAST_FRACTION {
+        format!("faster than rated for {} ({rated} MB/s) — likely measuring a cache (try a larger --size or --sync direct)", k.name)
+    } else {
+        format!("within expected range for {} ({rated} MB/s rated)", k.name)
+    })
+}
@@ -1,21 +1,55 @@
-use clap::{Parser};
-use rand::{rngs::SmallRng, RngCore, SeedableRng};
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufReader, BufWriter, Read, Write};
-use std::path::{PathBuf};
-use std::time::Instant;
+mod burnin;
+mod chart;
+mod clean;
+mod community;
+mod completions;
+mod config;
+mod copybench;
+mod destructive;
+mod diag;
+mod history;
+mod http;
+mod i18n;
+mod job;
+mod junit;
+mod known;
+mod man;
+mod metrics;
+mod notify;
+mod preset;
+mod progress;
+mod replay;
+mod report;
+mod samples;
+mod scan;
+mod schedule;
+mod selftest;
+mod serve;
+mod simulate;
+mod style;
+mod toml;
+mod tui;
+mod units;
+mod verify;
+mod watch;
+mod wipe;
+
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use crabwise_core::device::{bus, candidate_devices, device_info, internal_devices, is_network, network_devices, partition_alignment, Bus, DeviceSpec};
+use crabwise_core::{artifacts, fscheck};
+use crabwise_core::config::ReadPattern;
+use crabwise_core::guard::{below_expectations, AbortBelow};
+use crabwise_core::parse::{parse_abort_below, parse_device, parse_duration, parse_rate, parse_read_pattern, parse_size, parse_tag};
+use crabwise_core::stats::mbs;
+use crabwise_core::log::{self, Level};
+use crabwise_core::{debug, info, result, trace, warn, BenchConfig, BenchResult, BenchRunner, Progress, RawDeviceBackend, Thresholds};
 #[cfg(unix)]
-use std::os::unix::fs::OpenOptionsExt;
-#[cfg(target_os = "macos")]
-use std::os::fd::AsRawFd;
-#[cfg(target_os = "windows")]
-use std::os::windows::fs::OpenOptionsExt as WinOpenOptionsExt;
-use sysinfo::Disks;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use chrono::Local;
 
-#[cfg(target_os = "macos")]
-static MAC_MEDIA_DIRS: Lazy<Vec<&'static str>> = Lazy::new(|| vec!["/Volumes"]);
-
 fn clear_screen() {
     #[cfg(target_os = "windows")]
     { let _ = std::process::Command::new("cmd").args(["/C", "cls"]).status(); }
@@ -23,28 +57,12 @@ fn clear_screen() {
     { let _ = std::process::Command::new("clear").status(); }
 }
 
-fn mbps(bytes: u128, dur_s: f64) -> f64 { (bytes as f64 * 8.0) / 1_000_000f64 / dur_s }
-fn mbs(bytes: u128, dur_s: f64) -> f64 { (bytes as f64) / 1_000_000f64 / dur_s } // MB/s (decimal)
-
-fn print_progress(prefix: &str, done: u64, total: u64, start: Instant) {
-    let pct = (done as f64 / total as f64) * 100.0;
-    let elapsed = start.elapsed().as_secs_f64();
-    let speed_mbs = if elapsed > 0.0 {
-        (done as f64 / 1_000_000f64) / elapsed
-    } else { 0.0 };
-    print!("\r{prefix}... {pct:5.1}% ({:.2} MB/s)", speed_mbs);
-    let _ = std::io::stdout().flush();
-}
-
-fn finish_progress() { println!(); }
-
 fn prompt_yes_no(prompt: &str) -> io::Result<bool> {
-    print!("{} [y/N]: ", prompt);
+    print!("{} {}: ", prompt, tr!("[y/N]"));
     io::stdout().flush()?;
     let mut line = String::new();
     io::stdin().read_line(&mut line)?;
-    let s = line.trim().to_ascii_lowercase();
-    Ok(matches!(s.as_str(), "y" | "yes"))
+    Ok(i18n::is_yes(&line))
 }
 
 fn prompt_line(prompt: &str) -> io::Result<String> {
@@ -57,136 +75,397 @@ fn prompt_line(prompt: &str) -> io::Result<String> {
 
 #[derive(Parser, Debug)]
 #[command(name="usbbench", about="USB read/write speed test")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Print diagnostics on stderr: -v phase timings, -vv file flags, syncs and enumeration, -vvv everything
+    #[arg(short='v', long, action=clap::ArgAction::Count, global=true)]
+    verbose: u8,
+
+    /// Only print errors on stderr (no warnings)
+    #[arg(short='q', long, global=true, conflicts_with="verbose")]
+    quiet: bool,
+
+    /// Pure-ASCII output: no banner, screen clear, box drawing or sparkline glyphs (also when NO_COLOR is set or stdout is not a terminal)
+    #[arg(long, global=true)]
+    plain: bool,
+
+    /// Throughput and size units in human output: si (MB/s, GB), binary (MiB/s, GiB) or both [default: si]
+    #[arg(long, value_enum, global=true)]
+    units: Option<units::Units>,
+
+    /// Language of prompts and the results box: en, de or es [default: from LC_ALL/LC_MESSAGES/LANG, else en]
+    #[arg(long, value_enum, global=true)]
+    lang: Option<i18n::Lang>,
+
+    #[command(flatten)]
+    bench: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render an HTML or Markdown report from recorded results
+    Report(report::ReportArgs),
+    /// Run the stages of a job file in order and print a combined report
+    Run(job::RunArgs),
+    /// Replay a recorded I/O trace (CSV, blktrace or ftrace) against a drive and report each phase
+    Replay(replay::ReplayArgs),
+    /// Wait for removable drives to be inserted and benchmark each one unattended
+    Watch(watch::WatchArgs),
+    /// Copy from one drive to another, verify the copy and report which side limited it
+    Copybench(copybench::CopybenchArgs),
+    /// Write and verify the test region repeatedly, tracking throughput drift and failures per cycle
+    Burnin(burnin::BurninArgs),
+    /// Read a whole device or file, or a filesystem's free space, without writing and list unreadable or abnormally slow regions
+    Scan(scan::ScanArgs),
+    /// Overwrite a device or its free space with a pattern and verify it (asks for confirmation)
+    Wipe(wipe::WipeArgs),
+    /// Delete test files and directories that crashed or killed runs left in a directory
+    Clean(clean::CleanArgs),
+    /// Check a test file kept with --keep against its manifest, block by block
+    Verify(verify::VerifyArgs),
+    /// Collect an anonymized diagnostic bundle (platform, device descriptors, results, kernel log) to attach to a bug report
+    Diag(diag::DiagArgs),
+    /// Show community median throughput for a drive model (see --submit)
+    Lookup(community::LookupArgs),
+    /// Serve a local HTTP API for listing devices, starting runs and streaming their progress
+    Serve(serve::ServeArgs),
+    /// Run a tiny benchmark and internal checks, and list which capabilities work on this system
+    Selftest(selftest::SelftestArgs),
+    /// Print a tab-completion script for bash, zsh, fish or PowerShell
+    Completions(completions::CompletionsArgs),
+    /// Print the man page (or write one page per subcommand with --out DIR)
+    Man(man::ManArgs),
+}
+
+#[derive(clap::Args, Debug, Clone, Default)]
 struct Args {
-    /// Directory on the USB device to use (will write a temp file here). If omitted, you'll be prompted to pick a device.
+    /// Directory on the USB device to use (will write a temp file here), or a device node such as /dev/sdb to benchmark raw, destroying its data. If omitted, you'll be prompted to pick a device.
     target_dir: Option<PathBuf>,
 
-    /// Total test size (e.g., 1G, 512M)
-    #[arg(short='s', long, default_value="1G")]
-    size: String,
+    /// Total test size (e.g., 1G, 1.5G, 512MiB, 2GB — K/M/G and KiB/MiB/GiB are binary, KB/MB/GB decimal) [default: 1G]
+    #[arg(short='s', long, value_parser=size_arg)]
+    size: Option<String>,
+
+    /// Block size (e.g., 4M, 1M, 64K) [default: 4M]
+    #[arg(short='b', long, value_parser=size_arg)]
+    block: Option<String>,
+
+    /// Probe block sizes from 64K to 16M first and use the one where throughput levels off
+    #[arg(long, conflicts_with = "block")]
+    auto_block: bool,
 
-    /// Block size (e.g., 4M, 1M, 64K)
-    #[arg(short='b', long, default_value="4M")]
-    block: String,
+    /// I/O mode: `direct` bypasses the OS cache (O_SYNC / write-through), `buffered` only syncs at the end [default: direct]
+    #[arg(long, value_enum)]
+    sync: Option<SyncMode>,
+
+    /// Label the result with KEY=VALUE (repeatable, e.g. --tag port=front-usb-c --tag cable=short)
+    #[arg(long, value_name="KEY=VALUE", value_parser=parse_tag)]
+    tag: Vec<(String, String)>,
+
+    /// Append saved session lines to FILE instead of crabwise.log in the target directory
+    #[arg(long, value_name="FILE")]
+    log: Option<PathBuf>,
+
+    /// Result output: `text` (results box and prompts), `json` (one result object on stdout) or `junit` (JUnit XML on stdout, for CI) [default: text]
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Read defaults from this config file instead of ~/.config/crabwise/config.toml
+    #[arg(long, value_name="FILE")]
+    config: Option<PathBuf>,
+
+    /// Apply a bundle of settings: quick, standard or thorough (explicit flags still win)
+    #[arg(long, value_enum)]
+    preset: Option<preset::Preset>,
+
+    /// Run the access patterns of one use and judge the device for it: rpi-boot, steam-deck, dashcam or mister (--size sets the region [default: 256M])
+    #[arg(long, value_enum, value_name="USE")]
+    simulate: Option<simulate::Use>,
+
+    /// Repeat the sequential write/read pass N times and aggregate the results [default: 1]
+    #[arg(long, value_name="N", value_parser=clap::value_parser!(u32).range(1..))]
+    iterations: Option<u32>,
+
+    /// Add a 4 KiB random write/read test (IOPS) after the sequential passes
+    #[arg(long)]
+    random: bool,
+
+    /// Also write and read N test files at once (splitting --size) and compare with the single stream
+    #[arg(long, value_name="N", value_parser=clap::value_parser!(u32).range(1..))]
+    files: Option<u32>,
+
+    /// Run the 4 KiB random-read test for 5 s at each of these queue depths and show how IOPS scale (e.g., 1,2,4,8,16,32), to tell real UASP command queuing from none
+    #[arg(long, value_name="QD,...", value_delimiter=',', value_parser=clap::value_parser!(u32).range(1..))]
+    sweep_qd: Vec<u32>,
+
+    /// Time uncached 4 KiB reads at random offsets in the test file, one at a time, like HDTune's access time
+    #[arg(long)]
+    access_time: bool,
+
+    /// Also time N 4 KiB appends, each followed by fsync (FlushFileBuffers on Windows), and report the latency distribution (e.g., 1000)
+    #[arg(long, value_name="N", value_parser=clap::value_parser!(u32).range(1..))]
+    fsync: Option<u32>,
+
+    /// Block order of the read pass: seq, reverse, or stride:<n> (every n-th block, then the next offset) [default: seq]
+    #[arg(long, value_name="PATTERN", value_parser=parse_read_pattern)]
+    read_pattern: Option<ReadPattern>,
+
+    /// Also read the test file's blocks in shuffled order and report the random penalty versus sequential reads
+    #[arg(long)]
+    shuffled: bool,
 
-    /// Keep the test file (for repeat reads)
+    /// Also read the test file through the OS cache and report that next to the device read
+    #[arg(long)]
+    cached_read: bool,
+
+    /// Reserve the test file's full size before each write pass (fallocate), so writes don't include allocation and a full drive fails up front
+    #[arg(long)]
+    preallocate: bool,
+
+    /// Re-read and compare every byte written; mismatches fail the run (exit code 3)
+    #[arg(long)]
+    verify: bool,
+
+    /// Finish with a sequential write lasting this long to expose cache exhaustion (e.g., 60s, 5m)
+    #[arg(long, value_name="DURATION", value_parser=parse_duration)]
+    sustained: Option<f64>,
+
+    /// TRIM the filesystem's free space after the test file is deleted, like fstrim (Linux, needs root)
+    #[arg(long)]
+    trim: bool,
+
+    /// Also rewrite the test file over just-freed blocks, then again after a TRIM, and compare the write speeds (Linux, needs root)
+    #[arg(long)]
+    trim_compare: bool,
+
+    /// Checksum the filesystem's boot sectors and superblocks before and after the run and fail if they changed (reads the raw device; needs root)
+    #[arg(long)]
+    corruption_check: bool,
+
+    /// Before the passes, compare a zero-fill write with a random-data write and warn if the controller compresses or deduplicates
+    #[arg(long)]
+    compression_check: bool,
+
+    /// Time the write and read per 1% of the test file and show the speed profile by offset (slow regions of cheap flash)
+    #[arg(long)]
+    offset_map: bool,
+
+    /// Rerun the benchmark every DURATION (e.g., 30m) and summarize the trend at the end (non-interactive)
+    #[arg(long, value_name="DURATION", value_parser=parse_duration, conflicts_with_all=["tui", "progress_json", "progress_fd"])]
+    repeat_every: Option<f64>,
+
+    /// Number of scheduled runs for --repeat-every [default: until interrupted]
+    #[arg(long, value_name="N", requires="repeat_every", value_parser=clap::value_parser!(u64).range(1..))]
+    count: Option<u64>,
+
+    /// Serve Prometheus metrics for the scheduled runs at http://ADDR/metrics (e.g., 127.0.0.1:9477)
+    #[arg(long, value_name="ADDR", requires="repeat_every")]
+    metrics: Option<String>,
+
+    /// When the run finishes or fails, notify `desktop` or POST a JSON summary to `webhook:URL` (repeatable)
+    #[arg(long, value_name="TARGET", value_parser=notify::parse_notify)]
+    notify: Vec<notify::Notify>,
+
+    /// Upload an anonymized result (drive model, USB IDs, parameters, throughput) and compare with community medians
+    #[arg(long)]
+    submit: bool,
+
+    /// Results service used by --submit [default: results_service from the config file, or $CRABWISE_RESULTS_SERVICE]
+    #[arg(long, value_name="URL")]
+    results_service: Option<String>,
+
+    /// Also offer internal (non-removable) drives in the device picker; asks for confirmation before using one
+    #[arg(long)]
+    allow_internal: bool,
+
+    /// Test the device whose label, vendor or model contains NAME, or `serial:<serial>`, or drive letter `E:`; fails unless exactly one picker entry matches
+    #[arg(long, value_name="NAME", value_parser=parse_device, conflicts_with="target_dir")]
+    device: Option<DeviceSpec>,
+
+    /// Skip the device prompt when the picker has exactly one entry and use that device
+    #[arg(long)]
+    auto: bool,
+
+    /// Tune for an SMB/NFS share: buffered I/O with cache eviction, small-file and round-trip tests, no USB lookups
+    #[arg(long)]
+    network: bool,
+
+    /// I/O engine for the sequential passes: `sync` (one call per block) or `vectored` (writev/readv batches) [default: sync]
+    #[arg(long, value_enum)]
+    engine: Option<Engine>,
+
+    /// Keep the test file (for repeat reads or a later `crabwise verify`)
     #[arg(long)]
     keep: bool,
+
+    /// Confirm a run that overwrites a device node or fills the volume (--sustained, or a --size close to the free space) without the prompt, by giving what it asks for: the drive's serial number or device name for a device node, FILL for a fill (required when stdin is not a terminal)
+    #[arg(long, value_name="NAME")]
+    yes: Option<String>,
+
+    /// Unmount a target device node's mounted filesystems instead of refusing to benchmark it
+    #[arg(long)]
+    force_unmount: bool,
+
+    /// Set once the typed confirmation has been given, so scheduled repeats don't ask again.
+    #[arg(skip)]
+    confirmed: bool,
+
+    /// Set for runs nobody is watching (over HTTP, or started by `watch`): a destructive run
+    /// then needs `--yes` up front instead of prompting.
+    #[arg(skip)]
+    unattended: bool,
+
+    /// Fail (exit code 3) if write throughput is below this rate (e.g., 100MBps, 800Mbps, 95MiB/s)
+    #[arg(long, value_name="RATE", value_parser=parse_rate)]
+    min_write: Option<f64>,
+
+    /// Fail (exit code 3) if read throughput is below this rate (e.g., 200MBps)
+    #[arg(long, value_name="RATE", value_parser=parse_rate)]
+    min_read: Option<f64>,
+
+    /// Fail (exit code 3) if the 99th percentile per-block write latency exceeds this (e.g., 50ms)
+    #[arg(long, value_name="DURATION", value_parser=parse_duration)]
+    max_write_latency: Option<f64>,
+
+    /// Fail (exit code 3) if the 99th percentile per-block read latency exceeds this (e.g., 20ms)
+    #[arg(long, value_name="DURATION", value_parser=parse_duration)]
+    max_read_latency: Option<f64>,
+
+    /// Give up (exit code 3) once sequential throughput stays below RATE for DURATION, e.g. 5MBps@30s
+    #[arg(long, value_name="RATE@DURATION", value_parser=parse_abort_below)]
+    abort_below: Option<AbortBelow>,
+
+    /// Append the results as a JSON line to FILE (input for `crabwise report`)
+    #[arg(long, value_name="FILE")]
+    record: Option<PathBuf>,
+
+    /// Plot write and read throughput over time to FILE (.svg or .png)
+    #[arg(long, value_name="FILE")]
+    chart: Option<PathBuf>,
+
+    /// Plot write and read speed against offset in the test file to FILE (.svg or .png); implies --offset-map
+    #[arg(long, value_name="FILE")]
+    offset_chart: Option<PathBuf>,
+
+    /// Write each phase's per-second throughput and latency samples to FILE as CSV
+    #[arg(long, value_name="FILE")]
+    samples: Option<PathBuf>,
+
+    /// Full-screen dashboard with live graph, latency and temperature, plus an arrow-key device picker
+    #[arg(long)]
+    tui: bool,
+
+    /// Emit newline-delimited JSON progress events on stdout instead of human output (non-interactive)
+    #[arg(long, conflicts_with="tui")]
+    progress_json: bool,
+
+    /// Write the JSON progress events to this already-open file descriptor instead of stdout (Unix)
+    #[arg(long, value_name="FD", conflicts_with="tui")]
+    progress_fd: Option<i32>,
 }
 
-fn parse_size(s: &str) -> u64 {
-    // simple parser: supports K/M/G suffix (base 1024)
-    let (num, suf) = s.trim().split_at(s.trim().find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
-    let n: u64 = num.parse().expect("invalid size");
-    let mult = match suf.trim().to_ascii_uppercase().as_str() {
-        "" => 1,
-        "K" | "KB" => 1024,
-        "M" | "MB" => 1024*1024,
-        "G" | "GB" => 1024*1024*1024,
-        _ => panic!("unsupported size suffix"),
-    };
-    n * mult
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SyncMode {
+    Direct,
+    Buffered,
 }
 
-#[cfg(target_os = "macos")]
-fn set_nocache(file: &File) {
-    unsafe {
-        let fd = file.as_raw_fd();
-        let _ = libc::fcntl(fd, libc::F_NOCACHE, 1);
-    }
+/// How the sequential passes hand blocks to the OS.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Engine {
+    /// One write/read call per block
+    Sync,
+    /// Up to 64 blocks per writev/readv call, for small blocks where syscall overhead dominates
+    Vectored,
 }
 
-#[allow(dead_code)]
-#[cfg(not(target_os = "macos"))]
-fn set_nocache(_file: &File) {}
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Text,
+    Json,
+    Junit,
+}
 
-fn open_write(path: &std::path::Path, direct: bool) -> std::io::Result<File> {
-    #[cfg(target_os = "windows")]
-    {
-        use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_WRITE_THROUGH};
-        let mut opts = std::fs::OpenOptions::new();
-        opts.create(true).write(true).truncate(true);
-        if direct { opts.custom_flags(FILE_FLAG_WRITE_THROUGH as u32); } // Fix type
-        let f = opts.open(path)?;
-        Ok(f)
-    }
-    #[cfg(unix)]
-    {
-        let mut opts = std::fs::OpenOptions::new();
-        opts.create(true).write(true).truncate(true);
-        if direct {
-            opts.custom_flags(libc::O_SYNC);
-        }
-        let f = opts.open(path)?;
-        #[cfg(target_os = "macos")]
-        if direct { set_nocache(&f); }
-        Ok(f)
-    }
+/// Session log kept in the target directory unless `--log` says otherwise.
+const LOG_FILE_NAME: &str = "crabwise.log";
+
+/// Exit code used when a run completes but misses one of the --min-*/--max-* thresholds.
+const EXIT_THRESHOLD_FAILED: i32 = 3;
+
+/// Files written, read back and reopened by `--network`.
+const NETWORK_SMALL_FILES: u32 = 256;
+
+/// The picker's entries: removable drives, then internal ones with `--allow-internal`;
+/// mounted shares only with `--network`.
+fn picker_devices(args: &Args) -> Vec<(String, PathBuf)> {
+    if args.network { return network_devices(); }
+    let mut candidates = candidate_devices();
+    if args.allow_internal { candidates.extend(internal_devices()); }
+    candidates
 }
 
-fn open_read(path: &std::path::Path, direct: bool) -> std::io::Result<File> {
-    #[cfg(target_os = "windows")]
-    {
-        use windows_sys::Win32::Storage::FileSystem::{FILE_FLAG_WRITE_THROUGH};
-        let mut opts = std::fs::OpenOptions::new();
-        opts.read(true);
-        if direct { opts.custom_flags(FILE_FLAG_WRITE_THROUGH as u32); } // Fix type
-        let f = opts.open(path)?;
-        Ok(f)
-    }
-    #[cfg(unix)]
-    {
-        let mut opts = std::fs::OpenOptions::new();
-        opts.read(true);
-        if direct { opts.custom_flags(libc::O_SYNC); }
-        let f = opts.open(path)?;
-        #[cfg(target_os = "macos")]
-        if direct { set_nocache(&f); }
-        Ok(f)
-    }
-}
-
-fn choose_target_dir() -> io::Result<PathBuf> {
-    let disks = Disks::new_with_refreshed_list();
-
-    // Gather candidates
-    let mut candidates: Vec<(String, PathBuf)> = Vec::new();
-    for d in disks.list() {
-        let mount = d.mount_point().to_path_buf();
-        let name = d.name().to_string_lossy().to_string();
-        #[cfg(target_os = "windows")]
-        {
-            // Omit C: drive, include all others
-            let letter = mount.display().to_string().chars().next().unwrap_or('C');
-            if letter != 'C' {
-                candidates.push((format!("{} — {}", name, mount.display()), mount.clone()));
-            }
+/// The one picker entry `--device` names.
+fn find_device(args: &Args, spec: &DeviceSpec) -> io::Result<PathBuf> {
+    let found: Vec<(String, PathBuf)> = picker_devices(args).into_iter().filter(|(l, p)| spec.matches(l, p)).collect();
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    match found.as_slice() {
+        [(label, path)] => {
+            info!("--device {spec}: {label}");
+            Ok(path.clone())
         }
-        #[cfg(not(target_os = "windows"))]
-        {
-            if d.is_removable() {
-                candidates.push((format!("{} — {}", name, mount.display()), mount.clone()));
-            }
+        [] if args.allow_internal || args.network => Err(invalid(format!("--device {spec}: no detected device matches"))),
+        [] => Err(invalid(format!("--device {spec}: no detected device matches (--allow-internal also searches internal drives)"))),
+        many => {
+            let labels: Vec<&str> = many.iter().map(|(l, _)| l.as_str()).collect();
+            Err(invalid(format!("--device {spec}: {} devices match ({}); name one more precisely, e.g. serial:<serial>", many.len(), labels.join("; "))))
         }
     }
+}
 
-    // De-dup (some OSes report multiple entries for same mount point)
-    candidates.sort_by(|a,b| a.1.cmp(&b.1));
-    candidates.dedup_by(|a,b| a.1 == b.1);
+fn is_internal(path: &std::path::Path) -> bool {
+    internal_devices().iter().any(|(_, p)| p == path)
+}
 
+/// Warns before benchmarking a picked internal drive and asks to go ahead.
+fn confirm_internal(label: &str) -> io::Result<()> {
+    eprintln!("\n{}", tr!("WARNING: {} is an internal (non-removable) drive, possibly the one the OS runs from.", style::text(label)));
+    eprintln!("{}", tr!("The benchmark writes a test file of --size bytes to it, which adds wear and needs that much free space,"));
+    eprintln!("{}", tr!("and other activity on the drive will skew the results."));
+    if prompt_yes_no(tr!("Benchmark this internal drive anyway?"))? { Ok(()) } else {
+        Err(io::Error::new(io::ErrorKind::Interrupted, "internal drive not confirmed"))
+    }
+}
+
+fn choose_target_dir(args: &Args) -> io::Result<PathBuf> {
+    let candidates = picker_devices(args);
+    if let [(label, path)] = candidates.as_slice() {
+        if args.auto {
+            if args.allow_internal && is_internal(path) { confirm_internal(label)?; }
+            say!("{}", tr!("Only one device found, using: {}", label));
+            return Ok(path.clone());
+        }
+    }
     if candidates.is_empty() {
-        eprintln!("No removable/USB mounts detected. Enter a directory path to test:");
+        if args.network {
+            eprintln!("{}", tr!("No mounted network shares detected. Enter a directory on the share to test:"));
+        } else {
+            eprintln!("{}", tr!("No removable/USB mounts detected (--allow-internal also lists internal drives). Enter a directory path to test:"));
+        }
         let mut line = String::new();
         io::stdin().read_line(&mut line)?;
-        let p = PathBuf::from(line.trim());
-        return Ok(p);
+        if line.trim().is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no target directory given"));
+        }
+        return Ok(PathBuf::from(line.trim()));
     }
 
-    println!("Select a device/path to test:");
+    println!("{}", tr!("Select a device/path to test:"));
     for (i, (label, _)) in candidates.iter().enumerate() {
-        println!("  {}. {}", i + 1, label);
+        say!("  {}. {}", i + 1, label);
     }
-    print!("Enter number: ");
+    print!("{} ", tr!("Enter number:"));
     io::stdout().flush()?;
 
     let mut sel = String::new();
@@ -194,120 +473,791 @@ fn choose_target_dir() -> io::Result<PathBuf> {
     let idx: usize = sel.trim().parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid selection"))?;
     let idx0 = idx.checked_sub(1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "selection out of range"))?;
     if let Some((label, path)) = candidates.get(idx0) {
-        println!("Testing read/write speed to USB device: {}", label);
+        if args.allow_internal && is_internal(path) { confirm_internal(label)?; }
+        if args.network {
+            say!("{}", tr!("Testing read/write speed to network share: {}", label));
+        } else {
+            say!("{}", tr!("Testing read/write speed to USB device: {}", label));
+        }
         Ok(path.clone())
     } else {
         Err(io::Error::new(io::ErrorKind::InvalidInput, "selection out of range"))
     }
 }
 
-fn main() -> std::io::Result<()> {
-    clear_screen();
-    println!(r#" ██████╗██████╗  █████╗ ██████╗ ██╗    ██╗██╗███████╗███████╗
+/// Parses the command line. The benchmark's own flags only apply without a subcommand; the
+/// global ones (`-v`, `--plain`, `--units`, …) go anywhere.
+fn parse_cli(argv: impl IntoIterator<Item = std::ffi::OsString>) -> Result<Cli, clap::Error> {
+    let mut cmd = Cli::command();
+    let matches = cmd.try_get_matches_from_mut(argv)?;
+    if let Some((name, _)) = matches.subcommand() {
+        let bench = <Args as clap::Args>::augment_args(clap::Command::new("bench"));
+        let given = bench.get_arguments()
+            .filter(|a| !a.is_global_set() && matches.value_source(a.get_id().as_str()) == Some(clap::parser::ValueSource::CommandLine))
+            .map(|a| a.get_long().map_or_else(|| a.get_id().to_string().to_uppercase(), |l| format!("--{l}")))
+            .collect::<Vec<_>>();
+        if !given.is_empty() {
+            return Err(cmd.error(clap::error::ErrorKind::ArgumentConflict, format!("{} can't be used with the '{name}' subcommand", given.join(", "))));
+        }
+    }
+    Cli::from_arg_matches(&matches).map_err(|e| e.format(&mut cmd))
+}
+
+fn main() {
+    let cli = parse_cli(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    log::set_level(match (cli.quiet, cli.verbose) {
+        (true, _) => Level::Error,
+        (_, 0) => Level::Warn,
+        (_, 1) => Level::Info,
+        (_, 2) => Level::Debug,
+        _ => Level::Trace,
+    });
+    if let Some(u) = cli.units { units::set(u); }
+    if let Some(l) = cli.lang { i18n::set(l); }
+    style::init(cli.plain);
+    trace!("{cli:?}");
+    if let Err(e) = run(cli) {
+        eprintln!("error: {e}");
+        std::process::exit(if below_expectations(&e).is_some() { EXIT_THRESHOLD_FAILED } else { 1 });
+    }
+}
+
+fn run(cli: Cli) -> io::Result<()> {
+    match cli.command {
+        Some(Command::Report(r)) => report::run(r),
+        Some(Command::Replay(r)) => replay::run(r),
+        Some(Command::Run(r)) => {
+            if !job::run(r)? { std::process::exit(EXIT_THRESHOLD_FAILED); }
+            Ok(())
+        }
+        Some(Command::Watch(w)) => watch::run(w),
+        Some(Command::Copybench(c)) => {
+            if !copybench::run(c)? { std::process::exit(EXIT_THRESHOLD_FAILED); }
+            Ok(())
+        }
+        Some(Command::Burnin(b)) => {
+            if !burnin::run(b)? { std::process::exit(EXIT_THRESHOLD_FAILED); }
+            Ok(())
+        }
+        Some(Command::Scan(s)) => {
+            if !scan::run(s)? { std::process::exit(EXIT_THRESHOLD_FAILED); }
+            Ok(())
+        }
+        Some(Command::Wipe(w)) => {
+            if !wipe::run(w)? { std::process::exit(EXIT_THRESHOLD_FAILED); }
+            Ok(())
+        }
+        Some(Command::Serve(s)) => serve::run(s),
+        Some(Command::Clean(c)) => clean::run(c),
+        Some(Command::Verify(v)) => {
+            if !verify::run(v)? { std::process::exit(EXIT_THRESHOLD_FAILED); }
+            Ok(())
+        }
+        Some(Command::Diag(d)) => diag::run(d),
+        Some(Command::Selftest(s)) => {
+            if !selftest::run(s)? { std::process::exit(EXIT_THRESHOLD_FAILED); }
+            Ok(())
+        }
+        Some(Command::Lookup(l)) => community::lookup(l),
+        Some(Command::Completions(c)) => completions::run(c),
+        Some(Command::Man(m)) => man::run(m),
+        None => run_bench(cli.bench),
+    }
+}
+
+/// Layers the preset and config file under the flags; returns (total size, block size).
+fn resolve(args: &mut Args) -> io::Result<(u64, u64)> {
+    if let Some(p) = args.preset { info!("applying preset {p:?}"); }
+    if args.simulate.is_some() { args.size.get_or_insert_with(|| simulate::DEFAULT_SIZE.to_string()); }
+    preset::apply(args);
+    config::apply(args)?;
+    if args.network {
+        // Direct I/O is a local-disk notion most SMB/NFS clients reject or fake; read-back
+        // honesty comes from evicting the file from the client cache instead.
+        args.sync.get_or_insert(SyncMode::Buffered);
+    }
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let total = parse_size(args.size.as_deref().unwrap_or("1G")).map_err(|e| invalid(format!("--size: {e}")))?;
+    let block = parse_size(args.block.as_deref().unwrap_or("4M")).map_err(|e| invalid(format!("--block: {e}")))?;
+    if block == 0 || total < block {
+        return Err(invalid(format!("--block ({block} bytes) must be greater than 0 and no larger than --size ({total} bytes)")));
+    }
+    Ok((total, block))
+}
+
+/// Clap parser for `--size`/`--block`: checks the form but keeps the text so config layering still sees "unset".
+fn size_arg(s: &str) -> Result<String, String> {
+    parse_size(s).map(|_| s.to_string())
+}
+
+fn run_bench(mut args: Args) -> std::io::Result<()> {
+    let (total, block) = resolve(&mut args)?;
+    if let Some(spec) = args.device.clone() {
+        args.target_dir = Some(find_device(&args, &spec)?);
+    }
+
+    if args.progress_json || args.progress_fd.is_some() {
+        return bench_json(args, total, block);
+    }
+    if let Some(every) = args.repeat_every {
+        let target_dir = match args.target_dir.clone() {
+            Some(p) => p,
+            None => choose_target_dir(&args)?,
+        };
+        return schedule::run(args, target_dir, total, block, every);
+    }
+    if let Some(usage) = args.simulate {
+        let target_dir = match args.target_dir.clone() {
+            Some(p) => p,
+            None => choose_target_dir(&args)?,
+        };
+        let direct = args.sync.unwrap_or(SyncMode::Direct) == SyncMode::Direct;
+        if !simulate::run(usage, target_dir, total, direct, args.keep)? { std::process::exit(EXIT_THRESHOLD_FAILED); }
+        return Ok(());
+    }
+    if let Some(format @ (Format::Json | Format::Junit)) = args.format {
+        let Some(target_dir) = args.target_dir.clone() else {
+            let name = if format == Format::Json { "json" } else { "junit" };
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("--format {name} requires a target directory")));
+        };
+        if format == Format::Json {
+            return exit_on_failures(bench_with(args, target_dir, total, block, &mut progress::ResultJson, false)?);
+        }
+        let thresholds = Thresholds {
+            min_write: args.min_write,
+            min_read: args.min_read,
+            max_write_latency: args.max_write_latency,
+            max_read_latency: args.max_read_latency,
+        };
+        return exit_on_failures(bench_with(args, target_dir, total, block, &mut progress::ResultJunit { thresholds }, false)?);
+    }
+    if args.tui && style::plain() {
+        warn!("--tui ignored: plain output is on (--plain, NO_COLOR or stdout not a terminal)");
+    } else if args.tui {
+        match tui::Terminal::enter() {
+            Ok(term) => return bench_tui(args, term, total, block),
+            Err(e) => warn!("--tui unavailable ({e}); using standard output"),
+        }
+    }
+
+    if !style::plain() {
+        clear_screen();
+        println!(r#" ██████╗██████╗  █████╗ ██████╗ ██╗    ██╗██╗███████╗███████╗
 ██╔════╝██╔══██╗██╔══██╗██╔══██╗██║    ██║██║██╔════╝██╔════╝
 ██║     ██████╔╝███████║██████╔╝██║ █╗ ██║██║███████╗█████╗  
 ██║     ██╔══██╗██╔══██║██╔══██╗██║███╗██║██║╚════██║██╔══╝  
 ╚██████╗██║  ██║██║  ██║██████╔╝╚███╔███╔╝██║███████║███████╗
  ╚═════╝╚═╝  ╚═╝╚═╝  ╚═╝╚═════╝  ╚══╝╚══╝ ╚═╝╚══════╝╚══════╝
 "#);
-    println!("USB Device Benchmark Utility\n");
-    let args = Args::parse();
-    let total = parse_size(&args.size);
-    let block = parse_size(&args.block);
-    assert!(block > 0 && total >= block, "block must be >0 and <= total size");
+        println!("{}\n", tr!("USB Device Benchmark Utility"));
+    }
 
-    let target_dir = match args.target_dir {
+    let target_dir = match args.target_dir.clone() {
         Some(p) => p,
-        None => choose_target_dir()?,
+        None => choose_target_dir(&args)?,
     };
+    offer_cleanup(&target_dir)?;
+    confirm_fs_health(&fs_problems(&target_dir))?;
+    exit_on_failures(bench_with(args, target_dir, total, block, &mut progress::LineProgress { enabled: total >= 100 }, true)?)
+}
 
-    std::fs::create_dir_all(&target_dir)?;
-    let test_path = target_dir.join(".usbbench.tmp");
-
-    // -------- WRITE --------
-    let f = open_write(&test_path, true)?;
-    let mut writer = BufWriter::with_capacity(block as usize, f);
-
-    // precreate a block of pseudo-random bytes
-    let mut rng = SmallRng::seed_from_u64(0x5EED_CAFE);
-    let mut buf = vec![0u8; block as usize];
-    rng.fill_bytes(&mut buf);
-
-    let mut written: u64 = 0;
-    let t0 = Instant::now();
-    while written < total {
-        let to_write = std::cmp::min(block, total - written) as usize;
-        writer.write_all(&buf[..to_write])?;
-        written += to_write as u64;
-        if total >= 100 { print_progress("Writing", written, total, t0); }
-    }
-    writer.flush()?;
-    writer.get_ref().sync_all()?; // ensure data + metadata on disk
-    if total >= 100 { finish_progress(); }
-    let write_secs = t0.elapsed().as_secs_f64();
-
-    // -------- READ --------
-    let f = open_read(&test_path, true)?;
-    let mut reader = BufReader::with_capacity(block as usize, f);
-    let mut read_buf = vec![0u8; block as usize];
-    let mut read_total: u64 = 0;
-    let t1 = Instant::now();
-    loop {
-        let n = reader.read(&mut read_buf)?;
-        if n == 0 { break; }
-        read_total += n as u64;
-        if total >= 100 { print_progress("Reading", read_total, total, t1); }
-    }
-    if total >= 100 { finish_progress(); }
-    let read_secs = t1.elapsed().as_secs_f64();
-
-    let size_gib = (total as f64)/(1024.0*1024.0*1024.0);
-    let block_mib = (block as f64)/(1024.0*1024.0);
+/// Lists test files that crashed or killed runs left in `dir` and offers to delete them.
+fn offer_cleanup(dir: &std::path::Path) -> io::Result<()> {
+    let stale = artifacts::leftovers(dir);
+    if stale.is_empty() { return Ok(()); }
+    let bytes: u64 = stale.iter().map(|a| a.bytes).sum();
+    println!("{}", tr!("Found {} file(s) left by earlier runs ({}):", stale.len(), units::size(bytes)));
+    for a in &stale { println!("  {}", a.path.display()); }
+    if prompt_yes_no(tr!("Delete them before benchmarking?"))? {
+        for a in &stale {
+            if let Err(e) = artifacts::remove(a) { warn!("could not remove {}: {e}", a.path.display()); }
+        }
+    }
+    Ok(())
+}
+
+/// What the read-only filesystem check found on the volume holding `dir`; empty when it
+/// looks clean or couldn't be checked (no raw device access, unknown filesystem, a share).
+fn fs_problems(dir: &std::path::Path) -> Vec<String> {
+    let Some(info) = device_info(dir).filter(|i| !is_network(i)) else { return Vec::new() };
+    match fscheck::check(&info) {
+        Ok(Some(h)) => h.problems.into_iter().map(|p| format!("{} on {}: {p}", h.kind, info.name)).collect(),
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            debug!("filesystem check skipped: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// A run counts as filling the volume once its test file would take this share of the free space.
+const FILL_SHARE: f64 = 0.9;
+
+/// What a run would destroy or fill, and the drive to name for it: a device node as the
+/// target loses all its data and is confirmed with its serial or name, while `--sustained` or
+/// a test file close to the free space only fills the filesystem and is confirmed with FILL.
+/// `None` for an ordinary run.
+fn destructive_action(args: &Args, target: &Path, total: u64) -> Option<(String, Option<PathBuf>)> {
+    if let Some(d) = destructive::device_node(target) {
+        return Some((format!("DESTROY ALL DATA on {} by benchmarking it raw", d.display()), Some(d)));
+    }
+    let info = device_info(target)?;
+    let fill = if args.sustained.is_some() {
+        "a sustained write"
+    } else if total as f64 >= info.available_bytes as f64 * FILL_SHARE {
+        "the test file"
+    } else {
+        return None;
+    };
+    let action = format!("fill the filesystem holding {} ({} free) with {fill}", target.display(), units::size(info.available_bytes));
+    Some((action, None))
+}
+
+/// Has the user name the drive before a destructive run, as for `crabwise wipe`, then
+/// unmounts a target device node's filesystems (only after that, so declining leaves them).
+fn confirm_destructive(args: &mut Args, target: &Path, total: u64) -> io::Result<()> {
+    if args.confirmed { return Ok(()); }
+    if let Some((action, device)) = destructive_action(args, target, total) {
+        destructive::confirm_typed(&action, device.as_deref(), "FILL", args.yes.as_deref(), !args.unattended)?;
+    }
+    if let Some(d) = destructive::device_node(target) { destructive::ensure_unmounted(&d, args.force_unmount)?; }
+    args.confirmed = true;
+    Ok(())
+}
+
+/// Options that need a filesystem on the target, refused when it's a device node.
+fn refuse_on_raw(args: &Args) -> io::Result<()> {
+    let used = [
+        ("--auto-block", args.auto_block),
+        ("--files", args.files.unwrap_or(1) > 1),
+        ("--fsync", args.fsync.unwrap_or(0) > 0),
+        ("--sweep-qd", !args.sweep_qd.is_empty()),
+        ("--sustained", args.sustained.is_some()),
+        ("--network", args.network),
+        ("--keep", args.keep),
+        ("--preallocate", args.preallocate),
+        ("--trim", args.trim),
+        ("--trim-compare", args.trim_compare),
+        ("--compression-check", args.compression_check),
+        ("--corruption-check", args.corruption_check),
+    ];
+    match used.iter().find(|(_, on)| *on) {
+        Some((flag, _)) => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{flag} needs a filesystem; it can't be used with a device node as the target"))),
+        None => Ok(()),
+    }
+}
+
+/// Shows the filesystem check's findings and asks whether to write to the volume anyway.
+fn confirm_fs_health(problems: &[String]) -> io::Result<()> {
+    if problems.is_empty() { return Ok(()); }
+    eprintln!("\n{}", tr!("WARNING: the filesystem looks damaged or was not cleanly unmounted:"));
+    for p in problems { eprintln!("  {p}"); }
+    eprintln!("{}", tr!("Writing to it can make matters worse. Check it first: fsck on Linux/macOS, chkdsk /f on Windows."));
+    if prompt_yes_no(tr!("Benchmark it anyway?"))? { Ok(()) } else {
+        Err(io::Error::new(io::ErrorKind::Interrupted, "filesystem check not confirmed"))
+    }
+}
+
+/// Opens the destination for `--progress-fd`.
+fn progress_fd(fd: i32) -> io::Result<Box<dyn Write>> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::FromRawFd;
+        if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("--progress-fd {fd}: descriptor is not open")));
+        }
+        Ok(Box::new(unsafe { File::from_raw_fd(fd) }))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = fd;
+        Err(io::Error::new(io::ErrorKind::Unsupported, "--progress-fd is only supported on Unix"))
+    }
+}
+
+fn bench_json(args: Args, total: u64, block: u64) -> io::Result<()> {
+    let Some(target_dir) = args.target_dir.clone() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "--progress-json requires a target directory"));
+    };
+    let out: Box<dyn Write> = match args.progress_fd {
+        Some(fd) => progress_fd(fd)?,
+        None => Box::new(io::stdout()),
+    };
+    let mut json = progress::JsonProgress::new(out);
+    json.start(&target_dir.display().to_string(), total, block)?;
+    let result = bench_with(args, target_dir, total, block, &mut json, false);
+    if let Err(e) = &result {
+        let _ = json.error(e);
+    }
+    exit_on_failures(result?)
+}
+
+/// Informational line: stdout in interactive runs, stderr when stdout is reserved for JSON.
+fn note(interactive: bool, msg: String) {
+    if interactive { println!("{msg}"); } else { eprintln!("{msg}"); }
+}
+
+fn bench_tui(mut args: Args, mut term: tui::Terminal, total: u64, block: u64) -> io::Result<()> {
+    let target_dir = match args.target_dir.clone() {
+        Some(p) => p,
+        None => {
+            let candidates = picker_devices(&args);
+            if candidates.is_empty() {
+                // Nothing to pick from; fall back to the plain path prompt.
+                drop(term);
+                let p = choose_target_dir(&args)?;
+                term = tui::Terminal::enter()?;
+                p
+            } else {
+                let p = match candidates.as_slice() {
+                    [(_, only)] if args.auto => only.clone(),
+                    _ => tui::pick_device(&term, &candidates)?,
+                };
+                if args.allow_internal && is_internal(&p) {
+                    // The confirmation is a plain prompt; leave the dashboard screen for it.
+                    drop(term);
+                    let label = candidates.iter().find(|(_, c)| *c == p).map(|(l, _)| l.as_str()).unwrap_or_default();
+                    confirm_internal(label)?;
+                    term = tui::Terminal::enter()?;
+                }
+                p
+            }
+        }
+    };
+    let problems = fs_problems(&target_dir);
+    if !problems.is_empty() {
+        drop(term);
+        confirm_fs_health(&problems)?;
+        term = tui::Terminal::enter()?;
+    }
+    if !args.confirmed && destructive_action(&args, &target_dir, total).is_some() {
+        drop(term);
+        confirm_destructive(&mut args, &target_dir, total)?;
+        term = tui::Terminal::enter()?;
+    }
+    let mut dash = tui::Dashboard::new(term, &target_dir, device_info(&target_dir), total, block);
+    exit_on_failures(bench_with(args, target_dir, total, block, &mut dash, true)?)
+}
+
+/// Runs the write/read phases; `interactive` enables the results box and logging prompt.
+fn bench_with(
+    mut args: Args, target_dir: PathBuf, total: u64, block: u64, progress: &mut dyn Progress, interactive: bool,
+) -> io::Result<BenchResult> {
+    // Repeats, `watch` and the server run many times in one process; each gets its own files.
+    let _run = artifacts::begin_run();
+    let raw = destructive::device_node(&target_dir);
+    if raw.is_some() { refuse_on_raw(&args)?; }
+    confirm_destructive(&mut args, &target_dir, total)?;
+    let mut config = BenchConfig::new(&target_dir);
+    config.size = total;
+    config.block = block;
+    config.auto_block = args.auto_block;
+    config.keep_file = args.keep;
+    config.vectored = args.engine == Some(Engine::Vectored);
+    config.direct = args.sync.unwrap_or(SyncMode::Direct) == SyncMode::Direct;
+    config.iterations = args.iterations.unwrap_or(1);
+    config.random = args.random;
+    config.verify = args.verify;
+    config.files = args.files.unwrap_or(1);
+    config.access_time = args.access_time;
+    config.sweep_qd = args.sweep_qd.clone();
+    config.fsync_ops = args.fsync.unwrap_or(0);
+    config.read_pattern = args.read_pattern.unwrap_or_default();
+    config.shuffled = args.shuffled;
+    config.cached_read = args.cached_read;
+    config.preallocate = args.preallocate;
+    config.sustained = args.sustained;
+    config.abort_below = args.abort_below;
+    config.drop_cache = args.network;
+    config.trim = args.trim;
+    config.trim_compare = args.trim_compare;
+    config.corruption_check = args.corruption_check;
+    config.compression_check = args.compression_check;
+    config.offset_map = args.offset_map || args.offset_chart.is_some();
+    config.small_files = if args.network { NETWORK_SMALL_FILES } else { 0 };
+    for (k, v) in &args.tag {
+        // A key given twice keeps its last value.
+        config.tags.retain(|(t, _)| t != k);
+        config.tags.push((k.clone(), v.clone()));
+    }
+    config.thresholds = Thresholds {
+        min_write: args.min_write,
+        min_read: args.min_read,
+        max_write_latency: args.max_write_latency,
+        max_read_latency: args.max_read_latency,
+    };
+    // A device node isn't on a volume; the one holding /dev would be described instead.
+    let info = if raw.is_some() { None } else { device_info(&target_dir) };
+    if !args.network && info.as_ref().is_some_and(is_network) {
+        warn!("{} is on a network share; --network measures it with suitable settings", target_dir.display());
+    }
+    // The picker only offers USB drives unless asked; a directory given by hand could be anywhere.
+    if !args.network && args.target_dir.is_some() {
+        if let Some(b) = info.as_ref().and_then(bus).filter(|b| !matches!(b, Bus::Usb | Bus::Sd)) {
+            warn!("{} is on a {b} drive, not a USB one; the results describe that drive", target_dir.display());
+        }
+    }
+    let misaligned: Vec<String> = info.as_ref().map(partition_alignment).unwrap_or_default().into_iter()
+        .filter(|p| !p.aligned())
+        .map(|p| format!("{} starts at byte {}, not on a {} boundary", p.name, p.start_bytes, units::size(p.boundary)))
+        .collect();
+    if !interactive {
+        let stale = artifacts::leftovers(&target_dir);
+        if !stale.is_empty() {
+            warn!("{} file(s) left by earlier runs in {}; `crabwise clean` removes them", stale.len(), target_dir.display());
+        }
+        for m in &misaligned { warn!("partition {m}; small and random writes will suffer"); }
+        for p in fs_problems(&target_dir) { warn!("{p}; check it with fsck/chkdsk before writing to it"); }
+    }
+    let runner = BenchRunner::new(config);
+    let test_path = raw.clone().unwrap_or_else(|| runner.test_path());
+    let outcome = match &raw {
+        Some(d) => runner.run_with(&mut RawDeviceBackend::new(d), progress),
+        None => runner.run(progress),
+    };
+    let mut rec = match outcome {
+        Ok(rec) => rec,
+        Err(e) => {
+            notify::send_all(&args.notify, &target_dir, Err(&e));
+            return Err(e);
+        }
+    };
+
+    let block = rec.block_bytes;
+    let (written, write_secs) = (rec.write.bytes, rec.write.seconds);
+    let (read_total, read_secs) = (rec.read.bytes, rec.read.seconds);
     let w_mbs = mbs(written as u128, write_secs);
-    let w_mbps = mbps(written as u128, write_secs);
     let r_mbs = mbs(read_total as u128, read_secs);
-    let r_mbps = mbps(read_total as u128, read_secs);
+    let (w_p99, r_p99) = (rec.write.latency_p99_ms / 1e3, rec.read.latency_p99_ms / 1e3);
+    let failures = rec.threshold_failures.clone().unwrap_or_default();
+    let has_thresholds = args.min_write.is_some() || args.min_read.is_some()
+        || args.max_write_latency.is_some() || args.max_read_latency.is_some();
+
+    if interactive {
+        let top = "╔".to_string() + &"═".repeat(46) + "╗";
+        let mid = "╚".to_string() + &"═".repeat(46) + "╝";
+        say!("\n{}", top);
+        say!("║{:^46}║", if args.network { tr!("Network Share Benchmark Results") } else { tr!("USB Benchmark Results") });
+        say!("{}", mid);
 
-    let top = "╔".to_string() + &"═".repeat(46) + "╗";
-    let mid = "╚".to_string() + &"═".repeat(46) + "╝";
-    println!("\n{}", top);
-    println!("║{:^46}║", "USB Benchmark Results");
-    println!("{}", mid);
+        match &raw {
+            Some(d) => say!("{:<8} {}", tr!("Device:"), d.display()),
+            None => say!("{:<8} {} — {}", tr!("Device:"), target_dir.display(), test_path.parent().unwrap_or(&target_dir).display()),
+        }
+        if args.network {
+            if let Some(i) = &info { say!("{:<8} {} ({})", tr!("Share:"), i.name, i.file_system); }
+        }
+        if let Some(f) = &rec.fingerprint { say!("{:<8} {}", tr!("Drive:"), f); }
+        if let Some(p) = &rec.usb_power {
+            say!("{:<8} {}", tr!("Power:"), p.summary());
+            if p.short() { say!("{:<8} {}", "", tr!("more than the port supplies; use a powered hub")); }
+        }
+        say!("{:<8} {}", tr!("Test:"), test_path.display());
+        say!("{:<8} {}", tr!("Size:"), units::size(total));
+        match &rec.block_tune {
+            None => say!("{:<8} {}", tr!("Block:"), units::size(block)),
+            Some(t) => {
+                say!("{:<8} {} ({})", tr!("Block:"), units::size(block), tr!("auto-tuned"));
+                for p in &t.probes {
+                    let mark = if p.block == t.chosen { "  <" } else { "" };
+                    say!("{:<8} {:>9}  write {}  read {}{mark}", "", units::size(p.block), units::rate_w(p.write_mb_s, 9), units::rate_w(p.read_mb_s, 9));
+                }
+            }
+        }
+        if let Some(ok) = rec.preallocated {
+            say!("{:<8} {}", tr!("Alloc:"), if ok { "preallocated before each write pass" } else { "not possible on this filesystem; writes include allocation" });
+        }
+        if let Some(f) = &rec.fragmentation {
+            say!("{:<8} {}", tr!("Layout:"), f.summary());
+            if f.likely_affects() { say!("{:<8} {}", "", tr!("fragmented enough to slow sequential reads; free up space and rerun")); }
+        }
+        if !rec.tags.is_empty() { say!("{:<8} {}", tr!("Tags:"), rec.tags_text()); }
+        if rec.iterations > 1 { say!("{:<8} {:>6} {}", tr!("Passes:"), rec.iterations, tr!("(aggregated)")); }
+        if args.engine == Some(Engine::Vectored) {
+            say!("{:<8} vectored (up to {} blocks per call)", tr!("Engine:"), crabwise_core::config::VECTORED_BLOCKS);
+        }
+        if rec.read_pattern != ReadPattern::Sequential { say!("{:<8} {} reads", tr!("Pattern:"), rec.read_pattern); }
+        if args.trim || args.trim_compare {
+            let support = match rec.discard { Some(true) => "supported", Some(false) => "not supported by the device", None => "support unknown" };
+            let done = rec.trimmed_bytes.map(|n| format!(", {} trimmed after the run", units::size(n))).unwrap_or_default();
+            say!("{:<8} {support}{done}", tr!("TRIM:"));
+        }
+
+        let log_path = args.log.clone().unwrap_or_else(|| target_dir.join(LOG_FILE_NAME));
+        let prev = history::previous(&rec, args.record.as_deref(), &log_path);
+        // ", ▼ 31% vs 2024-05-01 run (142.30 MB/s)" after each rate when the drive was seen before.
+        let vs = |now: f64, before: fn(&history::Previous) -> f64| prev.as_ref()
+            .and_then(|p| history::change(now, before(p)).map(|c| format!(", {}", tr!("{} vs {} run ({})", c, p.date, units::rate(before(p))))))
+            .unwrap_or_default();
+        say!("\n{:<6} {} in {:>6.2}s{}", tr!("WRITE:"), units::rate_full(w_mbs, 9), write_secs, vs(w_mbs, |p| p.write_mb_s));
+        say!("{:<6} {} in {:>6.2}s{}\n", tr!("READ:"), units::rate_full(r_mbs, 9), read_secs, vs(r_mbs, |p| p.read_mb_s));
+        let steady = |p: &crabwise_core::PhaseStats| match (p.consistency_pct(), p.worst_sample_mb_s()) {
+            (Some(c), Some(w)) => format!("{c:>3.0}% (worst 1 s {})", units::rate(w)),
+            _ => "n/a (under 2 s)".to_string(),
+        };
+        if rec.write.consistency_pct().is_some() || rec.read.consistency_pct().is_some() {
+            say!("{:<14} write {}", tr!("Consistency:"), steady(&rec.write));
+            say!("{:<14} read  {}\n", "", steady(&rec.read));
+        }
+        say!("{:<14} avg {:>8.2} ms | p99 {:>8.2} ms", tr!("Write latency:"), rec.write.latency_avg_ms, w_p99 * 1e3);
+        say!("{:<14} avg {:>8.2} ms | p99 {:>8.2} ms", tr!("Read latency:"), rec.read.latency_avg_ms, r_p99 * 1e3);
+        say!("{:<14} write {:>8.0} | read {:>8.0} ({} per operation)", tr!("IOPS:"), rec.write.ops_per_s(), rec.read.ops_per_s(), units::size(block));
+        let cpu = |p: &crabwise_core::PhaseStats| match p.cpu {
+            Some(c) => format!("{:>4.0}% process | {:>3.0}% system | busiest core {:>3.0}%", c.process_pct, c.system_pct, c.busiest_core_pct),
+            None => "n/a".to_string(),
+        };
+        say!("{:<14} write {}", tr!("Host CPU:"), cpu(&rec.write));
+        say!("{:<14} read  {}\n", "", cpu(&rec.read));
+
+        if let Some(c) = &rec.compression {
+            say!("{:<8} zeros {} vs random {} ({:.2}x)", tr!("COMPR:"), units::rate(c.zero_mb_s), units::rate(c.random_mb_s), c.ratio());
+            say!("{:<8} {}\n", "", report::compression_verdict(c));
+        }
+        let io = crabwise_core::runner::RANDOM_IO_BYTES as u64;
+        for (name, p) in [(tr!("RAND W:"), &rec.random_write), (tr!("RAND R:"), &rec.random_read)] {
+            if let Some(p) = p {
+                say!("{:<8} {:>9.0} IOPS ({}) | p99 {:>8.2} ms", name, p.iops(io), units::rate_w(p.mb_s(), 7), p.latency_p99_ms);
+            }
+        }
+        for (qd, p) in &rec.qd_sweep {
+            say!("{:<8} {:>9.0} IOPS ({}) | p99 {:>8.2} ms", format!("QD {qd}:"), p.ops_per_s(), units::rate_w(p.mb_s(), 7), p.latency_p99_ms);
+        }
+        if let Some(v) = report::qd_verdict(&rec) { say!("{:<8} {}", tr!("QUEUE:"), v); }
+        if let (Some(w), Some(r), Some((ws, rs))) = (&rec.parallel_write, &rec.parallel_read, rec.parallel_scaling()) {
+            say!("{:<8} {} across {} files ({:.2}x single stream)", tr!("PARA W:"), units::rate_w(w.mb_s(), 9), rec.files, ws);
+            say!("{:<8} {} across {} files ({:.2}x single stream)", tr!("PARA R:"), units::rate_w(r.mb_s(), 9), rec.files, rs);
+        }
+        if let (Some(p), Some(x)) = (&rec.cached_read, rec.cache_speedup()) {
+            say!("{:<8} {} from the OS cache, {:.1}x the device read", tr!("CACHED:"), units::rate_w(p.mb_s(), 9), x);
+        }
+        if let Some(p) = &rec.shuffled_read {
+            say!("{:<8} {} | p99 {:>8.2} ms", tr!("SHUFFLE:"), units::rate_w(p.mb_s(), 9), p.latency_p99_ms);
+            if let Some(pen) = rec.random_penalty_pct() {
+                let dir = if pen >= 0.0 { "slower" } else { "faster" };
+                say!("{:<8} {:.1}% {dir} than sequential reads", tr!("PENALTY:"), pen.abs());
+            }
+        }
+        if let Some(p) = &rec.sustained {
+            let low = p.worst_sample_mb_s().unwrap_or(p.mb_s());
+            let steady = p.consistency_pct().map(|c| format!(", {c:.0}% consistency")).unwrap_or_default();
+            say!("{:<8} {} over {:.0}s (lowest 1 s sample {}{steady})", tr!("SUSTAIN:"), units::rate_w(p.mb_s(), 9), p.seconds, units::rate(low));
+        }
+        let small = crabwise_core::runner::SMALL_FILE_BYTES as u64;
+        for (name, p) in [(tr!("SMALL W:"), &rec.small_write), (tr!("SMALL R:"), &rec.small_read)] {
+            if let Some(p) = p {
+                say!("{:<8} {:>9.0} files/s ({}) | p99 {:>8.2} ms", name, p.iops(small), units::rate_w(p.mb_s(), 7), p.latency_p99_ms);
+            }
+        }
+        if let (Some(d), Some(t)) = (&rec.dirty_write, &rec.trimmed_write) {
+            say!("{:<8} {} over just-freed blocks", tr!("DIRTY W:"), units::rate_w(d.mb_s(), 9));
+            let gain = rec.trim_gain_pct().map(|g| format!(" ({:.1}% {} after TRIM)", g.abs(), if g >= 0.0 { "faster" } else { "slower" })).unwrap_or_default();
+            say!("{:<8} {}{gain}", tr!("TRIM W:"), units::rate_w(t.mb_s(), 9));
+        }
+        if let Some(p) = &rec.round_trip {
+            say!("{:<8} open+close avg {:>8.2} ms | p99 {:>8.2} ms", tr!("RTT:"), p.latency_avg_ms, p.latency_p99_ms);
+        }
+        if let Some(l) = &rec.access {
+            say!("{:<8} {}", tr!("ACCESS:"), report::latency_summary(l));
+            say!("{:<8} {} × {} reads ({})", "", l.ops, units::size(l.io_bytes), report::access_kind(l));
+        }
+        if let Some(l) = &rec.fsync {
+            say!("{:<8} {}", tr!("FSYNC:"), report::latency_summary(l));
+            say!("{:<8} {} × {} write+fsync, {:.0}/s", "", l.ops, units::size(l.io_bytes), l.ops_per_s());
+        }
+        match rec.verify_mismatches {
+            Some(0) => say!("{:<8} all {} bytes read back intact", tr!("VERIFY:"), read_total),
+            Some(n) => say!("{:<8} {} byte(s) differ from what was written", tr!("VERIFY:"), n),
+            None => {}
+        }
+        match rec.metadata_changes.as_deref() {
+            Some([]) => say!("{:<8} boot sectors and superblocks unchanged, no new problems", tr!("FS META:")),
+            Some(changes) => for c in changes { say!("{:<8} {c}", tr!("FS META:")); },
+            None => {}
+        }
+        for (label, map) in [(tr!("MAP W:"), &rec.write_map), (tr!("MAP R:"), &rec.read_map)] {
+            if let Some(m) = map {
+                say!("{:<8} {}", label, progress::offset_profile(m));
+                say!("{:<8} {}", "", report::offset_summary(m));
+            }
+        }
+        // Known-device expectations are for USB drives; a share's speed is the server's and the link's.
+        let expected = if args.network { None } else { known::annotate(&rec) };
+        if let Some(e) = &expected { say!("{:<8} {}", tr!("EXPECT:"), e); }
+        if rec.random_write.is_some() || !rec.qd_sweep.is_empty() || rec.parallel_write.is_some() || rec.cached_read.is_some() || rec.shuffled_read.is_some() || rec.access.is_some() || rec.sustained.is_some() || rec.small_write.is_some() || rec.fsync.is_some() || rec.dirty_write.is_some() || rec.verify_mismatches.is_some() || rec.metadata_changes.is_some() || rec.write_map.is_some() || rec.read_map.is_some() || expected.is_some() { say!(); }
 
-    println!("{:<8} {} — {}", "Device:", target_dir.display(), test_path.parent().unwrap_or(&target_dir).display());
-    println!("{:<8} {}", "Test:", test_path.display());
-    println!("{:<8} {:>6.2} GiB", "Size:", size_gib);
-    println!("{:<8} {:>6.2} MiB", "Block:", block_mib);
+        let bound: Vec<&str> = [("write", &rec.write), ("read", &rec.read)].into_iter()
+            .filter(|(_, p)| p.cpu.is_some_and(|c| c.host_bound()))
+            .map(|(n, _)| n)
+            .collect();
+        if !bound.is_empty() {
+            say!("{:<8} a CPU core was near 100% during {}; the host, not the drive, may be the bottleneck\n", tr!("HOST:"), bound.join(" and "));
+        }
+        if !misaligned.is_empty() {
+            for m in &misaligned { say!("{:<8} partition {m}", tr!("ALIGN:")); }
+            say!("{:<8} misaligned partitions split writes across flash pages; repartition to fix small/random writes\n", "");
+        }
+        if !rec.transforms.is_empty() {
+            for t in &rec.transforms { say!("{:<8} {}", tr!("VOLUME:"), t.note()); }
+            say!("{:<8} compare only with runs on a volume set up the same way\n", "");
+        }
 
-    println!("\n{:<6} {:>9.2} MB/s ({:>8.2} Mbps) in {:>6.2}s", "WRITE:", w_mbs, w_mbps, write_secs);
-    println!("{:<6} {:>9.2} MB/s ({:>8.2} Mbps) in {:>6.2}s\n", "READ:",  r_mbs, r_mbps, read_secs);
+        if !failures.is_empty() {
+            for f in &failures { say!("{:<8} {}", tr!("FAIL:"), f); }
+            say!();
+        } else if has_thresholds {
+            say!("{:<8} {}\n", tr!("PASS:"), tr!("all thresholds met"));
+        }
 
-    println!("{}", "═".repeat(48));
+        say!("{}", "═".repeat(48));
+    }
 
     // --- Optional logging ---
-    if prompt_yes_no("Save results to USB root?")? {
-        let mut session = prompt_line("Enter session name")?;
+    let save_prompt = match &args.log {
+        Some(p) => tr!("Save results to {}?", p.display()),
+        None if args.network => tr!("Save results to the share root?").to_string(),
+        None => tr!("Save results to USB root?").to_string(),
+    };
+    if interactive && prompt_yes_no(&save_prompt)? {
+        let mut session = prompt_line(tr!("Enter session name"))?;
         if session.is_empty() {
             session = Local::now().format("session-%Y%m%d-%H%M%S").to_string();
         }
-        let log_path = target_dir.join("crabwise.log");
-        let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
-        let line = format!(
-            "{:<30} | {:>7.2} Mbps | {:>7.2} Mbps | {}\n",
-            session, r_mbps, w_mbps, ts
-        );
-        let mut f = OpenOptions::new().create(true).append(true).open(&log_path)?;
-        f.write_all(line.as_bytes())?;
-        f.flush()?;
-        f.sync_all()?;
-        println!("Saved log entry to {}", log_path.display());
+        rec.session = Some(session.clone());
+        let log_path = args.log.clone().unwrap_or_else(|| target_dir.join(LOG_FILE_NAME));
+        append_log(&log_path, &session, &rec)?;
+        println!("{}", tr!("Saved log entry to {}", log_path.display()));
         if let Ok(contents) = std::fs::read_to_string(&log_path) {
-            println!("\n=== crabwise.log ===\n{}", contents);
+            let name = log_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            println!("\n=== {} ===\n{}", name, contents);
         }
     }
 
-    if !args.keep {
-        let _ = std::fs::remove_file(&test_path);
+    if let Some(path) = &args.chart {
+        let (w, r) = (rec.write.points(), rec.read.points());
+        chart::write_chart(path, &format!("Throughput over time — {}", target_dir.display()), chart::TIME_AXIS, &[
+            chart::Series { name: "Write", color: chart::WRITE_COLOR, points: &w },
+            chart::Series { name: "Read", color: chart::READ_COLOR, points: &r },
+        ])?;
+        note(interactive, format!("Saved throughput chart to {}", path.display()));
+    }
+    if let (Some(path), Some(wm), Some(rm)) = (&args.offset_chart, &rec.write_map, &rec.read_map) {
+        let (w, r) = (wm.points(), rm.points());
+        chart::write_chart(path, &format!("Speed by offset — {}", target_dir.display()), chart::OFFSET_AXIS, &[
+            chart::Series { name: "Write", color: chart::WRITE_COLOR, points: &w },
+            chart::Series { name: "Read", color: chart::READ_COLOR, points: &r },
+        ])?;
+        note(interactive, format!("Saved offset chart to {}", path.display()));
+    }
+    if let Some(path) = &args.samples {
+        samples::write(path, &rec)?;
+        note(interactive, format!("Saved samples to {}", path.display()));
+    }
+    if let Some(path) = &args.record {
+        result::append(path, &rec)?;
+        note(interactive, format!("Recorded results to {}", path.display()));
+    }
+    if args.submit && args.network {
+        warn!("--submit skipped: community results are for USB drives, not network shares");
+    } else if args.submit { community::submit(args.results_service.as_deref(), &rec); }
+    notify::send_all(&args.notify, &target_dir, Ok(&rec));
+
+    Ok(rec)
+}
+
+/// Appends one session line to a `crabwise.log`-style table; SI lines keep the original Mbps columns.
+/// The drive's fingerprint (`drive=...`) and the tags, when known, go in a last column.
+fn append_log(log_path: &std::path::Path, session: &str, rec: &BenchResult) -> io::Result<()> {
+    let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
+    let rate = |p: &crabwise_core::PhaseStats| match units::get() {
+        units::Units::Si => format!("{:>7.2} Mbps", p.mbps()),
+        _ => units::rate_w(p.mb_s(), 7),
+    };
+    let iops = format!("{:>6.0}/{:<6.0} IOPS", rec.read.ops_per_s(), rec.write.ops_per_s());
+    let extra: Vec<String> = rec.fingerprint.iter().map(|f| format!("drive={f}")).chain((!rec.tags.is_empty()).then(|| rec.tags_text())).collect();
+    let tags = if extra.is_empty() { String::new() } else { format!(" | {}", extra.join(" ")) };
+    let line = format!("{:<30} | {} | {} | {} | {}{tags}\n", session, rate(&rec.read), rate(&rec.write), iops, ts);
+    let mut f = OpenOptions::new().create(true).append(true).open(log_path)?;
+    f.write_all(line.as_bytes())?;
+    f.flush()?;
+    f.sync_all()
+}
+
+/// Exits with `EXIT_THRESHOLD_FAILED` when the run missed a threshold or failed verification.
+fn exit_on_failures(rec: BenchResult) -> io::Result<()> {
+    if rec.threshold_failures.is_some_and(|f| !f.is_empty()) {
+        std::process::exit(EXIT_THRESHOLD_FAILED);
     }
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compares `got` with the file `src/golden/<name>`; with `CRABWISE_BLESS=1` set, writes
+    /// `got` there instead, for output that changed on purpose.
+    pub(crate) fn assert_golden(name: &str, got: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/golden").join(name);
+        if std::env::var_os("CRABWISE_BLESS").is_some() {
+            std::fs::write(&path, got).unwrap();
+            return;
+        }
+        let want = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e} (CRABWISE_BLESS=1 creates it)", path.display()));
+        assert!(got == want, "{name} differs from {}; rerun with CRABWISE_BLESS=1 if the change is intended\n{got}", path.display());
+    }
+
+    fn parse(line: &str) -> Result<Cli, clap::Error> {
+        parse_cli(line.split_whitespace().map(Into::into))
+    }
+
+    #[test]
+    fn global_flags_go_before_any_subcommand() {
+        for sub in Cli::command().get_subcommands().map(|s| s.get_name().to_string()) {
+            for flag in ["-v", "-q", "--plain", "--units binary", "--lang de"] {
+                // --help stops parsing before the subcommand's required arguments are checked.
+                let e = parse(&format!("crabwise {flag} {sub} --help")).unwrap_err();
+                assert_eq!(e.kind(), clap::error::ErrorKind::DisplayHelp, "{flag} {sub}: {e}");
+                assert!(e.to_string().contains(&format!("Usage: crabwise {sub}")), "{flag} {sub} printed the wrong help");
+            }
+        }
+        let cli = parse("crabwise -vv --units binary scan /dev/sdb").unwrap();
+        assert_eq!((cli.verbose, cli.units), (2, Some(units::Units::Binary)));
+        assert!(matches!(cli.command, Some(Command::Scan(_))));
+    }
+
+    #[test]
+    fn benchmark_flags_need_no_subcommand() {
+        let cli = parse("crabwise -v /media/usb --size 64M").unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!((cli.bench.target_dir.as_deref(), cli.bench.size), (Some(Path::new("/media/usb")), Some("64M".to_string())));
+        let e = parse("crabwise --size 1G scan /dev/sdb").unwrap_err();
+        assert_eq!(e.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(parse("crabwise /media/usb verify FILE").is_err());
+    }
+
+    #[test]
+    fn runs_that_fill_or_overwrite_need_confirming() {
+        let dir = std::env::temp_dir();
+        let small = Args::default();
+        assert!(destructive_action(&small, &dir, 1 << 20).is_none());
+        let sustained = Args { sustained: Some(10.0), ..Args::default() };
+        let (action, device) = destructive_action(&sustained, &dir, 1 << 20).unwrap();
+        assert!(action.contains("with a sustained write"), "{action}");
+        assert_eq!(device, None, "a fill is confirmed with the word, not the drive");
+        assert!(destructive_action(&small, &dir, u64::MAX).is_some_and(|(a, _)| a.contains("with the test file")));
+
+        let mut unattended = Args { unattended: true, ..sustained };
+        assert!(confirm_destructive(&mut unattended, &dir, 1 << 20).is_err());
+        unattended.yes = Some("FILL".into());
+        confirm_destructive(&mut unattended, &dir, 1 << 20).unwrap();
+        assert!(unattended.confirmed);
+        #[cfg(unix)]
+        assert!(destructive_action(&small, Path::new("/dev/null"), 1 << 20).is_some_and(|(_, d)| d.as_deref() == Some(Path::new("/dev/null"))));
+    }
+
+    #[test]
+    fn raw_targets_refuse_filesystem_options() {
+        assert!(refuse_on_raw(&Args::default()).is_ok());
+        let err = refuse_on_raw(&Args { keep: true, ..Args::default() }).unwrap_err();
+        assert_eq!(err.to_string(), "--keep needs a filesystem; it can't be used with a device node as the target");
+    }
+}
@@ -0,0 +1,145 @@
+//! `crabwise man`: a roff man page rendered from the clap definitions, for packagers
+//! (`crabwise man > crabwise.1`, or `--out DIR` for one page per subcommand).
+
+use clap::{Arg, Command, CommandFactory};
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct ManArgs {
+    /// Write crabwise.1 and crabwise-<subcommand>.1 into DIR instead of printing crabwise.1
+    #[arg(long, value_name="DIR")]
+    out: Option<PathBuf>,
+}
+
+/// Escapes text for roff: backslashes, hyphens, and control characters at line starts.
+fn roff(s: &str) -> String {
+    s.lines()
+        .map(|l| {
+            let l = l.replace('\\', r"\e").replace('-', r"\-");
+            if l.starts_with('.') || l.starts_with('\'') { format!(r"\&{l}") } else { l }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn help(s: Option<&clap::builder::StyledStr>) -> String {
+    s.map(|s| s.to_string()).unwrap_or_default()
+}
+
+fn value_name(arg: &Arg) -> String {
+    arg.get_value_names().and_then(|n| n.first()).map(|n| n.to_string()).unwrap_or_else(|| arg.get_id().as_str().to_ascii_uppercase())
+}
+
+fn option(page: &mut String, arg: &Arg) {
+    let mut names = Vec::new();
+    if let Some(c) = arg.get_short() { names.push(format!(r"\fB\-{c}\fR")); }
+    if let Some(l) = arg.get_long() { names.push(format!(r"\fB\-\-{}\fR", roff(l))); }
+    let mut head = names.join(", ");
+    if arg.is_positional() {
+        head = format!(r"\fI{}\fR", roff(&value_name(arg)));
+    } else if arg.get_action().takes_values() {
+        let _ = write!(head, r" \fI{}\fR", roff(&value_name(arg)));
+    }
+    let _ = writeln!(page, ".TP\n{head}");
+    // A blank line would be a paragraph break in roff.
+    let text = help(arg.get_long_help().or(arg.get_help()));
+    if !text.is_empty() { let _ = writeln!(page, "{}", roff(&text)); }
+    let values: Vec<_> = arg.get_possible_values().into_iter().filter(|v| !v.is_hide_set()).collect();
+    if !values.is_empty() {
+        let list = values.iter().map(|v| match v.get_help() {
+            Some(h) => format!(r"\fB{}\fR: {}", roff(v.get_name()), roff(&h.to_string())),
+            None => format!(r"\fB{}\fR", roff(v.get_name())),
+        }).collect::<Vec<_>>().join(".\n.br\n");
+        let _ = writeln!(page, ".br\nValues:\n.br\n{list}");
+    }
+}
+
+/// One page: NAME, SYNOPSIS, DESCRIPTION, OPTIONS, and SUBCOMMANDS for the top level.
+fn page(cmd: &Command, name: &str) -> String {
+    let mut p = String::new();
+    let date = chrono::Local::now().format("%Y-%m-%d");
+    let _ = writeln!(p, ".TH {} 1 \"{date}\" \"crabwise {}\" \"User Commands\"", roff(&name.to_ascii_uppercase()), env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(p, ".SH NAME\n{} \\- {}", roff(name), roff(&help(cmd.get_about())));
+    let usage = cmd.clone().render_usage().to_string();
+    let usage = usage.trim_start_matches("Usage: ").lines().map(|l| roff(l.trim())).collect::<Vec<_>>().join("\n.br\n");
+    let _ = writeln!(p, ".SH SYNOPSIS\n{usage}");
+    if let Some(long) = cmd.get_long_about() { let _ = writeln!(p, ".SH DESCRIPTION\n{}", roff(&long.to_string())); }
+    let args: Vec<&Arg> = cmd.get_arguments().filter(|a| !a.is_hide_set()).collect();
+    if args.iter().any(|a| a.is_positional()) {
+        p.push_str(".SH ARGUMENTS\n");
+        for a in args.iter().filter(|a| a.is_positional()) { option(&mut p, a); }
+    }
+    p.push_str(".SH OPTIONS\n");
+    for a in args.iter().filter(|a| !a.is_positional()) { option(&mut p, a); }
+    let subs: Vec<&Command> = cmd.get_subcommands().filter(|s| !s.is_hide_set() && s.get_name() != "help").collect();
+    if !subs.is_empty() {
+        p.push_str(".SH SUBCOMMANDS\n");
+        for s in &subs {
+            let _ = writeln!(p, ".TP\n\\fB{}\\fR\n{}\n.br\nSee \\fB{}\\-{}\\fR(1).", roff(s.get_name()), roff(&help(s.get_about())), roff(name), roff(s.get_name()));
+        }
+    }
+    p
+}
+
+pub fn run(args: ManArgs) -> io::Result<()> {
+    let mut cmd = crate::Cli::command().name("crabwise");
+    cmd.build();
+    let Some(dir) = args.out else {
+        print!("{}", page(&cmd, "crabwise"));
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("crabwise.1"), page(&cmd, "crabwise"))?;
+    let mut written = 1;
+    for sub in cmd.get_subcommands().filter(|s| !s.is_hide_set() && s.get_name() != "help") {
+        let name = format!("crabwise-{}", sub.get_name());
+        std::fs::write(dir.join(format!("{name}.1")), page(sub, &name))?;
+        written += 1;
+    }
+    println!("Wrote {written} man page(s) to {}", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::assert_golden;
+    use clap::ArgAction;
+
+    fn fixture() -> Command {
+        let mut cmd = Command::new("crabwise").about("USB drive benchmark")
+            .long_about("Benchmarks a drive.\n.dotted lines and back\\slashes are escaped.")
+            .arg(Arg::new("target_dir").value_name("DIR").help("Directory to benchmark"))
+            .arg(Arg::new("size").short('s').long("size").value_name("SIZE").help("Test file size (e.g., 512M, 1G)"))
+            .arg(Arg::new("sync").long("sync").value_parser([
+                clap::builder::PossibleValue::new("direct").help("Bypass the page cache"),
+                clap::builder::PossibleValue::new("buffered"),
+            ]).help("How writes reach the drive"))
+            .arg(Arg::new("keep-file").long("keep-file").action(ArgAction::SetTrue).help("Keep the test file"))
+            .subcommand(Command::new("report").about("Summarize recorded runs").arg(Arg::new("input").value_name("FILE")));
+        cmd.build();
+        cmd
+    }
+
+    /// `page` without its first line, which carries today's date.
+    fn undated(page: &str) -> &str {
+        let (th, rest) = page.split_once('\n').unwrap();
+        assert!(th.starts_with(".TH ") && th.ends_with(" \"User Commands\""), "{th}");
+        rest
+    }
+
+    #[test]
+    fn pages_match_golden_files() {
+        let cmd = fixture();
+        assert_golden("crabwise.1", undated(&page(&cmd, "crabwise")));
+        let report = cmd.find_subcommand("report").unwrap();
+        assert_golden("crabwise-report.1", undated(&page(report, "crabwise-report")));
+    }
+
+    #[test]
+    fn roff_escapes() {
+        assert_eq!(roff("-s, --size\n.TH\n'quoted\nC:\\dir"), "\\-s, \\-\\-size\n\\&.TH\n\\&'quoted\nC:\\edir");
+    }
+}
@@ -0,0 +1,139 @@
+//! Prometheus `/metrics` for `serve` and `--repeat-every`: the latest run per device, as gauges.
+
+use crate::http;
+use crabwise_core::device::{self, device_info, DeviceInfo};
+use crabwise_core::{BenchResult, PhaseStats};
+use std::fmt::Write as _;
+use std::io;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Each device is identified by its serial, or by its device name when the OS doesn't expose one.
+struct DeviceMetrics {
+    serial: String,
+    info: Option<DeviceInfo>,
+    last: Option<BenchResult>,
+    last_unix: f64,
+    completed: u64,
+    errors: u64,
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    devices: Vec<DeviceMetrics>,
+}
+
+pub type Shared = Arc<Mutex<Metrics>>;
+
+fn lock(m: &Shared) -> std::sync::MutexGuard<'_, Metrics> {
+    m.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+impl Metrics {
+    /// Records one finished run; `None` means it ended in an I/O error.
+    pub fn record(&mut self, target_dir: &Path, rec: Option<&BenchResult>) {
+        let info = rec.and_then(|r| r.device.clone()).or_else(|| device_info(target_dir));
+        let serial = info.as_ref()
+            .map(|i| device::serial(i).unwrap_or_else(|| i.name.clone()))
+            .unwrap_or_else(|| target_dir.display().to_string());
+        let i = match self.devices.iter().position(|d| d.serial == serial) {
+            Some(i) => i,
+            None => {
+                self.devices.push(DeviceMetrics { serial, info: None, last: None, last_unix: 0.0, completed: 0, errors: 0 });
+                self.devices.len() - 1
+            }
+        };
+        let d = &mut self.devices[i];
+        if info.is_some() { d.info = info; }
+        d.last_unix = SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs_f64()).unwrap_or(0.0);
+        match rec {
+            Some(r) => { d.completed += 1; d.last = Some(r.clone()); }
+            None => d.errors += 1,
+        }
+    }
+
+    /// Prometheus text exposition format. Temperatures are read at scrape time.
+    pub fn render(&self) -> String {
+        let mut families: Vec<(&str, &str, &str, Vec<String>)> = vec![
+            ("crabwise_runs_total", "counter", "Benchmark runs by outcome (error = aborted by an I/O error).", vec![]),
+            ("crabwise_last_run_timestamp_seconds", "gauge", "Unix time the most recent run finished.", vec![]),
+            ("crabwise_throughput_bytes_per_second", "gauge", "Throughput of the last completed run, per phase.", vec![]),
+            ("crabwise_latency_seconds", "gauge", "Per-operation latency percentile of the last completed run.", vec![]),
+            ("crabwise_latency_avg_seconds", "gauge", "Mean per-operation latency of the last completed run.", vec![]),
+            ("crabwise_threshold_failures", "gauge", "Thresholds (and verification) missed by the last completed run.", vec![]),
+            ("crabwise_verify_mismatched_bytes", "gauge", "Bytes that read back differently in the last verified run.", vec![]),
+            ("crabwise_device_temperature_celsius", "gauge", "Drive temperature from its own sensor.", vec![]),
+            ("crabwise_iops", "gauge", "I/O operations per second of the last completed run, per phase.", vec![]),
+        ];
+        for d in &self.devices {
+            let base = labels(d);
+            let with = |extra: &str| format!("{base},{extra}");
+            families[0].3.push(format!("{{{}}} {}", with("outcome=\"completed\""), d.completed));
+            families[0].3.push(format!("{{{}}} {}", with("outcome=\"error\""), d.errors));
+            families[1].3.push(format!("{{{base}}} {}", d.last_unix));
+            if let Some(r) = &d.last {
+                for (phase, p) in phases(r) {
+                    let l = with(&format!("phase=\"{phase}\""));
+                    families[2].3.push(format!("{{{l}}} {}", p.mb_s() * 1e6));
+                    families[3].3.push(format!("{{{l},quantile=\"0.99\"}} {}", p.latency_p99_ms / 1e3));
+                    families[4].3.push(format!("{{{l}}} {}", p.latency_avg_ms / 1e3));
+                    families[8].3.push(format!("{{{l}}} {}", p.ops_per_s()));
+                }
+                families[5].3.push(format!("{{{base}}} {}", r.threshold_failures.as_ref().map_or(0, Vec::len)));
+                if let Some(n) = r.verify_mismatches { families[6].3.push(format!("{{{base}}} {n}")); }
+            }
+            if let Some(t) = d.info.as_ref().and_then(device::temperature) {
+                families[7].3.push(format!("{{{base}}} {t}"));
+            }
+        }
+        let mut out = String::new();
+        for (name, kind, help, samples) in families {
+            if samples.is_empty() { continue; }
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+            for s in samples { let _ = writeln!(out, "{name}{s}"); }
+        }
+        out
+    }
+}
+
+fn labels(d: &DeviceMetrics) -> String {
+    let esc = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    let mut l = format!("serial=\"{}\"", esc(&d.serial));
+    if let Some(i) = &d.info {
+        let _ = write!(l, ",device=\"{}\",mount_point=\"{}\"", esc(&i.name), esc(&i.mount_point));
+    }
+    l
+}
+
+fn phases(r: &BenchResult) -> Vec<(&'static str, &PhaseStats)> {
+    let mut v = vec![("write", &r.write), ("read", &r.read)];
+    let optional = [("random_write", &r.random_write), ("random_read", &r.random_read), ("parallel_write", &r.parallel_write),
+        ("parallel_read", &r.parallel_read), ("shuffled_read", &r.shuffled_read), ("cached_read", &r.cached_read),
+        ("sustained", &r.sustained), ("dirty_write", &r.dirty_write), ("trimmed_write", &r.trimmed_write)];
+    v.extend(optional.into_iter().filter_map(|(n, p)| p.as_ref().map(|p| (n, p))));
+    v
+}
+
+/// Answers the `/metrics` route; shared by `serve` and the standalone exporter.
+pub fn respond(out: &mut impl io::Write, m: &Shared) -> io::Result<()> {
+    let body = lock(m).render();
+    http::respond(out, 200, "text/plain; version=0.0.4", body.as_bytes())
+}
+
+/// Serves only `/metrics` on a background thread (for `--repeat-every`).
+pub fn spawn(addr: &str, m: Shared) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).map_err(|e| io::Error::new(e.kind(), format!("{addr}: {e}")))?;
+    eprintln!("Serving metrics on http://{}/metrics", listener.local_addr()?);
+    std::thread::spawn(move || http::serve(listener, move |req, stream| match (req.method.as_str(), req.path.as_str()) {
+        ("GET", "/metrics") => respond(stream, &m),
+        (_, "/metrics") => http::error(stream, 405, "method not allowed"),
+        _ => http::error(stream, 404, format!("no endpoint {}", req.path)),
+    }));
+    Ok(())
+}
+
+pub fn record(m: &Shared, target_dir: &Path, rec: Option<&BenchResult>) {
+    lock(m).record(target_dir, rec);
+}
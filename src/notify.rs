@@ -0,0 +1,101 @@
+//! `--notify`: tells someone when a long run finishes, by webhook or desktop notification.
+//!
+//! Both go through tools the OS already has (`curl`, `notify-send`, `osascript`,
+//! PowerShell) rather than an HTTP/TLS or D-Bus stack in the binary.
+
+use crate::{http, units};
+use crabwise_core::json::Value;
+use crabwise_core::{debug, warn, BenchResult};
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notify {
+    /// POST a JSON payload to this URL.
+    Webhook(String),
+    Desktop,
+}
+
+/// Parses `desktop` or `webhook:<http(s) url>`.
+pub fn parse_notify(s: &str) -> Result<Notify, String> {
+    if s.eq_ignore_ascii_case("desktop") { return Ok(Notify::Desktop); }
+    match s.strip_prefix("webhook:") {
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => Ok(Notify::Webhook(url.to_string())),
+        Some(url) => Err(format!("webhook URL '{url}' must start with http:// or https://")),
+        None => Err(format!("invalid notify target '{s}' (expected desktop or webhook:<url>)")),
+    }
+}
+
+/// One-line result summary used as the notification body.
+fn summary(outcome: Result<&BenchResult, &io::Error>) -> String {
+    match outcome {
+        Ok(r) => {
+            let mut s = format!("write {}, read {}", units::rate(r.write.mb_s()), units::rate(r.read.mb_s()));
+            match &r.threshold_failures {
+                Some(f) if f.is_empty() => s.push_str(" — PASS"),
+                Some(f) => s.push_str(&format!(" — FAIL: {}", f.join("; "))),
+                None => {}
+            }
+            s
+        }
+        Err(e) => format!("error: {e}"),
+    }
+}
+
+/// Sends every notification; failures are reported on stderr and never fail the run.
+pub fn send_all(targets: &[Notify], target_dir: &Path, outcome: Result<&BenchResult, &io::Error>) {
+    if targets.is_empty() { return; }
+    let failed = outcome.is_err() || outcome.is_ok_and(|r| r.threshold_failures.as_ref().is_some_and(|f| !f.is_empty()));
+    let title = format!("crabwise: {} {}", target_dir.display(), if failed { "failed" } else { "finished" });
+    let body = summary(outcome);
+    for t in targets {
+        debug!("notifying {t:?}");
+        let sent = match t {
+            Notify::Webhook(url) => {
+                let payload = Value::object(vec![
+                    ("event", if outcome.is_ok() { "completed" } else { "error" }.into()),
+                    ("passed", (!failed).into()),
+                    ("host", sysinfo::System::host_name().into()),
+                    ("target_dir", target_dir.display().to_string().into()),
+                    ("summary", body.as_str().into()),
+                    ("result", outcome.ok().map(BenchResult::to_json).unwrap_or(Value::Null)),
+                    ("error", outcome.err().map(|e| e.to_string()).into()),
+                ]);
+                http::fetch("POST", url, Some(&payload.to_string())).map(drop)
+            }
+            Notify::Desktop => desktop(&title, &body),
+        };
+        if let Err(e) = sent { warn!("notify: {e}"); }
+    }
+}
+
+fn desktop(title: &str, body: &str) -> io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut cmd = {
+        let mut c = Command::new("osascript");
+        c.args(["-e", "on run argv", "-e", "display notification (item 2 of argv) with title (item 1 of argv)", "-e", "end run", title, body]);
+        c
+    };
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        // Text goes through the environment so nothing needs quoting inside the script.
+        let script = "Add-Type -AssemblyName System.Windows.Forms; $n = New-Object System.Windows.Forms.NotifyIcon; \
+            $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+            $n.ShowBalloonTip(10000, $env:CRABWISE_TITLE, $env:CRABWISE_BODY, 'Info'); Start-Sleep -Seconds 10; $n.Dispose()";
+        let mut c = Command::new("powershell");
+        c.args(["-NoProfile", "-WindowStyle", "Hidden", "-Command", script]).env("CRABWISE_TITLE", title).env("CRABWISE_BODY", body);
+        c
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut cmd = {
+        let mut c = Command::new("notify-send");
+        c.args(["-a", "crabwise", title, body]);
+        c
+    };
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let status = cmd.stdout(Stdio::null()).status()
+        .map_err(|e| io::Error::new(e.kind(), format!("desktop notification needs {program} ({e})")))?;
+    if !status.success() { return Err(io::Error::other(format!("{program} failed ({status})"))); }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! `--preset`: named bundles of settings for people who don't want to learn every flag.
+//!
+//! A preset only fills in settings that were not given on the command line, and it
+//! is applied before the config file, so explicit flags > preset > config > defaults.
+
+use crate::Args;
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// 256 MiB sequential write/read, single pass
+    Quick,
+    /// 1 GiB sequential write/read plus the 4 KiB random test
+    Standard,
+    /// 1 GiB sequential x3 with verification, the 4 KiB random test, and a 2-minute sustained write
+    Thorough,
+}
+
+pub fn apply(args: &mut Args) {
+    let Some(preset) = args.preset else { return };
+    let (size, block, iterations) = match preset {
+        Preset::Quick => ("256M", "4M", 1),
+        Preset::Standard => ("1G", "4M", 1),
+        Preset::Thorough => ("1G", "4M", 3),
+    };
+    args.size.get_or_insert_with(|| size.to_string());
+    args.block.get_or_insert_with(|| block.to_string());
+    args.iterations.get_or_insert(iterations);
+    match preset {
+        Preset::Quick => {}
+        Preset::Standard => args.random = true,
+        Preset::Thorough => {
+            args.random = true;
+            args.verify = true;
+            args.sustained.get_or_insert(120.0);
+        }
+    }
+}
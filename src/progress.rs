@@ -0,0 +1,184 @@
+//! Terminal and JSON frontends for the runner's progress callbacks.
+
+use crabwise_core::json::Value;
+use crabwise_core::offsetmap::OffsetMap;
+use crabwise_core::sampler::smoothed;
+use crabwise_core::stats::mbs;
+use crabwise_core::{BenchResult, Phase, Progress, Sample, Thresholds, Update};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Number of recent samples shown in the progress sparkline.
+const SPARKLINE_WIDTH: usize = 20;
+
+/// Renders the most recent samples as a block-character sparkline scaled to the window's peak.
+pub fn sparkline(samples: &[Sample], width: usize) -> String {
+    let recent = &samples[samples.len().saturating_sub(width)..];
+    bars(&recent.iter().map(|s| s.mb_s).collect::<Vec<_>>())
+}
+
+/// An offset map as one line of bars, each the slowest of the regions it covers so a slow
+/// stretch isn't averaged away.
+pub fn offset_profile(m: &OffsetMap) -> String {
+    const COLUMNS: usize = 50;
+    bars(&m.mb_s.chunks(m.mb_s.len().div_ceil(COLUMNS).max(1)).map(|c| c.iter().copied().fold(f64::INFINITY, f64::min)).collect::<Vec<_>>())
+}
+
+/// One block character per value, scaled to the peak.
+pub fn bars(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let peak = values.iter().copied().fold(0.0, f64::max);
+    values.iter().map(|&v| {
+        if peak <= 0.0 { BARS[0] } else { BARS[((v / peak) * 7.0).round() as usize] }
+    }).collect()
+}
+
+/// Time left at `mb_s` for `bytes`, as `m:ss` or `h:mm:ss`; `--:--` when stalled.
+pub fn eta(bytes: u64, mb_s: f64) -> String {
+    if mb_s <= 0.0 { return "--:--".to_string(); }
+    let s = (bytes as f64 / 1e6 / mb_s).round() as u64;
+    if s >= 3600 { format!("{}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60) } else { format!("{}:{:02}", s / 60, s % 60) }
+}
+
+/// Current (smoothed) rate, the average since the phase started and the time left at the current rate.
+fn print_progress(prefix: &str, done: u64, total: u64, start: Instant, samples: &[Sample]) {
+    let pct = (done as f64 / total as f64) * 100.0;
+    let elapsed = start.elapsed().as_secs_f64();
+    let avg = if elapsed > 0.0 {
+        (done as f64 / 1_000_000f64) / elapsed
+    } else { 0.0 };
+    let now = smoothed(samples).unwrap_or(avg);
+    let line = format!("\r{prefix}... {pct:5.1}% ({}, avg {}, ETA {}) {:<width$}", crate::units::rate(now), crate::units::rate(avg),
+        eta(total.saturating_sub(done), now), sparkline(samples, SPARKLINE_WIDTH), width = SPARKLINE_WIDTH);
+    print!("{}", crate::style::text(&line));
+    let _ = std::io::stdout().flush();
+}
+
+fn finish_progress() { println!(); }
+
+/// The classic carriage-return progress line.
+pub struct LineProgress {
+    /// Tiny test sizes finish instantly; skip the progress line for those.
+    pub enabled: bool,
+}
+
+impl Progress for LineProgress {
+    fn update(&mut self, u: &Update) -> io::Result<()> {
+        if self.enabled { print_progress(u.phase.label(), u.done, u.total, u.start, u.samples); }
+        Ok(())
+    }
+
+    fn finish(&mut self, _phase: Phase) -> io::Result<()> {
+        if self.enabled { finish_progress(); }
+        Ok(())
+    }
+}
+
+/// `--format json`: no live progress, just the final result object on stdout.
+pub struct ResultJson;
+
+impl Progress for ResultJson {
+    fn update(&mut self, _u: &Update) -> io::Result<()> { Ok(()) }
+    fn finish(&mut self, _phase: Phase) -> io::Result<()> { Ok(()) }
+
+    fn done(&mut self, rec: &BenchResult) -> io::Result<()> {
+        println!("{}", rec.to_json());
+        Ok(())
+    }
+}
+
+/// Prints the result as JUnit XML once the run completes (`--format junit`).
+pub struct ResultJunit {
+    /// The limits the run is checked against, each reported as its own test case.
+    pub thresholds: Thresholds,
+}
+
+impl Progress for ResultJunit {
+    fn update(&mut self, _u: &Update) -> io::Result<()> { Ok(()) }
+    fn finish(&mut self, _phase: Phase) -> io::Result<()> { Ok(()) }
+
+    fn done(&mut self, rec: &BenchResult) -> io::Result<()> {
+        print!("{}", crate::junit::render(rec, &self.thresholds));
+        Ok(())
+    }
+}
+
+/// Minimum spacing between `progress` events in the JSON stream.
+const JSON_EVERY: Duration = Duration::from_millis(250);
+
+/// Newline-delimited JSON events for GUI frontends (`--progress-json`).
+///
+/// Events: `start`, `phase_start`, `progress`, `phase_end`, `result`, `error`.
+pub struct JsonProgress {
+    out: Box<dyn Write>,
+    phase: Option<Phase>,
+    last_emit: Option<Instant>,
+    /// (bytes done, elapsed seconds) from the latest update, for `phase_end`.
+    last: (u64, f64),
+}
+
+impl JsonProgress {
+    pub fn new(out: Box<dyn Write>) -> JsonProgress {
+        JsonProgress { out, phase: None, last_emit: None, last: (0, 0.0) }
+    }
+
+    fn emit(&mut self, event: &str, mut fields: Vec<(&str, Value)>) -> io::Result<()> {
+        fields.insert(0, ("event", event.into()));
+        writeln!(self.out, "{}", Value::object(fields))?;
+        self.out.flush()
+    }
+
+    pub fn start(&mut self, target_dir: &str, total: u64, block: u64) -> io::Result<()> {
+        self.emit("start", vec![
+            ("target_dir", target_dir.into()),
+            ("bytes_total", total.into()),
+            ("block_bytes", block.into()),
+        ])
+    }
+
+    pub fn error(&mut self, e: &io::Error) -> io::Result<()> {
+        self.emit("error", vec![("message", e.to_string().into())])
+    }
+}
+
+impl Progress for JsonProgress {
+    fn update(&mut self, u: &Update) -> io::Result<()> {
+        if self.phase != Some(u.phase) {
+            self.phase = Some(u.phase);
+            self.last_emit = None;
+            self.emit("phase_start", vec![("phase", u.phase.id().into())])?;
+        }
+        let elapsed = u.start.elapsed().as_secs_f64();
+        self.last = (u.done, elapsed);
+        let due = self.last_emit.is_none_or(|t| t.elapsed() >= JSON_EVERY);
+        if !due && u.done < u.total { return Ok(()); }
+        self.last_emit = Some(Instant::now());
+        let avg = if elapsed > 0.0 { u.done as f64 / 1e6 / elapsed } else { 0.0 };
+        let current = smoothed(u.samples).unwrap_or(avg);
+        let eta = if current > 0.0 { Value::from(u.total.saturating_sub(u.done) as f64 / 1e6 / current) } else { Value::Null };
+        self.emit("progress", vec![
+            ("phase", u.phase.id().into()),
+            ("bytes_done", u.done.into()),
+            ("bytes_total", u.total.into()),
+            ("percent", (u.done as f64 / u.total as f64 * 100.0).into()),
+            ("elapsed_s", elapsed.into()),
+            ("speed_mb_s", current.into()),
+            ("avg_mb_s", avg.into()),
+            ("eta_s", eta),
+        ])
+    }
+
+    fn finish(&mut self, phase: Phase) -> io::Result<()> {
+        let (bytes, secs) = self.last;
+        self.emit("phase_end", vec![
+            ("phase", phase.id().into()),
+            ("bytes", bytes.into()),
+            ("seconds", secs.into()),
+            ("mb_s", mbs(bytes as u128, secs).into()),
+        ])
+    }
+
+    fn done(&mut self, rec: &BenchResult) -> io::Result<()> {
+        self.emit("result", vec![("result", rec.to_json())])
+    }
+}
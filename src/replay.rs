@@ -0,0 +1,84 @@
+//! `crabwise replay TRACE DIR`: issues the reads and writes of a recorded I/O trace (crabwise
+//! CSV, blkparse output or ftrace `block_rq_issue` events) against a test file in DIR and
+//! reports throughput and latency per trace phase.
+
+use crate::progress::LineProgress;
+use crate::{units, SyncMode};
+use crabwise_core::artifacts;
+use crabwise_core::json::Value;
+use crabwise_core::parse::parse_size;
+use crabwise_core::trace::{self, PhaseReplay, ReplayOptions};
+use std::io;
+use std::path::PathBuf;
+
+/// Largest test file sized from the trace when `--size` isn't given.
+const MAX_REGION: u64 = 1 << 30;
+
+#[derive(clap::Args, Debug)]
+pub struct ReplayArgs {
+    /// Trace to replay: crabwise CSV (time,op,offset,length[,phase]), blkparse output or an ftrace block_rq_issue log
+    trace: PathBuf,
+    /// Directory on the device to replay against
+    target_dir: PathBuf,
+
+    /// Test file size; the trace's offsets wrap around within it [default: the trace's span, at most 1G]
+    #[arg(short='s', long, value_parser=parse_size)]
+    size: Option<u64>,
+
+    /// Keep the trace's timing, idle gaps included, instead of issuing each I/O as soon as the last completes
+    #[arg(long)]
+    paced: bool,
+
+    /// I/O mode, as for the benchmark [default: direct]
+    #[arg(long, value_enum)]
+    sync: Option<SyncMode>,
+
+    /// Also write the per-phase results to FILE as JSON
+    #[arg(short='o', long, value_name="FILE")]
+    out: Option<PathBuf>,
+
+    /// Keep the test file afterwards
+    #[arg(long)]
+    keep: bool,
+}
+
+pub fn run(a: ReplayArgs) -> io::Result<()> {
+    let text = std::fs::read_to_string(&a.trace).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", a.trace.display())))?;
+    let trace = trace::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", a.trace.display())))?;
+    let region = a.size.unwrap_or_else(|| trace.span().next_multiple_of(1 << 20).min(MAX_REGION));
+    let reads = trace.ios.iter().filter(|io| io.op == trace::Op::Read).count();
+    println!(
+        "Replaying {} ({} trace): {} I/Os ({reads} reads, {} writes) over {:.1} s in {} phase(s), against {} of {}",
+        a.trace.display(), trace.format, trace.ios.len(), trace.ios.len() - reads, trace.duration(), trace.phases.len(),
+        units::size(region), a.target_dir.display()
+    );
+    if trace.span() > region {
+        println!("The trace spans {}; offsets wrap around within the test file.", units::size(trace.span()));
+    }
+
+    std::fs::create_dir_all(&a.target_dir)?;
+    let path = a.target_dir.join(artifacts::name(".tmp"));
+    let opts = ReplayOptions { region, paced: a.paced, direct: a.sync.unwrap_or(SyncMode::Direct) == SyncMode::Direct };
+    let outcome = trace::replay(&trace, &path, opts, &mut LineProgress { enabled: true });
+    if !a.keep { let _ = std::fs::remove_file(&path); }
+    let phases = outcome?;
+
+    println!("\n{:<16} {:>7} {:>12} {:>9}  {:>17}  {:>17}", "Phase", "I/Os", "Throughput", "IOPS", "read p50/p99 ms", "write p50/p99 ms");
+    for p in &phases {
+        let lat = |s: &Option<crabwise_core::LatencyStats>| s.as_ref().map_or("-".to_string(), |s| format!("{:.2} / {:.2}", s.p50_ms, s.p99_ms));
+        println!("{:<16} {:>7} {} {:>9.0}  {:>17}  {:>17}", p.name, p.ops(), units::rate_w(p.mb_s(), 12), p.iops(), lat(&p.reads), lat(&p.writes));
+    }
+    if let Some(out) = &a.out {
+        let doc = Value::object(vec![
+            ("trace", a.trace.display().to_string().into()),
+            ("format", trace.format.into()),
+            ("target_dir", a.target_dir.display().to_string().into()),
+            ("size_bytes", region.into()),
+            ("paced", a.paced.into()),
+            ("phases", Value::Array(phases.iter().map(PhaseReplay::to_json).collect())),
+        ]);
+        std::fs::write(out, format!("{doc}\n"))?;
+        println!("\nWrote {}", out.display());
+    }
+    Ok(())
+}
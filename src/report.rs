@@ -0,0 +1,426 @@
+//! `crabwise report`: renders result records into a self-contained HTML or Markdown document.
+
+use crate::chart::{self, Series};
+use crate::units;
+use crabwise_core::config::ReadPattern;
+use crabwise_core::offsetmap::OffsetMap;
+use crabwise_core::result::{self, BenchResult, CompressionCheck, LatencyStats, PhaseStats};
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(clap::Args, Debug)]
+pub struct ReportArgs {
+    /// Result files written with --record (JSON lines)
+    #[arg(required = true)]
+    records: Vec<PathBuf>,
+
+    /// Output file; `.md`/`.markdown` renders Markdown, anything else HTML
+    #[arg(short = 'o', long)]
+    out: PathBuf,
+
+    /// Report title
+    #[arg(long, default_value = "Crabwise USB Benchmark Report")]
+    title: String,
+}
+
+pub fn run(args: ReportArgs) -> io::Result<()> {
+    let mut runs = Vec::new();
+    for p in &args.records {
+        runs.extend(result::load(p)?);
+    }
+    if runs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no result records found in the given files"));
+    }
+    let markdown = matches!(
+        args.out.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref(),
+        Some("md" | "markdown")
+    );
+    let doc = if markdown { render_markdown(&args.title, &runs) } else { render_html(&args.title, &runs) };
+    std::fs::write(&args.out, doc)?;
+    println!("Wrote report for {} run(s) to {}", runs.len(), args.out.display());
+    Ok(())
+}
+
+/// `cell` per `--units` rate column, filled from each decimal MB/s value in `mb_s`.
+fn rate_cells(mb_s: &[f64], cell: impl Fn(f64) -> String) -> String {
+    units::columns().iter().flat_map(|(_, f)| mb_s.iter().map(move |v| v * f)).map(cell).collect()
+}
+
+/// Header cells matching `rate_cells`, e.g. "Write MB/s", "Read MB/s", "Write Mbps", "Read Mbps".
+fn rate_headers(names: &[&str], cell: impl Fn(String) -> String) -> String {
+    units::columns().iter().flat_map(|(l, _)| names.iter().map(move |n| if n.is_empty() { l.to_string() } else { format!("{n} {l}") })).map(cell).collect()
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn md_escape(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+fn verdict(r: &BenchResult) -> &'static str {
+    match &r.threshold_failures {
+        None => "—",
+        Some(f) if f.is_empty() => "PASS",
+        Some(_) => "FAIL",
+    }
+}
+
+/// "93%" for the phase table, or "—" when the phase was too short to sample.
+fn consistency(p: &PhaseStats) -> String {
+    p.consistency_pct().map(|c| format!("{c:.0}%")).unwrap_or_else(|| "—".into())
+}
+
+/// Process / system CPU load, e.g. "45% / 12%", or "—" for records without it.
+fn cpu(p: &PhaseStats) -> String {
+    p.cpu.map(|c| format!("{:.0}% / {:.0}%", c.process_pct, c.system_pct)).unwrap_or_else(|| "—".into())
+}
+
+/// Every measured phase of a run, in the order they ran.
+fn phases(r: &BenchResult) -> Vec<(&'static str, &PhaseStats)> {
+    let mut v = vec![("Write", &r.write), ("Read", &r.read)];
+    let optional = [("Random 4K write", &r.random_write), ("Random 4K read", &r.random_read),
+        ("Parallel write", &r.parallel_write), ("Parallel read", &r.parallel_read), ("Shuffled read", &r.shuffled_read), ("Cached read", &r.cached_read), ("Sustained write", &r.sustained),
+        ("Small-file write", &r.small_write), ("Small-file read", &r.small_read), ("Open+close round trip", &r.round_trip),
+        ("Dirty rewrite", &r.dirty_write), ("Trimmed rewrite", &r.trimmed_write)];
+    v.extend(optional.into_iter().filter_map(|(n, p)| p.as_ref().map(|p| (n, p))));
+    v
+}
+
+/// Horizontal grouped bar chart of write/read throughput per run, as inline SVG.
+fn throughput_bars_svg(runs: &[BenchResult]) -> String {
+    let max = runs.iter().flat_map(|r| [r.write.mb_s(), r.read.mb_s()]).fold(1.0, f64::max);
+    let (label_w, bar_w, row_h) = (220.0, 460.0, 44.0);
+    let height = runs.len() as f64 * row_h + 40.0;
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{height}" font-family="sans-serif" font-size="12">"#,
+        w = label_w + bar_w + 90.0
+    );
+    for (i, r) in runs.iter().enumerate() {
+        let y = 10.0 + i as f64 * row_h;
+        let _ = write!(svg, r#"<text x="0" y="{}">{}</text>"#, y + 22.0, html_escape(&r.label()));
+        for (j, (v, color)) in [(r.write.mb_s(), "#d9534f"), (r.read.mb_s(), "#337ab7")].into_iter().enumerate() {
+            let by = y + j as f64 * 16.0;
+            let w = (v / max * bar_w).max(1.0);
+            let _ = write!(svg, r#"<rect x="{label_w}" y="{by}" width="{w:.1}" height="14" fill="{color}"/>"#);
+            let _ = write!(svg, r#"<text x="{:.1}" y="{:.1}">{:.1} {}</text>"#, label_w + w + 4.0, by + 11.0, units::scale(v), units::label());
+        }
+    }
+    let ly = height - 12.0;
+    let _ = write!(
+        svg,
+        r##"<rect x="{label_w}" y="{}" width="10" height="10" fill="#d9534f"/><text x="{}" y="{ly}">Write</text><rect x="{}" y="{}" width="10" height="10" fill="#337ab7"/><text x="{}" y="{ly}">Read</text></svg>"##,
+        ly - 9.0, label_w + 14.0, label_w + 70.0, ly - 9.0, label_w + 84.0
+    );
+    svg
+}
+
+/// What the compression check found, for one line of text.
+pub fn compression_verdict(c: &CompressionCheck) -> &'static str {
+    if c.suspected() {
+        "controller appears to compress/deduplicate — results with non-random data are unreliable (this run used random data)"
+    } else {
+        "no sign of compression or deduplication"
+    }
+}
+
+/// Gain from the deepest queues, as a multiple of the shallowest depth's IOPS, above which
+/// the drive or enclosure is taken to queue commands rather than serve them one at a time.
+const QUEUED_SCALING: f64 = 1.5;
+
+/// The queue-depth sweep's best gain and what it says about command queuing.
+pub fn qd_verdict(r: &BenchResult) -> Option<String> {
+    let (gain, qd) = r.qd_scaling()?;
+    let base = r.qd_sweep.iter().map(|(d, _)| *d).min()?;
+    let meaning = if gain >= QUEUED_SCALING {
+        "commands are queued (UASP or a native queue)"
+    } else {
+        "little gain from deeper queues; commands are served one at a time (BOT, or UASP without queuing)"
+    };
+    Some(format!("{gain:.2}x at QD {qd} over QD {base}: {meaning}"))
+}
+
+/// One `QD n: IOPS` entry per depth of the sweep.
+fn qd_list(r: &BenchResult) -> String {
+    r.qd_sweep.iter().map(|(qd, p)| format!("QD {qd}: {:.0} IOPS", p.ops_per_s())).collect::<Vec<_>>().join(", ")
+}
+
+/// Median access time (ms) below which the device has no seek to speak of.
+const ACCESS_FLASH_MS: f64 = 1.0;
+
+/// Average and percentiles of a latency distribution, for one line of text.
+pub fn latency_summary(l: &LatencyStats) -> String {
+    format!("avg {:.2} ms | p50 {:.2} ms | p99 {:.2} ms | p99.9 {:.2} ms | max {:.2} ms", l.avg_ms, l.p50_ms, l.p99_ms, l.p999_ms, l.max_ms)
+}
+
+/// What an access time suggests about the medium: a hard drive needs milliseconds to seek
+/// and wait for the platter; flash answers a random read in well under one.
+pub fn access_kind(l: &LatencyStats) -> &'static str {
+    if l.p50_ms < ACCESS_FLASH_MS { "no seek: flash" } else { "seeking: hard drive" }
+}
+
+/// `37–38%`: where region `i` of `m` lies in the test file.
+fn region_pct(m: &OffsetMap, i: usize) -> String {
+    let n = m.mb_s.len().max(1) as f64;
+    format!("{:.0}–{:.0}%", i as f64 * 100.0 / n, (i + 1) as f64 * 100.0 / n)
+}
+
+/// Slowest and fastest regions of an offset map against its median, for one line of text.
+pub fn offset_summary(m: &OffsetMap) -> String {
+    match (m.slowest(), m.fastest()) {
+        (Some((si, sv)), Some((fi, fv))) => format!(
+            "slowest {} at {} | fastest {} at {} | median {}",
+            units::rate(sv), region_pct(m, si), units::rate(fv), region_pct(m, fi), units::rate(m.median())
+        ),
+        _ => "no data".into(),
+    }
+}
+
+/// Write and read speed across the test file, one point per region.
+fn offset_map_svg(r: &BenchResult) -> String {
+    let (w, rd) = (r.write_map.as_ref().map(OffsetMap::points), r.read_map.as_ref().map(OffsetMap::points));
+    let series: Vec<Series> = [("Write", chart::WRITE_COLOR, &w), ("Read", chart::READ_COLOR, &rd)].into_iter()
+        .filter_map(|(name, color, p)| p.as_deref().map(|points| Series { name, color, points }))
+        .collect();
+    chart::line_chart_svg(&format!("Speed by offset — {}", r.label()), chart::OFFSET_AXIS, &series)
+}
+
+/// Write/read throughput-over-time chart for a single run.
+fn throughput_over_time_svg(r: &BenchResult) -> String {
+    let (w, rd) = (r.write.points(), r.read.points());
+    chart::line_chart_svg(
+        &format!("Throughput over time — {}", r.label()),
+        chart::TIME_AXIS,
+        &[
+            Series { name: "Write", color: chart::WRITE_COLOR, points: &w },
+            Series { name: "Read", color: chart::READ_COLOR, points: &rd },
+        ],
+    )
+}
+
+fn render_html(title: &str, runs: &[BenchResult]) -> String {
+    let mut h = String::new();
+    let _ = write!(
+        h,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{t}</title><style>\
+body{{font-family:sans-serif;margin:2em;color:#222}}table{{border-collapse:collapse;margin:1em 0}}\
+th,td{{border:1px solid #ccc;padding:4px 10px;text-align:left}}th{{background:#f2f2f2}}\
+td.num{{text-align:right}}.fail{{color:#c9302c;font-weight:bold}}.pass{{color:#3c763d;font-weight:bold}}\
+</style></head><body>\n<h1>{t}</h1>\n<p>Generated {ts} from {n} run(s).</p>\n",
+        t = html_escape(title),
+        ts = chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        n = runs.len()
+    );
+
+    let th = |s: String| format!("<th>{s}</th>");
+    let td = |v: f64| format!("<td class=\"num\">{v:.2}</td>");
+    let tagged = runs.iter().any(|r| !r.tags.is_empty());
+    let _ = writeln!(
+        h, "<h2>Summary</h2>\n<table><tr><th>Run</th><th>Timestamp</th><th>Device</th>{}{}<th>Result</th></tr>",
+        if tagged { "<th>Tags</th>" } else { "" }, rate_headers(&["Write", "Read"], th)
+    );
+    for r in runs {
+        let device = r.device.as_ref().map(|d| d.name.as_str()).unwrap_or("");
+        let tags = if tagged { format!("<td>{}</td>", html_escape(&r.tags_text())) } else { String::new() };
+        let v = verdict(r);
+        let _ = writeln!(
+            h,
+            "<tr><td>{}</td><td>{}</td><td>{}</td>{tags}{}<td class=\"{}\">{}</td></tr>",
+            html_escape(&r.label()), html_escape(&r.timestamp), html_escape(device),
+            rate_cells(&[r.write.mb_s(), r.read.mb_s()], td), v.to_ascii_lowercase(), v
+        );
+    }
+    h.push_str("</table>\n<h2>Throughput</h2>\n");
+    h.push_str(&throughput_bars_svg(runs));
+    h.push('\n');
+
+    for r in runs {
+        let _ = writeln!(h, "<h2>{}</h2>", html_escape(&r.label()));
+        h.push_str("<table>\n");
+        let mut row = |k: &str, v: String| { let _ = writeln!(h, "<tr><th>{k}</th><td>{}</td></tr>", html_escape(&v)); };
+        row("Timestamp", r.timestamp.clone());
+        if !r.tags.is_empty() { row("Tags", r.tags_text()); }
+        if let Some(f) = &r.fingerprint { row("Drive ID", f.clone()); }
+        if let Some(h) = &r.host { row("Host", h.summary()); }
+        if let Some(p) = &r.usb_power { row("USB power", p.summary()); }
+        if !r.transforms.is_empty() { row("Volume", r.transforms.iter().map(|t| t.note()).collect::<Vec<_>>().join("; ")); }
+        if let Some(d) = &r.device {
+            row("Device", d.name.clone());
+            row("Mount point", d.mount_point.clone());
+            row("File system", d.file_system.clone());
+            row("Capacity", format!("{}, {} free", units::size(d.total_bytes), units::size(d.available_bytes)));
+            row("Removable", if d.removable { "yes".into() } else { "no".into() });
+        }
+        row("Target", r.target_dir.clone());
+        row("Test file", r.test_file.clone());
+        row("Test size", units::size(r.size_bytes));
+        row("Block size", units::size(r.block_bytes));
+        if let Some(t) = &r.block_tune {
+            let probes: Vec<String> = t.probes.iter().map(|p| format!("{} {}", units::size(p.block), units::rate(p.mb_s()))).collect();
+            row("Block probes", format!("{} (auto-tuned)", probes.join(", ")));
+        }
+        if r.iterations > 1 { row("Passes", r.iterations.to_string()); }
+        if let Some(ok) = r.preallocated { row("Preallocated", if ok { "yes".into() } else { "not possible".into() }); }
+        if let Some(f) = &r.fragmentation {
+            row("File layout", if f.likely_affects() { format!("{} (slows sequential reads)", f.summary()) } else { f.summary() });
+        }
+        if r.read_pattern != ReadPattern::Sequential { row("Read pattern", r.read_pattern.to_string()); }
+        if let Some((w, rd)) = r.parallel_scaling() {
+            row("Parallel files", format!("{} (write {w:.2}x, read {rd:.2}x single stream)", r.files));
+        }
+        if let Some(pen) = r.random_penalty_pct() { row("Random penalty", format!("{pen:.1}% vs sequential read")); }
+        if let Some(x) = r.cache_speedup() { row("Cache speedup", format!("{x:.1}x the device read")); }
+        if let Some(g) = r.trim_gain_pct() { row("TRIM gain", format!("{g:+.1}% write speed after TRIM")); }
+        if let Some(n) = r.verify_mismatches {
+            row("Verify", if n == 0 { "intact".into() } else { format!("{n} byte(s) differ") });
+        }
+        if let Some(c) = &r.compression {
+            row("Compression check", format!("zeros {} vs random {} ({:.2}x): {}", units::rate(c.zero_mb_s), units::rate(c.random_mb_s), c.ratio(), compression_verdict(c)));
+        }
+        if !r.qd_sweep.is_empty() {
+            let verdict = qd_verdict(r).map(|v| format!(" — {v}")).unwrap_or_default();
+            row("Queue-depth sweep", format!("{}{verdict}", qd_list(r)));
+        }
+        if let Some(l) = &r.access {
+            row("Access time", format!("{} ({} reads, {})", latency_summary(l), l.ops, access_kind(l)));
+        }
+        if let Some(l) = &r.fsync {
+            row("Write+fsync latency", format!("{} ({} × {}, {:.0}/s)", latency_summary(l), l.ops, units::size(l.io_bytes), l.ops_per_s()));
+        }
+        if let Some(c) = &r.metadata_changes {
+            row("Metadata check", if c.is_empty() { "unchanged".into() } else { c.join("; ") });
+        }
+        if let Some(m) = &r.write_map { row("Write by offset", offset_summary(m)); }
+        if let Some(m) = &r.read_map { row("Read by offset", offset_summary(m)); }
+        let _ = writeln!(h, "</table>\n<table><tr><th>Phase</th>{}<th>IOPS</th><th>Consistency</th><th>CPU (proc/sys)</th><th>Time (s)</th><th>Latency avg (ms)</th><th>Latency p99 (ms)</th></tr>", rate_headers(&[""], th));
+        for (name, p) in phases(r) {
+            let _ = writeln!(
+                h,
+                "<tr><td>{name}</td>{}<td class=\"num\">{:.0}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.2}</td><td class=\"num\">{:.2}</td><td class=\"num\">{:.2}</td></tr>",
+                rate_cells(&[p.mb_s()], td), p.ops_per_s(), consistency(p), cpu(p), p.seconds, p.latency_avg_ms, p.latency_p99_ms
+            );
+        }
+        h.push_str("</table>\n");
+        if !r.write.samples.is_empty() || !r.read.samples.is_empty() {
+            h.push_str(&throughput_over_time_svg(r));
+            h.push('\n');
+        }
+        if r.write_map.is_some() || r.read_map.is_some() {
+            h.push_str(&offset_map_svg(r));
+            h.push('\n');
+        }
+        if let Some(f) = &r.threshold_failures {
+            if f.is_empty() {
+                h.push_str("<p class=\"pass\">All thresholds met.</p>\n");
+            } else {
+                h.push_str("<ul class=\"fail\">\n");
+                for msg in f { let _ = writeln!(h, "<li>{}</li>", html_escape(msg)); }
+                h.push_str("</ul>\n");
+            }
+        }
+    }
+    h.push_str("</body></html>\n");
+    h
+}
+
+fn render_markdown(title: &str, runs: &[BenchResult]) -> String {
+    let mut m = String::new();
+    let _ = writeln!(m, "# {title}\n");
+    let _ = writeln!(m, "Generated {} from {} run(s).\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), runs.len());
+    let th = |s: String| format!(" {s} |");
+    let td = |v: f64| format!(" {v:.2} |");
+    let tagged = runs.iter().any(|r| !r.tags.is_empty());
+    let _ = writeln!(
+        m, "## Summary\n\n| Run | Timestamp | Device |{}{} Result |\n|---|---|---|{}{}---|",
+        if tagged { " Tags |" } else { "" }, rate_headers(&["Write", "Read"], th),
+        if tagged { "---|" } else { "" }, rate_headers(&["", ""], |_| "---:|".into())
+    );
+    for r in runs {
+        let device = r.device.as_ref().map(|d| d.name.as_str()).unwrap_or("");
+        let tags = if tagged { format!(" {} |", md_escape(&r.tags_text())) } else { String::new() };
+        let _ = writeln!(
+            m,
+            "| {} | {} | {} |{tags}{} {} |",
+            md_escape(&r.label()), r.timestamp, md_escape(device),
+            rate_cells(&[r.write.mb_s(), r.read.mb_s()], td), verdict(r)
+        );
+    }
+    for r in runs {
+        let _ = writeln!(m, "\n## {}\n", r.label());
+        m.push_str("| | |\n|---|---|\n");
+        let mut row = |k: &str, v: String| { let _ = writeln!(m, "| {k} | {} |", md_escape(&v)); };
+        row("Timestamp", r.timestamp.clone());
+        if !r.tags.is_empty() { row("Tags", r.tags_text()); }
+        if let Some(f) = &r.fingerprint { row("Drive ID", f.clone()); }
+        if let Some(h) = &r.host { row("Host", h.summary()); }
+        if let Some(p) = &r.usb_power { row("USB power", p.summary()); }
+        if !r.transforms.is_empty() { row("Volume", r.transforms.iter().map(|t| t.note()).collect::<Vec<_>>().join("; ")); }
+        if let Some(d) = &r.device {
+            row("Device", d.name.clone());
+            row("Mount point", d.mount_point.clone());
+            row("File system", d.file_system.clone());
+            row("Capacity", format!("{}, {} free", units::size(d.total_bytes), units::size(d.available_bytes)));
+        }
+        row("Target", r.target_dir.clone());
+        row("Test size", units::size(r.size_bytes));
+        row("Block size", units::size(r.block_bytes));
+        if let Some(t) = &r.block_tune {
+            let probes: Vec<String> = t.probes.iter().map(|p| format!("{} {}", units::size(p.block), units::rate(p.mb_s()))).collect();
+            row("Block probes", format!("{} (auto-tuned)", probes.join(", ")));
+        }
+        if r.iterations > 1 { row("Passes", r.iterations.to_string()); }
+        if let Some(ok) = r.preallocated { row("Preallocated", if ok { "yes".into() } else { "not possible".into() }); }
+        if let Some(f) = &r.fragmentation {
+            row("File layout", if f.likely_affects() { format!("{} (slows sequential reads)", f.summary()) } else { f.summary() });
+        }
+        if r.read_pattern != ReadPattern::Sequential { row("Read pattern", r.read_pattern.to_string()); }
+        if let Some((w, rd)) = r.parallel_scaling() {
+            row("Parallel files", format!("{} (write {w:.2}x, read {rd:.2}x single stream)", r.files));
+        }
+        if let Some(pen) = r.random_penalty_pct() { row("Random penalty", format!("{pen:.1}% vs sequential read")); }
+        if let Some(x) = r.cache_speedup() { row("Cache speedup", format!("{x:.1}x the device read")); }
+        if let Some(g) = r.trim_gain_pct() { row("TRIM gain", format!("{g:+.1}% write speed after TRIM")); }
+        if let Some(n) = r.verify_mismatches {
+            row("Verify", if n == 0 { "intact".into() } else { format!("{n} byte(s) differ") });
+        }
+        if let Some(c) = &r.compression {
+            row("Compression check", format!("zeros {} vs random {} ({:.2}x): {}", units::rate(c.zero_mb_s), units::rate(c.random_mb_s), c.ratio(), compression_verdict(c)));
+        }
+        if !r.qd_sweep.is_empty() {
+            let verdict = qd_verdict(r).map(|v| format!(" — {v}")).unwrap_or_default();
+            row("Queue-depth sweep", format!("{}{verdict}", qd_list(r)));
+        }
+        if let Some(l) = &r.access {
+            row("Access time", format!("{} ({} reads, {})", latency_summary(l), l.ops, access_kind(l)));
+        }
+        if let Some(l) = &r.fsync {
+            row("Write+fsync latency", format!("{} ({} × {}, {:.0}/s)", latency_summary(l), l.ops, units::size(l.io_bytes), l.ops_per_s()));
+        }
+        if let Some(c) = &r.metadata_changes {
+            row("Metadata check", if c.is_empty() { "unchanged".into() } else { c.join("; ") });
+        }
+        if let Some(m) = &r.write_map { row("Write by offset", offset_summary(m)); }
+        if let Some(m) = &r.read_map { row("Read by offset", offset_summary(m)); }
+        let _ = writeln!(
+            m, "\n| Phase |{} IOPS | Consistency | CPU (proc/sys) | Time (s) | Latency avg (ms) | Latency p99 (ms) |\n|---|{}---:|---:|---:|---:|---:|---:|",
+            rate_headers(&[""], th), rate_headers(&[""], |_| "---:|".into())
+        );
+        for (name, p) in phases(r) {
+            let _ = writeln!(
+                m,
+                "| {name} |{} {:.0} | {} | {} | {:.2} | {:.2} | {:.2} |",
+                rate_cells(&[p.mb_s()], td), p.ops_per_s(), consistency(p), cpu(p), p.seconds, p.latency_avg_ms, p.latency_p99_ms
+            );
+        }
+        if let Some(f) = &r.threshold_failures {
+            m.push('\n');
+            if f.is_empty() { m.push_str("**PASS** — all thresholds met.\n"); }
+            for msg in f { let _ = writeln!(m, "- **FAIL**: {msg}"); }
+        }
+    }
+    m
+}
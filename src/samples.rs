@@ -0,0 +1,35 @@
+//! `--samples FILE`: the per-second throughput (and latency) samples of every phase as CSV,
+//! for plotting or analysis in a spreadsheet.
+
+use crabwise_core::{BenchResult, PhaseStats};
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+fn phases(r: &BenchResult) -> Vec<(String, &PhaseStats)> {
+    let mut v = vec![("write".to_string(), &r.write), ("read".to_string(), &r.read)];
+    let optional = [("random_write", &r.random_write), ("random_read", &r.random_read), ("parallel_write", &r.parallel_write),
+        ("parallel_read", &r.parallel_read), ("shuffled_read", &r.shuffled_read), ("cached_read", &r.cached_read), ("sustained", &r.sustained),
+        ("small_write", &r.small_write), ("small_read", &r.small_read), ("round_trip", &r.round_trip),
+        ("dirty_write", &r.dirty_write), ("trimmed_write", &r.trimmed_write)];
+    v.extend(optional.into_iter().filter_map(|(n, p)| p.as_ref().map(|p| (n.to_string(), p))));
+    v.extend(r.qd_sweep.iter().map(|(qd, p)| (format!("random_read_qd{qd}"), p)));
+    v
+}
+
+/// One row per sample: phase, seconds since the phase started, MB/s over the interval and the
+/// mean latency of the I/Os completed in it (empty where the phase doesn't time single I/Os).
+pub fn render(r: &BenchResult) -> String {
+    let mut out = String::from("phase,t_s,mb_s,latency_ms\n");
+    for (name, p) in phases(r) {
+        for s in &p.samples {
+            let latency = s.latency_ms.map(|l| format!("{l:.3}")).unwrap_or_default();
+            let _ = writeln!(out, "{name},{:.3},{:.2},{latency}", s.t, s.mb_s);
+        }
+    }
+    out
+}
+
+pub fn write(path: &Path, r: &BenchResult) -> io::Result<()> {
+    std::fs::write(path, render(r))
+}
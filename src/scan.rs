@@ -0,0 +1,126 @@
+//! `crabwise scan`: a read-only surface scan that lists unreadable and abnormally slow regions,
+//! of a whole device or file or of a mounted filesystem's free space.
+
+use crate::chart;
+use crate::progress::{offset_profile, LineProgress};
+use crate::report::offset_summary;
+use crate::units;
+use crabwise_core::parse::{parse_duration, parse_size};
+use crabwise_core::scan::{free_space, scan, RegionKind, ScanConfig, ScanReport};
+use std::fmt::Write as _;
+use std::io;
+use std::path::PathBuf;
+
+/// Regions listed on the terminal; the full list goes to `--out`.
+const SHOWN_REGIONS: usize = 20;
+
+#[derive(clap::Args, Debug)]
+pub struct ScanArgs {
+    /// Block device, partition or file to read, or a directory to read only its filesystem's free space (nothing is written)
+    target: PathBuf,
+
+    /// Bytes per read
+    #[arg(short='b', long, default_value="1M", value_parser=parse_size)]
+    block: u64,
+
+    /// Start scanning at this byte offset
+    #[arg(long, default_value="0", value_parser=parse_size)]
+    offset: u64,
+
+    /// Scan only this many bytes (needed where the target's size can't be read)
+    #[arg(long, value_parser=parse_size)]
+    length: Option<u64>,
+
+    /// Record a block as slow when one read takes longer than this
+    #[arg(long, value_name="DURATION", default_value="500ms", value_parser=parse_duration)]
+    slow: f64,
+
+    /// Also write the full report to FILE (.json, or Markdown otherwise)
+    #[arg(short='o', long, value_name="FILE")]
+    out: Option<PathBuf>,
+
+    /// Plot read speed against position in the scanned range to FILE (.svg or .png)
+    #[arg(long, value_name="FILE")]
+    chart: Option<PathBuf>,
+}
+
+/// Runs the scan; `Ok(false)` when any region was unreadable.
+pub fn run(a: ScanArgs) -> io::Result<bool> {
+    let mut cfg = ScanConfig { path: a.target.clone(), block: a.block, offset: a.offset, length: a.length, slow: a.slow, extents: Vec::new() };
+    if a.target.is_dir() {
+        if a.offset > 0 || a.length.is_some() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "--offset and --length apply to a device or file, not a free-space scan"));
+        }
+        crate::say!("Mapping the files under {}...", a.target.display());
+        let free = free_space(&a.target)?;
+        if free.extents.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::StorageFull, format!("{} has no free space to scan", free.mount_point.display())));
+        }
+        if free.unmapped > 0 {
+            crate::say!("{} of {} files and directories couldn't be mapped; their blocks are scanned as free space.", free.unmapped, free.files);
+        }
+        crate::say!(
+            "Scanning the free space of {} ({}, mounted at {}): {} in {} extent(s) (read-only)...",
+            free.device.display(), free.file_system, free.mount_point.display(), units::size(free.bytes()), free.extents.len()
+        );
+        cfg.path = free.device;
+        cfg.extents = free.extents;
+    } else {
+        crate::say!("Scanning {} (read-only)...", a.target.display());
+    }
+    let report = scan(&cfg, &mut LineProgress { enabled: true })?;
+    print_report(&report);
+    if let Some(out) = &a.out {
+        let doc = match out.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => format!("{}\n", report.to_json()),
+            _ => render_markdown(&report),
+        };
+        std::fs::write(out, doc)?;
+        println!("Wrote scan report to {}", out.display());
+    }
+    if let Some(path) = &a.chart {
+        let points = report.map.points();
+        chart::write_chart(path, &format!("Read speed by offset — {}", a.target.display()), chart::OFFSET_AXIS, &[
+            chart::Series { name: "Read", color: chart::READ_COLOR, points: &points },
+        ])?;
+        println!("Saved offset chart to {}", path.display());
+    }
+    Ok(report.bytes_of(RegionKind::Unreadable) == 0)
+}
+
+fn print_report(r: &ScanReport) {
+    crate::say!("\n{:<8} {} in {:.1} s ({})", "Scanned:", units::size(r.bytes), r.seconds, units::rate(r.mb_s()));
+    crate::say!("{:<8} median {:.2} ms, slowest {:.2} ms per {} read", "Latency:", r.latency_median_ms, r.latency_max_ms, units::size(r.block));
+    crate::say!("{:<8} {}", "Map:", offset_profile(&r.map));
+    crate::say!("{:<8} {}", "", offset_summary(&r.map));
+    for kind in [RegionKind::Unreadable, RegionKind::Slow] {
+        let n = r.regions.iter().filter(|g| g.kind == kind).count();
+        let label = if kind == RegionKind::Unreadable { "Bad:" } else { "Slow:" };
+        crate::say!("{:<8} {n} region(s), {}", label, units::size(r.bytes_of(kind)));
+    }
+    for g in r.regions.iter().take(SHOWN_REGIONS) {
+        crate::say!("  {:<10} {:>14} +{:<10} {:>9.1} ms{}", g.kind.id(), g.offset, g.len, g.worst_ms, g.error.as_ref().map(|e| format!("  {e}")).unwrap_or_default());
+    }
+    if r.regions.len() > SHOWN_REGIONS {
+        crate::say!("  … {} more (see --out)", r.regions.len() - SHOWN_REGIONS);
+    }
+    crate::say!("{:<8} {}", "Result:", if r.bytes_of(RegionKind::Unreadable) == 0 { "PASS" } else { "FAIL" });
+}
+
+fn render_markdown(r: &ScanReport) -> String {
+    let mut m = String::new();
+    let _ = writeln!(m, "# Surface scan: {}\n", r.path.display());
+    let from = if r.extents > 0 { format!("of free space in {} extents", r.extents) } else { format!("from offset {}", r.offset) };
+    let _ = writeln!(m, "{} {from} in {:.1} s ({}), {} reads.\n", units::size(r.bytes), r.seconds, units::rate(r.mb_s()), units::size(r.block));
+    let _ = writeln!(m, "- Unreadable: {}\n- Slow: {}\n- Latency: median {:.2} ms, slowest {:.2} ms\n- Read by offset: {}\n",
+        units::size(r.bytes_of(RegionKind::Unreadable)), units::size(r.bytes_of(RegionKind::Slow)), r.latency_median_ms, r.latency_max_ms, offset_summary(&r.map));
+    if r.regions.is_empty() {
+        m.push_str("No unreadable or slow regions.\n");
+        return m;
+    }
+    m.push_str("| Kind | Offset | Length | Worst (ms) | Error |\n|---|---:|---:|---:|---|\n");
+    for g in &r.regions {
+        let _ = writeln!(m, "| {} | {} | {} | {:.1} | {} |", g.kind.id(), g.offset, g.len, g.worst_ms, g.error.as_deref().unwrap_or("").replace('|', "\\|"));
+    }
+    m
+}
@@ -0,0 +1,174 @@
+//! `--repeat-every`: reruns the benchmark on a fixed cadence and summarises the trend.
+
+use crate::{bench_with, confirm_destructive, metrics, progress, units, Args, EXIT_THRESHOLD_FAILED};
+use chrono::Local;
+use crabwise_core::progress::{Phase, Progress, Update};
+use crabwise_core::{result, BenchResult};
+use crabwise_core::stats::linear_fit;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often the wait for the next run checks for Ctrl-C.
+const STOP_POLL: Duration = Duration::from_millis(200);
+
+/// Set by the first Ctrl-C: the current run stops and the loop ends with its summary.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+struct Run {
+    timestamp: String,
+    /// Hours since the first run started.
+    hours: f64,
+    write: f64,
+    read: f64,
+    failed: bool,
+    /// Why the run didn't finish; it has no figures then.
+    error: Option<String>,
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: libc::c_int) {
+    STOP.store(true, Ordering::SeqCst);
+    // A second Ctrl-C ends the process as usual.
+    unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL); }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn on_ctrl(kind: u32) -> windows_sys::Win32::Foundation::BOOL {
+    use windows_sys::Win32::System::Console::CTRL_C_EVENT;
+    // Handled (1) the first time; after that the default handler ends the process.
+    (kind == CTRL_C_EVENT && !STOP.swap(true, Ordering::SeqCst)) as _
+}
+
+/// Makes the first Ctrl-C end the loop rather than the process.
+fn catch_interrupt() {
+    #[cfg(unix)]
+    unsafe { libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t); }
+    #[cfg(windows)]
+    unsafe { windows_sys::Win32::System::Console::SetConsoleCtrlHandler(Some(on_ctrl), 1); }
+}
+
+/// Stops the run it reports for once Ctrl-C is pressed; the runner then removes its test file.
+struct Interruptible<'a>(&'a mut dyn Progress);
+
+impl Progress for Interruptible<'_> {
+    fn update(&mut self, u: &Update) -> io::Result<()> {
+        if STOP.load(Ordering::SeqCst) { return Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted")); }
+        self.0.update(u)
+    }
+
+    fn finish(&mut self, phase: Phase) -> io::Result<()> {
+        self.0.finish(phase)
+    }
+
+    fn done(&mut self, result: &BenchResult) -> io::Result<()> {
+        self.0.done(result)
+    }
+}
+
+pub fn run(mut args: Args, target_dir: PathBuf, total: u64, block: u64, every: f64) -> io::Result<()> {
+    let count = args.count;
+    // Recorded here rather than by bench_with so each record is labelled with its run number.
+    let record = args.record.take();
+    let label = count.map(|n| n.to_string()).unwrap_or_else(|| "∞".to_string());
+    crate::say!("Benchmarking {} every {} ({} runs)", target_dir.display(), fmt_interval(every), label);
+    let exporter = match args.metrics.take() {
+        Some(addr) => {
+            let m = metrics::Shared::default();
+            metrics::spawn(&addr, m.clone())?;
+            Some(m)
+        }
+        None => None,
+    };
+    // Ask once; every run after that is the one the user agreed to.
+    confirm_destructive(&mut args, &target_dir, total)?;
+    catch_interrupt();
+    let start = Instant::now();
+    let mut runs: Vec<Run> = Vec::new();
+    let mut i: u64 = 0;
+    while count.is_none_or(|n| i < n) && !STOP.load(Ordering::SeqCst) {
+        // Fixed cadence from the first start; an overrunning benchmark starts the next one immediately.
+        let due = start + Duration::from_secs_f64(every * i as f64);
+        while let Some(wait) = due.checked_duration_since(Instant::now()).filter(|_| !STOP.load(Ordering::SeqCst)) {
+            std::thread::sleep(wait.min(STOP_POLL));
+        }
+        if STOP.load(Ordering::SeqCst) { break; }
+        i += 1;
+        let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        crate::say!("\n[{timestamp}] Run {i}/{label}");
+        let hours = start.elapsed().as_secs_f64() / 3600.0;
+        let mut line_progress = progress::LineProgress { enabled: true };
+        let outcome = bench_with(args.clone(), target_dir.clone(), total, block, &mut Interruptible(&mut line_progress), false);
+        if STOP.load(Ordering::SeqCst) && outcome.is_err() {
+            crate::say!("\n  interrupted; run {i} not counted");
+            break;
+        }
+        if let Some(m) = &exporter { metrics::record(m, &target_dir, outcome.as_ref().ok()); }
+        // A run that fails outright (the enclosure dropped off the bus, an I/O error) is part
+        // of the trend being tracked, not a reason to stop tracking it.
+        let mut rec = match outcome {
+            Ok(rec) => rec,
+            Err(e) => {
+                crate::say!("\n  FAILED: {e}");
+                runs.push(Run { timestamp, hours, write: 0.0, read: 0.0, failed: true, error: Some(e.to_string()) });
+                continue;
+            }
+        };
+        rec.session = Some(format!("scheduled run {i}"));
+        if let Some(path) = &record { result::append(path, &rec)?; }
+        let failed = rec.threshold_failures.as_ref().is_some_and(|f| !f.is_empty());
+        runs.push(Run { timestamp: rec.timestamp.clone(), hours, write: rec.write.mb_s(), read: rec.read.mb_s(), failed, error: None });
+        let mut line = format!("  write {}, read {}", units::rate(rec.write.mb_s()), units::rate(rec.read.mb_s()));
+        if let (Some(w), Some(r)) = (slope(&runs, |r| r.write), slope(&runs, |r| r.read)) {
+            line.push_str(&format!("  (trend {:+.2} / {:+.2} {} per hour)", units::scale(w), units::scale(r), units::label()));
+        }
+        if failed { line.push_str("  FAIL"); }
+        crate::say!("{line}");
+    }
+    print_summary(&runs);
+    if runs.iter().any(|r| r.failed) {
+        std::process::exit(EXIT_THRESHOLD_FAILED);
+    }
+    Ok(())
+}
+
+/// Per-hour trend over the runs that produced figures.
+fn slope(runs: &[Run], f: impl Fn(&Run) -> f64) -> Option<f64> {
+    let pts: Vec<(f64, f64)> = runs.iter().filter(|r| r.error.is_none()).map(|r| (r.hours, f(r))).collect();
+    linear_fit(&pts).map(|(s, _)| s)
+}
+
+fn fmt_interval(s: f64) -> String {
+    match s {
+        s if s >= 3600.0 && s % 3600.0 == 0.0 => format!("{}h", s / 3600.0),
+        s if s >= 60.0 && s % 60.0 == 0.0 => format!("{}m", s / 60.0),
+        s => format!("{s}s"),
+    }
+}
+
+fn print_summary(runs: &[Run]) {
+    crate::say!("\n{}", "═".repeat(48));
+    crate::say!("{:^48}", "Trend Summary");
+    crate::say!("{}", "═".repeat(48));
+    let label = units::label();
+    crate::say!("{:>4}  {:<19} {:>11} {:>11}", "Run", "Time", format!("Write {label}"), format!("Read {label}"));
+    for (i, r) in runs.iter().enumerate() {
+        match &r.error {
+            Some(e) => crate::say!("{:>4}  {:<19} {:>23}  FAIL: {e}", i + 1, r.timestamp, "-"),
+            None => crate::say!("{:>4}  {:<19} {:>11.2} {:>11.2}{}", i + 1, r.timestamp, units::scale(r.write), units::scale(r.read), if r.failed { "  FAIL" } else { "" }),
+        }
+    }
+    let errors = runs.iter().filter(|r| r.error.is_some()).count();
+    if errors > 0 { crate::say!("\n{errors} of {} run(s) failed without results", runs.len()); }
+    crate::say!();
+    for (name, f) in [("Write", (|r: &Run| r.write) as fn(&Run) -> f64), ("Read", |r: &Run| r.read)] {
+        let v: Vec<f64> = runs.iter().filter(|r| r.error.is_none()).map(f).collect();
+        let (Some(first), Some(last)) = (v.first(), v.last()) else { continue };
+        let min = v.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = v.iter().copied().fold(0.0, f64::max);
+        let change = if *first > 0.0 { (last - first) / first * 100.0 } else { 0.0 };
+        let trend = slope(runs, f).map(|s| format!(", trend {:+.2} {label} per hour", units::scale(s))).unwrap_or_default();
+        crate::say!("{:<6} min {:.2} / max {:.2} {label}, first→last {:+.1}%{}", format!("{name}:"), units::scale(min), units::scale(max), change, trend);
+    }
+}
@@ -0,0 +1,182 @@
+//! `crabwise selftest`: a tiny benchmark in a scratch directory plus consistency checks of the
+//! parts a broken build or an odd system trips over first, and a list of which optional
+//! capabilities (direct I/O, cache eviction, TRIM, raw device access) work here.
+//!
+//! Meant for packagers and for "it doesn't work on my machine" reports: a `FAIL` is a bug or
+//! a broken install, a `--` only means the feature isn't usable on this system.
+
+use crate::units;
+use crabwise_core::device::{self, device_info};
+use crabwise_core::parse::{parse_duration, parse_rate, parse_size};
+use crabwise_core::{file, trim, BenchConfig, BenchResult, BenchRunner, NoProgress};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Size of the tiny benchmark; small enough for tmpfs and slow SD cards alike.
+const SIZE: u64 = 4 << 20;
+const BLOCK: u64 = 1 << 20;
+
+#[derive(clap::Args, Debug)]
+pub struct SelftestArgs {
+    /// Directory to test in, e.g. one on the USB drive [default: a new directory under the system temp dir]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    /// A capability this system or filesystem doesn't offer.
+    Unavailable,
+    Fail,
+}
+
+struct Check {
+    status: Status,
+    name: &'static str,
+    detail: String,
+}
+
+fn check(name: &'static str, r: Result<String, String>) -> Check {
+    match r {
+        Ok(detail) => Check { status: Status::Ok, name, detail },
+        Err(detail) => Check { status: Status::Fail, name, detail },
+    }
+}
+
+/// A capability: `Err` means unavailable here rather than broken.
+fn capability(name: &'static str, r: Result<String, String>) -> Check {
+    match r {
+        Ok(detail) => Check { status: Status::Ok, name, detail },
+        Err(detail) => Check { status: Status::Unavailable, name, detail },
+    }
+}
+
+/// Runs every check and prints one line each; returns false when any failed.
+pub fn run(a: SelftestArgs) -> io::Result<bool> {
+    let scratch = a.dir.is_none();
+    let dir = a.dir.unwrap_or_else(|| std::env::temp_dir().join(format!("crabwise-selftest-{}", std::process::id())));
+    std::fs::create_dir_all(&dir)?;
+    println!("crabwise {} self-test in {}\n", env!("CARGO_PKG_VERSION"), dir.display());
+
+    let bench = |vectored: bool| {
+        let mut config = BenchConfig::new(&dir);
+        config.size = SIZE;
+        config.block = BLOCK;
+        config.vectored = vectored;
+        config.random = true;
+        config.verify = true;
+        BenchRunner::new(config).run(&mut NoProgress)
+    };
+    let mut checks = vec![check("size parsing", parsing())];
+    let sync = bench(false);
+    checks.push(check("sync engine", sync.as_ref().map_err(|e| e.to_string()).and_then(benchmarked)));
+    checks.push(check("vectored engine", bench(true).map_err(|e| e.to_string()).and_then(|r| benchmarked(&r))));
+    if let Ok(r) = &sync {
+        checks.push(check("result JSON", round_trip(r)));
+        checks.push(check("session log", log_line(&dir, r)));
+    }
+    checks.push(capability("direct I/O", direct_io(&dir)));
+    checks.extend(o_direct(&dir).map(|r| capability("O_DIRECT", r)));
+    checks.push(capability("cache eviction", eviction(&dir)));
+    let info = device_info(&dir);
+    checks.push(capability("device detection", info.as_ref()
+        .map(|i| format!("{} ({}{})", i.name, i.file_system, device::bus(i).map(|b| format!(", {b}")).unwrap_or_default()))
+        .ok_or_else(|| "the volume holding this directory wasn't identified".to_string())));
+    checks.push(capability("TRIM", match trim::discard_supported(&dir) {
+        Some(true) => Ok("the device accepts discards (--trim needs root)".into()),
+        Some(false) => Err("the device doesn't accept discards".into()),
+        None => Err("can't be told on this system".into()),
+    }));
+    if let Some(i) = &info {
+        checks.push(capability("raw device read", std::fs::File::open(&i.name).map(|_| format!("{} is readable", i.name))
+            .map_err(|e| format!("{}: {e} (--corruption-check needs it)", i.name))));
+    }
+    if scratch { let _ = std::fs::remove_dir_all(&dir); }
+
+    for c in &checks {
+        let tag = match c.status { Status::Ok => "ok  ", Status::Unavailable => "--  ", Status::Fail => "FAIL" };
+        println!("  {tag}  {:<18} {}", c.name, c.detail);
+    }
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    if failed == 0 {
+        println!("\nAll checks passed; `--` marks capabilities not usable on this system.");
+    } else {
+        println!("\n{failed} check(s) failed.");
+    }
+    Ok(failed == 0)
+}
+
+fn parsing() -> Result<String, String> {
+    let sizes = [("4K", 4096), ("1.5G", 3 << 29), ("512MiB", 512 << 20), ("2GB", 2_000_000_000), ("64", 64)];
+    for (s, want) in sizes {
+        let got = parse_size(s)?;
+        if got != want { return Err(format!("'{s}' parsed as {got} bytes, expected {want}")); }
+    }
+    let rate = parse_rate("20MBps")?;
+    if rate != 20e6 { return Err(format!("'20MBps' parsed as {rate} B/s, expected 20000000")); }
+    let secs = parse_duration("2m")?;
+    if secs != 120.0 { return Err(format!("'2m' parsed as {secs} s, expected 120")); }
+    Ok(format!("{} sizes, a rate and a duration", sizes.len()))
+}
+
+fn benchmarked(r: &BenchResult) -> Result<String, String> {
+    if r.write.bytes != SIZE || r.read.bytes != SIZE {
+        return Err(format!("moved {} / {} bytes, expected {SIZE} each way", r.write.bytes, r.read.bytes));
+    }
+    if r.verify_mismatches.is_some_and(|m| m > 0) { return Err("data read back differs from what was written".into()); }
+    Ok(format!("{} written at {}, read at {}, verified", units::size(SIZE), units::rate(r.write.mb_s()), units::rate(r.read.mb_s())))
+}
+
+fn round_trip(r: &BenchResult) -> Result<String, String> {
+    let back = BenchResult::from_json(&r.to_json()).ok_or("the record doesn't parse back")?;
+    if back.write.bytes != r.write.bytes || back.read.bytes != r.read.bytes || back.write.samples.len() != r.write.samples.len() {
+        return Err("the record changed in a save and load".into());
+    }
+    Ok("a record survives a save and load".into())
+}
+
+fn log_line(dir: &Path, r: &BenchResult) -> Result<String, String> {
+    let path = dir.join("selftest.log");
+    crate::append_log(&path, "selftest", r).map_err(|e| format!("{}: {e}", path.display()))?;
+    let text = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let _ = std::fs::remove_file(&path);
+    if !text.starts_with("selftest") { return Err("the appended line doesn't read back".into()); }
+    Ok("a line appends and reads back".into())
+}
+
+/// Opening with the flags `--sync direct` uses and writing a block through them.
+fn direct_io(dir: &Path) -> Result<String, String> {
+    let path = dir.join("selftest-direct.tmp");
+    let r = file::open_write(&path, true).and_then(|f| file::write_all_at(&f, &[0x5a; 4096], 0));
+    let _ = std::fs::remove_file(&path);
+    r.map(|_| "synchronous writes work (--sync direct)".into()).map_err(|e| format!("{e}; use --sync buffered"))
+}
+
+/// Linux `O_DIRECT`, which some filesystems (tmpfs, some FUSE ones) refuse; `None` elsewhere.
+#[cfg(target_os = "linux")]
+fn o_direct(dir: &Path) -> Option<Result<String, String>> {
+    use std::os::unix::fs::OpenOptionsExt;
+    let path = dir.join("selftest-odirect.tmp");
+    // O_DIRECT wants the buffer aligned to the logical block size; 4 KiB covers every disk.
+    let buf = vec![0x5au8; 8192];
+    let at = buf.as_ptr().align_offset(4096);
+    let r = std::fs::OpenOptions::new().create(true).write(true).truncate(true).custom_flags(libc::O_DIRECT).open(&path)
+        .and_then(|f| file::write_all_at(&f, &buf[at..at + 4096], 0));
+    let _ = std::fs::remove_file(&path);
+    Some(r.map(|_| "the filesystem accepts uncached I/O".into()).map_err(|e| format!("not supported by this filesystem ({e})")))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn o_direct(_dir: &Path) -> Option<Result<String, String>> {
+    None
+}
+
+fn eviction(dir: &Path) -> Result<String, String> {
+    if !cfg!(any(target_os = "linux", target_os = "windows")) {
+        return Err("not available on this system; read passes rely on F_NOCACHE".into());
+    }
+    let path = dir.join("selftest-evict.tmp");
+    let r = std::fs::write(&path, [0x5a; 4096]).and_then(|_| file::evict(&path));
+    let _ = std::fs::remove_file(&path);
+    r.map(|_| "test files can be dropped from the OS cache".into()).map_err(|e| e.to_string())
+}
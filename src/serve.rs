@@ -0,0 +1,368 @@
+//! `crabwise serve`: a local HTTP API so other frontends can drive the benchmark.
+//!
+//! | Method | Path               | Purpose                                              |
+//! |--------|--------------------|------------------------------------------------------|
+//! | GET    | `/devices`         | removable mounts, as offered by the device picker    |
+//! | POST   | `/runs`            | start a run; JSON body of benchmark parameters       |
+//! | GET    | `/runs`            | every run since the daemon started                   |
+//! | GET    | `/runs/{id}`       | status, and the result record once finished          |
+//! | GET    | `/runs/{id}/events`| the `--progress-json` event stream, replayed then live |
+//! | GET    | `/metrics`         | Prometheus gauges for the latest run per device      |
+
+use crate::progress::JsonProgress;
+use crate::{bench_with, confirm_destructive, http, metrics, preset::Preset, resolve, Args, Engine, SyncMode};
+use chrono::Local;
+use clap::ValueEnum;
+use crabwise_core::device::candidate_devices;
+use crabwise_core::json::{self, Value};
+use crabwise_core::parse::{parse_abort_below, parse_duration, parse_queue_depths, parse_rate, parse_read_pattern, parse_size, parse_tag};
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often an open event stream checks for new events.
+const STREAM_POLL: Duration = Duration::from_millis(100);
+
+/// Events kept per run; past it the oldest progress events are dropped, half at a time.
+const MAX_EVENTS: usize = 4096;
+
+#[derive(clap::Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on; keep it on loopback unless the network is trusted
+    #[arg(long, value_name="ADDR", default_value="127.0.0.1:7070")]
+    listen: String,
+
+    /// Append every finished run as a JSON line to FILE (input for `crabwise report`)
+    #[arg(long, value_name="FILE")]
+    record: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Running,
+    Done,
+    Failed,
+}
+
+struct Run {
+    id: u64,
+    target_dir: PathBuf,
+    /// `target_dir` with symlinks and `..` resolved, for spotting a second run on it.
+    canonical: PathBuf,
+    started: String,
+    status: Status,
+    /// `--progress-json` lines emitted so far: the first one, then the latest [`MAX_EVENTS`].
+    events: Vec<String>,
+    /// Events dropped from after the first one to stay within [`MAX_EVENTS`].
+    dropped: usize,
+    result: Option<Value>,
+    error: Option<String>,
+}
+
+impl Run {
+    fn push_event(&mut self, line: String) {
+        if self.events.len() >= MAX_EVENTS {
+            self.events.drain(1..MAX_EVENTS / 2);
+            self.dropped += MAX_EVENTS / 2 - 1;
+        }
+        self.events.push(line);
+    }
+
+    /// How many events the run has emitted, dropped ones included.
+    fn emitted(&self) -> usize {
+        self.events.len() + self.dropped
+    }
+
+    /// The events from the `n`th emitted one on, skipping any dropped since.
+    fn events_from(&self, n: usize) -> &[String] {
+        let i = if n == 0 { 0 } else { n.saturating_sub(self.dropped).max(1) };
+        &self.events[i.min(self.events.len())..]
+    }
+
+    fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("id", self.id.into()),
+            ("target_dir", self.target_dir.display().to_string().into()),
+            ("started", self.started.as_str().into()),
+            ("status", match self.status { Status::Running => "running", Status::Done => "done", Status::Failed => "failed" }.into()),
+            ("result", self.result.clone().unwrap_or(Value::Null)),
+            ("error", self.error.clone().into()),
+        ])
+    }
+}
+
+type Runs = Arc<Mutex<Vec<Run>>>;
+
+fn lock(runs: &Runs) -> std::sync::MutexGuard<'_, Vec<Run>> {
+    runs.lock().unwrap_or_else(|p| p.into_inner())
+}
+
+pub fn run(s: ServeArgs) -> io::Result<()> {
+    let listener = TcpListener::bind(&s.listen).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", s.listen)))?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    let state = State { runs: Arc::default(), metrics: Arc::default(), record: s.record };
+    http::serve(listener, move |req, stream| route(req, stream, &state))
+}
+
+struct State {
+    runs: Runs,
+    metrics: metrics::Shared,
+    record: Option<PathBuf>,
+}
+
+fn route(req: http::Request, stream: &mut TcpStream, state: &State) -> io::Result<()> {
+    let runs = &state.runs;
+    let segs: Vec<&str> = req.path.trim_matches('/').split('/').collect();
+    match (req.method.as_str(), segs.as_slice()) {
+        ("GET", ["devices"]) => {
+            let list = candidate_devices().into_iter().map(|(label, mount)| Value::object(vec![
+                ("label", label.into()),
+                ("mount_point", mount.display().to_string().into()),
+            ])).collect();
+            http::json(stream, 200, &Value::Array(list))
+        }
+        ("GET", ["metrics"]) => metrics::respond(stream, &state.metrics),
+        ("GET", ["runs"]) => http::json(stream, 200, &Value::Array(lock(runs).iter().map(Run::to_json).collect())),
+        ("POST", ["runs"]) => {
+            // Requiring JSON forces a CORS preflight, so a web page can't start runs behind the user's back.
+            if !req.content_type.as_deref().is_some_and(|t| t.starts_with("application/json")) {
+                return http::error(stream, 415, "expected Content-Type: application/json");
+            }
+            let body = String::from_utf8_lossy(&req.body);
+            let params = match json::parse(&body) {
+                Ok(v @ Value::Object(_)) => v,
+                Ok(_) => return http::error(stream, 400, "expected a JSON object"),
+                Err(e) => return http::error(stream, 400, format!("invalid JSON: {e}")),
+            };
+            let mut args = match parse_params(&params) {
+                Ok(a) => a,
+                Err(e) => return http::error(stream, 400, e),
+            };
+            args.record = state.record.clone();
+            match start(state, args) {
+                Ok(id) => http::json(stream, 202, &lock(runs)[id as usize - 1].to_json()),
+                Err((status, e)) => http::error(stream, status, e),
+            }
+        }
+        ("GET", ["runs", id]) => match find(runs, id) {
+            Some(i) => http::json(stream, 200, &lock(runs)[i].to_json()),
+            None => http::error(stream, 404, format!("no run '{id}'")),
+        },
+        ("GET", ["runs", id, "events"]) => match find(runs, id) {
+            Some(i) => stream_events(stream, runs, i),
+            None => http::error(stream, 404, format!("no run '{id}'")),
+        },
+        (_, ["devices"] | ["metrics"] | ["runs"] | ["runs", _] | ["runs", _, "events"]) => http::error(stream, 405, "method not allowed"),
+        _ => http::error(stream, 404, format!("no endpoint {}", req.path)),
+    }
+}
+
+/// Index into the run list for a path id (ids are 1-based and never reused).
+fn find(runs: &Runs, id: &str) -> Option<usize> {
+    let i = id.parse::<usize>().ok()?.checked_sub(1)?;
+    (i < lock(runs).len()).then_some(i)
+}
+
+/// Converts a POST body into the same `Args` a command line would produce.
+fn parse_params(v: &Value) -> Result<Args, String> {
+    let Value::Object(pairs) = v else { return Err("expected a JSON object".into()) };
+    let mut args = Args::default();
+    for (key, value) in pairs {
+        let text = || match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Number(n) => Ok(n.to_string()),
+            _ => Err(format!("'{key}' must be a string or number")),
+        };
+        let flag = || value.as_bool().ok_or(format!("'{key}' must be true or false"));
+        match key.as_str() {
+            "target_dir" => args.target_dir = Some(PathBuf::from(text()?)),
+            "preset" => args.preset = Some(Preset::from_str(&text()?, true).map_err(|_| format!("unknown preset '{}'", text().unwrap_or_default()))?),
+            "size" => { let s = text()?; parse_size(&s)?; args.size = Some(s); }
+            "block" => { let s = text()?; parse_size(&s)?; args.block = Some(s); }
+            "sync" => args.sync = Some(SyncMode::from_str(&text()?, true).map_err(|_| "sync must be direct or buffered".to_string())?),
+            "iterations" => {
+                let n = value.as_u64().filter(|&n| n > 0 && n <= u64::from(u32::MAX)).ok_or("iterations must be a positive integer")?;
+                args.iterations = Some(n as u32);
+            }
+            "files" => {
+                let n = value.as_u64().filter(|&n| n > 0 && n <= u64::from(u32::MAX)).ok_or("files must be a positive integer")?;
+                args.files = Some(n as u32);
+            }
+            "access_time" => args.access_time = flag()?,
+            "sweep_qd" => args.sweep_qd = parse_queue_depths(&text()?)?,
+            "fsync" => {
+                let n = value.as_u64().filter(|&n| n > 0 && n <= u64::from(u32::MAX)).ok_or("fsync must be a positive integer")?;
+                args.fsync = Some(n as u32);
+            }
+            "engine" => args.engine = Some(Engine::from_str(&text()?, true).map_err(|_| "engine must be sync or vectored".to_string())?),
+            "random" => args.random = flag()?,
+            "verify" => args.verify = flag()?,
+            "read_pattern" => args.read_pattern = Some(parse_read_pattern(&text()?)?),
+            "shuffled" => args.shuffled = flag()?,
+            "cached_read" => args.cached_read = flag()?,
+            "preallocate" => args.preallocate = flag()?,
+            "auto_block" => args.auto_block = flag()?,
+            "trim" => args.trim = flag()?,
+            "trim_compare" => args.trim_compare = flag()?,
+            "corruption_check" => args.corruption_check = flag()?,
+            "compression_check" => args.compression_check = flag()?,
+            "offset_map" => args.offset_map = flag()?,
+            "keep" => args.keep = flag()?,
+            "yes" => args.yes = Some(text()?),
+            "tags" => {
+                let Value::Object(tags) = value else { return Err("'tags' must be an object of strings".into()) };
+                for (k, v) in tags {
+                    args.tag.push(parse_tag(&format!("{k}={}", v.as_str().ok_or(format!("tag '{k}' must be a string"))?))?);
+                }
+            }
+            "sustained" => args.sustained = Some(parse_duration(&text()?)?),
+            "abort_below" => args.abort_below = Some(parse_abort_below(&text()?)?),
+            "min_write" => args.min_write = Some(parse_rate(&text()?)?),
+            "min_read" => args.min_read = Some(parse_rate(&text()?)?),
+            "max_write_latency" => args.max_write_latency = Some(parse_duration(&text()?)?),
+            "max_read_latency" => args.max_read_latency = Some(parse_duration(&text()?)?),
+            other => return Err(format!("unknown parameter '{other}'")),
+        }
+    }
+    if args.target_dir.is_none() { return Err("'target_dir' is required".into()); }
+    Ok(args)
+}
+
+/// Registers a run and starts it on its own thread; returns its id.
+fn start(state: &State, mut args: Args) -> Result<u64, (u16, String)> {
+    let runs = &state.runs;
+    let (total, block) = resolve(&mut args).map_err(|e| (400, e.to_string()))?;
+    let target_dir = args.target_dir.clone().unwrap_or_default();
+    let canonical = std::fs::canonicalize(&target_dir).unwrap_or_else(|_| target_dir.clone());
+    // Nobody is at the server's terminal to answer: a destructive run needs `yes` in the request.
+    args.unattended = true;
+    confirm_destructive(&mut args, &target_dir, total).map_err(|e| (400, e.to_string()))?;
+    let id = {
+        let mut list = lock(runs);
+        // Two runs on one target would share (and clobber) the same test file.
+        if list.iter().any(|r| r.status == Status::Running && r.canonical == canonical) {
+            return Err((409, format!("a run on {} is already in progress", target_dir.display())));
+        }
+        let id = list.len() as u64 + 1;
+        list.push(Run {
+            id, target_dir: target_dir.clone(), canonical, started: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            status: Status::Running, events: Vec::new(), dropped: 0, result: None, error: None,
+        });
+        id
+    };
+    let (runs, metrics) = (Arc::clone(runs), Arc::clone(&state.metrics));
+    std::thread::spawn(move || {
+        let mut progress = JsonProgress::new(Box::new(EventSink { runs: Arc::clone(&runs), index: id as usize - 1, line: Vec::new() }));
+        let outcome = progress.start(&target_dir.display().to_string(), total, block)
+            .and_then(|_| bench_with(args, target_dir.clone(), total, block, &mut progress, false));
+        if let Err(e) = &outcome { let _ = progress.error(e); }
+        metrics::record(&metrics, &target_dir, outcome.as_ref().ok());
+        let mut list = lock(&runs);
+        let r = &mut list[id as usize - 1];
+        match outcome {
+            Ok(rec) => { r.status = Status::Done; r.result = Some(rec.to_json()); }
+            Err(e) => { r.status = Status::Failed; r.error = Some(e.to_string()); }
+        }
+    });
+    Ok(id)
+}
+
+/// Collects `JsonProgress` output into the run's event list, one entry per line.
+struct EventSink {
+    runs: Runs,
+    index: usize,
+    line: Vec<u8>,
+}
+
+impl Write for EventSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(n) = self.line.iter().position(|&b| b == b'\n') {
+            let rest = self.line.split_off(n + 1);
+            let line = String::from_utf8_lossy(&self.line[..n]).to_string();
+            self.line = rest;
+            lock(&self.runs)[self.index].push_event(line);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+/// Sends every event still kept, then follows the run until it ends (newline-delimited JSON).
+fn stream_events(stream: &mut TcpStream, runs: &Runs, index: usize) -> io::Result<()> {
+    http::head(stream, 200, "application/x-ndjson", None)?;
+    let mut sent = 0;
+    loop {
+        let (fresh, running) = {
+            let list = lock(runs);
+            let r = &list[index];
+            let fresh = r.events_from(sent).to_vec();
+            sent = r.emitted();
+            (fresh, r.status == Status::Running)
+        };
+        for e in &fresh { writeln!(stream, "{e}")?; }
+        stream.flush()?;
+        if !running && fresh.is_empty() { return Ok(()); }
+        std::thread::sleep(STREAM_POLL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run() -> Run {
+        Run {
+            id: 1, target_dir: PathBuf::new(), canonical: PathBuf::new(), started: String::new(),
+            status: Status::Running, events: Vec::new(), dropped: 0, result: None, error: None,
+        }
+    }
+
+    #[test]
+    fn events_stay_capped_and_resume_where_a_stream_left_off() {
+        let mut r = run();
+        for i in 0..MAX_EVENTS { r.push_event(i.to_string()); }
+        assert_eq!(r.events_from(MAX_EVENTS - 1), ["4095"]);
+        for i in MAX_EVENTS..3 * MAX_EVENTS { r.push_event(i.to_string()); }
+        assert!(r.events.len() <= MAX_EVENTS);
+        assert_eq!(r.emitted(), 3 * MAX_EVENTS);
+        assert_eq!(r.events[0], "0", "the start event is kept");
+        // A stream that fell behind the dropped ones picks up at the oldest kept after the first.
+        assert_eq!(r.events_from(1)[0], r.events[1]);
+        assert_eq!(r.events_from(3 * MAX_EVENTS - 2), ["12286", "12287"]);
+        assert!(r.events_from(r.emitted()).is_empty());
+    }
+
+    fn params(body: &str) -> Result<Args, String> {
+        parse_params(&json::parse(body).unwrap())
+    }
+
+    #[test]
+    fn params_become_args() {
+        let a = params(r#"{"target_dir": "/media/usb", "preset": "Quick", "size": "256M", "iterations": 3, "random": true,
+            "sync": "buffered", "yes": "FILL", "tags": {"port": "front"}}"#).unwrap();
+        assert_eq!(a.target_dir, Some(PathBuf::from("/media/usb")));
+        assert_eq!((a.preset, a.size.as_deref(), a.iterations, a.random), (Some(Preset::Quick), Some("256M"), Some(3), true));
+        assert_eq!((a.sync, a.yes.as_deref()), (Some(SyncMode::Buffered), Some("FILL")));
+        assert_eq!(a.tag, [("port".to_string(), "front".to_string())]);
+        // Sizes may be plain numbers of bytes, as on the command line.
+        assert_eq!(params(r#"{"target_dir": "/m", "block": 4096}"#).unwrap().block.as_deref(), Some("4096"));
+    }
+
+    #[test]
+    fn bad_params_are_refused() {
+        let err = |body: &str| params(body).err().unwrap_or_default();
+        assert_eq!(err(r#"{"size": "1G"}"#), "'target_dir' is required");
+        assert_eq!(err(r#"{"target_dir": "/m", "force_unmount": true}"#), "unknown parameter 'force_unmount'");
+        assert_eq!(err(r#"{"target_dir": "/m", "random": "yes"}"#), "'random' must be true or false");
+        assert_eq!(err(r#"{"target_dir": "/m", "iterations": 0}"#), "iterations must be a positive integer");
+        assert_eq!(err(r#"{"target_dir": "/m", "preset": "fastest"}"#), "unknown preset 'fastest'");
+        assert_eq!(err(r#"{"target_dir": "/m", "tags": {"port": 1}}"#), "tag 'port' must be a string");
+        assert_eq!(err(r#"{"target_dir": ["/m"]}"#), "'target_dir' must be a string or number");
+        assert!(!err(r#"{"target_dir": "/m", "size": "big"}"#).is_empty());
+        assert_eq!(parse_params(&Value::Array(Vec::new())).err().unwrap_or_default(), "expected a JSON object");
+    }
+}
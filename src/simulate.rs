@@ -0,0 +1,183 @@
+//! `--simulate USE`: runs the mix of access patterns a device sees in one job, as job-file
+//! stages, and answers the question the user actually has ("will this card boot my Pi
+//! well?") instead of leaving them to interpret MB/s.
+
+use crate::progress::LineProgress;
+use crate::units;
+use crabwise_core::stage::{Mode, Pattern};
+use crabwise_core::{run_stage, PhaseStats, Stage, StageResult};
+use clap::ValueEnum;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Region the stages work within when `--size` isn't given.
+pub const DEFAULT_SIZE: &str = "256M";
+
+/// Seconds each random-I/O stage runs for.
+const RANDOM_SECS: f64 = 20.0;
+
+/// The recording bitrate a dashcam stage writes at: 4 MB/s is a 32 Mbit/s 4K camera.
+const DASHCAM_RATE: f64 = 4e6;
+const DASHCAM_SECS: f64 = 60.0;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Use {
+    /// Raspberry Pi boot drive: sequential writes, then 4 KiB random reads and writes at QD1, rated against the SD A1/A2 classes
+    RpiBoot,
+    /// Steam Deck game library: installs, level loads and asset streaming at QD4
+    SteamDeck,
+    /// Dashcam: a minute of paced sequential writes at a 4K camera's bitrate
+    Dashcam,
+    /// MiSTer FPGA: sequential ROM loads and the seeks of CD-based cores
+    Mister,
+}
+
+impl Use {
+    fn title(self) -> &'static str {
+        match self {
+            Use::RpiBoot => "Raspberry Pi boot drive",
+            Use::SteamDeck => "Steam Deck game library",
+            Use::Dashcam => "dashcam",
+            Use::Mister => "MiSTer FPGA",
+        }
+    }
+
+    fn stages(self, dir: &Path, size: u64, direct: bool) -> Vec<Stage> {
+        let stage = |name: &str, pattern, mode, block, queue_depth, duration| Stage {
+            pattern,
+            mode,
+            block,
+            size,
+            queue_depth,
+            duration,
+            direct,
+            ..Stage::new(name, dir)
+        };
+        let random = Some(RANDOM_SECS);
+        match self {
+            Use::RpiBoot => vec![
+                stage("seq-write", Pattern::Sequential, Mode::Write, 1 << 20, 1, None),
+                stage("rand-read-4k", Pattern::Random, Mode::Read, 4 << 10, 1, random),
+                stage("rand-write-4k", Pattern::Random, Mode::Write, 4 << 10, 1, random),
+            ],
+            Use::SteamDeck => vec![
+                stage("install", Pattern::Sequential, Mode::Write, 1 << 20, 1, None),
+                stage("load", Pattern::Sequential, Mode::Read, 1 << 20, 1, None),
+                stage("stream-64k", Pattern::Random, Mode::Read, 64 << 10, 4, random),
+                stage("stream-4k", Pattern::Random, Mode::Read, 4 << 10, 4, random),
+            ],
+            Use::Dashcam => vec![Stage {
+                rate: Some(DASHCAM_RATE),
+                ..stage("record", Pattern::Sequential, Mode::Write, 1 << 20, 1, Some(DASHCAM_SECS))
+            }],
+            Use::Mister => vec![
+                stage("rom-load", Pattern::Sequential, Mode::Read, 1 << 20, 1, None),
+                stage("cd-seek", Pattern::Random, Mode::Read, 4 << 10, 1, random),
+            ],
+        }
+    }
+}
+
+/// How the results measure up for the use: a one-line verdict and whether the device is fit.
+struct Verdict {
+    fit: bool,
+    text: String,
+}
+
+/// The write or read phase of stage `i`.
+fn phase(rs: &[StageResult], i: usize) -> &PhaseStats {
+    rs[i].write.as_ref().or(rs[i].read.as_ref()).expect("every stage measures one direction")
+}
+
+fn iops(rs: &[StageResult], i: usize) -> f64 {
+    phase(rs, i).iops(rs[i].block_bytes)
+}
+
+fn verdict(usage: Use, rs: &[StageResult]) -> Verdict {
+    match usage {
+        // The SD Application Performance Classes: A1 is 1500 read / 500 write IOPS at 4 KiB,
+        // A2 is 4000 / 2000, both with 10 MB/s sustained sequential writes.
+        Use::RpiBoot => {
+            let (seq, read, write) = (phase(rs, 0).mb_s(), iops(rs, 1), iops(rs, 2));
+            let class = if seq >= 10.0 && read >= 4000.0 && write >= 2000.0 {
+                Some("A2")
+            } else if seq >= 10.0 && read >= 1500.0 && write >= 500.0 {
+                Some("A1")
+            } else {
+                None
+            };
+            let figures = format!("{read:.0} read / {write:.0} write IOPS, {}", units::rate(seq));
+            match class {
+                Some(c) => Verdict { fit: true, text: format!("good boot drive: performs like an {c} card ({figures})") },
+                None => Verdict { fit: false, text: format!("below the A1 class ({figures}): expect slow boots, updates and app starts") },
+            }
+        }
+        Use::SteamDeck => {
+            let (install, load, small) = (phase(rs, 0).mb_s(), phase(rs, 1).mb_s(), iops(rs, 3));
+            let figures = format!("loads at {}, installs at {}, {small:.0} 4K IOPS", units::rate(load), units::rate(install));
+            if load >= 90.0 && small >= 2000.0 {
+                Verdict { fit: true, text: format!("good for a game library: load times close to the UHS-I limit ({figures})") }
+            } else if load >= 40.0 {
+                Verdict { fit: true, text: format!("playable, but expect longer load times and stutter in open-world games ({figures})") }
+            } else {
+                Verdict { fit: false, text: format!("too slow for a game library ({figures})") }
+            }
+        }
+        // A camera buffers a second or two; a write that stalls longer loses footage.
+        Use::Dashcam => {
+            let p = phase(rs, 0);
+            let kept_up = p.mb_s() * 1e6 >= DASHCAM_RATE * 0.98;
+            let figures = format!("{} against {} needed, p99 write {:.0} ms", units::rate(p.mb_s()), units::rate(DASHCAM_RATE / 1e6), p.latency_p99_ms);
+            if kept_up && p.latency_p99_ms < 1000.0 {
+                Verdict { fit: true, text: format!("keeps up with continuous recording ({figures})") }
+            } else {
+                Verdict { fit: false, text: format!("drops frames: can't keep up with continuous recording ({figures})") }
+            }
+        }
+        // Cartridge cores load a ROM once; CD cores (PSX, Saturn, Mega CD) seek while playing.
+        Use::Mister => {
+            let (seq, seek) = (phase(rs, 0).mb_s(), phase(rs, 1).latency_p99_ms);
+            let figures = format!("ROMs load at {}, p99 seek {seek:.1} ms", units::rate(seq));
+            if seq >= 10.0 && seek < 20.0 {
+                Verdict { fit: true, text: format!("good for every core, including CD-based ones ({figures})") }
+            } else if seq >= 10.0 {
+                Verdict { fit: true, text: format!("fine for cartridge cores; CD-based cores may stutter ({figures})") }
+            } else {
+                Verdict { fit: false, text: format!("too slow even for loading ROMs ({figures})") }
+            }
+        }
+    }
+}
+
+/// Runs the use's stages in `dir` and prints their figures and the verdict; returns true
+/// when the device is fit for the use. The test file is removed unless `keep` is set.
+pub fn run(usage: Use, dir: PathBuf, size: u64, direct: bool, keep: bool) -> io::Result<bool> {
+    let stages = usage.stages(&dir, size, direct);
+    crate::say!("Simulating a {} on {} ({} stage(s))", usage.title(), dir.display(), stages.len());
+    let mut results = Vec::new();
+    let outcome = (|| -> io::Result<()> {
+        for (i, st) in stages.iter().enumerate() {
+            crate::say!(
+                "\n[{}/{}] {} — {} {}, QD {}{}",
+                i + 1, stages.len(), st.name, st.pattern.id(), st.mode.id(), st.queue_depth,
+                st.duration.map(|d| format!(", {d:.0}s")).unwrap_or_default()
+            );
+            let r = run_stage(st, &mut LineProgress { enabled: true })?;
+            for (name, p) in [("write", &r.write), ("read", &r.read)] {
+                if let Some(p) = p {
+                    println!("  {:<6} {}  {:>9.0} IOPS  p99 {:>8.2} ms", name, units::rate_w(p.mb_s(), 9), p.iops(r.block_bytes), p.latency_p99_ms);
+                }
+            }
+            results.push(r);
+        }
+        Ok(())
+    })();
+    if !keep {
+        if let Some(st) = stages.first() { let _ = std::fs::remove_file(st.test_path()); }
+    }
+    outcome?;
+
+    let v = verdict(usage, &results);
+    println!("\nVerdict ({}): {}", usage.title(), v.text);
+    Ok(v.fit)
+}
@@ -0,0 +1,63 @@
+//! `--plain`: pure-ASCII output for legacy consoles, `NO_COLOR` and pipes.
+
+use std::borrow::Cow;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Plain output when `--plain` is given, `NO_COLOR` is set, or stdout is not a terminal.
+pub fn init(flag: bool) {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    PLAIN.store(flag || no_color || !std::io::stdout().is_terminal(), Ordering::Relaxed);
+}
+
+pub fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// ASCII stand-ins for the box-drawing, sparkline and punctuation characters the output uses.
+fn ascii(c: char) -> Option<&'static str> {
+    Some(match c {
+        '═' | '─' => "=",
+        '║' | '│' => "|",
+        '╔' | '╗' | '╚' | '╝' => "+",
+        '—' | '–' => "-",
+        '→' => "->",
+        '▲' => "^",
+        '▼' => "v",
+        '…' => "...",
+        '°' => " deg",
+        '▁' => "_",
+        '▂' => ".",
+        '▃' => ":",
+        '▄' => "-",
+        '▅' => "=",
+        '▆' => "+",
+        '▇' => "*",
+        '█' => "#",
+        _ => return None,
+    })
+}
+
+/// `s` unchanged, or with the decoration characters above replaced in plain mode.
+///
+/// Other non-ASCII text (paths, volume labels, stage names) is data and is left alone.
+pub fn text(s: &str) -> Cow<'_, str> {
+    if !plain() || s.is_ascii() { return Cow::Borrowed(s); }
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match ascii(c) {
+            Some(a) => out.push_str(a),
+            None => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// `println!` through [`text`].
+#[macro_export]
+macro_rules! say {
+    () => { println!() };
+    ($($arg:tt)+) => { println!("{}", $crate::style::text(&format!($($arg)+))) };
+}
@@ -0,0 +1,151 @@
+//! The small subset of TOML used by `config.toml` and job files.
+//!
+//! Supported: `key = value` lines with string, integer, float or boolean values,
+//! `#` comments, `[table]` headers and `[[array]]` table headers. Nested tables,
+//! inline tables, arrays and multi-line strings are not.
+
+use std::io;
+use std::path::Path;
+
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Value {
+    /// Sizes, rates and durations may be written bare (`size = 1073741824`) or quoted (`size = "1G"`).
+    pub fn text(self) -> String {
+        match self {
+            Value::Str(s) => s,
+            Value::Int(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Bool(b) => b.to_string(),
+        }
+    }
+
+    pub fn flag(self) -> Result<bool, String> {
+        match self { Value::Bool(b) => Ok(b), other => Err(format!("expected true or false, got '{}'", other.text())) }
+    }
+}
+
+/// One `key = value` assignment and where it appeared.
+pub struct Entry {
+    pub line: usize,
+    /// Enclosing table name; empty at the top level.
+    pub table: String,
+    /// Position within an `[[array]]` of tables (0 for plain tables).
+    pub index: usize,
+    /// Key with `-` normalised to `_`.
+    pub key: String,
+    pub value: Value,
+}
+
+pub fn bad(path: &Path, line: usize, msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {}", path.display(), line, msg))
+}
+
+/// Parses `text` (read from `path`, used in error messages) into its assignments.
+pub fn parse(path: &Path, text: &str) -> io::Result<Vec<Entry>> {
+    let mut out = Vec::new();
+    let mut table = String::new();
+    let mut index = 0;
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let n = i + 1;
+        let line = strip_comment(raw).trim();
+        if line.is_empty() { continue; }
+        if let Some(name) = line.strip_prefix("[[") {
+            let name = name.strip_suffix("]]").ok_or_else(|| bad(path, n, "unterminated table header".into()))?.trim();
+            index = match counts.iter_mut().find(|(t, _)| t == name) {
+                Some((_, c)) => { *c += 1; *c - 1 }
+                None => { counts.push((name.to_string(), 1)); 0 }
+            };
+            table = name.to_string();
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name.strip_suffix(']').ok_or_else(|| bad(path, n, "unterminated table header".into()))?;
+            table = name.trim().to_string();
+            index = 0;
+            continue;
+        }
+        let (key, value) = line.split_once('=').ok_or_else(|| bad(path, n, format!("expected 'key = value', got '{line}'")))?;
+        out.push(Entry {
+            line: n,
+            table: table.clone(),
+            index,
+            key: key.trim().trim_matches('"').replace('-', "_"),
+            value: parse_value(value.trim()).map_err(|e| bad(path, n, e))?,
+        });
+    }
+    Ok(out)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_str = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_str = !in_str,
+            '#' if !in_str => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(v: &str) -> Result<Value, String> {
+    if let Some(s) = v.strip_prefix('"') {
+        let s = s.strip_suffix('"').ok_or("unterminated string")?;
+        return Ok(Value::Str(s.replace("\\\\", "\\").replace("\\\"", "\"")));
+    }
+    if let Some(s) = v.strip_prefix('\'') {
+        return Ok(Value::Str(s.strip_suffix('\'').ok_or("unterminated string")?.to_string()));
+    }
+    match v {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+    let digits = v.replace('_', "");
+    if let Ok(i) = digits.parse::<i64>() { return Ok(Value::Int(i)); }
+    if let Ok(f) = digits.parse::<f64>() { return Ok(Value::Float(f)); }
+    Err(format!("unsupported value '{v}' (quote strings)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(text: &str) -> Vec<Entry> {
+        parse(Path::new("job.toml"), text).unwrap()
+    }
+
+    #[test]
+    fn tables_and_arrays_of_tables() {
+        let e = entries("name = \"nightly\" # trailing\n[defaults]\nblock-size = '4M'\n\n[[stage]]\nqd = 32\n[[stage]]\nrate = 1.5\n[[stage]]\nrandom = true\n");
+        let seen: Vec<String> = e.into_iter().map(|e| format!("{}[{}].{} = {}", e.table, e.index, e.key, e.value.text())).collect();
+        assert_eq!(seen, ["[0].name = nightly", "defaults[0].block_size = 4M", "stage[0].qd = 32", "stage[1].rate = 1.5", "stage[2].random = true"]);
+    }
+
+    #[test]
+    fn values() {
+        let one = |v: &str| parse_value(v).map(Value::text);
+        assert_eq!(one("1_073_741_824"), Ok("1073741824".to_string()));
+        assert_eq!(one(r#""C:\\temp \"x\"""#), Ok(r#"C:\temp "x""#.to_string()));
+        assert_eq!(one("'# not a comment'"), Ok("# not a comment".to_string()));
+        assert!(one("\"open").is_err());
+        assert!(one("1G").is_err(), "sizes with units must be quoted");
+        assert_eq!(strip_comment(r#"path = "/mnt/#1" # usb"#), r#"path = "/mnt/#1" "#);
+        assert!(parse_value("1").unwrap().flag().is_err());
+    }
+
+    #[test]
+    fn errors_name_the_file_and_line() {
+        let err = |text: &str| parse(Path::new("job.toml"), text).err().map(|e| e.to_string());
+        assert_eq!(err("a = 1\n[[stage]\n"), Some("job.toml:2: unterminated table header".to_string()));
+        assert_eq!(err("\nsize\n"), Some("job.toml:2: expected 'key = value', got 'size'".to_string()));
+        assert_eq!(err("block = 4M"), Some("job.toml:1: unsupported value '4M' (quote strings)".to_string()));
+    }
+}
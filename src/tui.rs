@@ -0,0 +1,372 @@
+//! Full-screen terminal dashboard (`--tui`) drawn with plain ANSI escapes.
+//!
+//! Raw keyboard input uses termios, so the dashboard is only available on
+//! Unix-like systems; elsewhere `Terminal::enter` fails and the caller falls
+//! back to the line-oriented output.
+
+use crate::{progress, units};
+use crabwise_core::sampler::smoothed;
+use crabwise_core::stats::{mean, percentile};
+use crabwise_core::{BenchResult, DeviceInfo, Phase, Progress, Sample, Update};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use sysinfo::Components;
+
+const RESTORE: &str = "\x1b[0m\x1b[?25h\x1b[?1049l";
+const REDRAW_EVERY: Duration = Duration::from_millis(100);
+const TEMP_EVERY: Duration = Duration::from_secs(2);
+const GRAPH_ROWS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Enter,
+    Quit,
+    Other,
+}
+
+#[cfg(unix)]
+static SAVED: std::sync::OnceLock<libc::termios> = std::sync::OnceLock::new();
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: libc::c_int) {
+    // Only async-signal-safe calls in here.
+    unsafe {
+        if let Some(t) = SAVED.get() { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, t); }
+        libc::write(libc::STDOUT_FILENO, RESTORE.as_ptr() as *const libc::c_void, RESTORE.len());
+        libc::_exit(130);
+    }
+}
+
+/// Owns the terminal while the dashboard is up; restores it on drop.
+pub struct Terminal {
+    _private: (),
+}
+
+impl Terminal {
+    /// Switches to the alternate screen with unbuffered, non-echoing input.
+    pub fn enter() -> io::Result<Terminal> {
+        #[cfg(unix)]
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) == 0 || libc::isatty(libc::STDOUT_FILENO) == 0 {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "stdin/stdout is not a terminal"));
+            }
+            let mut t: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut t) != 0 { return Err(io::Error::last_os_error()); }
+            let saved = *SAVED.get_or_init(|| t);
+            let mut raw = saved;
+            // Keep ISIG so Ctrl-C still raises SIGINT; our handler restores the screen.
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 { return Err(io::Error::last_os_error()); }
+            libc::signal(libc::SIGINT, on_sigint as *const () as libc::sighandler_t);
+            print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+            io::stdout().flush()?;
+            Ok(Terminal { _private: () })
+        }
+        #[cfg(not(unix))]
+        Err(io::Error::new(io::ErrorKind::Unsupported, "the dashboard requires a Unix terminal"))
+    }
+
+    /// Terminal size as (columns, rows).
+    pub fn size(&self) -> (usize, usize) {
+        #[cfg(unix)]
+        unsafe {
+            let mut ws: libc::winsize = std::mem::zeroed();
+            if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) == 0 && ws.ws_col > 0 {
+                return (ws.ws_col as usize, ws.ws_row as usize);
+            }
+        }
+        (80, 24)
+    }
+
+    /// Waits up to `timeout` (forever if `None`) for a key press.
+    pub fn read_key(&self, timeout: Option<Duration>) -> io::Result<Option<Key>> {
+        #[cfg(unix)]
+        unsafe {
+            let mut pfd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+            let ms = timeout.map(|d| d.as_millis() as libc::c_int).unwrap_or(-1);
+            if libc::poll(&mut pfd, 1, ms) <= 0 { return Ok(None); }
+            let mut buf = [0u8; 8];
+            let n = libc::read(libc::STDIN_FILENO, buf.as_mut_ptr() as *mut libc::c_void, buf.len());
+            if n <= 0 { return Ok(None); }
+            Ok(Some(decode_key(&buf[..n as usize])))
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = timeout;
+            Ok(None)
+        }
+    }
+
+    fn draw(&self, lines: &[String]) -> io::Result<()> {
+        let mut out = String::from("\x1b[H");
+        for l in lines { out.push_str(l); out.push_str("\x1b[K\r\n"); }
+        out.push_str("\x1b[J");
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("{RESTORE}");
+        let _ = io::stdout().flush();
+        #[cfg(unix)]
+        unsafe {
+            if let Some(t) = SAVED.get() { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, t); }
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+        }
+    }
+}
+
+fn decode_key(bytes: &[u8]) -> Key {
+    match bytes {
+        b"\x1b[A" | b"\x1bOA" | b"k" => Key::Up,
+        b"\x1b[B" | b"\x1bOB" | b"j" => Key::Down,
+        b"\r" | b"\n" => Key::Enter,
+        b"q" | b"Q" | b"\x1b" => Key::Quit,
+        _ => Key::Other,
+    }
+}
+
+fn rule(title: &str, width: usize) -> String {
+    let head = format!("── {title} ");
+    let fill = width.saturating_sub(head.chars().count());
+    format!("\x1b[1m{head}{}\x1b[0m", "─".repeat(fill))
+}
+
+/// The picker screen with candidate `sel` highlighted.
+fn picker_lines(candidates: &[(String, PathBuf)], sel: usize, width: usize) -> Vec<String> {
+    let mut lines = vec![rule("Crabwise — select a device", width), String::new()];
+    for (i, (label, _)) in candidates.iter().enumerate() {
+        if i == sel {
+            lines.push(format!("  \x1b[7m> {label}\x1b[0m"));
+        } else {
+            lines.push(format!("    {label}"));
+        }
+    }
+    lines.push(String::new());
+    lines.push("  ↑/↓ move   Enter select   q quit".to_string());
+    lines
+}
+
+/// Arrow-key picker over `(label, path)` candidates.
+pub fn pick_device(term: &Terminal, candidates: &[(String, PathBuf)]) -> io::Result<PathBuf> {
+    let mut sel = 0usize;
+    loop {
+        let (cols, _) = term.size();
+        term.draw(&picker_lines(candidates, sel, cols.min(100)))?;
+        match term.read_key(None)? {
+            Some(Key::Up) => sel = sel.checked_sub(1).unwrap_or(candidates.len() - 1),
+            Some(Key::Down) => sel = (sel + 1) % candidates.len(),
+            Some(Key::Enter) => return Ok(candidates[sel].1.clone()),
+            Some(Key::Quit) => return Err(io::Error::new(io::ErrorKind::Interrupted, "device selection cancelled")),
+            _ => {}
+        }
+    }
+}
+
+/// Live benchmark dashboard; implements `Progress` so the loops can drive it.
+pub struct Dashboard {
+    /// Released in `done` so the terminal is restored before the results box prints.
+    term: Option<Terminal>,
+    target: String,
+    device: Option<DeviceInfo>,
+    total: u64,
+    block: u64,
+    last_draw: Option<Instant>,
+    components: Components,
+    last_temp: Option<Instant>,
+    temps: Vec<String>,
+    /// (average MB/s, avg latency ms, p99 latency ms) of the most recent draw.
+    last_stats: (f64, f64, f64),
+    /// One line per finished phase.
+    summaries: Vec<String>,
+}
+
+impl Dashboard {
+    pub fn new(term: Terminal, target: &Path, device: Option<DeviceInfo>, total: u64, block: u64) -> Dashboard {
+        Dashboard {
+            term: Some(term),
+            target: target.display().to_string(),
+            device,
+            total,
+            block,
+            last_draw: None,
+            components: Components::new_with_refreshed_list(),
+            last_temp: None,
+            temps: Vec::new(),
+            last_stats: (0.0, 0.0, 0.0),
+            summaries: Vec::new(),
+        }
+    }
+
+    fn term(&self) -> io::Result<&Terminal> {
+        self.term.as_ref().ok_or_else(|| io::Error::other("dashboard already closed"))
+    }
+
+    /// Prefers drive sensors (drivetemp/NVMe); otherwise shows the hottest host sensors.
+    fn refresh_temps(&mut self) {
+        if self.last_temp.is_some_and(|t| t.elapsed() < TEMP_EVERY) { return; }
+        self.last_temp = Some(Instant::now());
+        self.components.refresh();
+        let is_drive = |l: &str| {
+            let l = l.to_ascii_lowercase();
+            ["drivetemp", "nvme", "disk", "ssd"].iter().any(|k| l.contains(k))
+        };
+        let mut all: Vec<(bool, String, f32)> = self.components.iter()
+            .filter(|c| c.temperature().is_finite() && c.temperature() > 0.0)
+            .map(|c| (is_drive(c.label()), c.label().to_string(), c.temperature()))
+            .collect();
+        all.sort_by(|a, b| b.0.cmp(&a.0).then(b.2.total_cmp(&a.2)));
+        self.temps = all.into_iter().take(3)
+            .map(|(drive, label, t)| format!("{:<6} {label}: {t:.1}°C", if drive { "Drive" } else { "Host" }))
+            .collect();
+        if self.temps.is_empty() { self.temps.push("n/a (no sensors reported)".to_string()); }
+    }
+
+    fn graph(samples: &[Sample], width: usize) -> Vec<String> {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let recent = &samples[samples.len().saturating_sub(width)..];
+        let peak = recent.iter().map(|s| s.mb_s).fold(0.0, f64::max);
+        let levels: Vec<usize> = recent.iter()
+            .map(|s| if peak > 0.0 { ((s.mb_s / peak) * (GRAPH_ROWS * 8) as f64).round() as usize } else { 0 })
+            .collect();
+        (0..GRAPH_ROWS).rev().map(|row| {
+            let axis = match row {
+                r if r == GRAPH_ROWS - 1 => format!("{:>8.1} ┤", units::scale(peak)),
+                0 => format!("{:>8.1} ┤", 0.0),
+                _ => format!("{:>8} │", ""),
+            };
+            let cells: String = levels.iter().map(|&lv| {
+                match lv.saturating_sub(row * 8).min(8) {
+                    0 => ' ',
+                    n => BARS[n - 1],
+                }
+            }).collect();
+            format!("{axis}{cells}")
+        }).collect()
+    }
+
+    fn render(&mut self, u: &Update, footer: &str) -> io::Result<()> {
+        self.refresh_temps();
+        let (cols, _) = self.term()?.size();
+        let width = cols.clamp(40, 100);
+        let elapsed = u.start.elapsed().as_secs_f64();
+        let avg = if elapsed > 0.0 { u.done as f64 / 1e6 / elapsed } else { 0.0 };
+        let lat_avg = mean(u.latencies) * 1e3;
+        let lat_p99 = percentile(u.latencies, 99.0) * 1e3;
+        let lat_max = u.latencies.iter().copied().fold(0.0, f64::max) * 1e3;
+        self.last_stats = (avg, lat_avg, lat_p99);
+
+        let mut lines = vec![rule("Crabwise — USB Device Benchmark", width)];
+        match &self.device {
+            Some(d) => {
+                lines.push(format!(" Device:   {}  ({})", d.name, d.file_system));
+                lines.push(format!(" Mount:    {}  [{} free of {}]", d.mount_point, units::size(d.available_bytes), units::size(d.total_bytes)));
+            }
+            None => lines.push(" Device:   unknown".to_string()),
+        }
+        lines.push(format!(" Target:   {}", self.target));
+        lines.push(format!(" Test:     {} in {} blocks", units::size(self.total), units::size(self.block)));
+        lines.push(String::new());
+
+        lines.push(rule(u.phase.label(), width));
+        for s in &self.summaries { lines.push(format!(" {s}")); }
+        let pct = u.done as f64 / u.total as f64;
+        let gauge_w = width.saturating_sub(24);
+        let filled = ((pct * gauge_w as f64).round() as usize).min(gauge_w);
+        let now = smoothed(u.samples);
+        lines.push(format!(" [{}{}] {:5.1}%", "█".repeat(filled), "░".repeat(gauge_w - filled), pct * 100.0));
+        let mut speed = String::new();
+        let _ = write!(speed, " Speed:    {} avg", units::rate_w(avg, 9));
+        if let Some(n) = now { let _ = write!(speed, "   {} now", units::rate_w(n, 9)); }
+        let _ = write!(speed, "   ETA {}", progress::eta(u.total.saturating_sub(u.done), now.unwrap_or(avg)));
+        lines.push(speed);
+        lines.push(format!(" Latency:  avg {lat_avg:>8.2} ms   p99 {lat_p99:>8.2} ms   max {lat_max:>8.2} ms"));
+        lines.push(String::new());
+
+        lines.push(rule(&format!("Throughput ({}, 1 s samples)", units::label()), width));
+        lines.extend(Self::graph(u.samples, width.saturating_sub(12)));
+        lines.push(String::new());
+
+        lines.push(rule("Temperature", width));
+        for t in &self.temps { lines.push(format!(" {t}")); }
+        lines.push(String::new());
+        lines.push(format!(" {footer}"));
+        self.term()?.draw(&lines)
+    }
+}
+
+impl Progress for Dashboard {
+    fn update(&mut self, u: &Update) -> io::Result<()> {
+        if let Some(Key::Quit) = self.term()?.read_key(Some(Duration::ZERO))? {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "benchmark aborted by user"));
+        }
+        let due = self.last_draw.is_none_or(|t| t.elapsed() >= REDRAW_EVERY);
+        if due || u.done >= u.total {
+            self.last_draw = Some(Instant::now());
+            self.render(u, "q: abort")?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, phase: Phase) -> io::Result<()> {
+        let (avg, lat_avg, lat_p99) = self.last_stats;
+        let name = match phase { Phase::Write => "Write", Phase::Read => "Read", p => p.label() };
+        self.summaries.push(format!("{name} done: {}, latency avg {lat_avg:.2} ms / p99 {lat_p99:.2} ms", units::rate(avg)));
+        self.last_draw = None;
+        Ok(())
+    }
+
+    fn done(&mut self, _rec: &BenchResult) -> io::Result<()> {
+        let (cols, _) = self.term()?.size();
+        let mut lines = vec![rule("Crabwise — benchmark complete", cols.clamp(40, 100)), String::new()];
+        for s in &self.summaries { lines.push(format!(" {s}")); }
+        lines.push(String::new());
+        lines.push(" Press any key to continue".to_string());
+        self.term()?.draw(&lines)?;
+        self.term()?.read_key(None)?;
+        self.term = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::assert_golden;
+
+    #[test]
+    fn keys() {
+        let keys: Vec<Key> = [&b"\x1b[A"[..], b"\x1bOB", b"k", b"\r", b"q", b"\x1b", b"x", b"\x1b[C"].iter().map(|b| decode_key(b)).collect();
+        assert_eq!(keys, [Key::Up, Key::Down, Key::Up, Key::Enter, Key::Quit, Key::Quit, Key::Other, Key::Other]);
+    }
+
+    #[test]
+    fn picker_matches_golden_file() {
+        let candidates = [
+            ("SanDisk Ultra (sdb1) — /media/usb".to_string(), PathBuf::from("/media/usb")),
+            ("Kingston DataTraveler (sdc1) — /media/KINGSTON".to_string(), PathBuf::from("/media/KINGSTON")),
+        ];
+        assert_golden("picker.txt", &(picker_lines(&candidates, 1, 60).join("\n") + "\n"));
+    }
+
+    #[test]
+    fn graph_matches_golden_file() {
+        let samples: Vec<Sample> = [0.0, 10.0, 40.0, 80.0, 120.0, 118.5, 60.0, 121.3, 5.0]
+            .iter().enumerate().map(|(i, &mb_s)| Sample { t: i as f64, mb_s, latency_ms: None }).collect();
+        let mut out = Dashboard::graph(&samples, 8).join("\n");
+        out.push('\n');
+        // No samples yet: an empty graph with a zero scale.
+        out.push_str(&Dashboard::graph(&[], 8).join("\n"));
+        out.push('\n');
+        assert_golden("graph.txt", &out);
+    }
+}
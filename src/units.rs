@@ -0,0 +1,120 @@
+//! `--units`: how throughput and sizes are shown to people.
+//!
+//! Everything is measured in bytes and carried as decimal MB/s internally (and in JSON
+//! output); this only changes the labels and scale of human-readable text.
+
+use clap::ValueEnum;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Units {
+    /// Decimal: MB/s (and Mbps), GB — how drives and links are rated
+    #[default]
+    Si,
+    /// Binary: MiB/s, GiB — how most operating systems report sizes
+    Binary,
+    /// Both, decimal first: "112.00 MB/s (106.81 MiB/s)"
+    Both,
+}
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// 0 means not chosen yet (default SI); otherwise `Units as u8 + 1`.
+static UNITS: AtomicU8 = AtomicU8::new(0);
+
+/// Selects the units for the rest of the process (`--units`).
+pub fn set(u: Units) {
+    UNITS.store(u as u8 + 1, Ordering::Relaxed);
+}
+
+/// Selects the units unless `--units` already did (the config file's `units`).
+pub fn set_default(u: Units) {
+    let _ = UNITS.compare_exchange(0, u as u8 + 1, Ordering::Relaxed, Ordering::Relaxed);
+}
+
+pub fn get() -> Units {
+    match UNITS.load(Ordering::Relaxed) {
+        2 => Units::Binary,
+        3 => Units::Both,
+        _ => Units::Si,
+    }
+}
+
+/// Table columns for a rate as (label, factor from decimal MB/s).
+pub fn columns() -> &'static [(&'static str, f64)] {
+    match get() {
+        Units::Si => &[("MB/s", 1.0), ("Mbps", 8.0)],
+        Units::Binary => &[("MiB/s", 1e6 / MIB)],
+        Units::Both => &[("MB/s", 1.0), ("MiB/s", 1e6 / MIB)],
+    }
+}
+
+/// The main rate label: `MB/s` or `MiB/s`.
+pub fn label() -> &'static str {
+    columns()[0].0
+}
+
+/// `mb_s` (decimal MB/s) in the main unit.
+pub fn scale(mb_s: f64) -> f64 {
+    mb_s * columns()[0].1
+}
+
+/// A rate for running text: "112.00 MB/s", "106.81 MiB/s" or "112.00 MB/s (106.81 MiB/s)".
+pub fn rate(mb_s: f64) -> String {
+    rate_w(mb_s, 0)
+}
+
+/// Like [`rate`] with the main figure right-aligned in `width` columns.
+pub fn rate_w(mb_s: f64, width: usize) -> String {
+    let mut s = format!("{:>width$.2} {}", scale(mb_s), label());
+    if get() == Units::Both { s.push_str(&format!(" ({:.2} MiB/s)", mb_s * 1e6 / MIB)); }
+    s
+}
+
+/// Every column of [`columns`], e.g. "  112.00 MB/s (  896.00 Mbps)" for the results box.
+pub fn rate_full(mb_s: f64, width: usize) -> String {
+    let cols = columns();
+    let mut s = format!("{:>width$.2} {}", mb_s * cols[0].1, cols[0].0);
+    for (label, f) in &cols[1..] { s.push_str(&format!(" ({:>8.2} {label})", mb_s * f)); }
+    s
+}
+
+fn scaled(bytes: u64, base: f64, names: [&str; 5]) -> String {
+    let (mut v, mut i) = (bytes as f64, 0);
+    while v >= base && i < names.len() - 1 { v /= base; i += 1; }
+    if i == 0 { format!("{bytes} B") } else { format!("{v:.2} {}", names[i]) }
+}
+
+/// A byte count: "4.19 MB", "4.00 MiB" or "4.19 MB (4.00 MiB)".
+pub fn size(bytes: u64) -> String {
+    let si = || scaled(bytes, 1000.0, ["B", "kB", "MB", "GB", "TB"]);
+    let bin = || scaled(bytes, 1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]);
+    match get() {
+        Units::Si => si(),
+        Units::Binary => bin(),
+        Units::Both => format!("{} ({})", si(), bin()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_scale_to_the_largest_unit_below_the_value() {
+        let si = |b| scaled(b, 1000.0, ["B", "kB", "MB", "GB", "TB"]);
+        let bin = |b| scaled(b, 1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]);
+        assert_eq!([si(999), si(4_194_304), si(64_000_000_000), si(5_000_000_000_000_000)], ["999 B", "4.19 MB", "64.00 GB", "5000.00 TB"]);
+        assert_eq!([bin(1023), bin(4 << 20), bin(1536)], ["1023 B", "4.00 MiB", "1.50 KiB"]);
+    }
+
+    // Reads the process-wide setting, which no test changes.
+    #[test]
+    fn decimal_by_default() {
+        assert_eq!(get(), Units::Si);
+        assert_eq!(rate(112.0), "112.00 MB/s");
+        assert_eq!(rate_w(5.5, 8), "    5.50 MB/s");
+        assert_eq!(rate_full(112.0, 8), "  112.00 MB/s (  896.00 Mbps)");
+        assert_eq!(size(4_194_304), "4.19 MB");
+    }
+}
@@ -0,0 +1,54 @@
+//! `crabwise verify`: checks a test file kept with `--keep` against the manifest written next to it.
+
+use crate::progress::LineProgress;
+use crate::units;
+use crabwise_core::manifest::{self, verify, Manifest};
+use std::io;
+use std::path::PathBuf;
+
+/// Bad block offsets listed on the terminal; the full list goes to `--out`.
+const SHOWN_BLOCKS: usize = 20;
+
+#[derive(clap::Args, Debug)]
+pub struct VerifyArgs {
+    /// Test file left behind by a run with --keep
+    file: PathBuf,
+
+    /// Manifest to check against [default: FILE.manifest.json]
+    #[arg(long, value_name="FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Also write the verification report to FILE as JSON
+    #[arg(short='o', long, value_name="FILE")]
+    out: Option<PathBuf>,
+}
+
+/// Runs the check; `Ok(false)` when any block or the length doesn't match.
+pub fn run(a: VerifyArgs) -> io::Result<bool> {
+    let manifest_path = a.manifest.clone().unwrap_or_else(|| manifest::path_for(&a.file));
+    let m = Manifest::load(&manifest_path)?;
+    crate::say!("Verifying {} against {} (kept {})...", a.file.display(), manifest_path.display(), m.created);
+    let r = verify(&a.file, &m, &mut LineProgress { enabled: true })?;
+
+    crate::say!("\n{:<8} {} in {:.1} s ({})", "Read:", units::size(r.bytes), r.seconds, units::rate(r.mb_s()));
+    if let Some(actual) = r.length_mismatch {
+        crate::say!("{:<8} file is {} bytes, manifest says {}", "Length:", actual, m.bytes);
+    }
+    let blocks = r.bytes.div_ceil(m.block);
+    match r.bad_bytes {
+        Some(n) => crate::say!("{:<8} {} of {blocks} {} block(s) differ ({n} bytes)", "Blocks:", r.bad_blocks.len(), units::size(m.block)),
+        None => crate::say!("{:<8} {} of {blocks} {} block(s) fail their checksum (first block damaged)", "Blocks:", r.bad_blocks.len(), units::size(m.block)),
+    }
+    for off in r.bad_blocks.iter().take(SHOWN_BLOCKS) {
+        crate::say!("  bad block at offset {off}");
+    }
+    if r.bad_blocks.len() > SHOWN_BLOCKS {
+        crate::say!("  … {} more (see --out)", r.bad_blocks.len() - SHOWN_BLOCKS);
+    }
+    crate::say!("{:<8} {}", "Result:", if r.ok() { "PASS" } else { "FAIL" });
+    if let Some(out) = &a.out {
+        std::fs::write(out, format!("{}\n", r.to_json()))?;
+        println!("Wrote verification report to {}", out.display());
+    }
+    Ok(r.ok())
+}
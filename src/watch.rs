@@ -0,0 +1,120 @@
+//! `crabwise watch`: benchmarks removable drives unattended as they are inserted.
+//!
+//! Mounts are polled through the same enumeration as the device picker, which works
+//! the same on every platform and needs no udev/WM_DEVICECHANGE/DiskArbitration hooks;
+//! a new drive is picked up within one `--interval` of being mounted.
+
+use crate::preset::Preset;
+use crate::{append_log, known, bench_with, progress, resolve, units, Args, LOG_FILE_NAME};
+use chrono::Local;
+use crabwise_core::device::candidate_devices;
+use crabwise_core::parse::parse_duration;
+use crabwise_core::result;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(clap::Args, Debug)]
+pub struct WatchArgs {
+    /// Settings bundle to run on each drive (config file defaults apply otherwise)
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+
+    /// Append every drive's session line to FILE instead of crabwise.log on that drive
+    #[arg(long, value_name="FILE")]
+    log: Option<PathBuf>,
+
+    /// Append each result as a JSON line to FILE (input for `crabwise report`)
+    #[arg(long, value_name="FILE")]
+    record: Option<PathBuf>,
+
+    /// How often to look for new mounts
+    #[arg(long, value_name="DURATION", default_value="1s", value_parser=parse_duration)]
+    interval: f64,
+
+    /// Also benchmark drives that are already mounted when watching starts
+    #[arg(long)]
+    existing: bool,
+
+    /// Stop after benchmarking N drives
+    #[arg(long, value_name="N")]
+    count: Option<u64>,
+
+    /// Let runs that fill each drive (--preset thorough's sustained write, or a --size close to the free space) go ahead without asking; WORD must be FILL
+    #[arg(long, value_name="WORD")]
+    yes: Option<String>,
+}
+
+/// What each drive is benchmarked with; nobody is there to confirm, so it never prompts.
+fn drive_args(w: &WatchArgs, mount: Option<PathBuf>) -> Args {
+    Args {
+        target_dir: mount,
+        preset: w.preset,
+        log: w.log.clone(),
+        record: w.record.clone(),
+        yes: w.yes.clone(),
+        unattended: true,
+        ..Args::default()
+    }
+}
+
+/// Refuses up front a watch whose every run would stop at the confirmation, rather than
+/// failing on each drive as it is inserted.
+fn check_confirmation(w: &WatchArgs) -> io::Result<()> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    if let Some(word) = w.yes.as_deref().filter(|&y| y != "FILL") {
+        return Err(invalid(format!("--yes {word}: watch only confirms fills, with --yes FILL")));
+    }
+    let mut probe = drive_args(w, None);
+    resolve(&mut probe)?;
+    if probe.sustained.is_some() && w.yes.is_none() {
+        return Err(invalid("a sustained write fills every drive it runs on; confirm it up front with --yes FILL".into()));
+    }
+    Ok(())
+}
+
+pub fn run(w: WatchArgs) -> io::Result<()> {
+    check_confirmation(&w)?;
+    let mut seen: Vec<PathBuf> = if w.existing { Vec::new() } else { candidate_devices().into_iter().map(|(_, p)| p).collect() };
+    println!("Watching for removable drives (Ctrl-C to stop)...");
+    let mut benchmarked = 0;
+    loop {
+        let now = candidate_devices();
+        // Forget unplugged drives so reinserting one benchmarks it again.
+        seen.retain(|p| now.iter().any(|(_, m)| m == p));
+        for (label, mount) in now {
+            if seen.contains(&mount) { continue; }
+            seen.push(mount.clone());
+            println!("\n[{}] Detected {}", Local::now().format("%H:%M:%S"), label);
+            // A drive pulled mid-run shouldn't end the watch; report and keep going.
+            if let Err(e) = bench_one(&w, &label, mount) {
+                eprintln!("  {label}: {e}");
+            }
+            benchmarked += 1;
+            if w.count.is_some_and(|n| benchmarked >= n) { return Ok(()); }
+        }
+        std::thread::sleep(Duration::from_secs_f64(w.interval));
+    }
+}
+
+fn bench_one(w: &WatchArgs, label: &str, mount: PathBuf) -> io::Result<()> {
+    let mut args = drive_args(w, Some(mount.clone()));
+    let (total, block) = resolve(&mut args)?;
+    // Recorded here rather than by bench_with so the record carries the drive label.
+    let record = args.record.take();
+    let log_path = args.log.clone().unwrap_or_else(|| mount.join(LOG_FILE_NAME));
+    let mut rec = bench_with(args, mount, total, block, &mut progress::LineProgress { enabled: true }, false)?;
+    rec.session = Some(label.to_string());
+    append_log(&log_path, label, &rec)?;
+    if let Some(path) = &record { result::append(path, &rec)?; }
+    let verdict = match &rec.threshold_failures {
+        None => "",
+        Some(f) if f.is_empty() => "  PASS",
+        Some(_) => "  FAIL",
+    };
+    crate::say!("  write {}, read {}{verdict}", units::rate(rec.write.mb_s()), units::rate(rec.read.mb_s()));
+    for f in rec.threshold_failures.iter().flatten() { crate::say!("  FAIL: {f}"); }
+    if let Some(e) = known::annotate(&rec) { crate::say!("  {e}"); }
+    println!("  Saved log entry to {}", log_path.display());
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! `crabwise wipe`: overwrites a device or a filesystem's free space and verifies the overwrite.
+
+use crate::destructive::{confirm_typed, device_node, ensure_unmounted};
+use crate::progress::LineProgress;
+use crate::units;
+use crabwise_core::parse::{parse_size, parse_wipe_pattern};
+use crabwise_core::wipe::{wipe, WipeConfig, WipePattern};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::Args, Debug)]
+pub struct WipeArgs {
+    /// Block device or file to overwrite entirely, or a directory to fill only the free space of
+    target: PathBuf,
+
+    /// What to write: zero, ones, random, or a byte value such as 0xAA
+    #[arg(long, default_value="zero", value_parser=parse_wipe_pattern)]
+    pattern: WipePattern,
+
+    /// Bytes per write/read
+    #[arg(short='b', long, default_value="4M", value_parser=parse_size)]
+    block: u64,
+
+    /// Skip reading the wiped data back
+    #[arg(long)]
+    no_verify: bool,
+
+    /// Confirm without the prompt by giving what it asks for: the drive's serial number or device name, or WIPE for a plain file or free space (required when stdin is not a terminal)
+    #[arg(long, value_name="NAME")]
+    yes: Option<String>,
+
+    /// Unmount the device's mounted filesystems instead of refusing to wipe it
+    #[arg(long)]
+    force_unmount: bool,
+
+    /// Also write the wipe report to FILE as JSON
+    #[arg(short='o', long, value_name="FILE")]
+    out: Option<PathBuf>,
+}
+
+/// Asks the user to type the serial or name of the drive being wiped (the word WIPE for a
+/// plain file or a free-space wipe, which leaves the files alone) before anything is written.
+fn confirm(cfg: &WipeConfig, device: Option<&Path>, yes: Option<&str>) -> io::Result<()> {
+    let what = if cfg.free_space() {
+        format!("fill all free space on the filesystem holding {}", cfg.path.display())
+    } else {
+        format!("DESTROY ALL DATA on {}", cfg.path.display())
+    };
+    confirm_typed(&format!("{what} by overwriting it with {}", cfg.pattern), device, "WIPE", yes, true)
+}
+
+/// Runs the wipe; `Ok(false)` when verification found bytes that didn't take the pattern.
+pub fn run(a: WipeArgs) -> io::Result<bool> {
+    let cfg = WipeConfig { path: a.target.clone(), pattern: a.pattern, block: a.block, verify: !a.no_verify };
+    let device = device_node(&a.target);
+    confirm(&cfg, device.as_deref(), a.yes.as_deref())?;
+    // Only once the user has named the drive: declining must leave its volumes mounted.
+    if let Some(d) = &device { ensure_unmounted(d, a.force_unmount)?; }
+    crate::say!("Wiping {} with {}...", a.target.display(), a.pattern);
+    let r = wipe(&cfg, &mut LineProgress { enabled: true })?;
+
+    crate::say!("\n{:<8} {} {}", "Wiped:", units::size(r.bytes), if r.free_space { "of free space" } else { "(whole target)" });
+    crate::say!("{:<8} {} ({:.1} s)", "Write:", units::rate(r.write_mb_s()), r.write_seconds);
+    match (r.verify_mb_s(), r.verify_seconds) {
+        (Some(mbs), Some(secs)) => {
+            crate::say!("{:<8} {} ({:.1} s)", "Verify:", units::rate(mbs), secs);
+            match r.first_mismatch {
+                None => crate::say!("{:<8} every byte reads back as {}", "Result:", r.pattern),
+                Some(at) => crate::say!("{:<8} FAIL: {} bytes differ, first at offset {at}", "Result:", r.mismatches),
+            }
+        }
+        _ => crate::say!("{:<8} not verified (--no-verify)", "Result:"),
+    }
+    if r.free_space {
+        crate::say!("Only free space was overwritten; blocks the drive has remapped or kept spare are out of reach.");
+    }
+    if let Some(out) = &a.out {
+        std::fs::write(out, format!("{}\n", r.to_json()))?;
+        println!("Wrote wipe report to {}", out.display());
+    }
+    Ok(r.mismatches == 0)
+}